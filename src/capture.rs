@@ -0,0 +1,141 @@
+// src/capture.rs
+
+use crate::hook;
+use dlog::config;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 配置项：记录的输出最多保留的字节数（KB），超出时只保留开头和结尾各一半
+const MAX_OUTPUT_KEY: &str = "cmd_max_output";
+const DEFAULT_MAX_OUTPUT_KB: u64 = 200;
+
+/// 'log --cmd' 执行的命令及其结果
+pub struct Captured {
+    pub command: String,
+    pub status: ExitStatus,
+    pub duration: Duration,
+    pub output: String,
+    /// 因超过上限而省略的字节数
+    pub omitted: usize,
+}
+
+impl Captured {
+    /// 命令的退出码；被信号终止时没有退出码，记为 1
+    pub fn exit_code(&self) -> i32 {
+        self.status.code().unwrap_or(1)
+    }
+
+    /// 作为日志内容的文本：命令行、退出状态和耗时，以及放在代码块中的输出
+    pub fn to_content(&self) -> String {
+        let status = match self.status.code() {
+            Some(code) => format!("exit {}", code),
+            None => self.status.to_string(),
+        };
+        let mut content = format!("$ {}\n\n{}, {:.1}s\n", self.command, status, self.duration.as_secs_f64());
+        if self.output.trim().is_empty() {
+            content.push_str("\n(no output)");
+            return content;
+        }
+        // 输出本身包含 ``` 时使用更长的围栏
+        let longest = self
+            .output
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest.max(2) + 1);
+        content.push_str(&format!("\n{}text\n{}\n{}", fence, self.output.trim_end(), fence));
+        content
+    }
+}
+
+/// 输出的开头和结尾部分，中间超出上限的部分被丢弃
+struct Buffer {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    half: usize,
+    omitted: usize,
+}
+
+impl Buffer {
+    fn push(&mut self, bytes: &[u8]) {
+        let room = self.half.saturating_sub(self.head.len()).min(bytes.len());
+        self.head.extend_from_slice(&bytes[..room]);
+        self.tail.extend(&bytes[room..]);
+        if self.tail.len() > self.half {
+            let excess = self.tail.len() - self.half;
+            self.tail.drain(..excess);
+            self.omitted += excess;
+        }
+    }
+}
+
+/// 通过 shell 执行 `command`，一边把输出原样显示在终端上一边记录下来
+///
+/// 标准输出和标准错误按到达的顺序记录在一起；标准输入继承自 dlog。
+/// 命令的输出不是终端，依赖终端的交互式程序可能表现不同。
+pub fn run(command: &str) -> io::Result<Captured> {
+    let max_bytes = config::number(MAX_OUTPUT_KEY, DEFAULT_MAX_OUTPUT_KB).saturating_mul(1024);
+    let buffer = Arc::new(Mutex::new(Buffer {
+        head: Vec::new(),
+        tail: VecDeque::new(),
+        half: usize::try_from(max_bytes / 2).unwrap_or(usize::MAX),
+        omitted: 0,
+    }));
+
+    let started = Instant::now();
+    let mut child = hook::shell(command)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = child.stdout.take().map(|pipe| forward(pipe, io::stdout(), Arc::clone(&buffer)));
+    let stderr = child.stderr.take().map(|pipe| forward(pipe, io::stderr(), Arc::clone(&buffer)));
+    let status = child.wait()?;
+    for reader in [stdout, stderr].into_iter().flatten() {
+        let _ = reader.join();
+    }
+    let duration = started.elapsed();
+
+    let buffer = buffer.lock().unwrap_or_else(|e| e.into_inner());
+    let mut output = String::from_utf8_lossy(&buffer.head).into_owned();
+    if buffer.omitted > 0 {
+        output.push_str(&format!("\n… {} bytes omitted …\n", buffer.omitted));
+    }
+    let tail: Vec<u8> = buffer.tail.iter().copied().collect();
+    output.push_str(&String::from_utf8_lossy(&tail));
+    Ok(Captured {
+        command: command.to_string(),
+        status,
+        duration,
+        output,
+        omitted: buffer.omitted,
+    })
+}
+
+/// 在单独的线程中读取管道，写到 `out` 的同时追加到 `buffer`
+fn forward<R, W>(mut pipe: R, mut out: W, buffer: Arc<Mutex<Buffer>>) -> std::thread::JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let mut chunk = [0u8; 8192];
+        loop {
+            match pipe.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => {
+                    // 终端已关闭时仍继续记录
+                    let _ = out.write_all(&chunk[..n]);
+                    let _ = out.flush();
+                    buffer.lock().unwrap_or_else(|e| e.into_inner()).push(&chunk[..n]);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+    })
+}
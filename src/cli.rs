@@ -1,6 +1,7 @@
 // src/cli.rs
 
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -26,10 +27,37 @@ dlog - 专为开发者设计的命令行日志工具
   dlog get -t bugfix          # 查看所有带有bugfix标签的日志
   dlog del 3,5-7             # 删除ID为3、5、6、7的日志
 
-数据库位置：~/.config/dlog/dlog.db
+数据库位置：~/.local/share/dlog/dlog.db（遵循 $XDG_DATA_HOME）
+可以通过 --db、DLOG_DB 环境变量或 'dlog init --db-path' 指定其他位置
+配置文件：~/.config/dlog/config.toml（遵循 $XDG_CONFIG_HOME）
     "#
 )]
 pub struct Cli {
+    #[arg(long, global = true, value_name = "PATH",
+          help = "本次运行使用指定的数据库文件",
+          long_help = "使用指定的数据库文件，优先于 DLOG_DB 环境变量和 'dlog init --db-path' 记录的位置。")]
+    pub db: Option<String>,
+
+    #[arg(long, global = true, value_name = "NAME",
+          help = "使用指定配置的数据库",
+          long_help = "每个配置使用独立的数据库，例如将工作和个人日志分开。也可以通过 DLOG_PROFILE 环境变量设置，此参数优先。默认配置为 default。")]
+    pub profile: Option<String>,
+
+    #[arg(long, global = true,
+          help = "不使用分页器",
+          long_help = "直接输出全部内容，不通过分页器显示。默认情况下，终端中超过一屏的输出会通过 $DLOG_PAGER、配置文件中的 pager、$PAGER 或 less -FRX 显示；在 ~/.config/dlog/config.toml 中写入 pager = false 可以始终关闭分页。")]
+    pub no_pager: bool,
+
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto,
+          help = "何时使用颜色输出",
+          long_help = "auto 时只在输出到终端且未设置 NO_COLOR 环境变量时使用颜色；always 总是使用；never 从不使用。")]
+    pub color: ColorChoice,
+
+    #[arg(short, long, global = true,
+          help = "只输出结果和错误",
+          long_help = "不输出成功提示、摘要和提示信息，只输出日志内容和错误，便于在脚本中使用。get 和 todo 没有匹配结果时以退出码 3 结束，出错时为 1。")]
+    pub quiet: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -39,39 +67,140 @@ pub enum Commands {
     /// 初始化dlog数据库和目录同步
     ///
     /// 此命令将：
-    /// 1. 在 ~/.config/dlog/dlog.db 创建数据库
-    /// 2. 检查是否存在指向已删除目录的日志
-    /// 3. 提示您清理这些孤立的日志条目
+    /// 1. 在 ~/.local/share/dlog/dlog.db 创建数据库（遵循 $XDG_DATA_HOME）
+    /// 2. 如果数据库还在旧位置 ~/.config/dlog/dlog.db，提示将其移动到新位置
+    /// 3. 检查是否存在指向已删除目录的日志
+    /// 4. 提示您清理这些孤立的日志条目
     ///
     /// 示例：
     ///   dlog init
-    Init,
+    ///   dlog init --yes    # 不经确认移动旧位置的数据库，保留孤立的日志条目
+    ///   dlog init --prune  # 不经确认直接清理孤立的日志条目
+    ///   dlog init --keep   # 在安装脚本中使用，只确保数据库存在
+    ///   dlog init --remap /home/old/proj=/home/new/proj  # 项目移动后更新日志的目录
+    ///   dlog init --compact # 初始化后整理数据库文件
+    ///   dlog init --db-path /mnt/sync/dlog.db  # 使用自定义的数据库位置
+    Init {
+        #[arg(short, long,
+              help = "跳过确认，直接移动旧位置的数据库",
+              long_help = "不再交互式确认，将旧位置的数据库移动到新位置。指向已删除目录的日志只会被列出并保留，要删除它们需要同时指定 --prune。在脚本或非交互环境中使用。")]
+        yes: bool,
+
+        #[arg(long,
+              help = "不经确认删除指向已删除目录的日志",
+              long_help = "直接删除指向已不存在目录的日志，不询问；锁定的日志会被保留。不会自动移动旧位置的数据库，需要时再加上 --yes。")]
+        prune: bool,
+
+        #[arg(long, conflicts_with = "prune",
+              help = "不检查已删除的目录，保留所有日志",
+              long_help = "只确保数据库存在且结构为最新，跳过对已不存在目录的检查。未指定 --prune 或 --keep 时，只有在终端中运行才会询问是否删除，否则保留日志并列出这些目录。")]
+        keep: bool,
+
+        #[arg(long, value_name = "OLD=NEW", action = ArgAction::Append,
+              help = "将已移动目录的日志改为指向新位置（可重复）",
+              long_help = "将目录 OLD 及其所有子目录中的日志改为指向 NEW 下对应的路径，例如移动项目或主目录之后。NEW 必须存在。可以多次指定，在检查已删除的目录之前执行。")]
+        remap: Vec<String>,
+
+        #[arg(long,
+              help = "完成后整理数据库（同 'dlog compact'）",
+              long_help = "在初始化和目录同步完成后执行 'dlog compact'，回收已删除日志占用的空间。")]
+        compact: bool,
+
+        #[arg(long, value_name = "PATH",
+              help = "在指定位置创建数据库并记住该位置",
+              long_help = "在指定位置创建数据库（不存在的父目录会被创建），并将该位置记录在 ~/.config/dlog/db-path 中，之后的所有命令都会使用它。已存在的文件必须是 dlog 数据库。")]
+        db_path: Option<String>,
+    },
+
+    /// 整理数据库文件，回收已删除日志占用的空间
+    ///
+    /// 依次重建索引、执行 VACUUM 和 ANALYZE，并显示整理前后的文件大小。
+    /// 如果其他进程正在使用数据库，命令会拒绝执行。
+    ///
+    /// 示例：
+    ///   dlog compact
+    Compact,
+
+    /// 按时间顺序将日志ID重新编号为 1、2、3……
+    ///
+    /// 删除过大量日志后ID会变得很大且不连续，此命令按记录时间重新分配连续的ID，
+    /// 同时更新日志之间的关联。执行前会在数据库旁边保存一份完整备份，并要求输入 renumber 确认。
+    /// 所有修改在一个事务中完成；其他 dlog 进程正在使用数据库时拒绝执行。
+    /// 注意：脚本、笔记或提交信息中记下的旧ID不会随之改变，可以使用 --map-file 保存对照表。
+    ///
+    /// 示例：
+    ///   dlog renumber --dry-run
+    ///   dlog renumber --map-file ~/dlog-ids.tsv
+    #[command(verbatim_doc_comment)]
+    Renumber {
+        #[arg(long, value_name = "FILE",
+              help = "将旧ID到新ID的对照表保存到文件",
+              long_help = "将对照表写入指定文件而不是输出到终端，每行为以制表符分隔的旧ID和新ID。")]
+        map_file: Option<String>,
+
+        #[arg(long,
+              help = "只显示会改变的ID，不做修改")]
+        dry_run: bool,
+
+        #[arg(short, long,
+              help = "跳过确认",
+              long_help = "不再要求输入 renumber 确认，直接执行。仍然会先保存备份。")]
+        yes: bool,
+    },
+
+    /// 检查数据库的完整性和数据问题
+    ///
+    /// 检查项目：
+    /// • SQLite 完整性检查（PRAGMA integrity_check）
+    /// • 目录为相对路径或未规范化的日志
+    /// • 目录在磁盘上已不存在的日志
+    /// • 内容为空的日志
+    /// • 时间戳、目录和内容完全相同的重复日志
+    /// • 时间戳无法解析或不是统一存储格式（UTC，以 Z 结尾）的日志
+    ///
+    /// 默认只读；使用 --fix 重新规范化目录，并在确认后删除空日志；
+    /// 使用 --fix-timestamps 改写其他格式的时间戳。
+    /// 存在未修复的问题时以非零状态退出，便于在脚本中使用。
+    #[command(verbatim_doc_comment)]
+    Doctor {
+        #[arg(long,
+              help = "修复可以安全修复的问题",
+              long_help = "重新规范化日志的目录路径，并在确认后删除内容为空的日志。")]
+        fix: bool,
+
+        #[arg(long,
+              help = "将时间戳改写为统一的存储格式",
+              long_help = "将带其他时区偏移的时间戳换算为 UTC，并尝试按其他常见格式解析无效的时间戳：不带时区的 YYYY-MM-DD HH:MM:SS（按 UTC 处理）和 Unix 时间戳（秒）。结果统一写为以 Z 结尾、精确到毫秒的 RFC3339。无法识别的时间戳会被列出，保持不变。")]
+        fix_timestamps: bool,
+
+        #[arg(short, long, requires = "fix",
+              help = "删除空日志时跳过确认",
+              long_help = "配合 --fix 使用，不经确认直接删除内容为空的日志。")]
+        yes: bool,
+    },
 
     /// 添加新的日志条目到当前目录
     ///
-    /// 如果没有提供 -m 参数，将打开默认编辑器（$EDITOR）供您输入详细内容。
+    /// 如果没有提供 -m 参数，将打开默认编辑器（$EDITOR）供您输入详细内容，
+    /// 第一行的 'Tags:' 用于填写标签，删除开头的两行表示不加标签。
+    /// 在非交互环境中（标准输入不是终端），将从标准输入读取日志内容。
     /// 日志会自动关联到当前工作目录，方便后续按项目查找。
     ///
     /// 示例：
     ///   dlog log -m "完成了用户认证模块" -t "feature,auth"
     ///   dlog log                              # 打开编辑器输入
     ///   dlog log -t "bugfix,urgent"           # 带标签的编辑器输入
-    Log {
-        #[arg(short, long, 
-              help = "简短的日志内容（类似git commit -m）",
-              long_help = "直接提供日志内容，避免打开编辑器。适用于快速记录简短信息。")]
-        message: Option<String>,
-
-        #[arg(short, long, 
-              help = "逗号分隔的标签",
-              long_help = "使用标签对日志进行分类。多个标签用逗号分隔，例如：feature,backend,high-priority")]
-        tags: Option<String>,
-    },
+    ///   echo "构建完成" | dlog log            # 从标准输入读取
+    ///   dlog log -m "补充单元测试" --todo      # 记录为待办事项
+    ///   dlog log -m "接口已变更" --dir ../api  # 记录到其他目录
+    ///   dlog log --cmd "cargo test" -t test   # 记录命令的输出和退出码
+    ///   dlog log -m "修复部署脚本" --time yesterday  # 补记昨天的日志
+    Log(LogArgs),
 
     /// 检索和显示日志条目
     ///
-    /// 默认显示当前目录的最新10条日志。
-    /// 使用 -r 参数可以递归搜索子目录。
+    /// 默认显示当前目录的最新10条日志，每条日志最多显示6行内容。
+    /// 使用 -r 参数可以递归搜索子目录，使用 --full 显示完整内容。
     /// 支持按标签、日期和关键词过滤。
     ///
     /// 示例：
@@ -82,49 +211,561 @@ pub enum Commands {
     ///   dlog get --date 2024-01-15  # 显示特定日期的日志
     ///   dlog get -s "error"         # 搜索包含"error"的日志
     ///   dlog get /path/to/project   # 查看指定目录的日志
-    Get {
-        /// 要搜索的目录路径，默认为当前目录
-        #[arg(help = "目标目录路径（相对或绝对路径）",
-              long_help = "指定要搜索日志的目录。可以是相对路径（./project）或绝对路径（/home/user/project）。")]
+    ///   dlog get --oneline          # 每条日志只显示一行
+    ///   dlog get --page 2           # 显示第二页
+    ///   dlog get --before-id 117    # 显示ID为117的日志之前的日志
+    ///   dlog get -r --since 3d      # 最近3天的日志
+    ///   dlog get --path-glob '*/backend/*'  # 所有 backend 目录下的日志
+    ///   dlog get --all -s "connection pool"  # 在所有目录中搜索
+    // GetArgs 比其他命令的参数大得多，装箱以免所有变体都占用同样的空间
+    Get(Box<GetArgs>),
+
+    /// 在所有目录中搜索日志，按相关度排序
+    ///
+    /// 多个词需要同时出现，双引号包围的短语按字面匹配，均不区分大小写。
+    /// 标签和标题行中的匹配比正文中的匹配权重更高；相关度相同时较新的日志在前。
+    /// 每条结果显示命中所在的一行内容和日志所在目录。
+    ///
+    /// 示例：
+    ///   dlog search connection pooling        # 同时包含两个词的日志
+    ///   dlog search '"connection pooling"'    # 包含完整短语的日志
+    ///   dlog search deploy -t backend --since 30d
+    #[command(verbatim_doc_comment)]
+    Search(SearchArgs),
+
+    /// 列出未完成的待办事项
+    ///
+    /// 默认显示当前目录中所有未完成的待办事项。
+    /// 使用 -r 参数可以包含子目录。
+    ///
+    /// 示例：
+    ///   dlog todo       # 当前目录的待办事项
+    ///   dlog todo -r    # 包含子目录的待办事项
+    Todo {
+        #[arg(short, long,
+              help = "递归搜索子目录",
+              long_help = "在当前目录及其所有子目录中查找未完成的待办事项。")]
+        recursive: bool,
+    },
+
+    /// 将待办事项标记为已完成
+    ///
+    /// 记录完成时间，已完成的待办事项在 'dlog get' 中会带有完成标记。
+    ///
+    /// 示例：
+    ///   dlog done 12    # 完成ID为12的待办事项
+    Done {
+        #[arg(help = "要完成的待办事项ID",
+              long_help = "使用 --todo 记录的日志ID。使用 'dlog todo' 查看未完成的待办事项。")]
+        id: i32,
+    },
+
+    /// 关联两条日志
+    ///
+    /// 关联是双向的，'dlog get' 会在两条日志下都显示关联的日志ID。
+    /// 使用 'dlog get --linked ID' 查看与一条日志相关的所有日志。
+    ///
+    /// 示例：
+    ///   dlog link 87 93    # 将ID为93的后续记录与87关联
+    #[command(verbatim_doc_comment)]
+    Link {
+        #[arg(help = "要关联的日志ID")]
+        a: i32,
+        #[arg(help = "要关联的另一条日志ID")]
+        b: i32,
+    },
+
+    /// 取消两条日志的关联
+    ///
+    /// 示例：
+    ///   dlog unlink 87 93
+    #[command(verbatim_doc_comment)]
+    Unlink {
+        #[arg(help = "已关联的日志ID")]
+        a: i32,
+        #[arg(help = "已关联的另一条日志ID")]
+        b: i32,
+    },
+
+    /// 锁定日志，防止被删除
+    ///
+    /// 锁定的日志不会被 'dlog del'、'dlog prune' 或 'dlog archive' 删除或移走，
+    /// 除非对 'dlog del' 使用 --include-locked。锁定不影响 'dlog fix' 修改内容。
+    /// 'dlog get' 会在锁定的日志标题行显示 LOCKED 标记。
+    ///
+    /// 示例：
+    ///   dlog lock 12
+    ///   dlog lock 3,7-9
+    #[command(verbatim_doc_comment)]
+    Lock {
+        #[arg(value_name = "ID_LIST",
+              help = "要锁定的日志ID列表",
+              long_help = "要锁定的日志ID，格式与 'dlog del' 相同，如 5、3,5,8、7-9。")]
+        ids: String,
+    },
+
+    /// 解除日志的锁定
+    ///
+    /// 示例：
+    ///   dlog unlock 12
+    #[command(verbatim_doc_comment)]
+    Unlock {
+        #[arg(value_name = "ID_LIST",
+              help = "要解锁的日志ID列表",
+              long_help = "要解锁的日志ID，格式与 'dlog del' 相同，如 5、3,5,8、7-9。")]
+        ids: String,
+    },
+
+    /// 管理所有日志中的标签
+    ///
+    /// 示例：
+    ///   dlog tag rename bugifx bugfix    # 修正拼写错误的标签
+    ///   dlog tag rm wip                  # 从当前目录的日志中移除wip标签
+    #[command(verbatim_doc_comment)]
+    Tag {
+        #[command(subcommand)]
+        action: TagCommands,
+    },
+
+    /// 管理多个配置（各自独立的数据库）
+    ///
+    /// 示例：
+    ///   dlog profile list                # 列出所有配置
+    ///   dlog --profile work init         # 创建 work 配置
+    ///   dlog --profile work log -m "..."  # 记录到 work 配置
+    #[command(verbatim_doc_comment)]
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommands,
+    },
+
+    /// 生成 shell 自动补全脚本
+    ///
+    /// 补全脚本输出到标准输出，重定向到 shell 的补全目录即可使用。
+    /// 此命令不需要数据库。
+    ///
+    /// 示例：
+    ///   dlog completions bash > ~/.local/share/bash-completion/completions/dlog
+    ///   dlog completions zsh > ~/.zfunc/_dlog
+    ///   dlog completions fish > ~/.config/fish/completions/dlog.fish
+    #[command(verbatim_doc_comment)]
+    Completions {
+        #[arg(value_enum,
+              help = "目标 shell",
+              long_help = "要生成补全脚本的 shell：bash、zsh、fish、powershell 或 elvish。")]
+        shell: Shell,
+    },
+
+    /// 生成 Markdown 格式的周报或月报
+    ///
+    /// 收集指定时间范围内的日志，按天分节、按标签分组，
+    /// 每条日志以首行作为要点，其余内容缩进在下方。默认输出本周的日志。
+    ///
+    /// 示例：
+    ///   dlog report                              # 本周（从周一开始）
+    ///   dlog report --month -r                   # 本月，包含子目录
+    ///   dlog report --from 2024-06-01 --to 2024-06-15 --all
+    ///   dlog report --week --output report.md    # 写入文件
+    ///   dlog report --digest --since 7d --all | mail -s "Weekly log" me@example.com
+    #[command(verbatim_doc_comment)]
+    Report(ReportArgs),
+
+    /// 将日志导出为 Obsidian 的每日笔记
+    ///
+    /// 每个本地日期写入一个文件（如 2024-06-03.md），每条日志为一个
+    /// "## HH:MM — 首行" 小节，标签转换为 #标签，目录记录为元数据行。
+    /// dlog 生成的内容位于标记注释之间，重复导出只会替换这部分内容，
+    /// 文件中的其他笔记保持不变。
+    ///
+    /// 示例：
+    ///   dlog export --obsidian ~/vault/Daily            # 当前目录的日志
+    ///   dlog export --obsidian ~/vault/Daily -r         # 包含子目录
+    ///   dlog export --obsidian ~/work/Daily --path ~/work -r
+    #[command(verbatim_doc_comment)]
+    Export(ExportArgs),
+
+    /// 从其他日记格式导入日志
+    ///
+    /// 保留条目的原始时间，并关联到 --dir 指定的目录（默认为当前目录）。
+    /// 已存在的相同日志（时间、目录和内容都相同）会被跳过，因此可以重复导入。
+    ///
+    /// 支持的格式：
+    /// • jrnl：jrnl 默认格式，条目以 [YYYY-MM-DD HH:MM] 标题 开头，@标签 转换为 dlog 标签
+    /// • plain：每个文件一条日志；使用 --date-from-filename 从 YYYY-MM-DD.txt 文件名
+    ///   获取日期，否则使用文件的修改时间。可以传入目录以导入其中所有文件
+    /// • --from-git：当前 git 仓库的提交历史，每个提交一条日志，标签为 git
+    ///
+    /// 示例：
+    ///   dlog import --format jrnl journal.txt --dry-run
+    ///   dlog import --format jrnl journal.txt --dir ~/notes
+    ///   dlog import --format plain --date-from-filename ~/diary/
+    ///   dlog import --from-git --since 2023-01-01 --author me@example.com --no-merges
+    #[command(verbatim_doc_comment)]
+    Import(ImportArgs),
+
+    /// 将另一个 dlog 数据库中的日志合并到当前数据库
+    ///
+    /// 以只读方式打开另一个数据库，复制本地还没有的日志（时间、目录和内容都相同的视为同一条），
+    /// 以及它们之间的关联。原来的ID在本地未被占用时保留，否则分配新的ID。
+    /// 所有修改在一个事务中完成，中途出错时当前数据库保持不变。
+    ///
+    /// 示例：
+    ///   dlog merge ~/laptop-dlog.db --dry-run
+    ///   dlog merge ~/laptop-dlog.db --prefix-dir /home/alice=/Users/alice
+    #[command(verbatim_doc_comment)]
+    Merge {
+        #[arg(value_name = "FILE",
+              help = "要合并进来的数据库文件")]
+        file: String,
+
+        #[arg(long, value_name = "OLD=NEW", action = ArgAction::Append,
+              help = "将目录前缀 OLD 替换为 NEW",
+              long_help = "合并前改写日志的目录：OLD 或其子目录中的日志改为 NEW 下对应的路径，用于两台机器的主目录不同的情况，例如 /home/alice=/Users/alice。可以多次指定，使用第一个匹配的前缀。OLD 按另一台机器上的路径原样比较，不展开 ~。")]
+        prefix_dir: Vec<String>,
+
+        #[arg(long,
+              help = "只列出会导入的日志，不做修改")]
+        dry_run: bool,
+    },
+
+    /// 通过同步包在两台机器之间同步日志
+    ///
+    /// 'dlog sync export' 把日志和删除记录写成每行一个 JSON 对象的同步包，
+    /// 在另一台机器上用 'dlog sync import' 应用。日志按 UUID 对应：两边都修改过时保留较新的修改，
+    /// 本地已删除的日志不会被恢复，锁定的日志不会因为对方删除而被删除。不需要服务器，
+    /// 同步包可以通过任何方式传递。
+    ///
+    /// 示例：
+    ///   dlog sync export --since-last ~/Dropbox/laptop.jsonl
+    ///   dlog sync import ~/Dropbox/laptop.jsonl --dry-run
+    #[command(verbatim_doc_comment)]
+    Sync {
+        #[command(subcommand)]
+        action: SyncCommands,
+    },
+
+    /// 统计日志的数量和写作量
+    ///
+    /// 默认显示日志条数、记录过日志的天数以及第一条和最近一条日志的日期。
+    /// 使用 --words 统计词数和字数：总量、每条的平均值、最长的一条，以及最近12周每周写下的词数。
+    /// 汉字和假名每个字算作一个词。
+    ///
+    /// 示例：
+    ///   dlog stats --words -r
+    ///   dlog stats --words --all --json
+    #[command(verbatim_doc_comment)]
+    Stats {
+        #[arg(long, value_name = "DIR", conflicts_with = "all",
+              help = "统计指定目录而不是当前目录的日志")]
         path: Option<String>,
 
-        #[arg(short, long, 
-              help = "显示最新的N条日志",
-              long_help = "限制显示的日志数量。默认显示10条，使用0显示所有匹配的日志。")]
+        #[arg(short, long,
+              help = "包含子目录的日志")]
+        recursive: bool,
+
+        #[arg(long, conflicts_with = "recursive",
+              help = "统计所有目录的日志")]
+        all: bool,
+
+        #[arg(long,
+              help = "统计词数、字数和每周的写作量")]
+        words: bool,
+
+        #[arg(long,
+              help = "以 JSON 格式输出",
+              long_help = "以一个 JSON 对象输出统计结果，便于脚本处理。日期为本地日期（YYYY-MM-DD）。")]
+        json: bool,
+    },
+
+    /// 列出记录过日志的目录
+    ///
+    /// 默认按目录排列，显示每个目录最近一条日志的时间（本地时间）和日志数量。
+    /// 使用 --recent 按最近活动排列，配合 --paths-only 可以交给 fzf 等工具选择目录。
+    ///
+    /// 示例：
+    ///   dlog dirs --recent -n 10
+    ///   cd "$(dlog dirs --recent --paths-only --existing | fzf)"
+    #[command(verbatim_doc_comment)]
+    Dirs {
+        #[arg(long,
+              help = "按最近一条日志的时间从新到旧排列",
+              long_help = "按每个目录最近一条日志的时间从新到旧排列，时间相同时按目录排列。默认按目录排列。")]
+        recent: bool,
+
+        #[arg(short, long, value_name = "N",
+              help = "最多列出N个目录",
+              long_help = "只列出排在前面的N个目录，默认列出全部。")]
         num: Option<u32>,
 
-        #[arg(short, long, 
-              help = "递归搜索子目录",
-              long_help = "在指定目录及其所有子目录中搜索日志。搜索结果会显示每条日志的完整路径。")]
+        #[arg(long,
+              help = "只输出目录的绝对路径",
+              long_help = "每行只输出一个目录的绝对路径，不显示时间、数量和颜色，便于交给其他命令处理。")]
+        paths_only: bool,
+
+        #[arg(long,
+              help = "只列出仍然存在的目录",
+              long_help = "跳过已经不存在于磁盘上的目录（例如已删除或移动的项目）。")]
+        existing: bool,
+    },
+
+    /// 以日历热力图显示记录日志的活跃程度
+    ///
+    /// 每列代表一周，每行代表星期几，颜色越深表示当天的日志越多。
+    /// 同时显示当前连续记录天数和最长连续记录天数。
+    /// 终端支持真彩色时使用彩色方块，否则使用 ASCII 字符表示密度。
+    ///
+    /// 示例：
+    ///   dlog calendar              # 当前目录最近26周
+    ///   dlog calendar -w 52 --all  # 所有目录最近一年
+    #[command(verbatim_doc_comment)]
+    Calendar {
+        #[arg(short, long, default_value_t = 26,
+              value_parser = clap::value_parser!(u32).range(1..=520),
+              help = "显示最近N周",
+              long_help = "显示最近N周的活跃程度，默认26周。")]
+        weeks: u32,
+
+        #[arg(short, long,
+              help = "包含子目录的日志",
+              long_help = "统计当前目录及其所有子目录中的日志。")]
+        recursive: bool,
+
+        #[arg(long, conflicts_with = "recursive",
+              help = "包含所有目录的日志",
+              long_help = "统计数据库中所有目录的日志。")]
+        all: bool,
+
+        #[arg(long,
+              help = "每周从周日开始",
+              long_help = "默认每周从周一开始，使用此选项改为从周日开始。")]
+        sunday: bool,
+    },
+
+    /// 随机显示一条过去的日志
+    ///
+    /// 默认从当前目录的日志中选取，可以用 -r、--all 和 -t 限定范围。
+    /// 适合放在 shell 启动脚本中，每次打开终端时回顾一条旧笔记。
+    ///
+    /// 示例：
+    ///   dlog random             # 当前目录的一条日志
+    ///   dlog random --all -t til
+    #[command(verbatim_doc_comment)]
+    Random {
+        #[arg(short, long,
+              help = "包含子目录的日志",
+              long_help = "从当前目录及其所有子目录的日志中选取。")]
         recursive: bool,
 
-        #[arg(short, long, 
-              help = "按标签过滤日志",
-              long_help = "只显示包含指定标签的日志。支持部分匹配，例如'test'会匹配'test'、'integration-test'等。")]
+        #[arg(long, conflicts_with = "recursive",
+              help = "包含所有目录的日志",
+              long_help = "从数据库中所有目录的日志中选取。")]
+        all: bool,
+
+        #[arg(short, long,
+              help = "只选取包含指定标签的日志")]
         tag: Option<String>,
+    },
+
+    /// 只显示日志中匹配的行，类似 grep
+    ///
+    /// 在当前目录（-r 包含子目录，--all 所有目录）的日志内容中逐行查找，
+    /// 每条日志先显示一行标题，之后按 `ID:行号: 内容` 显示匹配的行，`ID-行号- 内容` 为上下文行。
+    /// 默认按字面匹配、区分大小写；有匹配时退出码为 0，没有匹配时为 3。
+    ///
+    /// 示例：
+    ///   dlog grep "connection refused" -r
+    ///   dlog grep -E 'timeout=\d+' -C 2 --all
+    #[command(verbatim_doc_comment)]
+    Grep {
+        #[arg(value_name = "PATTERN",
+              help = "要查找的文本，使用 -E 时为正则表达式")]
+        pattern: String,
+
+        #[arg(short = 'E', long = "regex",
+              help = "将 PATTERN 作为正则表达式",
+              long_help = "按正则表达式匹配每一行，而不是按字面查找。")]
+        regex: bool,
 
-        #[arg(long, 
-              help = "按日期过滤日志（格式：YYYY-MM-DD）",
-              long_help = "只显示指定日期的日志。日期格式必须为年-月-日，例如：2024-01-15。")]
-        date: Option<String>,
+        #[arg(short, long,
+              help = "不区分大小写")]
+        ignore_case: bool,
 
-        #[arg(short, long, 
-              help = "在内容和标签中搜索关键词",
-              long_help = "在日志内容和标签中搜索包含指定关键词的条目。搜索不区分大小写。")]
-        search: Option<String>,
+        #[arg(short = 'C', long, value_name = "N", default_value_t = 0,
+              help = "显示匹配行前后各N行",
+              long_help = "在每个匹配行前后各显示N行上下文；相邻或重叠的范围合并显示，不相连的范围之间以 -- 分隔。")]
+        context: usize,
+
+        #[arg(short, long,
+              help = "包含子目录的日志")]
+        recursive: bool,
+
+        #[arg(long, conflicts_with = "recursive",
+              help = "在所有目录的日志中查找")]
+        all: bool,
     },
 
+    /// 显示往年今天记录的日志
+    ///
+    /// 列出在今天的月份和日期、但早于今年记录的日志，按年份从近到远分组。
+    /// 日期按本地时间计算。
+    ///
+    /// 示例：
+    ///   dlog onthisday          # 当前目录
+    ///   dlog onthisday --all    # 所有目录
+    #[command(name = "onthisday", verbatim_doc_comment)]
+    OnThisDay {
+        #[arg(short, long,
+              help = "包含子目录的日志",
+              long_help = "包含当前目录及其所有子目录中的日志。")]
+        recursive: bool,
+
+        #[arg(long, conflicts_with = "recursive",
+              help = "包含所有目录的日志",
+              long_help = "包含数据库中所有目录的日志。")]
+        all: bool,
+    },
+
+    /// 删除或归档早于保留期限的日志
+    ///
+    /// 默认作用于当前目录，使用 -r 包含子目录，使用 --all 作用于所有目录。
+    /// 执行前显示按目录统计的条数并要求确认。使用 --archive-to 时，
+    /// 日志会被移动到另一个结构相同的 SQLite 文件而不是直接删除；
+    /// 重复归档到同一个文件会追加日志。
+    ///
+    /// 示例：
+    ///   dlog prune --older-than 1y --dry-run
+    ///   dlog prune --older-than 6m --all --archive-to ~/dlog-archive.db
+    #[command(verbatim_doc_comment)]
+    Prune(PruneArgs),
+
+    /// 将旧日志移动到归档文件，或从归档文件移回
+    ///
+    /// 归档文件是结构相同的 SQLite 文件，默认为 ~/.config/dlog/archive.db，
+    /// 不存在时自动创建，已存在时追加；归档中的日志使用各自的ID。
+    /// 一起移动的日志之间的关联随之移动，与未移动的日志的关联会被移除。
+    /// 使用 'dlog get --from-archive' 或 'dlog search --from-archive' 以只读方式查询归档。
+    ///
+    /// 示例：
+    ///   dlog archive --older-than 2y --all
+    ///   dlog archive --older-than 1y --to ~/backup/old-logs.db
+    ///   dlog archive --restore --ids 12,15-18
+    #[command(verbatim_doc_comment)]
+    Archive(ArchiveArgs),
+
+    /// 为匹配条件的一批日志添加或移除标签
+    ///
+    /// 过滤条件与 'dlog get' 相同，默认作用于当前目录。执行前列出会被修改的日志并要求确认，
+    /// 所有修改在一个事务中完成。添加的标签按与 'dlog log -t' 相同的规则整理，不会产生重复。
+    ///
+    /// 示例：
+    ///   dlog retag -s kubernetes --add-tag k8s -r
+    ///   dlog retag -t wip --remove-tag wip --add-tag done --dry-run
+    ///   dlog retag --date 2024-06 --path ~/proj --add-tag q2
+    #[command(verbatim_doc_comment)]
+    Retag(RetagArgs),
+
     /// 通过ID编辑现有的日志条目
     ///
-    /// 使用默认编辑器打开指定的日志进行编辑。
-    /// 如果内容没有变化，操作将被取消。
+    /// 使用默认编辑器打开指定的日志进行编辑，或通过 -m 直接替换内容。
+    /// 编辑器中第一行的 'Tags:' 列出当前标签，可以同时修改。
+    /// 如果内容和标签都没有变化，操作将被取消。
     ///
     /// 示例：
-    ///   dlog fix 5    # 编辑ID为5的日志
+    ///   dlog fix 5                    # 编辑ID为5的日志
+    ///   dlog fix 5 -m "修正后的内容"  # 不打开编辑器，直接替换内容
+    ///   dlog fix 5 --append "补充"    # 在末尾追加一行，带有当前时间
+    ///   dlog fix 5 --level high       # 只修改级别
+    #[command(verbatim_doc_comment)]
     Fix {
         #[arg(help = "要编辑的日志ID",
               long_help = "要编辑的日志条目的数字ID。使用 'dlog get' 命令查看可用的ID。")]
         id: i32,
+
+        #[arg(short, long, action = ArgAction::Append,
+              help = "用新内容替换日志，不打开编辑器",
+              long_help = "直接用给定的内容替换日志内容，适用于脚本和简单的修正。与 'dlog log -m' 一样可以多次指定，各段内容以空行分隔。新内容与原内容相同时不做修改。")]
+        message: Vec<String>,
+
+        #[arg(long, value_name = "TEXT", conflicts_with = "message",
+              help = "在日志末尾追加内容，不打开编辑器",
+              long_help = "在原有内容之后另起一行追加给定的文本，默认在前面加上当前的本地时间，例如 [2024-06-03 14:20]。")]
+        append: Option<String>,
+
+        #[arg(long, requires = "append",
+              help = "追加内容时不加时间前缀")]
+        no_stamp: bool,
+
+        #[arg(long, value_name = "LEVEL",
+              help = "修改日志的级别（low、normal、high）",
+              long_help = "修改日志的重要程度。单独使用时只修改级别，不打开编辑器；也可以与 -m 或 --append 一起使用。")]
+        level: Option<String>,
+    },
+
+    /// 将一条日志复制到当前目录
+    ///
+    /// 副本包含原日志的内容和标签，使用当前时间，原日志保持不变。
+    /// 待办事项的副本重新标记为未完成。
+    ///
+    /// 示例：
+    ///   dlog copy 42                  # 复制到当前目录
+    ///   dlog copy 42 --edit           # 复制后立即编辑副本
+    ///   dlog copy 42 --to ~/new-proj  # 复制到其他目录
+    #[command(verbatim_doc_comment)]
+    Copy {
+        #[arg(help = "要复制的日志ID",
+              long_help = "要复制的日志条目的数字ID。使用 'dlog get' 命令查看可用的ID。")]
+        id: i32,
+
+        #[arg(long, value_name = "DIR",
+              help = "复制到指定目录",
+              long_help = "将副本关联到指定目录而不是当前目录。目录必须存在，支持 ~ 表示主目录。")]
+        to: Option<String>,
+
+        #[arg(short, long,
+              help = "复制后在编辑器中打开副本",
+              long_help = "复制完成后使用默认编辑器打开副本，与 'dlog fix' 相同。不做修改直接保存时保留原样的副本。")]
+        edit: bool,
+    },
+
+    /// 列出或恢复写入失败时保存的编辑内容
+    ///
+    /// 通过编辑器记录或修改日志时，内容会先保存到 ~/.config/dlog/recovery，
+    /// 写入数据库成功后删除。写入失败或 dlog 被中断时内容会保留下来。
+    /// 不带参数时列出保存的内容，指定名称时将其记录到当前目录并删除保存的文件。
+    ///
+    /// 示例：
+    ///   dlog recover                    # 列出保存的内容
+    ///   dlog recover 20261014-081530    # 将其记录为当前目录的日志
+    #[command(verbatim_doc_comment)]
+    Recover {
+        #[arg(value_name = "NAME",
+              help = "要恢复的内容名称",
+              long_help = "'dlog recover' 列出的名称，可以带 .md 后缀。不指定时只列出保存的内容。")]
+        name: Option<String>,
+    },
+
+    /// 启动只读的网页服务，在浏览器中浏览和搜索日志
+    ///
+    /// 首页列出所有目录，目录页支持按标签、日期和关键词过滤，
+    /// /api/logs 以 JSON 返回相同过滤条件下的日志。服务只读取数据库，不提供任何修改接口。
+    /// 默认只监听 127.0.0.1，需要让其他机器访问时使用 --bind 指定地址。按 Ctrl-C 停止。
+    ///
+    /// 示例：
+    ///   dlog serve                      # http://127.0.0.1:7171/
+    ///   dlog serve --port 8080
+    ///   dlog serve --bind 0.0.0.0       # 允许局域网访问
+    ///   curl 'http://127.0.0.1:7171/api/logs?path=/home/me/proj&tag=bug&q=crash'
+    #[command(verbatim_doc_comment)]
+    Serve {
+        #[arg(short, long, default_value_t = 7171,
+              help = "监听的端口",
+              long_help = "HTTP 服务监听的端口，默认7171。")]
+        port: u16,
+
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1",
+              help = "监听的地址，默认只允许本机访问",
+              long_help = "HTTP 服务监听的IP地址，默认 127.0.0.1。日志内容没有任何访问控制，只有在信任所在网络时才使用 0.0.0.0 等非本机地址。")]
+        bind: std::net::IpAddr,
     },
 
     /// 删除一个或多个日志条目
@@ -135,25 +776,838 @@ pub enum Commands {
     /// • 范围删除：dlog del 7-9（删除7、8、9）
     /// • 混合模式：dlog del 3,7-9,12
     /// • 递归删除：dlog del -r（删除当前目录及子目录所有日志）
+    /// • 按时间删除：dlog del --older-than 6m（删除当前目录6个月前的日志，可与 -r 组合）
+    /// • 指定目录：dlog del -r --path ~/old-project（目录已不存在时同样可以删除）
+    /// • 按条件删除：dlog del -r --tag experiment --before 2024-01-01
+    ///              dlog del -r --search "scratch"
+    ///
+    /// 删除前列出将被删除的日志；所有删除操作都需要确认，输入 'y' 继续；
+    /// 使用 --yes 跳过确认，使用 --dry-run 只列出而不删除。
+    /// 使用 'dlog lock' 锁定的日志会被跳过，除非使用 --include-locked。
+    #[command(verbatim_doc_comment)]
+    Del(DelArgs),
+
+    /// 为 shell 补全脚本列出候选值，每行一个
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        #[arg(value_enum)]
+        what: CompleteKind,
+
+        #[arg(default_value = "", allow_hyphen_values = true)]
+        prefix: String,
+    },
+}
+
+/// '__complete' 可以列出的候选值
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CompleteKind {
+    /// 数据库中出现过的标签
+    Tags,
+    /// 有日志的目录
+    Dirs,
+    /// 当前目录最近的日志ID，后面跟着标题
+    Ids,
+}
+
+/// 'tag' 命令的子命令
+#[derive(Subcommand, Debug)]
+pub enum TagCommands {
+    /// 在所有日志中重命名一个标签
+    ///
+    /// 只替换完全相同的标签（重命名 test 不会影响 unit-test），
+    /// 如果日志已经带有新标签则自动去重。所有修改在一个事务中完成。
+    ///
+    /// 示例：
+    ///   dlog tag rename bugifx bugfix
+    ///   dlog tag rename wip draft --dry-run
+    #[command(verbatim_doc_comment)]
+    Rename {
+        #[arg(help = "原标签名")]
+        old: String,
+
+        #[arg(help = "新标签名")]
+        new: String,
+
+        #[arg(long,
+              help = "只显示会受影响的日志，不做修改",
+              long_help = "列出包含该标签的日志ID，但不修改数据库。")]
+        dry_run: bool,
+    },
+
+    /// 从日志中移除一个标签
+    ///
+    /// 默认只作用于当前目录的日志，使用 -r 包含子目录，使用 --all 作用于所有日志。
+    /// 其余标签保持原有顺序，移除后没有剩余标签的日志标签会被清空。
+    /// 批量修改前需要确认，输入 'y' 继续；使用 --yes 跳过确认。
+    ///
+    /// 示例：
+    ///   dlog tag rm wip
+    ///   dlog tag rm wip -r
+    ///   dlog tag rm wip --all --yes
+    #[command(verbatim_doc_comment)]
+    Rm {
+        #[arg(help = "要移除的标签名")]
+        name: String,
+
+        #[arg(short, long,
+              help = "包含子目录的日志")]
+        recursive: bool,
+
+        #[arg(long, conflicts_with = "recursive",
+              help = "作用于所有目录的日志",
+              long_help = "从数据库中所有日志移除该标签，而不仅仅是当前目录。")]
+        all: bool,
+
+        #[arg(short, long,
+              help = "跳过确认",
+              long_help = "不再交互式确认，直接修改日志。在脚本或非交互环境中必须使用此选项。")]
+        yes: bool,
+    },
+
+    /// 统计每个标签的使用情况
+    ///
+    /// 对每个标签显示总次数、最近30天的次数、首次和最近一次使用的日期，
+    /// 以及使用最多的目录，按总次数排序；之后列出最常一起出现的标签组合。
+    ///
+    /// 示例：
+    ///   dlog tag stats -r
+    ///   dlog tag stats --all --top 5
+    #[command(verbatim_doc_comment)]
+    Stats {
+        #[arg(long, value_name = "DIR", conflicts_with = "all",
+              help = "统计指定目录而不是当前目录的日志")]
+        path: Option<String>,
+
+        #[arg(short, long,
+              help = "包含子目录的日志")]
+        recursive: bool,
+
+        #[arg(long, conflicts_with = "recursive",
+              help = "统计所有目录的日志")]
+        all: bool,
+
+        #[arg(long, value_name = "N",
+              help = "只显示使用最多的前N个标签",
+              long_help = "只显示使用次数最多的前N个标签和前N个标签组合。默认全部显示。")]
+        top: Option<usize>,
+    },
+}
+
+/// 'profile' 命令的子命令
+#[derive(Subcommand, Debug)]
+pub enum ProfileCommands {
+    /// 列出所有配置及其日志条数和数据库大小
+    ///
+    /// 当前使用的配置以 * 标出。
+    List,
+}
+
+/// 'sync' 命令的子命令
+#[derive(Subcommand, Debug)]
+pub enum SyncCommands {
+    /// 把日志和删除记录写入同步包
+    ///
+    /// 默认导出所有日志和删除记录；使用 --since-last 只导出上次导出之后创建、修改或删除的。
+    /// 导出成功后记录本次导出的时间，--dry-run 不会记录。
+    ///
+    /// 示例：
+    ///   dlog sync export bundle.jsonl
+    ///   dlog sync export --since-last bundle.jsonl
+    #[command(verbatim_doc_comment)]
+    Export {
+        #[arg(value_name = "FILE",
+              help = "同步包文件，- 表示标准输出")]
+        file: String,
+
+        #[arg(long,
+              help = "只导出上次导出之后的修改",
+              long_help = "只导出上次 'dlog sync export' 之后创建、修改或删除的日志。从未导出过时导出全部。")]
+        since_last: bool,
+
+        #[arg(long,
+              help = "覆盖已存在的文件")]
+        force: bool,
+
+        #[arg(long,
+              help = "只统计会导出的条数，不写文件")]
+        dry_run: bool,
+    },
+
+    /// 应用另一台机器导出的同步包
+    ///
+    /// 输出新增、更新或删除的条数，无需处理的条数，以及保留了本地版本的冲突。
     ///
-    /// 所有删除操作都需要确认，输入 'y' 继续。
+    /// 示例：
+    ///   dlog sync import bundle.jsonl --dry-run
     #[command(verbatim_doc_comment)]
-    Del {
-        /// 要删除的日志ID列表
-        #[arg(conflicts_with = "recursive", 
-              value_name = "ID_LIST",
-              help = "要删除的日志ID列表",
-              long_help = r#"要删除的日志ID，支持多种格式：
+    Import {
+        #[arg(value_name = "FILE",
+              help = "同步包文件，- 表示标准输入")]
+        file: String,
+
+        #[arg(long,
+              help = "只统计会应用的修改，不做修改")]
+        dry_run: bool,
+    },
+}
+
+/// 'log' 命令的参数
+#[derive(Args, Debug)]
+pub struct LogArgs {
+    #[arg(short, long, action = ArgAction::Append,
+          help = "简短的日志内容（类似git commit -m）",
+          long_help = "直接提供日志内容，避免打开编辑器。适用于快速记录简短信息。可以多次指定，各段内容以空行分隔，第一段作为标题行。")]
+    pub message: Vec<String>,
+
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = ["message", "file"],
+          help = "执行命令并把它的输出记录为日志",
+          long_help = "通过 shell 执行命令，输出照常显示在终端上，同时记录命令行、退出码、耗时和输出（标准输出与标准错误合在一起，放在代码块中）。输出超过配置项 cmd_max_output（KB，默认 200）时只保留开头和结尾。命令继承 dlog 的标准输入，但输出不是终端，依赖终端的交互式程序可能表现不同。命令失败时同样记录日志，dlog 以该命令的退出码退出（除非使用 --ignore-status）。")]
+    pub cmd: Option<String>,
+
+    #[arg(long, requires = "cmd",
+          help = "命令失败时 dlog 仍以 0 退出",
+          long_help = "配合 --cmd 使用，忽略命令的退出码：只要日志记录成功，dlog 即以 0 退出。")]
+    pub ignore_status: bool,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "message",
+          help = "从文件读取日志内容",
+          long_help = "读取 UTF-8 文本文件，去掉两端空白后作为日志内容，不会打开编辑器。使用 - 表示从标准输入读取。文件为空时与空日志一样被跳过。")]
+    pub file: Option<String>,
+
+    #[arg(long, requires = "file",
+          help = "记录成功后删除 --file 指定的文件",
+          long_help = "配合 --file 使用，日志写入数据库后删除源文件。写入失败、内容为空或因重复被跳过时文件会保留；从标准输入读取时没有文件可删除。")]
+    pub rm: bool,
+
+    #[arg(short, long, 
+          help = "逗号分隔的标签",
+          long_help = "使用标签对日志进行分类。多个标签用逗号分隔，例如：feature,backend,high-priority。标签会去掉两端空白、去重并转为小写；在配置文件中设置 lowercase_tags = false（或 DLOG_LOWERCASE_TAGS=0）可以保留大小写。")]
+    pub tags: Option<String>,
+
+    #[arg(long,
+          help = "本次不使用 .dlog.toml 中的默认标签",
+          long_help = "记录日志时，dlog 从日志所在目录向上查找 .dlog.toml（到主目录或根目录为止），把最近一个文件中 default_tags = [\"ops\"] 列出的标签与 -t 给出的标签合并。使用此选项时本次只使用 -t 的标签。")]
+    pub no_default_tags: bool,
+
+    #[arg(long,
+          help = "将日志标记为待办事项",
+          long_help = "将这条日志记录为未完成的待办事项。使用 'dlog todo' 查看，使用 'dlog done <ID>' 标记完成。")]
+    pub todo: bool,
+
+    #[arg(long, value_name = "LEVEL",
+          help = "日志的重要程度：low、normal（默认）或 high",
+          long_help = "设置日志的重要程度，可选 low、normal、high，默认为 normal。high 级别的日志在 'dlog get' 中以醒目的标记显示。")]
+    pub level: Option<String>,
+
+    #[arg(long, value_name = "PATH",
+          help = "将日志记录到指定目录",
+          long_help = "将日志关联到指定目录而不是当前工作目录。可以是相对路径或绝对路径，目录必须已经存在（除非使用 --force-dir）。")]
+    pub dir: Option<String>,
+
+    #[arg(short = 'p', long, conflicts_with = "dir",
+          help = "将日志记录到当前项目的根目录",
+          long_help = "从当前目录向上查找最近的项目根目录（包含 .git、Cargo.toml 或 package.json 的目录），把日志关联到该目录而不是当前目录。标记文件名可以通过配置项 project_markers 设置，多个文件名以逗号分隔，例如 .git,go.mod。找不到项目根目录时使用当前目录并给出提示。")]
+    pub project: bool,
+
+    #[arg(long, requires = "dir",
+          help = "允许记录到尚不存在的目录",
+          long_help = "配合 --dir 使用，即使目录还不存在也记录日志。")]
+    pub force_dir: bool,
+
+    #[arg(long, value_name = "WHEN", allow_hyphen_values = true,
+          help = "指定日志时间，用于补记",
+          long_help = "使用指定的本地时间代替当前时间。支持 YYYY-MM-DD、YYYY-MM-DD HH:MM、today、yesterday，以及 -3h、-2d、-1w 等相对形式。")]
+    pub time: Option<String>,
+
+    #[arg(long, requires = "time",
+          help = "允许使用未来的时间",
+          long_help = "配合 --time 使用。默认情况下未来的时间会被拒绝。")]
+    pub allow_future: bool,
+
+    #[arg(long,
+          help = "即使与刚记录的日志重复也照常记录",
+          long_help = "默认情况下，如果当前目录在几分钟内已经记录过内容完全相同（忽略两端空白）的日志，本次记录会被跳过并给出提示，避免重复执行同一条命令产生重复的日志。时间窗口由配置项 duplicate_window 设置（分钟，默认 5，设为 0 关闭检查）；设置 duplicate_skip = false（或 DLOG_DUPLICATE_SKIP=0）时只提示不跳过。在编辑器中编写的日志不做检查。")]
+    pub allow_duplicate: bool,
+
+    #[arg(long,
+          help = "本次不执行记录后的钩子命令",
+          long_help = "不执行配置项 post_log_hook（或 DLOG_POST_LOG_HOOK 环境变量）中的命令。钩子命令在每次成功记录日志后由 shell 执行，通过 DLOG_ID、DLOG_DIR、DLOG_TAGS、DLOG_TIMESTAMP、DLOG_LEVEL 和 DLOG_TODO 环境变量获得日志信息，日志内容写入其标准输入；超过 hook_timeout 秒（默认 10）未结束时会被终止。钩子失败只显示警告。")]
+    pub no_hook: bool,
+}
+
+/// 'report' 命令的参数
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    #[arg(long, conflicts_with_all = ["month", "from", "since"],
+          help = "本周的日志（默认）",
+          long_help = "从本周一到今天的日志。未指定时间范围时默认使用此选项。")]
+    pub week: bool,
+
+    #[arg(long, value_name = "YYYY-MM", num_args = 0..=1, conflicts_with_all = ["from", "since"],
+          help = "本月或指定月份的日志",
+          long_help = "不带参数时为从本月1日到今天的日志；指定 YYYY-MM 时为该月整月的日志，只写月份（如 05）表示今年的该月。")]
+    pub month: Option<Option<String>>,
+
+    #[arg(long, value_name = "YYYY-MM-DD",
+          help = "起始日期（包含）",
+          long_help = "自定义时间范围的起始日期（包含当天）。")]
+    pub from: Option<String>,
+
+    #[arg(long, value_name = "YYYY-MM-DD", requires = "from",
+          help = "结束日期（包含），默认为今天",
+          long_help = "自定义时间范围的结束日期（包含当天），需要与 --from 一起使用。默认为今天。")]
+    pub to: Option<String>,
+
+    #[arg(long, value_name = "DURATION", conflicts_with = "from",
+          help = "最近一段时间内的日志（如 7d、2w）",
+          long_help = "收集在指定时长内记录的日志，按完整时间戳比较，报告的起始日期为该时刻所在的日期。时长格式为数字加单位：h（小时）、d（天）、w（周）、m（月）、y（年）。")]
+    pub since: Option<String>,
+
+    #[arg(short, long,
+          help = "包含子目录的日志",
+          long_help = "收集当前目录及其所有子目录中的日志，并在每条日志后注明所在目录。")]
+    pub recursive: bool,
+
+    #[arg(long, conflicts_with = "recursive",
+          help = "包含所有目录的日志",
+          long_help = "收集数据库中所有目录的日志，并在每条日志后注明所在目录。")]
+    pub all: bool,
+
+    #[arg(short, long, value_name = "FILE",
+          help = "将报告写入文件",
+          long_help = "将报告写入指定文件而不是输出到标准输出，不使用分页器，目录显示为完整路径而不缩写为 ~。上级目录不存在时自动创建；文件已存在时需要 --force 才会覆盖。使用 - 表示写到标准输出。")]
+    pub output: Option<String>,
+
+    #[arg(long, requires = "output",
+          help = "允许覆盖 --output 指定的已有文件")]
+    pub force: bool,
+
+    #[arg(long,
+          help = "输出适合邮件或聊天的纯文本摘要",
+          long_help = "代替 Markdown 报告输出简短的纯文本摘要：日志总数、每个项目的日志数、最常用的标签，以及按项目分组的每条日志首行。项目是日志目录向上最近的项目根目录（标记文件与 --project 相同），找不到时为日志目录本身，以最后一级目录名显示。每行不超过 72 列，从不包含颜色代码。没有日志时不输出任何内容，除非使用 --empty-ok。")]
+    pub digest: bool,
+
+    #[arg(long, requires = "digest",
+          help = "没有日志时也输出摘要",
+          long_help = "配合 --digest 使用。时间范围内没有日志时输出一份说明没有活动的简短摘要，而不是什么都不输出。")]
+    pub empty_ok: bool,
+}
+
+/// 'export' 命令的参数
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    #[arg(long, value_name = "VAULT_DIR",
+          help = "导出到 Obsidian 每日笔记目录",
+          long_help = "Obsidian 仓库中存放每日笔记的目录，必须已经存在。每个日期对应一个 YYYY-MM-DD.md 文件。")]
+    pub obsidian: String,
+
+    #[arg(long, value_name = "PATH",
+          help = "要导出的目录，默认为当前目录",
+          long_help = "导出指定目录的日志而不是当前工作目录的日志。可以是相对路径或绝对路径。")]
+    pub path: Option<String>,
+
+    #[arg(short, long,
+          help = "包含子目录的日志",
+          long_help = "导出目标目录及其所有子目录中的日志。")]
+    pub recursive: bool,
+
+    #[arg(long, conflicts_with_all = ["recursive", "path"],
+          help = "导出所有目录的日志",
+          long_help = "导出数据库中所有目录的日志。")]
+    pub all: bool,
+}
+
+/// --color 的取值
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    /// 输出到终端时使用颜色
+    Auto,
+    /// 总是使用颜色
+    Always,
+    /// 从不使用颜色
+    Never,
+}
+
+/// 'get --sort' 的取值
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    /// 按时间从新到旧
+    Time,
+    /// 按级别从高到低，级别相同时按时间
+    Level,
+}
+
+/// 'get --field' 的取值
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum GetField {
+    Id,
+    Timestamp,
+    Directory,
+    Tags,
+    Content,
+}
+
+/// 'search' 命令的参数
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    #[arg(required = true, value_name = "QUERY",
+          help = "要搜索的词或短语",
+          long_help = "要搜索的内容。多个词之间为“且”的关系；用双引号包围的部分作为一个短语按字面匹配（在 shell 中需要再用单引号包围）。")]
+    pub query: Vec<String>,
+
+    #[arg(short, long, default_value_t = 10,
+          help = "最多显示的结果数",
+          long_help = "最多显示多少条相关度最高的结果，默认10条，使用0显示所有结果。")]
+    pub num: u32,
+
+    #[arg(short, long,
+          help = "只搜索包含指定标签的日志",
+          long_help = "只在包含指定标签的日志中搜索，与 'dlog get -t' 相同。")]
+    pub tag: Option<String>,
+
+    #[arg(long, value_name = "DURATION",
+          help = "只搜索最近一段时间内的日志（如 3h、3d、2w）",
+          long_help = "只搜索在指定时长内记录的日志。时长格式为数字加单位：h（小时）、d（天）、w（周）、m（月）、y（年）。")]
+    pub since: Option<String>,
+
+    #[arg(long,
+          help = "区分大小写",
+          long_help = "只匹配大小写完全相同的词。默认情况下搜索不区分大小写，包括非 ASCII 字母。")]
+    pub case_sensitive: bool,
+
+    #[arg(long, value_name = "FILE", num_args = 0..=1, require_equals = true,
+          help = "在归档文件中搜索（只读）",
+          long_help = "以只读方式在归档文件中搜索，而不是当前数据库。不指定文件时使用默认的归档文件 ~/.config/dlog/archive.db；指定时写作 --from-archive=FILE。")]
+    pub from_archive: Option<Option<String>>,
+}
+
+/// 'import' 命令支持的格式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ImportFormat {
+    /// jrnl 默认的纯文本格式
+    Jrnl,
+    /// 每个文件一条日志的纯文本
+    Plain,
+}
+
+/// 'import' 命令的参数
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    #[arg(required_unless_present = "from_git", conflicts_with = "from_git", value_name = "FILE",
+          help = "要导入的文件或目录",
+          long_help = "要导入的文件。plain 格式下也可以是目录，导入其中的所有文件。")]
+    pub files: Vec<String>,
+
+    #[arg(short, long, value_enum, required_unless_present = "from_git", conflicts_with = "from_git",
+          help = "输入文件的格式",
+          long_help = "输入文件的格式：jrnl 或 plain。")]
+    pub format: Option<ImportFormat>,
+
+    #[arg(long, conflicts_with_all = ["dir", "date_from_filename"],
+          help = "将当前 git 仓库的提交历史导入为日志",
+          long_help = "在 git 仓库中运行，每个提交导入为一条日志：时间为提交时间，内容为提交信息并在末尾记录短哈希，标签为 git，目录为仓库的根目录。已经导入过的提交（按短哈希判断）会被跳过，因此可以重复运行。")]
+    pub from_git: bool,
+
+    #[arg(long, value_name = "DATE", requires = "from_git",
+          help = "只导入该日期之后的提交（--from-git）",
+          long_help = "原样传给 'git log --since'，例如 2023-01-01 或 \"2 weeks ago\"。")]
+    pub since: Option<String>,
+
+    #[arg(long, value_name = "PATTERN", requires = "from_git",
+          help = "只导入指定作者的提交（--from-git）",
+          long_help = "原样传给 'git log --author'，匹配作者的名字或邮箱。")]
+    pub author: Option<String>,
+
+    #[arg(long, requires = "from_git",
+          help = "跳过合并提交（--from-git）")]
+    pub no_merges: bool,
+
+    #[arg(long,
+          help = "从 YYYY-MM-DD.txt 文件名获取日期（plain 格式）",
+          long_help = "plain 格式下从文件名获取日志日期，文件名无法解析为日期的文件会被跳过。")]
+    pub date_from_filename: bool,
+
+    #[arg(long, value_name = "PATH",
+          help = "将导入的日志记录到指定目录",
+          long_help = "将导入的日志关联到指定目录而不是当前工作目录。目录必须已经存在。")]
+    pub dir: Option<String>,
+
+    #[arg(long,
+          help = "只显示会导入的日志，不写入数据库",
+          long_help = "解析输入并列出会导入的日志，但不修改数据库。")]
+    pub dry_run: bool,
+}
+
+/// 'prune' 命令的参数
+#[derive(Args, Debug)]
+pub struct PruneArgs {
+    #[arg(long, value_name = "DURATION",
+          help = "保留期限（如 30d、6m、1y）",
+          long_help = "处理早于指定时长的日志。时长格式为数字加单位：h（小时）、d（天）、w（周）、m（月）、y（年）。")]
+    pub older_than: String,
+
+    #[arg(short, long,
+          help = "包含子目录的日志",
+          long_help = "处理当前目录及其所有子目录中的日志。")]
+    pub recursive: bool,
+
+    #[arg(long, conflicts_with = "recursive",
+          help = "包含所有目录的日志",
+          long_help = "处理数据库中所有目录的日志。")]
+    pub all: bool,
+
+    #[arg(long, value_name = "FILE",
+          help = "将日志移动到归档数据库而不是删除",
+          long_help = "将匹配的日志移动到指定的 SQLite 文件。文件不存在时会被创建，已存在时追加。可以使用 'dlog get --from-archive=FILE' 查看归档。")]
+    pub archive_to: Option<String>,
+
+    #[arg(long,
+          help = "只显示会处理的日志，不做修改",
+          long_help = "统计会被删除或归档的日志，但不修改数据库。")]
+    pub dry_run: bool,
+
+    #[arg(short, long,
+          help = "跳过确认",
+          long_help = "不再交互式确认，直接执行。在脚本或非交互环境中必须使用此选项。")]
+    pub yes: bool,
+}
+
+/// 'retag' 命令的参数
+#[derive(Args, Debug)]
+#[command(group(clap::ArgGroup::new("change").required(true).multiple(true).args(["add_tag", "remove_tag"])))]
+pub struct RetagArgs {
+    #[arg(long, value_name = "TAGS",
+          help = "要添加的标签（逗号分隔）",
+          long_help = "为匹配的日志添加标签，可以用逗号分隔多个。保留日志原有的标签，已存在的标签不会重复添加。")]
+    pub add_tag: Option<String>,
+
+    #[arg(long, value_name = "TAGS",
+          help = "要移除的标签（逗号分隔）",
+          long_help = "从匹配的日志中移除标签，可以用逗号分隔多个。只移除完全相同的标签。")]
+    pub remove_tag: Option<String>,
+
+    #[arg(short, long,
+          help = "只处理包含指定关键词的日志",
+          long_help = "与 'dlog get -s' 相同：多个词需要同时出现在内容或标签中，双引号包围的短语按字面匹配。")]
+    pub search: Option<String>,
+
+    #[arg(short, long,
+          help = "只处理包含指定标签的日志")]
+    pub tag: Option<String>,
+
+    #[arg(long,
+          help = "只处理指定日期的日志",
+          long_help = "与 'dlog get --date' 相同，支持 YYYY-MM-DD、YYYY-MM、today、yesterday 和星期名称。")]
+    pub date: Option<String>,
+
+    #[arg(long, value_name = "DIR",
+          help = "代替当前目录，处理指定目录中的日志")]
+    pub path: Option<String>,
+
+    #[arg(short, long,
+          help = "包含子目录的日志")]
+    pub recursive: bool,
+
+    #[arg(long, conflicts_with_all = ["recursive", "path"],
+          help = "包含所有目录的日志")]
+    pub all: bool,
+
+    #[arg(long,
+          help = "只列出会被修改的日志，不做修改")]
+    pub dry_run: bool,
+
+    #[arg(short, long,
+          help = "跳过确认",
+          long_help = "不再交互式确认，直接修改。在脚本或非交互环境中必须使用此选项。")]
+    pub yes: bool,
+}
+
+/// 'del' 命令的参数
+#[derive(Args, Debug)]
+pub struct DelArgs {
+    /// 要删除的日志ID列表
+    #[arg(conflicts_with_all = ["recursive", "older_than", "tag", "date", "before", "search"],
+          value_name = "ID_LIST",
+          help = "要删除的日志ID列表",
+          long_help = r#"要删除的日志ID，支持多种格式：
   • 单个ID: 5
   • 逗号分隔: 3,5,8  
   • 范围: 7-9（删除7、8、9）
   • 混合: 3,7-9,12（删除3、7、8、9、12）"#)]
-        ids: Option<String>,
+    pub ids: Option<String>,
 
-        /// 递归删除当前目录及子目录的所有日志
-        #[arg(short, long, 
-              help = "递归删除当前目录及子目录的所有日志",
-              long_help = "删除当前工作目录及其所有子目录中的所有日志条目。此操作不可逆，请谨慎使用。")]
-        recursive: bool,
-    },
+    /// 递归删除当前目录及子目录的所有日志
+    #[arg(short, long, 
+          help = "递归删除当前目录及子目录的所有日志",
+          long_help = "删除当前工作目录及其所有子目录中的所有日志条目，可以用 --tag、--date、--before、--search 和 --older-than 缩小范围。此操作不可逆，请谨慎使用。")]
+    pub recursive: bool,
+
+    #[arg(long, value_name = "DURATION",
+          help = "删除早于指定时长的日志（如 30d、6m、1y）",
+          long_help = "删除当前目录中早于指定时长的日志，与 -r 一起使用时包含子目录。时长格式为数字加单位：h（小时）、d（天）、w（周）、m（月）、y（年）。")]
+    pub older_than: Option<String>,
+
+    #[arg(short, long,
+          help = "只删除包含指定标签的日志",
+          long_help = "与 'dlog get -t' 相同，只删除包含指定标签的日志。不加 -r 时只作用于当前目录。")]
+    pub tag: Option<String>,
+
+    #[arg(long,
+          help = "只删除指定日期的日志",
+          long_help = "与 'dlog get --date' 相同，支持 YYYY-MM-DD、YYYY-MM、today、yesterday 和星期名称。")]
+    pub date: Option<String>,
+
+    #[arg(long, value_name = "DATE",
+          help = "只删除指定日期之前的日志",
+          long_help = "只删除在指定日期（本地时间）零点之前记录的日志，不包含当天。日期格式与 --date 相同，YYYY-MM 表示该月的第一天。")]
+    pub before: Option<String>,
+
+    #[arg(short, long,
+          help = "只删除包含指定关键词的日志",
+          long_help = "与 'dlog get -s' 相同：多个词需要同时出现在内容或标签中，双引号包围的短语按字面匹配。")]
+    pub search: Option<String>,
+
+    #[arg(long, value_name = "DIR", conflicts_with = "ids",
+          help = "代替当前目录，删除指定目录中的日志",
+          long_help = "与 -r 或其他过滤条件一起使用，删除指定目录（而不是当前目录）中的日志。目录已经不存在时按记录时保存的路径匹配，便于清理已删除项目留下的日志。")]
+    pub path: Option<String>,
+
+    #[arg(long,
+          help = "同时删除锁定的日志",
+          long_help = "默认跳过使用 'dlog lock' 锁定的日志并提示跳过的条数；使用此选项时锁定的日志也会被列出和删除。")]
+    pub include_locked: bool,
+
+    #[arg(long,
+          help = "只列出会被删除的日志，不做修改")]
+    pub dry_run: bool,
+
+    #[arg(short, long,
+          help = "跳过删除确认",
+          long_help = "不再交互式确认，直接执行删除。在脚本或非交互环境中必须使用此选项。")]
+    pub yes: bool,
+}
+
+/// 'archive' 命令的参数
+#[derive(Args, Debug)]
+pub struct ArchiveArgs {
+    #[arg(long, value_name = "DURATION", required_unless_present = "restore", conflicts_with = "restore",
+          help = "归档早于指定时长的日志（如 6m、1y、2y）",
+          long_help = "将早于指定时长的日志移动到归档文件。时长格式为数字加单位：h（小时）、d（天）、w（周）、m（月）、y（年）。")]
+    pub older_than: Option<String>,
+
+    #[arg(long, value_name = "FILE",
+          help = "归档文件，默认为 ~/.config/dlog/archive.db")]
+    pub to: Option<String>,
+
+    #[arg(short, long,
+          help = "包含子目录的日志")]
+    pub recursive: bool,
+
+    #[arg(long, conflicts_with = "recursive",
+          help = "包含所有目录的日志")]
+    pub all: bool,
+
+    #[arg(long, requires = "ids",
+          help = "将日志从归档文件移回数据库",
+          long_help = "将 --ids 指定的归档日志移回当前数据库，移回的日志获得新的ID。ID 是归档中的ID，可以通过 'dlog get --from-archive' 查看。")]
+    pub restore: bool,
+
+    #[arg(long, value_name = "ID_LIST", requires = "restore",
+          help = "要移回的归档日志ID（如 3,7-9）")]
+    pub ids: Option<String>,
+
+    #[arg(long, conflicts_with = "restore",
+          help = "只显示会归档的日志，不做修改")]
+    pub dry_run: bool,
+
+    #[arg(short, long,
+          help = "跳过确认",
+          long_help = "不再交互式确认，直接执行。在脚本或非交互环境中必须使用此选项。")]
+    pub yes: bool,
+}
+
+/// 'get' 命令的参数
+#[derive(Args, Debug)]
+pub struct GetArgs {
+    /// 要搜索的目录路径，默认为当前目录
+    #[arg(help = "目标目录路径（相对或绝对路径）",
+          long_help = "指定要搜索日志的目录。可以是相对路径（./project）或绝对路径（/home/user/project）。")]
+    pub path: Option<String>,
+
+    #[arg(short, long, 
+          help = "显示最新的N条日志",
+          long_help = "限制显示的日志数量。默认显示10条，使用0显示所有匹配的日志。")]
+    pub num: Option<u32>,
+
+    #[arg(short, long, 
+          help = "递归搜索子目录",
+          long_help = "在指定目录及其所有子目录中搜索日志。搜索结果会显示每条日志的完整路径。")]
+    pub recursive: bool,
+
+    #[arg(long, value_name = "N", requires = "recursive",
+          help = "递归时最多包含N层子目录",
+          long_help = "配合 -r 使用，限制递归的深度：1 表示当前目录及其直接子目录，0 等同于不递归。")]
+    pub depth: Option<u32>,
+
+    #[arg(long, value_name = "K", requires = "recursive",
+          value_parser = clap::value_parser!(u32).range(1..),
+          help = "递归时每个目录最多显示K条",
+          long_help = "配合 -r 使用，每个目录只取最新的K条日志，再按 -n 限制总条数，避免日志很多的目录占满结果。日志不足K条的目录显示全部。")]
+    pub limit_per_dir: Option<u32>,
+
+    #[arg(short = 'p', long, conflicts_with_all = ["path", "all"],
+          help = "查询当前项目的所有日志",
+          long_help = "从当前目录向上查找最近的项目根目录（包含 .git、Cargo.toml 或 package.json 的目录），查询其中及所有子目录的日志，相当于 'dlog get -r 项目根目录'。标记文件名可以通过配置项 project_markers 设置，多个文件名以逗号分隔，例如 .git,go.mod。找不到项目根目录时使用当前目录并给出提示。")]
+    pub project: bool,
+
+    #[arg(long, conflicts_with_all = ["recursive", "path"],
+          help = "搜索所有目录的日志",
+          long_help = "忽略目录范围，在整个数据库中查询，并显示每条日志所在的目录。可以与其他过滤条件一起使用。")]
+    pub all: bool,
+
+    #[arg(short, long, 
+          help = "按标签过滤日志",
+          long_help = "只显示包含指定标签的日志。支持部分匹配，例如'test'会匹配'test'、'integration-test'等。")]
+    pub tag: Option<String>,
+
+    #[arg(long, 
+          help = "按日期过滤日志（YYYY-MM-DD、YYYY-MM、today、yesterday 或星期名）",
+          long_help = "只显示指定日期（本地时间）的日志。支持以下形式：\n  • 年-月-日，例如 2024-01-15\n  • 年-月，表示整个月，例如 2024-02\n  • today、yesterday\n  • 星期名，例如 monday 或 mon，表示最近的那一天（含今天）")]
+    pub date: Option<String>,
+
+    #[arg(long, value_name = "YYYY-MM", conflicts_with = "date",
+          help = "按月份过滤日志（格式：YYYY-MM 或 MM）",
+          long_help = "只显示指定月份（本地时间）的日志，例如 2024-05。只写月份（如 05）时表示今年的该月。可以与 -t、-s 等过滤条件组合。")]
+    pub month: Option<String>,
+
+    #[arg(long, value_name = "DURATION",
+          help = "只显示最近一段时间内的日志（如 3h、3d、2w）",
+          long_help = "只显示在指定时长内记录的日志，按完整时间戳比较。时长格式为数字加单位：h（小时）、d（天）、w（周）、m（月）、y（年）。")]
+    pub since: Option<String>,
+
+    #[arg(long, value_name = "HH:MM-HH:MM",
+          help = "只显示一天中某个时间段内的日志（如 09:00-12:00）",
+          long_help = "只显示记录时间（本地时间）落在该时间段内的日志，包含开始时刻、不包含结束时刻。开始晚于结束时表示跨越午夜，例如 22:00-02:00。可以与 --date、--month、--since 和 -t 组合。")]
+    pub between: Option<String>,
+
+    #[arg(short, long, 
+          help = "在内容和标签中搜索关键词",
+          long_help = "在日志内容和标签中搜索包含所有关键词的条目。多个关键词以空格分隔，每个都必须出现（顺序不限）；用双引号包围的部分作为一个短语按原样匹配，例如 -s '\"connection refused\" docker'。默认不区分大小写（包括 Ä、ẞ 等非 ASCII 字母），使用 --case-sensitive 按大小写精确匹配。")]
+    pub search: Option<String>,
+
+    #[arg(long, value_name = "KEYWORD", action = ArgAction::Append,
+          help = "排除内容或标签包含该关键词的日志",
+          long_help = "不显示内容或标签中包含该关键词的日志，例如排除日常的站会记录：--exclude standup。可以多次指定，包含其中任意一个的日志都会被排除。大小写规则与 -s 相同，可以与 -s、-t 和日期过滤组合。")]
+    pub exclude: Vec<String>,
+
+    #[arg(long,
+          help = "-s 和 --exclude 区分大小写",
+          long_help = "按大小写精确匹配 -s 和 --exclude 的关键词。默认情况下两者都不区分大小写，包括非 ASCII 字母。")]
+    pub case_sensitive: bool,
+
+    #[arg(long, value_name = "PATTERN",
+          help = "只显示目录匹配 glob 模式的日志",
+          long_help = "按日志所在的绝对路径匹配 glob 模式，例如 '*/backend/*'。* 可以匹配任意字符（包括 /），? 匹配单个字符，[...] 匹配字符集合。单独使用时搜索所有目录；与 -r 或目录参数一起使用时只在该目录树中搜索。")]
+    pub path_glob: Option<String>,
+
+    #[arg(long, value_name = "HOST",
+          help = "只显示在指定主机上记录的日志（. 表示本机）",
+          long_help = "只显示在指定主机上记录的日志，主机名不区分大小写，'.' 表示当前主机。没有记录主机名的较早日志不会显示。使用此选项时输出中会显示每条日志的用户和主机。")]
+    pub host: Option<String>,
+
+    #[arg(long, value_name = "FILE", num_args = 0..=1, require_equals = true, conflicts_with = "watch",
+          help = "查询归档文件中的日志（只读）",
+          long_help = "以只读方式查询 'dlog archive' 归档的日志，而不是当前数据库。不指定文件时使用默认的归档文件 ~/.config/dlog/archive.db；指定时写作 --from-archive=FILE。其他过滤条件照常使用。")]
+    pub from_archive: Option<Option<String>>,
+
+    #[arg(long, value_name = "LEVEL", conflicts_with = "min_level",
+          help = "只显示指定级别的日志（low、normal、high）")]
+    pub level: Option<String>,
+
+    #[arg(long, value_name = "LEVEL",
+          help = "只显示不低于指定级别的日志",
+          long_help = "只显示级别不低于指定值的日志，级别从低到高为 low、normal、high。例如 --min-level normal 隐藏 low 级别的日志。")]
+    pub min_level: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = SortKey::Time,
+          help = "结果的排列方式",
+          long_help = "time 按时间从新到旧排列（默认）；level 先按级别从高到低、再按时间排列，便于把重要的日志放在最前面。--sort level 不能与 --before-id 或 --watch 一起使用。")]
+    pub sort: SortKey,
+
+    #[arg(long, value_name = "ID",
+          help = "只显示与指定日志相关的日志",
+          long_help = "显示指定日志以及通过 'dlog link' 与它直接或间接关联的所有日志。单独使用时搜索所有目录；与 -r 或目录参数一起使用时只在该目录树中搜索。")]
+    pub linked: Option<i32>,
+
+    #[arg(short, long,
+          help = "显示日志的所有字段",
+          long_help = "在每条日志的内容之前逐行列出所有字段：ID、时间（本地时间和数据库中存储的原始值）、完整的目录、标签、级别、待办状态、完成时间、主机名和用户名，没有值的字段显示为 (none)，便于排查过滤条件为什么没有匹配某条日志。与 --oneline 或 --titles-only 一起使用时只附加记录日志的用户和主机。较早的日志没有记录主机和用户；在配置文件中设置 record_host = false（或 DLOG_RECORD_HOST=0）可以在记录时不保存这些信息。")]
+    pub verbose: bool,
+
+    #[arg(long,
+          help = "每条日志只显示一行",
+          long_help = "紧凑显示：每条日志一行，包含ID、日期、内容首行（过长时截断）和标签。递归查询时附加日志所在目录。")]
+    pub oneline: bool,
+
+    #[arg(long, conflicts_with = "oneline",
+          help = "只显示每条日志的标题",
+          long_help = "每条日志一行，包含ID、日期、标签和标题。标题是内容中第一行非空白的文本，Markdown 标题会去掉开头的 #；与 --oneline 不同，标题不会被截断。递归查询时附加日志所在目录。")]
+    pub titles_only: bool,
+
+    #[arg(long, conflicts_with_all = ["oneline", "titles_only", "full", "verbose", "watch"],
+          help = "只输出匹配日志的ID",
+          long_help = "每行输出一个匹配日志的ID，不输出其他任何内容（包括 \"No logs found.\" 和翻页提示），也不使用颜色，便于在管道中传给 del、tag 等命令。仍然应用所有过滤条件和 -n 的数量限制；没有匹配时以退出码 3 结束。")]
+    pub ids_only: bool,
+
+    #[arg(long, value_name = "ID_LIST",
+          help = "只显示指定ID的日志",
+          long_help = "只显示指定ID的日志，格式与 'dlog del' 相同，如 42、3,5,8、7-9。单独使用时在所有目录中查找，与 -r 或目录参数一起使用时只在该目录树中查找。未指定 -n 时显示所有指定的日志。")]
+    pub ids: Option<String>,
+
+    #[arg(long, value_enum, value_name = "FIELD",
+          conflicts_with_all = ["oneline", "titles_only", "ids_only", "full", "verbose", "watch"],
+          help = "只输出每条日志的一个字段，不加任何修饰",
+          long_help = "每条日志只输出指定字段的原始值：id、timestamp（数据库中存储的 RFC3339 时间）、directory、tags 或 content，没有标题、颜色、分页和提示信息，便于在脚本中使用，例如 dlog get --ids 42 --field content | pbcopy。每条记录之后输出一个换行符（使用 --print0 时为 NUL 字符）；字段为空（例如没有标签）时输出空记录。没有匹配时以退出码 3 结束。")]
+    pub field: Option<GetField>,
+
+    #[arg(long, requires = "field",
+          help = "以 NUL 字符结束每条记录",
+          long_help = "配合 --field 使用，每条记录之后输出 NUL 字符而不是换行符，多行内容也能被 xargs -0 等工具正确分隔。")]
+    pub print0: bool,
+
+    #[arg(long, conflicts_with = "oneline",
+          help = "显示日志的完整内容",
+          long_help = "默认情况下，超过6行的日志会被截断并提示剩余行数。使用此选项显示每条日志的全部内容。")]
+    pub full: bool,
+
+    #[arg(long, value_name = "ID",
+          help = "只显示比指定日志更早的日志",
+          long_help = "游标分页：只显示排在指定ID的日志之后（更早）的条目。上一页输出的末尾会提示下一页应使用的ID。")]
+    pub before_id: Option<i32>,
+
+    #[arg(long, value_name = "K", conflicts_with = "before_id",
+          value_parser = clap::value_parser!(u32).range(1..),
+          help = "显示第K页的日志",
+          long_help = "按 -n 指定的每页条数分页，显示第K页（从1开始）。")]
+    pub page: Option<u32>,
+
+    #[arg(short, long, conflicts_with_all = ["page", "before_id"],
+          help = "持续显示新记录的日志",
+          long_help = "显示当前结果后继续运行，定期检查数据库并按相同的过滤条件输出新记录的日志，直到按 Ctrl-C 退出。此时不使用分页器。")]
+    pub watch: bool,
+
+    #[arg(long, value_name = "SECS", default_value_t = 2, requires = "watch",
+          value_parser = clap::value_parser!(u64).range(1..),
+          help = "--watch 检查新日志的间隔秒数",
+          long_help = "使用 --watch 时每隔多少秒检查一次新日志，默认2秒。")]
+    pub interval: u64,
+
+    #[arg(short, long, value_name = "FILE", conflicts_with = "watch",
+          help = "将结果写入文件",
+          long_help = "将结果写入指定文件而不是显示在终端上，不使用颜色和分页器，也不包含翻页和待办提示；目录显示为完整路径而不缩写为 ~。上级目录不存在时自动创建；文件已存在时需要 --force 才会覆盖。使用 - 表示写到标准输出。")]
+    pub output: Option<String>,
+
+    #[arg(long, requires = "output",
+          help = "允许覆盖 --output 指定的已有文件")]
+    pub force: bool,
 }
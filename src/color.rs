@@ -0,0 +1,50 @@
+// src/color.rs
+
+use crate::cli::ColorChoice;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// 通过 --color 指定的颜色设置，只在进程启动时设置一次
+static CHOICE: OnceLock<ColorChoice> = OnceLock::new();
+/// 输出写入文件时关闭颜色，优先于 --color
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// 设置本次运行的颜色输出方式
+pub fn set_choice(choice: ColorChoice) {
+    let _ = CHOICE.set(choice);
+}
+
+/// 本次运行不再输出颜色，即使指定了 --color always
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// 是否输出颜色：auto 时只在标准输出是终端且未设置 NO_COLOR 时输出
+pub fn enabled() -> bool {
+    if DISABLED.load(Ordering::Relaxed) {
+        return false;
+    }
+    match CHOICE.get().copied().unwrap_or(ColorChoice::Auto) {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        }
+    }
+}
+
+/// 是否可以使用 24 位真彩色，需要终端通过 COLORTERM 声明支持
+pub fn truecolor() -> bool {
+    enabled() && std::env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit")
+}
+
+/// 以醒目的红色粗体显示文本
+pub fn alert(text: &str) -> String {
+    format!("\x1b[1;31m{}\x1b[0m", text)
+}
+
+/// 以暗淡样式显示文本
+pub fn dim(text: &str) -> String {
+    format!("\x1b[2m{}\x1b[0m", text)
+}
@@ -1,154 +1,3913 @@
 // src/commands.rs
 
-use crate::db;
-use crate::error::{DlogError, Result};
-use chrono::{DateTime, NaiveDate, Utc};
-use std::collections::BTreeSet;
+use crate::cli::{
+    ArchiveArgs, Cli, CompleteKind, DelArgs, ExportArgs, GetArgs, GetField, ImportArgs, ImportFormat, LogArgs, PruneArgs,
+    ReportArgs, RetagArgs, SearchArgs, SortKey,
+};
+use crate::capture;
+use crate::color;
+use crate::complete;
+use crate::highlight;
+use crate::hook;
+use crate::pager;
+use crate::recovery;
+use crate::serve;
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc,
+    Weekday,
+};
+use clap::CommandFactory;
+use clap_complete::Shell;
+use dlog::db::{self, Scope};
+use dlog::import::{self, ImportedEntry};
+use dlog::models::{content_title, title_of, Level, LogEntry, TODO_DONE, TODO_OPEN};
+use dlog::sync::{self, SyncRecord};
+use dlog::{config, dir_config, json, search, tags, words, DlogError, Result};
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
-use std::io::{self, Read, Write};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// 通过 --quiet 关闭提示性的输出
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// 本次运行只输出结果和错误，不输出成功提示等信息
+pub fn set_quiet() {
+    QUIET.store(true, Ordering::Relaxed);
+}
+
+fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// 命令的执行结果，用于决定退出码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// 执行成功；查询类命令表示至少有一条结果
+    Success,
+    /// 执行成功但没有匹配的结果
+    NoMatches,
+    /// 日志已经记录，但 'log --cmd' 执行的命令以非零的退出码结束
+    CommandFailed(i32),
+}
+
+/// 向用户请求确认，非交互环境下除非指定 --yes 否则返回错误
+fn confirm(prompt: &str, yes: bool) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    if !io::stdin().is_terminal() {
+        return Err(DlogError::NonInteractive(
+            "confirmation required, pass --yes to proceed".to_string(),
+        ));
+    }
+    print!("{} (y/N): ", prompt);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
+/// 输出提示并读取一行输入，返回去掉两端空白的内容；输入结束时返回空字符串
+fn prompt_line(prompt: &str) -> Result<String> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
 
 /// 处理 'init' 命令
-pub fn handle_init() -> Result<()> {
+pub fn handle_init(
+    yes: bool,
+    orphans: OrphanAction,
+    remaps: Vec<String>,
+    compact: bool,
+    db_path: Option<String>,
+) -> Result<()> {
+    // 先检查所有映射，避免只执行了一部分
+    let remaps = remaps
+        .iter()
+        .map(|spec| parse_remap(spec))
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(path) = &db_path {
+        let path = PathBuf::from(db::normalize_path(&expand_path(path)?)?);
+        if path.is_dir() {
+            return Err(DlogError::InvalidInput(format!(
+                "{} is a directory, expected a database file path",
+                display_path(&path)
+            )));
+        }
+        if path.exists() && !db::is_dlog_database(&path)? {
+            return Err(DlogError::InvalidInput(format!(
+                "{} exists but is not a dlog database",
+                display_path(&path)
+            )));
+        }
+        db::set_db_pointer(&path)?;
+        println!(
+            "✓ Database location set to: {} (recorded in {})",
+            display_path(&path),
+            display_path(db::db_pointer_path()?)
+        );
+    }
+
+    if let Some((legacy, target)) = db::pending_db_move()? {
+        println!("Found a database at the old location: {}", display_path(&legacy));
+        println!("The new default location is: {}", display_path(&target));
+        // 非交互环境下不迁移，继续使用旧位置，避免 init 因此失败
+        if !yes && !io::stdin().is_terminal() {
+            println!("Keeping the old location. Run 'dlog init' in a terminal to move it.");
+        } else if confirm("Move the database to the new location?", yes)? {
+            db::move_db_file(&legacy, &target)?;
+            println!("✓ Moved {} -> {}", display_path(&legacy), display_path(&target));
+        } else {
+            println!("Keeping the old location.");
+        }
+    }
+
+    let already_initialized = db::is_initialized()?;
     db::initialize_db()?;
-    println!("✓ Database initialized successfully at: {:?}", db::get_db_path()?);
 
     // 检查并同步目录
     let conn = db::open_connection()?;
-    let dirs_in_db = db::get_distinct_directories(&conn)?;
-    let mut deleted_dirs = Vec::new();
+    if already_initialized {
+        println!(
+            "✓ Database already initialized at: {:?}, {} logs present.",
+            db::get_db_path()?,
+            db::count_logs(&conn)?
+        );
+    } else {
+        println!("✓ Database initialized successfully at: {:?}", db::get_db_path()?);
+    }
+    for (old, new) in &remaps {
+        let count = db::remap_directory(&conn, old, new)?;
+        println!("✓ {} -> {} ({} logs updated)", display_path(old), display_path(new), count);
+    }
+
+    if orphans == OrphanAction::Keep {
+        println!("Skipped the check for vanished directories.");
+    } else {
+        sync_vanished_directories(&conn, orphans)?;
+    }
+
+    if compact {
+        handle_compact()?;
+    }
+
+    Ok(())
+}
+
+/// 'init' 如何处理指向已不存在目录的日志
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanAction {
+    /// 在终端中询问；标准输入不是终端时保留
+    Prompt,
+    /// 不经确认直接删除
+    Prune,
+    /// 列出这些目录但不询问，保留日志
+    List,
+    /// 不检查
+    Keep,
+}
+
+/// 查找数据库中记录但在磁盘上已不存在的目录，返回 (目录, 日志条数)
+fn find_vanished_directories(conn: &rusqlite::Connection) -> Result<Vec<(String, i64)>> {
+    Ok(db::count_logs_by_directory(conn)?
+        .into_iter()
+        .filter(|(dir, _)| !Path::new(dir).exists())
+        .collect())
+}
+
+/// 解析 `--remap OLD=NEW`，返回规范化后的 (旧目录, 新目录)；新目录必须存在
+fn parse_remap(spec: &str) -> Result<(String, String)> {
+    let invalid = || DlogError::InvalidInput(format!("Invalid remap '{}', expected OLD=NEW", spec));
+    let (old, new) = spec.split_once('=').ok_or_else(invalid)?;
+    if old.trim().is_empty() || new.trim().is_empty() {
+        return Err(invalid());
+    }
+    let new_path = expand_path(new.trim())?;
+    if !new_path.is_dir() {
+        return Err(DlogError::InvalidInput(format!(
+            "{} does not exist or is not a directory",
+            display_path(&new_path)
+        )));
+    }
+    // 旧目录已经不存在，只做字符串上的规范化
+    let old = db::normalize_path(&expand_path(old.trim())?)?;
+    Ok((old, db::normalize_path(&new_path)?))
+}
+
+/// 逐个询问已不存在目录的新位置，将日志（包括子目录中的）改为指向新位置
+fn prompt_remaps(conn: &rusqlite::Connection, vanished: &[(String, i64)]) -> Result<()> {
+    println!("If a directory was moved, enter its new location; leave it empty to skip.");
+    let sep = std::path::MAIN_SEPARATOR;
+    let mut remapped: Vec<&str> = Vec::new();
+    for (dir, _) in vanished {
+        // 上级目录已经映射过，其中的日志已一起更新
+        if remapped.iter().any(|old| dir.starts_with(&format!("{}{}", old.trim_end_matches(sep), sep))) {
+            continue;
+        }
+        loop {
+            let input = prompt_line(&format!("  {} -> ", display_path(dir)))?;
+            if input.is_empty() {
+                break;
+            }
+            let path = match expand_path(&input) {
+                Ok(path) => path,
+                Err(e) => {
+                    println!("  {}", e);
+                    continue;
+                }
+            };
+            if !path.is_dir() {
+                println!("  {} does not exist or is not a directory.", display_path(&path));
+                continue;
+            }
+            let new = db::normalize_path(&path)?;
+            let count = db::remap_directory(conn, dir, &new)?;
+            println!("  ✓ {} log(s) now point to {}", count, display_path(&new));
+            remapped.push(dir);
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// 列出已不存在的目录，并按 `action` 删除或保留其中的日志；在终端中询问时先提供重新映射
+fn sync_vanished_directories(conn: &rusqlite::Connection, action: OrphanAction) -> Result<()> {
+    let mut vanished = find_vanished_directories(conn)?;
+    if vanished.is_empty() {
+        println!("✓ All log directories are in sync with the filesystem.");
+        return Ok(());
+    }
+
+    println!("\nWarning: The following directories with logs no longer exist:");
+    for (dir, count) in &vanished {
+        println!("- {} ({} logs)", display_path(dir), count);
+    }
+    if action == OrphanAction::Prompt && io::stdin().is_terminal() {
+        prompt_remaps(conn, &vanished)?;
+        vanished = find_vanished_directories(conn)?;
+        if vanished.is_empty() {
+            println!("✓ All log directories are in sync with the filesystem.");
+            return Ok(());
+        }
+    }
+    let prune = match action {
+        OrphanAction::Prune => true,
+        OrphanAction::List => {
+            println!("Keeping these logs. Run 'dlog init --prune' to delete them.");
+            false
+        }
+        // 在脚本或安装程序中运行时不阻塞，保留日志
+        _ if !io::stdin().is_terminal() => {
+            println!("Not running in a terminal, keeping these logs. Run 'dlog init --prune' to delete them.");
+            false
+        }
+        _ => confirm(
+            &format!("Permanently delete the logs from the {} remaining directories?", vanished.len()),
+            false,
+        )?,
+    };
+
+    if prune {
+        let dirs: Vec<String> = vanished.iter().map(|(dir, _)| dir.clone()).collect();
+        let deletion = db::delete_logs_by_directory(conn, &dirs)?;
+        // 目录中剩下的是锁定的日志
+        let kept: BTreeMap<String, i64> = find_vanished_directories(conn)?.into_iter().collect();
+        for (dir, count) in &vanished {
+            let locked = kept.get(dir).copied().unwrap_or(0);
+            println!("  removed {:>5}  {}", count - locked, display_path(dir));
+            if locked > 0 {
+                println!("  kept    {:>5}  {} (locked)", locked, display_path(dir));
+            }
+        }
+        println!("✓ Deleted {} log entries from vanished directories.", deletion.deleted);
+        report_locked_skipped(&deletion.locked);
+    } else {
+        for (dir, count) in &vanished {
+            println!("  kept    {:>5}  {}", count, display_path(dir));
+        }
+        println!("No logs were deleted.");
+    }
+    Ok(())
+}
+
+/// 处理 'doctor' 命令
+pub fn handle_doctor(fix: bool, fix_timestamps: bool, yes: bool) -> Result<()> {
+    let conn = db::open_connection()?;
+    let mut unresolved = 0;
 
-    for dir_str in &dirs_in_db {
-        if !Path::new(dir_str).exists() {
-            deleted_dirs.push(dir_str.clone());
+    println!("== Integrity check ==");
+    let integrity = db::integrity_check(&conn)?;
+    if integrity.is_empty() {
+        println!("✓ ok");
+    } else {
+        for message in &integrity {
+            println!("✗ {}", message);
         }
+        unresolved += integrity.len();
     }
 
-    if !deleted_dirs.is_empty() {
-        println!("\nWarning: The following directories with logs no longer exist:");
-        for dir in &deleted_dirs {
-            println!("- {}", dir);
+    println!("\n== Directory paths ==");
+    let mut bad_paths = 0;
+    for (dir, count) in db::count_logs_by_directory(&conn)? {
+        if Path::new(&dir).is_relative() {
+            // 相对路径无法确定原始的基准目录，只能报告
+            println!("✗ {} ({} logs): relative path, cannot be repaired automatically", dir, count);
+            unresolved += count as usize;
+            bad_paths += count;
+            continue;
         }
-        print!("Do you want to permanently delete all logs from these directories? (y/N): ");
-        io::stdout().flush()?;
+        let normalized = db::normalize_path(Path::new(&dir))?;
+        if normalized == dir {
+            continue;
+        }
+        bad_paths += count;
+        if fix {
+            let updated = db::rename_directory(&conn, &dir, &normalized)?;
+            println!(
+                "✓ {} -> {} ({} logs fixed)",
+                display_path(&dir),
+                display_path(&normalized),
+                updated
+            );
+        } else {
+            println!(
+                "✗ {} ({} logs): not normalized, expected {}",
+                display_path(&dir),
+                count,
+                display_path(&normalized)
+            );
+            unresolved += count as usize;
+        }
+    }
+    if bad_paths == 0 {
+        println!("✓ All directory paths are normalized.");
+    }
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
+    println!("\n== Missing directories ==");
+    let vanished = find_vanished_directories(&conn)?;
+    if vanished.is_empty() {
+        println!("✓ All log directories exist.");
+    } else {
+        for (dir, count) in &vanished {
+            println!("✗ {} ({} logs)", display_path(dir), count);
+        }
+        println!("  Run 'dlog init --prune' to delete logs from vanished directories.");
+        unresolved += vanished.len();
+    }
 
-        if input.trim().eq_ignore_ascii_case("y") {
-            let count = db::delete_logs_by_directory(&conn, &deleted_dirs)?;
-            println!("✓ Deleted {} log entries from vanished directories.", count);
+    println!("\n== Empty entries ==");
+    let empty_ids = db::find_empty_logs(&conn)?;
+    if empty_ids.is_empty() {
+        println!("✓ No empty entries.");
+    } else {
+        println!("✗ {} empty log(s): {:?}", empty_ids.len(), empty_ids);
+        if fix && confirm("Delete these empty logs?", yes)? {
+            let deletion = db::delete_logs_by_id(&conn, &empty_ids, false)?;
+            report_locked_skipped(&deletion.locked);
+            println!("✓ Deleted {} empty log(s).", deletion.deleted);
         } else {
-            println!("Cancelled. No logs were deleted.");
+            unresolved += empty_ids.len();
         }
+    }
+
+    println!("\n== Duplicate entries ==");
+    let duplicates = db::find_duplicate_logs(&conn)?;
+    if duplicates.is_empty() {
+        println!("✓ No duplicate entries.");
     } else {
-        println!("✓ All log directories are in sync with the filesystem.");
+        for group in &duplicates {
+            println!("✗ Identical logs: {:?}", group);
+        }
+        println!("  Review these and remove the extras with 'dlog del'.");
+        unresolved += duplicates.len();
+    }
+
+    println!("\n== Timestamps ==");
+    let invalid = db::find_invalid_timestamps(&conn)?;
+    if invalid.is_empty() {
+        println!("✓ All timestamps are valid.");
+    } else if fix_timestamps {
+        let mut updates = Vec::new();
+        for (id, raw) in &invalid {
+            match db::reparse_timestamp(raw) {
+                Some(timestamp) => {
+                    println!("✓ #{}: {:?} -> {}", id, raw, db::canonical_timestamp(timestamp));
+                    updates.push((*id, timestamp));
+                }
+                None => {
+                    println!("✗ #{}: {:?} is not a recognized format", id, raw);
+                    unresolved += 1;
+                }
+            }
+        }
+        let count = db::update_log_timestamps(&conn, &updates)?;
+        println!("✓ Rewrote {} timestamp(s).", count);
+    } else {
+        for (id, raw) in &invalid {
+            // 能够解析但格式不统一的时间戳会导致排序错误
+            let kind = if local_time(raw).is_some() { "non-canonical" } else { "invalid" };
+            println!("✗ #{}: {} timestamp {:?}", id, kind, raw);
+        }
+        println!("  Run 'dlog doctor --fix-timestamps' to rewrite the ones in a known format.");
+        unresolved += invalid.len();
+    }
+
+    println!();
+    if unresolved > 0 {
+        if !fix && !fix_timestamps {
+            println!("Run 'dlog doctor --fix' to repair what can be fixed automatically.");
+        }
+        return Err(DlogError::ProblemsFound(unresolved));
+    }
+    println!("✓ No problems found.");
+    Ok(())
+}
+
+/// 处理 'compact' 命令
+pub fn handle_compact() -> Result<()> {
+    let db_path = db::get_db_path()?;
+    let conn = db::open_connection()?;
+
+    let size_before = std::fs::metadata(&db_path)?.len();
+    db::compact(&conn)?;
+    let size_after = std::fs::metadata(&db_path)?.len();
+
+    println!(
+        "✓ Database compacted: {} -> {}",
+        format_size(size_before),
+        format_size(size_after)
+    );
+    Ok(())
+}
+
+/// 处理 'renumber' 命令
+pub fn handle_renumber(map_file: Option<String>, dry_run: bool, yes: bool) -> Result<()> {
+    let db_path = db::get_db_path()?;
+    let conn = db::open_connection()?;
+    let plan = db::renumber_plan(&conn)?;
+    if plan.is_empty() {
+        println!("Log IDs are already contiguous and in time order.");
+        return Ok(());
+    }
+
+    let total = db::count_logs(&conn)?;
+    println!(
+        "{} of {} log(s) will get a new ID (highest ID {} -> {}).",
+        plan.len(),
+        total,
+        db::max_log_id(&conn)?,
+        total
+    );
+    if dry_run {
+        for (old, new) in &plan {
+            println!("  {} -> {}", old, new);
+        }
+        println!("Dry run: no changes made.");
+        return Ok(());
+    }
+
+    println!("IDs saved elsewhere (scripts, notes, commit messages) will no longer match.");
+    if !yes {
+        if !io::stdin().is_terminal() {
+            return Err(DlogError::NonInteractive(
+                "confirmation required, pass --yes to proceed".to_string(),
+            ));
+        }
+        if prompt_line("Type 'renumber' to continue: ")? != "renumber" {
+            println!("Cancelled.");
+            return Ok(());
+        }
+    }
+
+    let stem = db_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let backup = db_path.with_file_name(format!(
+        "{}.before-renumber-{}.db",
+        stem,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    db::backup_to(&conn, &backup)?;
+    println!("✓ Backup saved to: {}", display_path(&backup));
+
+    let mapping = db::renumber_logs(&conn)?;
+    let mut table = String::new();
+    for (old, new) in &mapping {
+        table.push_str(&format!("{}\t{}\n", old, new));
+    }
+    match map_file {
+        Some(path) => {
+            let path = expand_path(&path)?;
+            std::fs::write(&path, table)?;
+            println!("✓ Renumbered {} log(s), mapping saved to: {}", mapping.len(), display_path(&path));
+        }
+        None => {
+            for (old, new) in &mapping {
+                println!("  {} -> {}", old, new);
+            }
+            println!("✓ Renumbered {} log(s).", mapping.len());
+        }
+    }
+    Ok(())
+}
+
+/// 将字节数格式化为便于阅读的大小
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
     }
+}
 
+/// 处理 'profile list' 命令
+pub fn handle_profile_list() -> Result<()> {
+    let active = db::active_profile()?.unwrap_or_else(|| db::DEFAULT_PROFILE.to_string());
+    let names = db::list_profiles()?;
+    let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    for name in &names {
+        let path = db::profile_db_path(name)?;
+        let marker = if *name == active { '*' } else { ' ' };
+        let details = if path.exists() {
+            let size = format_size(std::fs::metadata(&path)?.len());
+            match db::count_logs_in_file(&path)? {
+                Some(count) => format!("{} logs, {}", count, size),
+                None => format!("not a dlog database, {}", size),
+            }
+        } else {
+            "not created yet".to_string()
+        };
+        println!("{} {:<width$}  {}  ({})", marker, name, details, display_path(&path), width = width);
+    }
     Ok(())
 }
 
 /// 处理 'log' 命令
-pub fn handle_log(message: Option<String>, tags: Option<String>) -> Result<()> {
-    let content = if let Some(msg) = message {
-        msg
+pub fn handle_log(args: LogArgs) -> Result<Outcome> {
+    let LogArgs {
+        message,
+        cmd,
+        ignore_status,
+        file,
+        rm,
+        tags,
+        no_default_tags,
+        todo,
+        level,
+        dir,
+        project,
+        force_dir,
+        time,
+        allow_future,
+        allow_duplicate,
+        no_hook,
+    } = args;
+    let level = level.as_deref().map(str::parse).transpose()?.unwrap_or_default();
+
+    let mut tags = match tags.as_deref() {
+        Some(t) => tags::normalize_tags(t)?,
+        None => None,
+    };
+
+    let timestamp = match &time {
+        Some(t) => {
+            let ts = parse_log_time(t)?;
+            if !allow_future && ts > Utc::now() {
+                return Err(DlogError::InvalidInput(format!(
+                    "Time '{}' is in the future (use --allow-future to log it anyway)",
+                    t
+                )));
+            }
+            Some(ts)
+        }
+        None => None,
+    };
+
+    // 先确定目标目录，避免用户在编辑器中写完内容后才发现目录有误
+    let target_dir = match &dir {
+        Some(d) => {
+            let path = expand_path(d)?;
+            if !force_dir && !path.is_dir() {
+                return Err(DlogError::InvalidInput(format!(
+                    "Directory does not exist: {} (use --force-dir to log against it anyway)",
+                    d
+                )));
+            }
+            db::normalize_path(&path)?
+        }
+        None if project => db::normalize_path(&project_dir()?)?,
+        None => db::normalize_path(&env::current_dir()?)?,
+    };
+
+    // 目录配置的默认标签排在 -t 之后，在编辑器中打开时也会显示出来
+    if !no_default_tags {
+        if let Some(config) = dir_config::find(Path::new(&target_dir))? {
+            if !config.default_tags.is_empty() {
+                let mut merged = tags.take().unwrap_or_default();
+                for tag in &config.default_tags {
+                    merged.push(',');
+                    merged.push_str(tag);
+                }
+                tags = tags::normalize_tags(&merged)?;
+            }
+        }
+    }
+
+    let mut from_editor = false;
+    let mut captured = None;
+    let content = if let Some(command) = &cmd {
+        let result = capture::run(command)?;
+        let content = result.to_content();
+        captured = Some(result);
+        content
+    } else if let Some(file) = &file {
+        read_log_file(file)?
+    } else if !message.is_empty() {
+        join_messages(&message)
+    } else if !io::stdin().is_terminal() {
+        // 非交互环境下无法启动编辑器，改为从标准输入读取内容
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf)?;
+        if buf.trim().is_empty() {
+            return Err(DlogError::NonInteractive(
+                "no log content on stdin, pass -m or pipe the content in".to_string(),
+            ));
+        }
+        buf
     } else {
-        // 在这个函数中 temp_file 不需要 mut，因为我们没有直接写入它
-        let temp_file = tempfile::NamedTempFile::new()?;
+        // 第一行是 -t 给出的标签，可以在编辑器中修改
+        let mut temp_file = tempfile::NamedTempFile::new()?;
+        temp_file.write_all(tags::with_front_matter(tags.as_deref(), "").as_bytes())?;
+        temp_file.flush()?;
         let editor = env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
         let status = Command::new(&editor).arg(temp_file.path()).status()?;
 
-        if !status.success() {
-            return Err(DlogError::EditorError);
-        }
-        let mut buf = String::new();
-        temp_file.reopen()?.read_to_string(&mut buf)?;
-        buf
+        if !status.success() {
+            return Err(DlogError::EditorError);
+        }
+        let mut buf = String::new();
+        temp_file.reopen()?.read_to_string(&mut buf)?;
+        let (edited_tags, body) = split_edited_tags(&buf, "")?;
+        tags = edited_tags;
+        from_editor = true;
+        body.to_string()
+    };
+
+    // --cmd 的命令失败时以它的退出码结束，即使日志因为重复被跳过
+    let outcome = match &captured {
+        Some(result) if !ignore_status && !result.status.success() => Outcome::CommandFailed(result.exit_code()),
+        _ => Outcome::Success,
+    };
+
+    if content.trim().is_empty() {
+        if !quiet() {
+            eprintln!("Empty log, skipped.");
+        }
+        return Ok(outcome);
+    }
+
+    // 编辑器中写的内容是有意重新输入的，不当作重复
+    let timestamp = timestamp.unwrap_or_else(Utc::now);
+    if !from_editor && !allow_duplicate {
+        if let Some(id) = find_duplicate(&target_dir, &content, timestamp)? {
+            let skip = config::flag(DUPLICATE_SKIP_KEY, true);
+            if !quiet() {
+                eprintln!(
+                    "Warning: log #{} with the same content was just recorded here{}",
+                    id,
+                    if skip { ", skipped (use --allow-duplicate to record it anyway)." } else { "." }
+                );
+            }
+            if skip {
+                return Ok(outcome);
+            }
+        }
+    }
+
+    // 编辑器中写的内容先保存一份，写入失败时可以通过 'dlog recover' 恢复
+    let saved = if from_editor { Some(recovery::save(&content)?) } else { None };
+    let result = write_log(&target_dir, &content, tags.as_deref(), todo, level, timestamp);
+    let id = recovery::finish(saved, result)?;
+
+    // 日志已经写入数据库，此时才删除源文件
+    if rm {
+        if let Some(file) = file.as_deref().filter(|f| *f != "-") {
+            if let Err(e) = std::fs::remove_file(expand_path(file)?) {
+                eprintln!("Warning: could not remove {}: {}", file, e);
+            }
+        }
+    }
+
+    if let Some(result) = captured.as_ref().filter(|r| r.omitted > 0 && !quiet()) {
+        eprintln!(
+            "Note: {} bytes of output were left out of the log (see cmd_max_output in the config file).",
+            result.omitted
+        );
+    }
+    let kind = if todo { "Todo" } else { "Log" };
+    if !quiet() {
+        if dir.is_some() {
+            println!("✓ {} recorded in: {}", kind, display_path(&target_dir));
+        } else {
+            println!("✓ {} recorded.", kind);
+        }
+    }
+
+    if !no_hook {
+        let entry = hook::HookEntry {
+            id,
+            directory: &target_dir,
+            content: &content,
+            tags: tags.as_deref(),
+            timestamp: &db::canonical_timestamp(timestamp),
+            level: level.as_str(),
+            todo,
+        };
+        // 日志已经保存，钩子失败只给出警告
+        if let Err(reason) = hook::run_post_log(&entry) {
+            eprintln!("Warning: post-log hook {}", reason);
+        }
+    }
+    Ok(outcome)
+}
+
+/// 将日志写入数据库，首次记录日志时自动创建数据库，目录同步仍只在显式 init 时进行
+fn write_log(
+    dir: &str,
+    content: &str,
+    tags: Option<&str>,
+    todo: bool,
+    level: Level,
+    timestamp: DateTime<Utc>,
+) -> Result<i32> {
+    if !db::is_initialized()? {
+        db::initialize_db()?;
+        if !quiet() {
+            println!("✓ Created a new database at: {:?}", db::get_db_path()?);
+        }
+    }
+    let conn = db::open_connection()?;
+    db::add_log_at(&conn, dir, content, tags, todo, level, timestamp)
+}
+
+/// 读取 --file 指定的文件（- 表示标准输入），返回去掉两端空白的内容
+///
+/// 内容必须是 UTF-8 文本；包含 NUL 字节的文件视为二进制文件而拒绝。
+fn read_log_file(file: &str) -> Result<String> {
+    let bytes = if file == "-" {
+        let mut buf = Vec::new();
+        io::stdin().read_to_end(&mut buf)?;
+        buf
+    } else {
+        std::fs::read(expand_path(file)?)
+            .map_err(|e| DlogError::InvalidInput(format!("Cannot read {}: {}", file, e)))?
+    };
+    let name = if file == "-" { "stdin" } else { file };
+    if bytes.contains(&0) {
+        return Err(DlogError::InvalidInput(format!("{} looks like a binary file", name)));
+    }
+    let mut content = String::from_utf8(bytes)
+        .map_err(|_| DlogError::InvalidInput(format!("{} is not valid UTF-8 text", name)))?;
+    // 原地去掉两端空白，避免为几 MB 的文件再复制一份
+    content.truncate(content.trim_end().len());
+    let start = content.len() - content.trim_start().len();
+    content.drain(..start);
+    Ok(content)
+}
+
+/// 合并多个 -m 的内容：与 git commit -m 一致，多段内容以空行分隔，空白的段落被忽略
+fn join_messages(message: &[String]) -> String {
+    let paragraphs: Vec<&str> = message
+        .iter()
+        .map(String::as_str)
+        .filter(|m| !m.trim().is_empty())
+        .collect();
+    paragraphs.join("\n\n")
+}
+
+/// 配置项：重复检查的时间窗口（分钟），设为 0 时关闭检查
+const DUPLICATE_WINDOW_KEY: &str = "duplicate_window";
+/// 配置项：设为 false 时发现重复只提示、照常记录
+const DUPLICATE_SKIP_KEY: &str = "duplicate_skip";
+const DEFAULT_DUPLICATE_WINDOW_MINUTES: u64 = 5;
+
+/// 在 `at` 前后的时间窗口内查找同一目录中内容相同的日志
+fn find_duplicate(dir: &str, content: &str, at: DateTime<Utc>) -> Result<Option<i32>> {
+    let minutes = config::number(DUPLICATE_WINDOW_KEY, DEFAULT_DUPLICATE_WINDOW_MINUTES);
+    if minutes == 0 || !db::is_initialized()? {
+        return Ok(None);
+    }
+    let window = Duration::minutes(minutes.min(i32::MAX as u64) as i64);
+    let conn = db::open_connection()?;
+    db::find_recent_duplicate(&conn, dir, content, at - window, at + window)
+}
+
+/// 将本地时间换算为 UTC；夏令时切换导致本地时间不存在时返回错误
+fn local_to_utc(naive: NaiveDateTime) -> Result<DateTime<Utc>> {
+    naive
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| DlogError::InvalidInput(format!("Local time {} does not exist", naive)))
+}
+
+/// 本地日期对应的 UTC 时间范围 [当天零点, 次日零点)
+pub fn local_day_range(day: NaiveDate) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = local_to_utc(day.and_time(NaiveTime::MIN))?;
+    let next_day = day.succ_opt().unwrap_or(day);
+    let end = local_to_utc(next_day.and_time(NaiveTime::MIN))?;
+    Ok((start, end))
+}
+
+/// 解析 `log --time` 的参数（本地时间）
+///
+/// 支持 YYYY-MM-DD（当天零点）、YYYY-MM-DD HH:MM、now、today（当天零点）、
+/// yesterday（昨天的此刻）以及 -3h、-2d、-1w 等相对当前时间的形式。
+fn parse_log_time(s: &str) -> Result<DateTime<Utc>> {
+    let s = s.trim();
+    let now = Utc::now();
+    match s.to_ascii_lowercase().as_str() {
+        "now" => return Ok(now),
+        "today" => return local_day_range(Local::now().date_naive()).map(|(start, _)| start),
+        "yesterday" => return Ok(now - Duration::days(1)),
+        _ => {}
+    }
+
+    if let Some(relative) = s.strip_prefix('-') {
+        return parse_duration_cutoff(relative);
+    }
+
+    if let Ok(naive) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M") {
+        return local_to_utc(naive);
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return local_day_range(day).map(|(start, _)| start);
+    }
+    Err(invalid_time(s))
+}
+
+fn invalid_time(s: &str) -> DlogError {
+    DlogError::InvalidInput(format!(
+        "Invalid time '{}'. Use YYYY-MM-DD, 'YYYY-MM-DD HH:MM', today, yesterday or a relative form like -2d.",
+        s
+    ))
+}
+
+/// 解析 `3h`、`3d`、`2w`、`6m`、`1y` 形式的时长，返回从现在往前推算的时间点
+///
+/// `m` 表示月而不是分钟；月和年按日历计算。
+fn parse_duration_cutoff(s: &str) -> Result<DateTime<Utc>> {
+    let invalid = || {
+        DlogError::InvalidInput(format!(
+            "Invalid duration '{}'. Use a positive number followed by h (hours), d (days), w (weeks), m (months) or y (years), e.g. 3d.",
+            s
+        ))
+    };
+
+    let s = s.trim();
+    let unit = s.chars().last().ok_or_else(invalid)?;
+    let amount: u32 = s[..s.len() - unit.len_utf8()].parse().map_err(|_| invalid())?;
+    if amount == 0 {
+        return Err(invalid());
+    }
+
+    let now = Utc::now();
+    let cutoff = match unit {
+        'h' => now.checked_sub_signed(Duration::hours(amount as i64)),
+        'd' => now.checked_sub_signed(Duration::days(amount as i64)),
+        'w' => now.checked_sub_signed(Duration::weeks(amount as i64)),
+        'm' => now.checked_sub_months(Months::new(amount)),
+        'y' => amount
+            .checked_mul(12)
+            .and_then(|months| now.checked_sub_months(Months::new(months))),
+        _ => return Err(invalid()),
+    };
+    cutoff.ok_or_else(invalid)
+}
+
+/// 处理 'get' 命令
+pub fn handle_get(args: GetArgs) -> Result<Outcome> {
+    let GetArgs {
+        path,
+        num,
+        recursive,
+        depth,
+        limit_per_dir,
+        project,
+        all,
+        tag,
+        date,
+        month,
+        since,
+        between,
+        search,
+        exclude,
+        case_sensitive,
+        path_glob,
+        linked,
+        host,
+        from_archive,
+        level,
+        min_level,
+        sort,
+        oneline,
+        titles_only,
+        ids_only,
+        ids,
+        field,
+        print0,
+        full,
+        verbose,
+        before_id,
+        page,
+        watch,
+        interval,
+        output,
+        force,
+    } = args;
+    if output.is_some() {
+        color::disable();
+    }
+
+    // 与记录时的整理方式一致，保证查询和存储的标签可以对应
+    let tag = tag.as_deref().map(tags::normalize_tag).transpose()?;
+
+    // --project 相当于对项目根目录使用 -r
+    let recursive = recursive || project;
+    let ids = ids.as_deref().map(parse_id_range).transpose()?;
+    // 单独使用 --path-glob、--linked 或 --ids 时搜索所有目录，指定了目录或 -r 时限定在该目录树中
+    let cross_directory = path_glob.is_some() || linked.is_some() || ids.is_some();
+    let scope = match cross_directory {
+        _ if all => Scope::All,
+        true if path.is_none() && !recursive => Scope::All,
+        true => Scope::with_depth(true, depth),
+        false => Scope::with_depth(recursive, depth),
+    };
+    let host = match host.as_deref() {
+        Some(".") => Some(db::current_hostname().ok_or_else(|| {
+            DlogError::InvalidInput("Cannot determine the hostname of this machine".to_string())
+        })?),
+        other => other.map(str::to_string),
+    };
+    // 跨目录或按主机过滤时显示来源，便于区分不同机器上的日志
+    let style = if oneline {
+        ListStyle::Oneline
+    } else if titles_only {
+        ListStyle::Titles
+    } else if verbose {
+        ListStyle::Verbose
+    } else {
+        ListStyle::Full
+    };
+    let options = RenderOptions {
+        style,
+        show_path: scope != Scope::Dir,
+        show_origin: verbose || host.is_some() || scope != Scope::Dir,
+        max_lines: (!full).then_some(PREVIEW_LINES),
+        fit_terminal: output.is_none(),
+        absolute_paths: output.is_some(),
+    };
+
+    let target_path = match path {
+        Some(p) => expand_path(&p)?,
+        None if project => project_dir()?,
+        None => env::current_dir()?,
+    };
+
+    // 按本地日期过滤，换算为对应的 UTC 时间范围 [首日零点, 末日次日零点)
+    let day_span = match (&date, &month) {
+        (Some(d), _) => Some(parse_day_span(d)?),
+        (None, Some(m)) => Some(parse_month_span(m)?),
+        (None, None) => None,
+    };
+    let date_range = match day_span {
+        Some((first, last)) => Some((local_day_range(first)?.0, local_day_range(last)?.1)),
+        None => None,
+    };
+
+    let since = since.as_deref().map(parse_duration_cutoff).transpose()?;
+    let between = between.as_deref().map(parse_time_range).transpose()?;
+    let level: Option<Level> = level.as_deref().map(str::parse).transpose()?;
+    let min_level: Option<Level> = min_level.as_deref().map(str::parse).transpose()?;
+    // 游标翻页和 --watch 都依赖按时间排列
+    let by_level = sort == SortKey::Level;
+    if by_level && (before_id.is_some() || watch) {
+        return Err(DlogError::InvalidInput(
+            "--sort level cannot be combined with --before-id or --watch.".to_string(),
+        ));
+    }
+    // 每个词都必须出现，默认不区分大小写（包括非 ASCII 字符）
+    let terms: Vec<String> = match search.as_deref() {
+        Some(s) => {
+            let terms: Vec<String> = search::split_query(s).into_iter().map(str::to_string).collect();
+            if terms.is_empty() {
+                return Err(DlogError::InvalidInput("Search keyword is empty.".to_string()));
+            }
+            terms
+        }
+        None => Vec::new(),
+    };
+    if exclude.iter().any(|w| w.trim().is_empty()) {
+        return Err(DlogError::InvalidInput("Exclude keyword is empty.".to_string()));
+    }
+
+    let reading_archive = from_archive.is_some();
+    if !reading_archive && !db::is_initialized()? {
+        if !ids_only {
+            print_no_database_hint();
+        }
+        return Ok(Outcome::NoMatches);
+    }
+
+    // 明确列出的ID默认全部显示
+    let limit = num.unwrap_or(if ids.is_some() { 0 } else { 10 });
+    // 多取一条用于判断是否还有下一页；-n 或 --page 很大时不能溢出
+    let fetch_limit = match limit {
+        0 => 0,
+        n => n
+            .checked_add(1)
+            .ok_or_else(|| DlogError::InvalidInput(format!("-n {} is too large", limit)))?,
+    };
+    let offset = match page {
+        Some(k) => (k - 1)
+            .checked_mul(limit)
+            .ok_or_else(|| DlogError::InvalidInput(format!("--page {} is out of range for -n {}", k, limit)))?,
+        None => 0,
+    };
+    let conn = match from_archive {
+        Some(file) => open_archive(file)?,
+        None => db::open_connection()?,
+    };
+    for id in before_id.into_iter().chain(linked) {
+        db::get_log_content(&conn, id)?.ok_or(DlogError::LogNotFound(id))?;
+    }
+
+    // 在查询之前记录，之后插入的日志由 --watch 输出
+    let watch_from = if watch { Some(db::max_log_id(&conn)?) } else { None };
+
+    // 多取一条用于判断是否还有下一页
+    let query = db::LogQuery {
+        tag: tag.as_deref(),
+        terms: &terms,
+        exclude: &exclude,
+        case_sensitive,
+        path_glob: path_glob.as_deref(),
+        // 同时指定 --date（或 --month）和 --since 时取两者的交集
+        since: date_range.map(|(start, _)| start).max(since),
+        until: date_range.map(|(_, end)| end),
+        between,
+        month_day: None,
+        before_id,
+        after_id: None,
+        linked,
+        ids: ids.as_deref(),
+        host: host.as_deref(),
+        level,
+        min_level,
+        by_level,
+        limit_per_dir: limit_per_dir.unwrap_or(0),
+        limit: fetch_limit,
+        offset,
+    };
+    let mut logs = db::fetch_logs(&conn, &target_path, scope, &query)?;
+    let has_more = limit > 0 && logs.len() > limit as usize;
+    logs.truncate(if limit == 0 { logs.len() } else { limit as usize });
+
+    // 供其他命令使用的输出中只有ID，没有提示信息
+    if ids_only {
+        if logs.is_empty() {
+            return Ok(Outcome::NoMatches);
+        }
+        let out: String = logs.iter().map(|l| format!("{}\n", l.id)).collect();
+        match &output {
+            Some(file) => write_output(file, &out, force)?,
+            None => pager::page(&out)?,
+        }
+        return Ok(Outcome::Success);
+    }
+
+    // 脚本使用的原始字段值，NULL 输出为空记录
+    if let Some(field) = field {
+        if logs.is_empty() {
+            return Ok(Outcome::NoMatches);
+        }
+        let terminator = if print0 { '\0' } else { '\n' };
+        let mut out = String::new();
+        for log in &logs {
+            match field {
+                GetField::Id => out.push_str(&log.id.to_string()),
+                GetField::Timestamp => out.push_str(&log.timestamp),
+                GetField::Directory => out.push_str(&log.directory),
+                GetField::Tags => out.push_str(log.tags.as_deref().unwrap_or("")),
+                GetField::Content => out.push_str(&log.content),
+            }
+            out.push(terminator);
+        }
+        write_output(output.as_deref().unwrap_or("-"), &out, force)?;
+        return Ok(Outcome::Success);
+    }
+
+    if logs.is_empty() {
+        if !quiet() {
+            // 只查询当前目录时，日志常常记录在子目录中；翻页到末尾时不提示
+            let children = if scope == Scope::Dir && offset == 0 && before_id.is_none() {
+                db::count_matching_logs_by_child(&conn, &target_path, &query)?
+            } else {
+                Vec::new()
+            };
+            match subdirectory_hint(&children) {
+                Some(hint) => println!("{}", hint),
+                None => println!("No logs found."),
+            }
+        }
+        if !watch {
+            return Ok(Outcome::NoMatches);
+        }
+    }
+
+    let mut out = String::new();
+    render_logs(&mut out, &conn, &logs, options)?;
+
+    // 写入文件的只有日志本身，翻页提示改为显示在终端上
+    if let Some(file) = &output {
+        write_output(file, &out, force)?;
+        if file != "-" && !quiet() {
+            let more = if has_more {
+                let total = db::count_matching_logs(&conn, &target_path, scope, &query)?;
+                format!(" ({} more matched, use -n 0 to write all)", total - offset as i64 - logs.len() as i64)
+            } else {
+                String::new()
+            };
+            println!("✓ Wrote {} entries to {}{}", logs.len(), file, more);
+        }
+        return Ok(Outcome::Success);
+    }
+
+    // 结果被 -n 截断时提示总数，总数与结果使用相同的过滤条件计算
+    if has_more && !quiet() {
+        let total = db::count_matching_logs(&conn, &target_path, scope, &query)?;
+        let shown = match offset {
+            0 => logs.len().to_string(),
+            _ => format!("{}-{}", offset + 1, offset as usize + logs.len()),
+        };
+        let mut hints = vec!["-n 0".to_string()];
+        // 游标翻页与 --page 不能同时使用，按级别排列时游标翻页不可用
+        if before_id.is_none() {
+            hints.push(format!("--page {}", page.unwrap_or(1) + 1));
+        }
+        if page.is_none() && !by_level {
+            hints.push(format!("--before-id {}", logs.last().map_or(0, |l| l.id)));
+        }
+        let last = hints.pop().unwrap_or_default();
+        let hints = if hints.is_empty() { last } else { format!("{} or {}", hints.join(", "), last) };
+        out.push_str(&format!(
+            "Showing {} of {} matching entries (use {} to see more)\n",
+            shown, total, hints
+        ));
+    }
+
+    // 'dlog todo' 没有对应的 glob、关联和主机过滤以及全局范围，此时不显示以免数字误导
+    let open_todos = if cross_directory || all || host.is_some() || reading_archive || quiet() {
+        0
+    } else {
+        db::count_open_todos(&conn, &target_path, scope)?
+    };
+    if open_todos > 0 {
+        out.push_str(&format!(
+            "{} open todo(s) here, run 'dlog todo{}' to list them.\n",
+            open_todos,
+            if recursive { " -r" } else { "" }
+        ));
+    }
+    if let Some(last_id) = watch_from {
+        let last_id = logs.iter().map(|l| l.id).fold(last_id, i32::max);
+        drop(conn);
+        return watch_logs(&out, &target_path, scope, &query, last_id, options, interval);
+    }
+    pager::page(&out)?;
+    Ok(Outcome::Success)
+}
+
+/// 子目录提示中最多列出的子目录数
+const SUBDIR_HINT_MAX: usize = 5;
+
+/// 当前目录没有日志但子目录中有时的提示，`children` 为 `count_matching_logs_by_child` 的结果
+fn subdirectory_hint(children: &[(String, i64)]) -> Option<String> {
+    if children.is_empty() {
+        return None;
+    }
+    let total: i64 = children.iter().map(|(_, count)| count).sum();
+    let mut listed: Vec<String> = children
+        .iter()
+        .take(SUBDIR_HINT_MAX)
+        .map(|(name, count)| format!("{}: {}", name, count))
+        .collect();
+    if children.len() > SUBDIR_HINT_MAX {
+        listed.push(format!("{} more", children.len() - SUBDIR_HINT_MAX));
+    }
+    Some(format!(
+        "No logs in this directory, but {} {} in subdirectories ({}) — try -r",
+        total,
+        if total == 1 { "entry exists" } else { "entries exist" },
+        listed.join(", ")
+    ))
+}
+
+/// 输出一批日志，单行形式时每条一行，否则显示内容预览和关联的日志
+fn render_logs(
+    out: &mut String,
+    conn: &rusqlite::Connection,
+    logs: &[LogEntry],
+    options: RenderOptions,
+) -> Result<()> {
+    if matches!(options.style, ListStyle::Oneline | ListStyle::Titles) {
+        // 按本批次中最大的ID确定补零宽度，保证各行对齐
+        let id_width = logs.iter().map(|l| l.id.to_string().len()).max().unwrap_or(1);
+        for log in logs {
+            match options.style {
+                ListStyle::Titles => render_log_title(out, log, id_width, options),
+                _ => render_log_oneline(out, log, id_width, options),
+            }
+        }
+        return Ok(());
+    }
+    let layout = if options.fit_terminal { ListLayout::for_batch(logs) } else { None };
+    let ids: Vec<i32> = logs.iter().map(|l| l.id).collect();
+    let links = db::fetch_links(conn, &ids)?;
+    for log in logs {
+        let related = links.get(&log.id).map_or(&[][..], Vec::as_slice);
+        match &layout {
+            _ if options.style == ListStyle::Verbose => render_log_verbose(out, log, options, related),
+            Some(layout) => render_log_aligned(out, log, layout, options, related),
+            None => render_log(out, log, options, related),
+        }
+    }
+    Ok(())
+}
+
+/// 'get --watch'：输出已有结果后，定期按相同的过滤条件输出ID大于 `last_id` 的新日志
+///
+/// 按ID而不是时间戳判断新日志，时间戳相同或补记过去时间的日志也只输出一次。
+/// 每次检查都重新打开数据库，数据库被替换（例如恢复备份）后也能继续；
+/// 替换后的最大ID小于已输出的ID时以新的最大ID为起点。
+fn watch_logs(
+    initial: &str,
+    path: &Path,
+    scope: Scope,
+    filter: &db::LogQuery,
+    mut last_id: i32,
+    options: RenderOptions,
+    interval: u64,
+) -> Result<Outcome> {
+    let mut out = initial.to_string();
+    if !quiet() {
+        out.push_str(&format!("Watching for new logs every {}s, press Ctrl-C to stop.\n", interval));
+    }
+    loop {
+        match pager::print(&out) {
+            // 输出端已关闭（例如通过管道接到 head），正常结束
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(Outcome::Success),
+            other => other?,
+        }
+        std::thread::sleep(std::time::Duration::from_secs(interval));
+
+        out.clear();
+        let conn = match db::open_connection() {
+            Ok(conn) => conn,
+            // 数据库正在被替换或被其他进程锁定时等待下一次检查
+            Err(DlogError::DatabaseNotInitialized | DlogError::DatabaseBusy) => continue,
+            Err(e) => return Err(e),
+        };
+        last_id = last_id.min(db::max_log_id(&conn)?);
+        let query = db::LogQuery {
+            after_id: Some(last_id),
+            before_id: None,
+            limit: 0,
+            offset: 0,
+            ..filter.clone()
+        };
+        let mut logs = db::fetch_logs(&conn, path, scope, &query)?;
+        // 按插入顺序输出
+        logs.sort_by_key(|l| l.id);
+        last_id = logs.iter().map(|l| l.id).fold(last_id, i32::max);
+        render_logs(&mut out, &conn, &logs, options)?;
+    }
+}
+
+/// 搜索结果中摘要的最大显示宽度
+const SNIPPET_WIDTH: usize = 80;
+
+/// 处理 'search' 命令
+pub fn handle_search(args: SearchArgs) -> Result<Outcome> {
+    let terms = search::parse_query(&args.query.join(" "));
+    if terms.is_empty() {
+        return Err(DlogError::InvalidInput("Search query is empty.".to_string()));
+    }
+    let tag = args.tag.as_deref().map(tags::normalize_tag).transpose()?;
+    let since = args.since.as_deref().map(parse_duration_cutoff).transpose()?;
+
+    if args.from_archive.is_none() && !db::is_initialized()? {
+        print_no_database_hint();
+        return Ok(Outcome::NoMatches);
+    }
+    // 相关度按不区分大小写的词计算，--case-sensitive 只影响哪些日志匹配
+    let exact_terms: Vec<String> = if args.case_sensitive {
+        search::split_query(&args.query.join(" ")).into_iter().map(str::to_string).collect()
+    } else {
+        terms.clone()
+    };
+    let conn = match args.from_archive {
+        Some(file) => open_archive(file)?,
+        None => db::open_connection()?,
+    };
+    let query = db::LogQuery {
+        tag: tag.as_deref(),
+        terms: &exact_terms,
+        case_sensitive: args.case_sensitive,
+        since,
+        ..Default::default()
+    };
+    let logs = db::fetch_logs(&conn, &env::current_dir()?, Scope::All, &query)?;
+    if logs.is_empty() {
+        if !quiet() {
+            println!("No logs found.");
+        }
+        return Ok(Outcome::NoMatches);
+    }
+
+    // fetch_logs 按时间倒序返回，稳定排序保证相关度相同时较新的在前
+    let mut ranked: Vec<(usize, &LogEntry)> = logs.iter().map(|l| (search::score(l, &terms), l)).collect();
+    ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    let total = ranked.len();
+    if args.num > 0 {
+        ranked.truncate(args.num as usize);
+    }
+
+    let mut out = String::new();
+    let id_width = ranked.iter().map(|(_, l)| l.id.to_string().len()).max().unwrap_or(1);
+    for (_, log) in &ranked {
+        let mut header = format!(
+            "{:>width$} {}  {}",
+            log.id,
+            format_time(&log.timestamp, "%Y-%m-%d"),
+            display_path(&log.directory),
+            width = id_width
+        );
+        if let Some(tags) = log.tags.as_deref().filter(|t| !t.is_empty()) {
+            header.push_str(&format!("  [{}]", tags));
+        }
+        let (clipped, line) = search::snippet(&log.content, &terms);
+        let prefix = if clipped { "…" } else { "" };
+        out.push_str(&format!("{}\n", header));
+        out.push_str(&format!("{}  {}{}\n", " ".repeat(id_width), prefix, truncate_width(line, SNIPPET_WIDTH)));
+    }
+    if total > ranked.len() && !quiet() {
+        out.push_str(&format!("… {} more results, try -n {}\n", total - ranked.len(), total));
+    }
+    pager::page(&out)?;
+    Ok(Outcome::Success)
+}
+
+/// 默认每条日志显示的最大内容行数
+const PREVIEW_LINES: usize = 6;
+
+/// 数据库尚未创建时，只读命令给出提示而不是报错
+fn print_no_database_hint() {
+    if quiet() {
+        return;
+    }
+    println!("No database yet, run 'dlog init' or log something first.");
+    println!("No logs found.");
+}
+
+/// 显示路径时将主目录前缀替换为 `~`
+pub fn display_path(path: impl AsRef<Path>) -> String {
+    let path = path.as_ref();
+    if let Some(home) = dirs::home_dir() {
+        if let Ok(rest) = path.strip_prefix(&home) {
+            if rest.as_os_str().is_empty() {
+                return "~".to_string();
+            }
+            return format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display());
+        }
+    }
+    path.display().to_string()
+}
+
+/// 配置项：以逗号分隔的项目标记文件名，设置后代替默认的列表
+const PROJECT_MARKERS_KEY: &str = "project_markers";
+const DEFAULT_PROJECT_MARKERS: &str = ".git,Cargo.toml,package.json";
+
+/// --project 使用的目录：从当前目录向上最近的项目根目录，找不到时为当前目录
+fn project_dir() -> Result<PathBuf> {
+    let cwd = env::current_dir()?;
+    let markers = project_markers();
+    let markers: Vec<&str> = markers.iter().map(String::as_str).collect();
+    match find_project_root(&cwd, &markers) {
+        Some(root) => Ok(root.to_path_buf()),
+        None => {
+            if !quiet() {
+                eprintln!(
+                    "Note: no project marker ({}) found above {}, using the current directory.",
+                    markers.join(", "),
+                    display_path(&cwd)
+                );
+            }
+            Ok(cwd)
+        }
+    }
+}
+
+/// 项目标记文件名：配置项 project_markers 或默认的列表
+fn project_markers() -> Vec<String> {
+    let markers = config::text(PROJECT_MARKERS_KEY).unwrap_or_else(|| DEFAULT_PROJECT_MARKERS.to_string());
+    markers.split(',').map(str::trim).filter(|m| !m.is_empty()).map(str::to_string).collect()
+}
+
+/// 从 `start` 开始逐级向上，返回第一个包含任一标记文件（或目录）的目录
+///
+/// 嵌套的项目（例如子模块）中离 `start` 最近的标记优先。
+fn find_project_root<'a>(start: &'a Path, markers: &[&str]) -> Option<&'a Path> {
+    start
+        .ancestors()
+        .find(|dir| markers.iter().any(|marker| dir.join(marker).exists()))
+}
+
+/// 展开路径参数开头的 `~`、`~user` 以及其中的 `$VAR`/`${VAR}`（例如参数被引号包围时 shell 不会展开）
+///
+/// 不在开头的 `~` 原样保留；引用了未设置的环境变量时报错，而不是按字面使用。
+pub fn expand_path(path: &str) -> Result<PathBuf> {
+    let (home, rest) = match path.strip_prefix('~') {
+        Some(after) => {
+            let end = after.find(['/', std::path::MAIN_SEPARATOR]).unwrap_or(after.len());
+            let home = match &after[..end] {
+                "" => dirs::home_dir(),
+                user => user_home(user),
+            };
+            match home {
+                Some(home) => (Some(home), &after[end..]),
+                None => (None, path),
+            }
+        }
+        None => (None, path),
+    };
+    let rest = expand_vars(rest)?;
+    // 与 shell 一样，变量的值以分隔符开头（如 ~/$DIR）时仍然位于主目录之下
+    Ok(match home {
+        Some(home) => match rest.trim_start_matches(['/', std::path::MAIN_SEPARATOR]) {
+            "" => home,
+            rest => home.join(rest),
+        },
+        None => PathBuf::from(rest),
+    })
+}
+
+/// 替换 `$NAME` 和 `${NAME}` 形式的环境变量引用，其他的 `$` 原样保留
+fn expand_vars(path: &str) -> Result<String> {
+    let is_name = |c: char| c == '_' || c.is_ascii_alphanumeric();
+    let mut out = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, next) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(close) if !braced[..close].is_empty() && braced[..close].chars().all(is_name) => {
+                    (&braced[..close], &braced[close + 1..])
+                }
+                _ => ("", after),
+            },
+            None => {
+                let end = after.find(|c: char| !is_name(c)).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        let value = env::var_os(name).ok_or_else(|| {
+            DlogError::InvalidInput(format!("Environment variable ${} in path '{}' is not set", name, path))
+        })?;
+        out.push_str(&value.to_string_lossy());
+        rest = next;
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// 查找其他用户的主目录，找不到时与 shell 一样按字面保留 `~user`
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<PathBuf> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        (fields.len() >= 6 && fields[0] == user).then(|| PathBuf::from(fields[5]))
+    })
+}
+
+#[cfg(not(unix))]
+fn user_home(_user: &str) -> Option<PathBuf> {
+    None
+}
+
+/// 将存储的 RFC3339 时间戳解析为本地时间，无法解析时返回 None
+pub fn local_time(timestamp: &str) -> Option<DateTime<Local>> {
+    let dt: DateTime<Utc> = timestamp.parse().ok()?;
+    Some(dt.with_timezone(&Local))
+}
+
+/// 提示无法按日期归类的日志被跳过
+fn warn_invalid_timestamp(log: &LogEntry) {
+    if !quiet() {
+        eprintln!(
+            "Warning: skipping log #{} with an invalid timestamp ({}), run 'dlog doctor --fix-timestamps' to repair it.",
+            log.id, log.timestamp
+        );
+    }
+}
+
+/// 按 `fmt` 显示存储的时间戳；无法解析时显示 `<invalid: 原始值>`，而不是用其他时间代替
+pub fn format_time(timestamp: &str, fmt: &str) -> String {
+    match local_time(timestamp) {
+        Some(time) => time.format(fmt).to_string(),
+        None => format!("<invalid: {}>", timestamp),
+    }
+}
+
+/// 每条日志的输出形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListStyle {
+    /// 标题行加内容预览
+    Full,
+    /// 每条一行，内容首行过长时截断（'get --oneline'）
+    Oneline,
+    /// 每条一行，只显示完整的标题（'get --titles-only'）
+    Titles,
+    /// 内容之前列出所有字段（'get --verbose'）
+    Verbose,
+}
+
+/// 'get' 和 'todo' 输出每条日志时的显示选项
+#[derive(Debug, Clone, Copy)]
+struct RenderOptions {
+    style: ListStyle,
+    /// 显示日志所在的目录
+    show_path: bool,
+    /// 显示记录日志的用户和主机
+    show_origin: bool,
+    /// 最多显示的内容行数，None 表示显示全部
+    max_lines: Option<usize>,
+    /// 按终端宽度对齐；输出写入文件时为假
+    fit_terminal: bool,
+    /// 显示完整的目录而不是以 `~` 开头的路径；输出写入文件时为真
+    absolute_paths: bool,
+}
+
+impl RenderOptions {
+    /// 按选项显示日志所在的目录
+    fn path(&self, dir: &str) -> String {
+        if self.absolute_paths {
+            dir.to_string()
+        } else {
+            display_path(dir)
+        }
+    }
+}
+
+/// 根据一批日志计算的列宽，用于对齐 'get' 和 'todo' 的输出
+struct ListLayout {
+    term_width: usize,
+    id_width: usize,
+    tags_width: usize,
+}
+
+impl ListLayout {
+    /// 标准输出不是终端或无法获取终端宽度时返回 None，此时使用简单格式
+    fn for_batch(logs: &[LogEntry]) -> Option<Self> {
+        if !io::stdout().is_terminal() {
+            return None;
+        }
+        let (terminal_size::Width(term_width), _) = terminal_size::terminal_size()?;
+        let term_width = term_width as usize;
+
+        let id_width = logs.iter().map(|l| l.id.to_string().len()).max().unwrap_or(1);
+        // "[ID] YYYY-MM-DD HH:MM:SS  " 之后剩余的宽度留给标签和待办标记
+        let fixed = id_width + 2 + 1 + 19 + 2;
+        let todo_width = logs
+            .iter()
+            .map(|l| todo_marker(l).width() + lock_marker(l).width())
+            .max()
+            .unwrap_or(0);
+        let max_tags = logs
+            .iter()
+            .filter_map(|l| l.tags.as_deref())
+            .map(UnicodeWidthStr::width)
+            .max()
+            .unwrap_or(0);
+        let tags_width = max_tags.min(term_width.saturating_sub(fixed + todo_width));
+        Some(ListLayout {
+            term_width,
+            id_width,
+            tags_width,
+        })
+    }
+}
+
+/// 以对齐的列输出一条日志，内容按终端宽度折行并悬挂缩进
+fn render_log_aligned(
+    out: &mut String,
+    log: &LogEntry,
+    layout: &ListLayout,
+    options: RenderOptions,
+    related: &[i32],
+) {
+    let formatted_time = format_time(&log.timestamp, "%Y-%m-%d %H:%M:%S");
+    let tags = truncate_width(log.tags.as_deref().unwrap_or(""), layout.tags_width);
+    let header = format!(
+        "[{:>id_width$}] {}  {}{}{}{}",
+        log.id,
+        formatted_time,
+        pad_width(&tags, layout.tags_width),
+        todo_marker(log),
+        level_marker(log),
+        lock_marker(log),
+        id_width = layout.id_width
+    );
+    out.push_str(&format!("{}\n", header.trim_end()));
+    push_location(out, log, options);
+
+    for styled in highlight::style_content(&preview_content(log.content.trim_end(), options.max_lines)) {
+        let line = styled.text.as_str();
+        // 代码块保持原样，不折行
+        if styled.in_code || line.width() <= layout.term_width {
+            out.push_str(&format!("{}\n", line));
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let hanging = format!("{}  ", &line[..indent]);
+        let rest_width = layout.term_width.saturating_sub(hanging.width()).max(1);
+        for (i, part) in wrap_width(line, layout.term_width, rest_width).iter().enumerate() {
+            if i == 0 {
+                out.push_str(&format!("{}\n", part));
+            } else {
+                out.push_str(&format!("{}{}\n", hanging, part));
+            }
+        }
+    }
+    push_related(out, related);
+    out.push_str(&format!("{}\n", "─".repeat(40.min(layout.term_width))));
+}
+
+/// 以标准格式输出一条日志
+fn render_log(out: &mut String, log: &LogEntry, options: RenderOptions, related: &[i32]) {
+    let formatted_time = format_time(&log.timestamp, "%Y-%m-%d %H:%M:%S");
+    let tags_display = log.tags.as_ref().map_or("".to_string(), |t| format!(" | Tags: {}", t));
+
+    out.push_str(&format!(
+        "[{}] {} {}{}{}{}\n",
+        log.id,
+        formatted_time,
+        tags_display,
+        todo_marker(log),
+        level_marker(log),
+        lock_marker(log)
+    ));
+    push_location(out, log, options);
+    let lines: Vec<String> = highlight::style_content(&preview_content(log.content.trim_end(), options.max_lines))
+        .into_iter()
+        .map(|l| l.text)
+        .collect();
+    out.push_str(&format!("{}\n", lines.join("\n")));
+    push_related(out, related);
+    out.push_str(&format!("{}\n", "─".repeat(40)));
+}
+
+/// 先以对齐的 `字段: 值` 列出日志的所有字段（见 `LogEntry::fields`），再输出内容和关联的日志
+fn render_log_verbose(out: &mut String, log: &LogEntry, options: RenderOptions, related: &[i32]) {
+    let fields = log.fields();
+    let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0) + 1;
+    for (name, value) in fields {
+        // 时间同时显示本地时间和存储的原始值
+        let value = match value {
+            Some(ts) if name == "timestamp" || name == "completed_at" => {
+                format!("{} ({})", format_time(&ts, "%Y-%m-%d %H:%M:%S %:z"), ts)
+            }
+            Some(value) => value,
+            None => "(none)".to_string(),
+        };
+        out.push_str(&format!("{:<width$} {}\n", format!("{}:", name), value, width = width));
+    }
+    out.push('\n');
+    let lines: Vec<String> = highlight::style_content(&preview_content(log.content.trim_end(), options.max_lines))
+        .into_iter()
+        .map(|l| l.text)
+        .collect();
+    out.push_str(&format!("{}\n", lines.join("\n")));
+    push_related(out, related);
+    out.push_str(&format!("{}\n", "─".repeat(40)));
+}
+
+/// 按显示选项输出日志所在目录（递归查询时）以及记录日志的用户和主机
+fn push_location(out: &mut String, log: &LogEntry, options: RenderOptions) {
+    if options.show_path {
+        out.push_str(&format!("  └─ Path: {}\n", options.path(&log.directory)));
+    }
+    if options.show_origin {
+        if let Some(origin) = log_origin(log) {
+            out.push_str(&format!("  └─ Host: {}\n", origin));
+        }
+    }
+}
+
+/// 以 `用户@主机` 的形式描述日志的来源，旧日志没有记录时返回 None
+fn log_origin(log: &LogEntry) -> Option<String> {
+    match (&log.username, &log.hostname) {
+        (Some(user), Some(host)) => Some(format!("{}@{}", user, host)),
+        (None, Some(host)) => Some(host.clone()),
+        (Some(user), None) => Some(user.clone()),
+        (None, None) => None,
+    }
+}
+
+/// 输出与日志关联的其他日志ID，没有关联时不输出
+fn push_related(out: &mut String, related: &[i32]) {
+    if related.is_empty() {
+        return;
+    }
+    let ids: Vec<String> = related.iter().map(i32::to_string).collect();
+    out.push_str(&format!("↪ related: {}\n", ids.join(", ")));
+}
+
+/// 按行截断日志内容并附加剩余行数的提示
+fn preview_content(content: &str, max_lines: Option<usize>) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let max_lines = match max_lines {
+        Some(n) if lines.len() > n => n,
+        _ => return content.to_string(),
+    };
+
+    let mut shown = lines[..max_lines].join("\n");
+    // 截断位置落在代码块内部时补上结束标记，避免影响后续日志的显示
+    let open_fences = lines[..max_lines]
+        .iter()
+        .filter(|l| l.trim_start().starts_with("```"))
+        .count();
+    if open_fences % 2 == 1 {
+        shown.push_str("\n```");
+    }
+    shown.push_str(&format!(
+        "\n… (+{} more lines, use --full to see everything)",
+        lines.len() - max_lines
+    ));
+    shown
+}
+
+/// 只输出ID、日期、标签和完整的标题
+fn render_log_title(out: &mut String, log: &LogEntry, id_width: usize, options: RenderOptions) {
+    let date = format_time(&log.timestamp, "%Y-%m-%d");
+    let mut line = format!("{:0width$} {}", log.id, date, width = id_width);
+    if let Some(tags) = log.tags.as_deref().filter(|t| !t.is_empty()) {
+        line.push_str(&format!(" [{}]", tags));
+    }
+    line.push_str(&format!(" {}{}", level_prefix(log), title_of(log)));
+    if options.show_path {
+        line.push_str(&format!("  {}", options.path(&log.directory)));
+    }
+    out.push_str(&format!("{}\n", line.trim_end()));
+}
+
+/// 单行模式下内容摘要的最大字符数
+const ONELINE_CONTENT_WIDTH: usize = 60;
+
+/// 以紧凑的单行格式输出一条日志
+fn render_log_oneline(out: &mut String, log: &LogEntry, id_width: usize, options: RenderOptions) {
+    let date = format_time(&log.timestamp, "%Y-%m-%d");
+    let summary = truncate_width(title_of(log), ONELINE_CONTENT_WIDTH);
+
+    let mut line = format!("{:0width$} {} {}{}", log.id, date, level_prefix(log), summary, width = id_width);
+    if let Some(tags) = log.tags.as_deref().filter(|t| !t.is_empty()) {
+        line.push_str(&format!(" [{}]", tags));
+    }
+    if options.show_path {
+        line.push_str(&format!("  {}", options.path(&log.directory)));
+    }
+    if let Some(origin) = log_origin(log).filter(|_| options.show_origin) {
+        line.push_str(&format!("  {}", origin));
+    }
+    out.push_str(&format!("{}\n", line));
+}
+
+/// 按终端显示宽度截断字符串，超出部分以省略号代替（中日韩文字和 emoji 占两列）
+fn truncate_width(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if width + w + 1 > max_width {
+            break;
+        }
+        truncated.push(c);
+        width += w;
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// 用空格补齐到指定的显示宽度
+fn pad_width(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(s.width())))
+}
+
+/// 按显示宽度折行，首行宽度为 `first_width`，其余各行为 `rest_width`
+///
+/// 优先在空格处断开，过长的单词按字符断开。
+fn wrap_width(line: &str, first_width: usize, rest_width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in line.split(' ') {
+        let max_width = if lines.is_empty() { first_width } else { rest_width };
+        if !current.is_empty() {
+            if current.width() + 1 + word.width() > max_width {
+                lines.push(std::mem::take(&mut current));
+            } else {
+                current.push(' ');
+            }
+        }
+        for c in word.chars() {
+            let max_width = if lines.is_empty() { first_width } else { rest_width };
+            if !current.is_empty() && current.width() + c.width().unwrap_or(0) > max_width {
+                lines.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+/// 待办事项的状态标记，非待办日志返回空字符串
+fn todo_marker(log: &LogEntry) -> String {
+    match log.status.as_deref() {
+        Some(TODO_OPEN) => " | [ ] TODO".to_string(),
+        Some(TODO_DONE) => match completed_time(log) {
+            Some(done_at) => format!(" | [x] DONE {}", done_at),
+            None => " | [x] DONE".to_string(),
+        },
+        _ => "".to_string(),
+    }
+}
+
+/// 标题行中 high 级别日志的标记
+fn level_marker(log: &LogEntry) -> String {
+    match log.level {
+        Level::High if color::enabled() => format!(" | {}", color::alert("! HIGH")),
+        Level::High => " | ! HIGH".to_string(),
+        _ => "".to_string(),
+    }
+}
+
+/// 标题行中锁定日志的标记
+fn lock_marker(log: &LogEntry) -> &'static str {
+    if log.locked {
+        " | LOCKED"
+    } else {
+        ""
+    }
+}
+
+/// 单行输出中 high 级别日志内容前的标记
+fn level_prefix(log: &LogEntry) -> String {
+    match log.level {
+        Level::High if color::enabled() => format!("{} ", color::alert("!")),
+        Level::High => "! ".to_string(),
+        _ => "".to_string(),
+    }
+}
+
+/// 格式化待办事项的完成时间
+fn completed_time(log: &LogEntry) -> Option<String> {
+    let dt: DateTime<Utc> = log.completed_at.as_deref()?.parse().ok()?;
+    Some(dt.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+}
+
+/// 处理 'todo' 命令
+pub fn handle_todo(recursive: bool) -> Result<Outcome> {
+    let current_dir = env::current_dir()?;
+    if !db::is_initialized()? {
+        print_no_database_hint();
+        return Ok(Outcome::NoMatches);
+    }
+    let conn = db::open_connection()?;
+    let todos = db::fetch_open_todos(&conn, &current_dir, Scope::from_recursive(recursive))?;
+
+    if todos.is_empty() {
+        if !quiet() {
+            println!("No open todos.");
+        }
+        return Ok(Outcome::NoMatches);
+    }
+
+    let mut out = String::new();
+    let layout = ListLayout::for_batch(&todos);
+    let options = RenderOptions {
+        style: ListStyle::Full,
+        show_path: recursive,
+        show_origin: false,
+        max_lines: Some(PREVIEW_LINES),
+        fit_terminal: true,
+        absolute_paths: false,
+    };
+    for log in &todos {
+        match &layout {
+            Some(layout) => render_log_aligned(&mut out, log, layout, options, &[]),
+            None => render_log(&mut out, log, options, &[]),
+        }
+    }
+    if !quiet() {
+        out.push_str(&format!("{} open todo(s).\n", todos.len()));
+    }
+    pager::page(&out)?;
+    Ok(Outcome::Success)
+}
+
+/// 处理 'done' 命令
+pub fn handle_done(id: i32) -> Result<()> {
+    let conn = db::open_connection()?;
+    let log = db::get_log(&conn, id)?.ok_or(DlogError::LogNotFound(id))?;
+
+    match log.status.as_deref() {
+        Some(TODO_OPEN) => {
+            db::complete_todo(&conn, id)?;
+            if !quiet() {
+                println!("✓ Todo #{} marked as done.", id);
+            }
+        }
+        Some(TODO_DONE) => match completed_time(&log) {
+            Some(done_at) => println!("Todo #{} was already done at {}.", id, done_at),
+            None => println!("Todo #{} was already done.", id),
+        },
+        _ => return Err(DlogError::NotATodo(id)),
+    }
+    Ok(())
+}
+
+/// 处理 'link' 命令
+pub fn handle_link(a: i32, b: i32) -> Result<()> {
+    if a == b {
+        return Err(DlogError::InvalidInput("Cannot link a log to itself.".to_string()));
+    }
+    let conn = db::open_connection()?;
+    for id in [a, b] {
+        db::get_log_content(&conn, id)?.ok_or(DlogError::LogNotFound(id))?;
+    }
+    if !db::add_link(&conn, a, b)? {
+        return Err(DlogError::InvalidInput(format!("Logs #{} and #{} are already linked.", a, b)));
+    }
+    if !quiet() {
+        println!("✓ Linked log #{} and #{}.", a, b);
+    }
+    Ok(())
+}
+
+/// 处理 'unlink' 命令
+pub fn handle_unlink(a: i32, b: i32) -> Result<()> {
+    let conn = db::open_connection()?;
+    if !db::remove_link(&conn, a, b)? {
+        return Err(DlogError::InvalidInput(format!("Logs #{} and #{} are not linked.", a, b)));
+    }
+    if !quiet() {
+        println!("✓ Unlinked log #{} and #{}.", a, b);
+    }
+    Ok(())
+}
+
+/// 处理 'tag rename' 命令
+pub fn handle_tag_rename(old: String, new: String, dry_run: bool) -> Result<()> {
+    let old = tags::normalize_tag(&old)?;
+    let new = tags::normalize_tag(&new)?;
+    if old == new {
+        return Err(DlogError::InvalidInput("The new tag name is the same as the old one.".to_string()));
+    }
+
+    let conn = db::open_connection()?;
+    let affected = db::rename_tag(&conn, &old, &new, dry_run)?;
+
+    if affected.is_empty() {
+        println!("No logs are tagged '{}'.", old);
+    } else if dry_run {
+        println!(
+            "Would rename tag '{}' to '{}' in {} log(s): {:?}",
+            old,
+            new,
+            affected.len(),
+            affected
+        );
+    } else {
+        println!("✓ Renamed tag '{}' to '{}' in {} log(s).", old, new, affected.len());
+    }
+    Ok(())
+}
+
+/// 处理 'tag rm' 命令
+pub fn handle_tag_rm(name: String, recursive: bool, all: bool, yes: bool) -> Result<()> {
+    let name = tags::normalize_tag(&name)?;
+    let current_dir = env::current_dir()?;
+    let scope = if all { Scope::All } else { Scope::from_recursive(recursive) };
+
+    let conn = db::open_connection()?;
+    let affected = db::remove_tag(&conn, &name, &current_dir, scope, true)?;
+    if affected.is_empty() {
+        println!("No logs are tagged '{}'.", name);
+        return Ok(());
+    }
+
+    println!(
+        "Tag '{}' will be removed from {} log(s): {:?}",
+        name,
+        affected.len(),
+        affected
+    );
+    if !confirm("Continue?", yes)? {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    let removed = db::remove_tag(&conn, &name, &current_dir, scope, false)?;
+    println!("✓ Removed tag '{}' from {} log(s).", name, removed.len());
+    Ok(())
+}
+
+/// 'tag stats' 中"最近"的天数
+const RECENT_TAG_DAYS: i64 = 30;
+
+/// 处理 'tag stats' 命令
+pub fn handle_tag_stats(
+    path: Option<String>,
+    recursive: bool,
+    all: bool,
+    top: Option<usize>,
+) -> Result<()> {
+    let target_dir = match path {
+        Some(p) => expand_path(&p)?,
+        None => env::current_dir()?,
+    };
+    let scope = if all { Scope::All } else { Scope::from_recursive(recursive) };
+
+    let rows = if db::is_initialized()? {
+        let conn = db::open_connection()?;
+        db::fetch_tag_usage(&conn, &target_dir, scope)?
+    } else {
+        Vec::new()
+    };
+    let recent_since = db::canonical_timestamp(Utc::now() - Duration::days(RECENT_TAG_DAYS));
+    let mut stats = tags::usage_stats(&rows, &recent_since);
+    if stats.is_empty() {
+        println!("No tagged logs found.");
+        return Ok(());
+    }
+    let mut pairs = tags::co_occurrence(&rows);
+    if let Some(n) = top {
+        stats.truncate(n);
+        pairs.truncate(n);
+    }
+
+    let date = |ts: &str| format_time(ts, "%Y-%m-%d");
+    let tag_width = stats.iter().map(|s| s.tag.width()).max().unwrap_or(0).max("TAG".len());
+    let mut out = format!(
+        "{}  {:>5}  {:>5}  {:<10}  {:<10}  TOP DIRECTORY\n",
+        pad_width("TAG", tag_width),
+        "TOTAL",
+        format!("{}D", RECENT_TAG_DAYS),
+        "FIRST",
+        "LAST"
+    );
+    for s in &stats {
+        out.push_str(&format!(
+            "{}  {:>5}  {:>5}  {}  {}  {}\n",
+            pad_width(&s.tag, tag_width),
+            s.total,
+            s.recent,
+            date(&s.first_used),
+            date(&s.last_used),
+            display_path(&s.top_directory)
+        ));
+    }
+    if !pairs.is_empty() {
+        out.push_str("\nOften used together:\n");
+        for ((a, b), count) in &pairs {
+            out.push_str(&format!("  {:>5}  {} + {}\n", count, a, b));
+        }
+    }
+    pager::page(&out)
+}
+
+/// 解析 YYYY-MM-DD 格式的日期参数
+fn parse_date_arg(s: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| DlogError::InvalidInput(format!("Invalid date '{}'. Use YYYY-MM-DD.", s)))
+}
+
+/// 解析 `get --between` 的时间段 HH:MM-HH:MM，开始晚于结束时表示跨越午夜
+fn parse_time_range(s: &str) -> Result<(NaiveTime, NaiveTime)> {
+    let invalid = || {
+        DlogError::InvalidInput(format!(
+            "Invalid time range '{}'. Use HH:MM-HH:MM, e.g. 09:00-12:00.",
+            s
+        ))
+    };
+    let (start, end) = s.trim().split_once('-').ok_or_else(invalid)?;
+    let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").map_err(|_| invalid())?;
+    let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|_| invalid())?;
+    if start == end {
+        return Err(DlogError::InvalidInput(format!(
+            "Time range '{}' is empty, the start and end must differ.",
+            s
+        )));
+    }
+    Ok((start, end))
+}
+
+/// 解析 `get --date` 的参数，返回覆盖的本地日期范围（首尾两天都包含在内）
+///
+/// 支持 YYYY-MM-DD、today、yesterday、星期名（monday 或 mon，表示最近的那一天，
+/// 今天恰好是该星期几时即为今天）以及表示整月的 YYYY-MM。
+pub fn parse_day_span(s: &str) -> Result<(NaiveDate, NaiveDate)> {
+    day_span_on(s, Local::now().date_naive())
+}
+
+/// 以 `today` 为今天解析 `--date` 的参数，见 `parse_day_span`
+fn day_span_on(s: &str, today: NaiveDate) -> Result<(NaiveDate, NaiveDate)> {
+    let invalid = || {
+        DlogError::InvalidInput(format!(
+            "Invalid date '{}'. Use YYYY-MM-DD, YYYY-MM, today, yesterday or a weekday name.",
+            s
+        ))
+    };
+    let lowered = s.trim().to_ascii_lowercase();
+    let day = match lowered.as_str() {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        other => match other.parse::<Weekday>() {
+            Ok(weekday) => {
+                let back = (today.weekday().num_days_from_monday() + 7
+                    - weekday.num_days_from_monday())
+                    % 7;
+                today.checked_sub_signed(Duration::days(back as i64))
+            }
+            Err(_) => NaiveDate::parse_from_str(other, "%Y-%m-%d").ok(),
+        },
+    };
+    if let Some(day) = day {
+        return Ok((day, day));
+    }
+
+    match lowered.split_once('-') {
+        Some((year, month)) if year.len() == 4 && month.len() == 2 => {
+            month_span(year, month).ok_or_else(invalid)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// 解析 `--month` 的参数（YYYY-MM，或只有月份的 MM 表示今年），返回该月的首日和末日
+fn parse_month_span(s: &str) -> Result<(NaiveDate, NaiveDate)> {
+    let invalid =
+        || DlogError::InvalidInput(format!("Invalid month '{}'. Use YYYY-MM or MM.", s));
+    let s = s.trim();
+    let span = match s.split_once('-') {
+        Some((year, month)) if year.len() == 4 && (1..=2).contains(&month.len()) => {
+            month_span(year, month)
+        }
+        Some(_) => None,
+        None if (1..=2).contains(&s.len()) => {
+            month_span(&Local::now().year().to_string(), s)
+        }
+        None => None,
+    };
+    span.ok_or_else(invalid)
+}
+
+/// 某年某月的首日和末日（下月1日的前一天），年月不合法时返回 None
+fn month_span(year: &str, month: &str) -> Option<(NaiveDate, NaiveDate)> {
+    if !year.bytes().chain(month.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let first = NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+    Some((first, last))
+}
+
+/// 处理 'report' 命令
+pub fn handle_report(args: ReportArgs) -> Result<()> {
+    let (start, end, scope, logs) = collect_report(&args)?;
+    // 摘要常用于定时任务，没有日志时默认什么都不输出
+    if args.digest && logs.is_empty() && !args.empty_ok {
+        return Ok(());
+    }
+    let (report, kind) = if args.digest {
+        (render_digest(&logs, start, end), "Digest")
+    } else {
+        // 写入文件的报告保留完整的目录
+        let absolute_paths = args.output.is_some();
+        (render_report(&logs, start, end, scope != Scope::Dir, absolute_paths), "Report")
+    };
+    match &args.output {
+        Some(file) => {
+            write_output(file, &report, args.force)?;
+            if file != "-" {
+                println!("✓ {} with {} entries written to: {}", kind, logs.len(), file);
+            }
+        }
+        None => pager::page(&report)?,
+    }
+    Ok(())
+}
+
+/// 报告的起止日期、范围和按时间正序排列的日志，Markdown 报告和 --digest 共用
+fn collect_report(args: &ReportArgs) -> Result<(NaiveDate, NaiveDate, Scope, Vec<LogEntry>)> {
+    let today = Local::now().date_naive();
+    let mut cutoff = None;
+    let (start, end) = if let Some(from) = &args.from {
+        let start = parse_date_arg(from)?;
+        let end = match &args.to {
+            Some(to) => parse_date_arg(to)?,
+            None => today,
+        };
+        if start > end {
+            return Err(DlogError::InvalidInput(format!(
+                "Start date {} is after end date {}",
+                start, end
+            )));
+        }
+        (start, end)
+    } else if let Some(since) = &args.since {
+        let at = parse_duration_cutoff(since)?;
+        cutoff = Some(at);
+        (at.with_timezone(&Local).date_naive(), today)
+    } else if let Some(month) = &args.month {
+        match month {
+            Some(m) => parse_month_span(m)?,
+            None => (today.with_day(1).unwrap_or(today), today),
+        }
+    } else {
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        (monday, today)
+    };
+
+    let scope = if args.all {
+        Scope::All
+    } else {
+        Scope::from_recursive(args.recursive)
+    };
+
+    let logs = if db::is_initialized()? {
+        let conn = db::open_connection()?;
+        let query = db::LogQuery {
+            since: Some(match cutoff {
+                Some(at) => at,
+                None => local_day_range(start)?.0,
+            }),
+            until: Some(local_day_range(end)?.1),
+            ..Default::default()
+        };
+        let mut logs = db::fetch_logs(&conn, &env::current_dir()?, scope, &query)?;
+        logs.reverse(); // 报告按时间正序排列
+        logs
+    } else {
+        Vec::new()
+    };
+    Ok((start, end, scope, logs))
+}
+
+/// 将 --output 的内容写入文件，`-` 表示标准输出（不经过分页器）
+///
+/// 自动创建上级目录；文件已存在时除非 `force` 否则报错。写入失败的错误信息中包含文件名。
+fn write_output(file: &str, text: &str, force: bool) -> Result<()> {
+    if file == "-" {
+        pager::disable();
+        return pager::page(text);
+    }
+    let path = expand_path(file)?;
+    let with_name = |e: io::Error| DlogError::Io(io::Error::new(e.kind(), format!("{}: {}", file, e)));
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(with_name)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut out = match options.open(&path) {
+        Ok(out) => out,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(DlogError::InvalidInput(format!(
+                "{} already exists, use --force to overwrite it.",
+                file
+            )));
+        }
+        Err(e) => return Err(with_name(e)),
+    };
+    out.write_all(text.as_bytes()).map_err(with_name)?;
+    Ok(())
+}
+
+/// 生成 Markdown 报告：按本地日期分节，每天内按首个标签分组
+///
+/// `show_path` 时在每条日志后附上目录，`absolute_paths` 时不把主目录缩写为 `~`。
+fn render_report(logs: &[LogEntry], start: NaiveDate, end: NaiveDate, show_path: bool, absolute_paths: bool) -> String {
+    let mut out = format!("# dlog report: {} to {}\n", start, end);
+    if logs.is_empty() {
+        out.push_str("\n_No entries in this period._\n");
+        return out;
+    }
+
+    // 没有日志的日期不会出现在分组中
+    let mut days: BTreeMap<NaiveDate, Vec<&LogEntry>> = BTreeMap::new();
+    for log in logs {
+        let Some(time) = local_time(&log.timestamp) else {
+            warn_invalid_timestamp(log);
+            continue;
+        };
+        days.entry(time.date_naive()).or_default().push(log);
+    }
+
+    for (day, entries) in &days {
+        out.push_str(&format!("\n## {} ({})\n", day, day.format("%a")));
+
+        // 按首个标签分组，保持每个标签首次出现的顺序，无标签的日志放在最后
+        let mut groups: Vec<(Option<&str>, Vec<&LogEntry>)> = Vec::new();
+        for log in entries {
+            let tag = log.tags.as_deref().and_then(|t| tags::split_tags(t).first().copied());
+            match groups.iter_mut().find(|(t, _)| *t == tag) {
+                Some((_, group)) => group.push(log),
+                None => groups.push((tag, vec![log])),
+            }
+        }
+        groups.sort_by_key(|(tag, _)| tag.is_none());
+
+        for (tag, group) in &groups {
+            out.push_str(&format!("\n### {}\n\n", tag.unwrap_or("Untagged")));
+            for log in group {
+                out.push_str(&format!("- {}", title_of(log)));
+                if show_path {
+                    let path = if absolute_paths { log.directory.clone() } else { display_path(&log.directory) };
+                    out.push_str(&format!(" (`{}`)", path));
+                }
+                out.push('\n');
+
+                let body = content_after_first_line(&log.content);
+                if !body.is_empty() {
+                    out.push('\n');
+                    for line in body.lines() {
+                        if line.trim().is_empty() {
+                            out.push('\n');
+                        } else {
+                            out.push_str(&format!("  {}\n", line));
+                        }
+                    }
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    // 嵌套内容之后紧跟新的小节时会多出一个空行
+    while out.contains("\n\n\n") {
+        out = out.replace("\n\n\n", "\n\n");
+    }
+    out
+}
+
+/// --digest 每行的最大宽度，适合纯文本邮件
+const DIGEST_WIDTH: usize = 72;
+/// --digest 中列出的标签数
+const DIGEST_TOP_TAGS: usize = 5;
+
+/// 生成纯文本摘要：先是总数、各项目的日志数和常用标签，再按项目列出每条日志的首行
+///
+/// 项目按日志数从多到少排列，项目内的日志按时间正序排列。输出从不包含颜色代码。
+fn render_digest(logs: &[LogEntry], start: NaiveDate, end: NaiveDate) -> String {
+    let mut out = format!("dlog digest: {} to {}\n\n", start, end);
+    if logs.is_empty() {
+        out.push_str("No activity in this period.\n");
+        return out;
+    }
+
+    // 每个目录只查找一次项目根目录
+    let markers = project_markers();
+    let markers: Vec<&str> = markers.iter().map(String::as_str).collect();
+    let mut roots: BTreeMap<&str, String> = BTreeMap::new();
+    let mut projects: BTreeMap<String, Vec<&LogEntry>> = BTreeMap::new();
+    for log in logs {
+        let root = roots.entry(&log.directory).or_insert_with(|| {
+            let dir = Path::new(&log.directory);
+            find_project_root(dir, &markers).unwrap_or(dir).to_string_lossy().into_owned()
+        });
+        projects.entry(root.clone()).or_default().push(log);
+    }
+    let mut projects: Vec<(String, Vec<&LogEntry>)> = projects.into_iter().collect();
+    projects.sort_by(|(a, a_logs), (b, b_logs)| b_logs.len().cmp(&a_logs.len()).then_with(|| a.cmp(b)));
+
+    // 以最后一级目录名显示项目，重名时改用完整路径
+    let short_name = |root: &str| {
+        Path::new(root)
+            .file_name()
+            .map_or_else(|| root.to_string(), |name| name.to_string_lossy().into_owned())
+    };
+    let names: Vec<String> = projects
+        .iter()
+        .map(|(root, _)| {
+            let name = short_name(root);
+            if projects.iter().filter(|(other, _)| short_name(other) == name).count() > 1 {
+                display_path(root)
+            } else {
+                name
+            }
+        })
+        .collect();
+
+    let mut tag_counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for log in logs {
+        for tag in log.tags.as_deref().map(tags::split_tags).unwrap_or_default() {
+            *tag_counts.entry(tag).or_default() += 1;
+        }
+    }
+    let mut top_tags: Vec<(&str, usize)> = tag_counts.into_iter().collect();
+    top_tags.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    top_tags.truncate(DIGEST_TOP_TAGS);
+
+    out.push_str(&format!(
+        "{} {} in {} {}\n",
+        logs.len(),
+        if logs.len() == 1 { "entry" } else { "entries" },
+        projects.len(),
+        if projects.len() == 1 { "project" } else { "projects" }
+    ));
+    let per_project: Vec<String> = names
+        .iter()
+        .zip(&projects)
+        .map(|(name, (_, entries))| format!("{} ({})", name, entries.len()))
+        .collect();
+    push_wrapped(&mut out, &format!("Projects: {}", per_project.join(", ")), "", "  ");
+    if !top_tags.is_empty() {
+        let top: Vec<String> = top_tags.iter().map(|(tag, count)| format!("{} ({})", tag, count)).collect();
+        push_wrapped(&mut out, &format!("Top tags: {}", top.join(", ")), "", "  ");
+    }
+
+    for (name, (_, entries)) in names.iter().zip(&projects) {
+        out.push('\n');
+        push_wrapped(&mut out, &format!("{} ({})", name, entries.len()), "", "  ");
+        for log in entries {
+            push_wrapped(&mut out, title_of(log), "  - ", "    ");
+        }
+    }
+    out
+}
+
+/// 将一行文本按 DIGEST_WIDTH 折行后追加到 `out`，首行和后续行分别加上前缀
+fn push_wrapped(out: &mut String, text: &str, first_prefix: &str, rest_prefix: &str) {
+    let first_width = DIGEST_WIDTH.saturating_sub(first_prefix.width());
+    let rest_width = DIGEST_WIDTH.saturating_sub(rest_prefix.width());
+    for (i, line) in wrap_width(&plain_text(text), first_width, rest_width).iter().enumerate() {
+        out.push_str(if i == 0 { first_prefix } else { rest_prefix });
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+/// 去掉文本中的 ANSI 转义序列（例如 --cmd 记录的彩色输出）和其他控制字符，制表符换成空格
+fn plain_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // CSI 序列以 0x40..=0x7E 范围内的字符结束
+            '\x1b' => {
+                if chars.clone().next() == Some('[') {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+            }
+            '\t' => out.push(' '),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 返回首个非空行之后的内容，并去掉首尾的空行
+fn content_after_first_line(content: &str) -> &str {
+    let mut rest = content.trim_start();
+    rest = rest.split_once('\n').map_or("", |(_, tail)| tail);
+    rest.trim_matches(|c| c == '\n' || c == '\r')
+        .trim_end()
+}
+
+/// 每日笔记中 dlog 管理部分的起止标记
+const OBSIDIAN_BEGIN: &str = "<!-- dlog:begin -->";
+const OBSIDIAN_END: &str = "<!-- dlog:end -->";
+
+/// 处理 'export' 命令
+pub fn handle_export(args: ExportArgs) -> Result<()> {
+    let vault = expand_path(&args.obsidian)?;
+    if !vault.is_dir() {
+        return Err(DlogError::InvalidInput(format!(
+            "Vault directory does not exist: {}",
+            display_path(&vault)
+        )));
+    }
+    if !db::is_initialized()? {
+        print_no_database_hint();
+        return Ok(());
+    }
+
+    let target_path = match &args.path {
+        Some(p) => expand_path(p)?,
+        None => env::current_dir()?,
+    };
+    let scope = if args.all {
+        Scope::All
+    } else {
+        Scope::from_recursive(args.recursive)
+    };
+
+    let conn = db::open_connection()?;
+    let mut logs = db::fetch_logs(&conn, &target_path, scope, &db::LogQuery::default())?;
+    logs.reverse(); // 每日笔记按时间正序排列
+
+    let mut days: BTreeMap<NaiveDate, Vec<&LogEntry>> = BTreeMap::new();
+    for log in &logs {
+        let Some(time) = local_time(&log.timestamp) else {
+            warn_invalid_timestamp(log);
+            continue;
+        };
+        days.entry(time.date_naive()).or_default().push(log);
+    }
+
+    let (mut created, mut updated) = (0, 0);
+    for (day, entries) in &days {
+        // 文件名只由日期生成，日志内容不会进入路径
+        let file = vault.join(format!("{}.md", day.format("%Y-%m-%d")));
+        let section = render_obsidian_section(entries);
+        let note = match std::fs::read_to_string(&file) {
+            Ok(existing) => {
+                updated += 1;
+                replace_obsidian_section(&existing, &section)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                created += 1;
+                section
+            }
+            Err(e) => return Err(e.into()),
+        };
+        std::fs::write(&file, note)?;
+    }
+
+    println!(
+        "✓ Exported {} entries to {} daily note(s) in: {} ({} created, {} updated)",
+        logs.len(),
+        days.len(),
+        display_path(&vault),
+        created,
+        updated
+    );
+    Ok(())
+}
+
+/// 生成一天的 dlog 管理部分（包含起止标记）
+fn render_obsidian_section(entries: &[&LogEntry]) -> String {
+    let mut out = format!("{}\n", OBSIDIAN_BEGIN);
+    for log in entries {
+        out.push_str(&format!(
+            "\n## {} — {}\n\n",
+            format_time(&log.timestamp, "%H:%M"),
+            obsidian_heading(title_of(log))
+        ));
+
+        let hashtags: Vec<String> = log
+            .tags
+            .as_deref()
+            .map(tags::split_tags)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(obsidian_hashtag)
+            .collect();
+        if !hashtags.is_empty() {
+            out.push_str(&format!("{}\n", hashtags.join(" ")));
+        }
+        out.push_str(&format!("directory:: `{}`\n", log.directory));
+        if let Some(hostname) = &log.hostname {
+            out.push_str(&format!("host:: {}\n", hostname));
+        }
+        if let Some(username) = &log.username {
+            out.push_str(&format!("user:: {}\n", username));
+        }
+        if log.level != Level::Normal {
+            out.push_str(&format!("level:: {}\n", log.level.as_str()));
+        }
+        if let Some(status) = &log.status {
+            out.push_str(&format!("status:: {}\n", status));
+        }
+
+        let body = content_after_first_line(&log.content);
+        if !body.is_empty() {
+            out.push_str(&format!("\n{}\n", body));
+        }
+    }
+    out.push_str(&format!("\n{}\n", OBSIDIAN_END));
+    out
+}
+
+/// 用新内容替换笔记中已有的 dlog 管理部分，没有标记时追加到末尾
+fn replace_obsidian_section(existing: &str, section: &str) -> String {
+    if let Some(begin) = existing.find(OBSIDIAN_BEGIN) {
+        if let Some(end) = existing[begin..].find(OBSIDIAN_END) {
+            let after = &existing[begin + end + OBSIDIAN_END.len()..];
+            let after = after.strip_prefix('\n').unwrap_or(after);
+            return format!("{}{}{}", &existing[..begin], section, after);
+        }
+    }
+    let mut note = existing.trim_end().to_string();
+    if !note.is_empty() {
+        note.push_str("\n\n");
+    }
+    note.push_str(section);
+    note
+}
+
+/// 将日志首行转换为安全的 Markdown 标题文本
+fn obsidian_heading(line: &str) -> String {
+    // 这些字符在 Obsidian 标题中用于链接和块引用
+    let cleaned: String = line
+        .chars()
+        .map(|c| match c {
+            '#' | '[' | ']' | '|' | '^' | '/' | '\\' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    if cleaned.is_empty() {
+        "(untitled)".to_string()
+    } else {
+        truncate_width(&cleaned, ONELINE_CONTENT_WIDTH)
+    }
+}
+
+/// 将 dlog 标签转换为 Obsidian 的 #标签，空格替换为连字符，去掉不允许的字符
+fn obsidian_hashtag(tag: &str) -> Option<String> {
+    let name: String = tag
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '_' || c == '-' || c == '/' => Some(c),
+            c if c.is_whitespace() => Some('-'),
+            _ => None,
+        })
+        .collect();
+    // Obsidian 不把纯数字识别为标签
+    if name.chars().all(|c| c.is_ascii_digit() || c == '-' || c == '/') {
+        None
+    } else {
+        Some(format!("#{}", name))
+    }
+}
+
+/// 处理 'import' 命令
+pub fn handle_import(args: ImportArgs) -> Result<()> {
+    if args.date_from_filename && args.format != Some(ImportFormat::Plain) {
+        return Err(DlogError::InvalidInput(
+            "--date-from-filename can only be used with --format plain".to_string(),
+        ));
+    }
+
+    let target_dir = match &args.dir {
+        // 提交历史记录到仓库的根目录，--dir 与 --from-git 不能同时使用
+        None if args.from_git => {
+            let root = run_git(&["rev-parse".to_string(), "--show-toplevel".to_string()])?;
+            db::normalize_path(Path::new(root.trim()))?
+        }
+        Some(d) => {
+            let path = expand_path(d)?;
+            if !path.is_dir() {
+                return Err(DlogError::InvalidInput(format!("Directory does not exist: {}", d)));
+            }
+            db::normalize_path(&path)?
+        }
+        None => db::normalize_path(&env::current_dir()?)?,
+    };
+
+    let mut skipped = 0;
+    let mut entries: Vec<ImportedEntry> = Vec::new();
+    match args.format {
+        // 没有 --format 时即为 --from-git，由 clap 保证
+        None => entries = import::parse_git_log(&run_git(&git_log_args(&args))?),
+        Some(ImportFormat::Jrnl) => {
+            for file in &args.files {
+                entries.extend(import::parse_jrnl(&std::fs::read_to_string(expand_path(file)?)?));
+            }
+        }
+        Some(ImportFormat::Plain) => {
+            for path in collect_import_files(&args.files)? {
+                let timestamp = if args.date_from_filename {
+                    match import::date_from_filename(&path) {
+                        Some(day) => day.and_time(NaiveTime::MIN),
+                        None => {
+                            eprintln!("Skipped {}: file name is not a YYYY-MM-DD date", display_path(&path));
+                            skipped += 1;
+                            continue;
+                        }
+                    }
+                } else {
+                    DateTime::<Local>::from(std::fs::metadata(&path)?.modified()?).naive_local()
+                };
+                entries.push(ImportedEntry {
+                    timestamp,
+                    content: std::fs::read_to_string(&path)?.trim().to_string(),
+                    tags: Vec::new(),
+                });
+            }
+        }
+    }
+    let parsed = entries.len() + skipped;
+
+    let conn = if db::is_initialized()? {
+        Some(db::open_connection()?)
+    } else if args.dry_run {
+        None
+    } else {
+        db::initialize_db()?;
+        println!("✓ Created a new database at: {:?}", db::get_db_path()?);
+        Some(db::open_connection()?)
+    };
+
+    // 已经导入过的提交的短哈希
+    let recorded: Vec<String> = match &conn {
+        Some(conn) if args.from_git => {
+            let query = db::LogQuery { tag: Some("git"), ..Default::default() };
+            db::fetch_logs(conn, Path::new(&target_dir), Scope::Dir, &query)?
+                .iter()
+                .filter_map(|log| import::recorded_commit(&log.content).map(str::to_string))
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let mut pending = Vec::new();
+    for entry in &entries {
+        let timestamp = local_to_utc(entry.timestamp)?;
+        let duplicate = match &conn {
+            Some(conn) => db::log_exists(conn, &target_dir, &entry.content, timestamp)?,
+            None => false,
+        } || import::recorded_commit(&entry.content)
+            .is_some_and(|hash| recorded.iter().any(|r| import::same_commit(r, hash)));
+        if entry.content.is_empty() || duplicate {
+            skipped += 1;
+        } else {
+            pending.push((timestamp, entry));
+        }
+    }
+
+    if args.dry_run {
+        for (_, entry) in &pending {
+            let tags = if entry.tags.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", entry.tags.join(","))
+            };
+            println!(
+                "{}  {}{}",
+                entry.timestamp.format("%Y-%m-%d %H:%M"),
+                truncate_width(content_title(&entry.content), ONELINE_CONTENT_WIDTH),
+                tags
+            );
+        }
+        println!(
+            "Dry run: parsed {}, would import {} into {}, skipped {}.",
+            parsed,
+            pending.len(),
+            display_path(&target_dir),
+            skipped
+        );
+        return Ok(());
+    }
+
+    if let Some(conn) = &conn {
+        // 全部导入或全部不导入，避免中途出错时留下一半数据
+        db::with_transaction(conn, |tx| {
+            for (timestamp, entry) in &pending {
+                let tags = tags::normalize_tags(&entry.tags.join(","))?;
+                db::add_log_at(tx, &target_dir, &entry.content, tags.as_deref(), false, Level::Normal, *timestamp)?;
+            }
+            Ok(())
+        })?;
+    }
+    println!(
+        "✓ Parsed {}, imported {} into {}, skipped {}.",
+        parsed,
+        pending.len(),
+        display_path(&target_dir),
+        skipped
+    );
+    Ok(())
+}
+
+/// 'import --from-git' 传给 git 的参数，按时间从旧到新列出提交
+fn git_log_args(args: &ImportArgs) -> Vec<String> {
+    let mut git_args = vec!["log".to_string(), "--reverse".to_string(), import::GIT_LOG_FORMAT.to_string()];
+    if let Some(since) = &args.since {
+        git_args.push(format!("--since={}", since));
+    }
+    if let Some(author) = &args.author {
+        git_args.push(format!("--author={}", author));
+    }
+    if args.no_merges {
+        git_args.push("--no-merges".to_string());
+    }
+    git_args
+}
+
+/// 在当前目录运行 git 并返回标准输出，失败时报告 git 的错误信息
+fn run_git(args: &[String]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| DlogError::InvalidInput(format!("Could not run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(DlogError::InvalidInput(format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 展开 plain 导入的参数：目录替换为其中的文件（不含隐藏文件），按文件名排序
+fn collect_import_files(paths: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for p in paths {
+        let path = expand_path(p)?;
+        if !path.is_dir() {
+            files.push(path);
+            continue;
+        }
+        let mut children = Vec::new();
+        for entry in std::fs::read_dir(&path)? {
+            let child = entry?.path();
+            let hidden = child.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            if child.is_file() && !hidden {
+                children.push(child);
+            }
+        }
+        children.sort();
+        files.extend(children);
+    }
+    Ok(files)
+}
+
+/// 处理 'merge' 命令
+pub fn handle_merge(file: String, prefix_dir: Vec<String>, dry_run: bool) -> Result<()> {
+    let prefixes = prefix_dir.iter().map(|spec| parse_prefix_dir(spec)).collect::<Result<Vec<_>>>()?;
+    let path = expand_path(&file)?;
+    let other = db::open_readonly_any_version_at(&path)?;
+
+    let db_path = db::get_db_path()?;
+    if db_path.exists() && path.canonicalize()? == db_path.canonicalize()? {
+        return Err(DlogError::InvalidInput(
+            "Cannot merge the database into itself".to_string(),
+        ));
+    }
+    if !dry_run && !db::is_initialized()? {
+        db::initialize_db()?;
+        println!("✓ Created a new database at: {:?}", db_path);
+    }
+    let conn = db::open_connection()?;
+    let report = db::merge_logs(&conn, &other, &prefixes, dry_run)?;
+
+    if dry_run {
+        for log in &report.imported {
+            println!(
+                "  [{}] {}  {}  {}",
+                log.id,
+                format_time(&log.timestamp, "%Y-%m-%d"),
+                display_path(&log.directory),
+                truncate_width(title_of(log), 60)
+            );
+        }
+        println!(
+            "Dry run: would import {} (of which {} need a new ID), skip {} already present.",
+            report.imported.len(),
+            report.conflicted,
+            report.skipped
+        );
+        return Ok(());
+    }
+
+    println!(
+        "✓ Imported {} log(s) from {}, skipped {} already present.",
+        report.imported.len(),
+        display_path(&path),
+        report.skipped
+    );
+    if report.conflicted > 0 {
+        println!("  {} of them got a new ID because their ID was already used here.", report.conflicted);
+    }
+    if report.links > 0 {
+        println!("  {} link(s) between logs added.", report.links);
+    }
+    Ok(())
+}
+
+/// 处理 'sync export' 命令
+pub fn handle_sync_export(file: String, since_last: bool, force: bool, dry_run: bool) -> Result<()> {
+    let conn = db::open_connection()?;
+    // 在查询之前取时间，导出期间的修改下次还会导出
+    let started = db::canonical_timestamp(Utc::now());
+    let since = if since_last { db::last_sync_export(&conn)? } else { None };
+    let records = db::sync_changes(&conn, since.as_deref())?;
+    let deletions = records.iter().filter(|r| matches!(r, SyncRecord::Deleted { .. })).count();
+    let entries = records.len() - deletions;
+    let changes = match &since {
+        Some(at) => format!(" changed since the last export ({})", format_time(at, "%Y-%m-%d %H:%M")),
+        None => String::new(),
+    };
+
+    if dry_run {
+        println!(
+            "Dry run: would export {} entries and {} deletion(s){}, no changes made.",
+            entries, deletions, changes
+        );
+        return Ok(());
+    }
+    if records.is_empty() {
+        if !quiet() {
+            match &since {
+                Some(at) => println!("No changes since the last export ({}).", format_time(at, "%Y-%m-%d %H:%M")),
+                None => println!("Nothing to export, the database is empty."),
+            }
+        }
+        return Ok(());
+    }
+    let out: String = records.iter().map(|r| format!("{}\n", r.to_line())).collect();
+    write_output(&file, &out, force)?;
+    db::record_sync_export(&conn, &started)?;
+    if file != "-" && !quiet() {
+        println!("✓ Exported {} entries and {} deletion(s){} to {}", entries, deletions, changes, file);
+    }
+    Ok(())
+}
+
+/// 处理 'sync import' 命令
+pub fn handle_sync_import(file: String, dry_run: bool) -> Result<()> {
+    let text = if file == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        let path = expand_path(&file)?;
+        std::fs::read_to_string(&path)
+            .map_err(|e| DlogError::Io(io::Error::new(e.kind(), format!("{}: {}", file, e))))?
+    };
+    let records = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            sync::parse_line(line).map_err(|e| match e {
+                DlogError::InvalidInput(msg) => DlogError::InvalidInput(format!("{} line {}: {}", file, i + 1, msg)),
+                other => other,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if !dry_run && !db::is_initialized()? {
+        db::initialize_db()?;
+        println!("✓ Created a new database at: {:?}", db::get_db_path()?);
+    }
+    let conn = db::open_connection()?;
+    let report = db::apply_sync_records(&conn, &records, dry_run)?;
+
+    for conflict in &report.conflicts {
+        let id = conflict.local_id.map_or("-".to_string(), |id| format!("#{}", id));
+        let reason = match conflict.kind {
+            db::SyncConflictKind::LocalNewer => "kept the local version, it is newer",
+            db::SyncConflictKind::DeletedLocally => "edited there but deleted here, kept it deleted",
+            db::SyncConflictKind::EditedAfterDeletion => "deleted there but edited here afterwards, kept it",
+            db::SyncConflictKind::Locked => "deleted there but locked here, kept it",
+        };
+        println!("  conflict {:>6}  {}  {}", id, conflict.uuid, reason);
+    }
+    println!(
+        "{} {} change(s), skipped {}, {} conflict(s).",
+        if dry_run { "Dry run: would apply" } else { "✓ Applied" },
+        report.applied,
+        report.skipped,
+        report.conflicts.len()
+    );
+    Ok(())
+}
+
+/// 解析 `--prefix-dir OLD=NEW`：OLD 是另一台机器上的路径，按原样比较；NEW 按本机路径规范化
+fn parse_prefix_dir(spec: &str) -> Result<(String, String)> {
+    let invalid = || DlogError::InvalidInput(format!("Invalid prefix '{}', expected OLD=NEW", spec));
+    let (old, new) = spec.split_once('=').ok_or_else(invalid)?;
+    let (old, new) = (old.trim(), new.trim());
+    if old.is_empty() || new.is_empty() {
+        return Err(invalid());
+    }
+    let old = match old.trim_end_matches(['/', '\\']) {
+        "" => old,
+        trimmed => trimmed,
+    };
+    Ok((old.to_string(), db::normalize_path(&expand_path(new)?)?))
+}
+
+/// 热力图中各活跃等级（0 表示没有日志）使用的字符
+const HEATMAP_ASCII: [char; 5] = ['.', '-', '+', '*', '#'];
+const HEATMAP_BLOCKS: [char; 5] = ['·', '░', '▒', '▓', '█'];
+/// 真彩色终端下各等级的颜色
+const HEATMAP_COLORS: [(u8, u8, u8); 5] = [
+    (110, 118, 129),
+    (14, 68, 41),
+    (0, 109, 50),
+    (38, 166, 65),
+    (57, 211, 83),
+];
+
+/// 'stats --words' 统计每周写作量的周数
+const WORD_TREND_WEEKS: usize = 12;
+/// 每周写作量柱状图的最大宽度
+const WORD_TREND_BAR_WIDTH: usize = 30;
+
+/// 处理 'stats' 命令
+pub fn handle_stats(path: Option<String>, recursive: bool, all: bool, words: bool, json: bool) -> Result<Outcome> {
+    let target_dir = match path {
+        Some(p) => expand_path(&p)?,
+        None => env::current_dir()?,
+    };
+    let scope = if all { Scope::All } else { Scope::from_recursive(recursive) };
+    let logs = if db::is_initialized()? {
+        let conn = db::open_connection()?;
+        db::fetch_logs(&conn, &target_dir, scope, &db::LogQuery::default())?
+    } else {
+        Vec::new()
+    };
+    if logs.is_empty() && !json {
+        println!("No logs found.");
+        return Ok(Outcome::NoMatches);
+    }
+
+    let dates: Vec<Option<NaiveDate>> =
+        logs.iter().map(|log| local_time(&log.timestamp).map(|t| t.date_naive())).collect();
+    let days: BTreeSet<NaiveDate> = dates.iter().flatten().copied().collect();
+    let (first, last) = (days.first().copied(), days.last().copied());
+
+    let counts: Vec<words::TextCount> = logs.iter().map(|log| words::count(&log.content)).collect();
+    let total_words: usize = counts.iter().map(|c| c.words).sum();
+    let total_chars: usize = counts.iter().map(|c| c.chars).sum();
+    let average = |total: usize| if logs.is_empty() { 0.0 } else { total as f64 / logs.len() as f64 };
+    let longest = logs.iter().zip(&counts).max_by_key(|(_, c)| c.words).filter(|(_, c)| c.words > 0);
+
+    // 最近几周（从周一开始，本地时间）每周写下的词数
+    let today = Local::now().date_naive();
+    let first_week = today
+        - Duration::days(today.weekday().num_days_from_monday() as i64 + 7 * (WORD_TREND_WEEKS as i64 - 1));
+    let mut weekly = [0usize; WORD_TREND_WEEKS];
+    for (date, count) in dates.iter().zip(&counts) {
+        let Some(date) = date.filter(|d| *d >= first_week) else {
+            continue;
+        };
+        if let Some(week) = weekly.get_mut(((date - first_week).num_days() / 7) as usize) {
+            *week += count.words;
+        }
+    }
+    let week_start = |i: usize| first_week + Duration::days(7 * i as i64);
+
+    let mut out = String::new();
+    if json {
+        let date = |d: Option<NaiveDate>| d.map_or("null".to_string(), |d| format!("\"{}\"", d));
+        out.push_str(&format!(
+            "{{\"entries\": {}, \"active_days\": {}, \"first\": {}, \"last\": {}",
+            logs.len(),
+            days.len(),
+            date(first),
+            date(last)
+        ));
+        if words {
+            let longest = match longest {
+                Some((log, count)) => format!(
+                    "{{\"id\": {}, \"words\": {}, \"title\": {}}}",
+                    log.id,
+                    count.words,
+                    json::json_string(title_of(log))
+                ),
+                None => "null".to_string(),
+            };
+            let weeks: Vec<String> = weekly
+                .iter()
+                .enumerate()
+                .map(|(i, w)| format!("{{\"week\": \"{}\", \"words\": {}}}", week_start(i), w))
+                .collect();
+            out.push_str(&format!(
+                ", \"words\": {}, \"characters\": {}, \"words_per_entry\": {:.1}, \"characters_per_entry\": {:.1}, \"longest\": {}, \"weeks\": [{}]",
+                total_words,
+                total_chars,
+                average(total_words),
+                average(total_chars),
+                longest,
+                weeks.join(", ")
+            ));
+        }
+        out.push_str("}\n");
+        pager::page(&out)?;
+        return Ok(if logs.is_empty() { Outcome::NoMatches } else { Outcome::Success });
+    }
+
+    let date = |d: Option<NaiveDate>| d.map_or("-".to_string(), |d| d.to_string());
+    out.push_str(&format!("Entries:      {} on {} day(s)\n", logs.len(), days.len()));
+    out.push_str(&format!("First entry:  {}\n", date(first)));
+    out.push_str(&format!("Last entry:   {}\n", date(last)));
+    if words {
+        out.push_str(&format!("Words:        {} ({:.1} per entry)\n", total_words, average(total_words)));
+        out.push_str(&format!("Characters:   {} ({:.1} per entry)\n", total_chars, average(total_chars)));
+        if let Some((log, count)) = longest {
+            out.push_str(&format!(
+                "Longest:      #{}, {} words: {}\n",
+                log.id,
+                count.words,
+                truncate_width(title_of(log), ONELINE_CONTENT_WIDTH)
+            ));
+        }
+        out.push_str(&format!("\nWords written per week (last {} weeks):\n", WORD_TREND_WEEKS));
+        let max = weekly.iter().copied().max().unwrap_or(0);
+        let width = max.to_string().len();
+        for (i, w) in weekly.iter().enumerate() {
+            let bar = if max == 0 { 0 } else { (w * WORD_TREND_BAR_WIDTH).div_ceil(max) };
+            let line = format!("  {}  {:>width$}  {}", week_start(i), w, "#".repeat(bar), width = width);
+            out.push_str(&format!("{}\n", line.trim_end()));
+        }
+    }
+    pager::page(&out)?;
+    Ok(Outcome::Success)
+}
+
+/// 处理 'dirs' 命令
+pub fn handle_dirs(recent: bool, num: Option<u32>, paths_only: bool, existing: bool) -> Result<Outcome> {
+    if !db::is_initialized()? {
+        if !paths_only {
+            print_no_database_hint();
+        }
+        return Ok(Outcome::NoMatches);
+    }
+    let conn = db::open_connection()?;
+    let mut dirs = db::directory_activity(&conn, recent)?;
+    if existing {
+        dirs.retain(|(dir, _, _)| Path::new(dir).is_dir());
+    }
+    if let Some(n) = num {
+        dirs.truncate(n as usize);
+    }
+    if dirs.is_empty() {
+        if !paths_only {
+            println!("No directories found.");
+        }
+        return Ok(Outcome::NoMatches);
+    }
+
+    let mut out = String::new();
+    let width = dirs.iter().map(|(_, _, count)| count.to_string().len()).max().unwrap_or(1);
+    for (dir, last, count) in &dirs {
+        if paths_only {
+            out.push_str(&format!("{}\n", dir));
+            continue;
+        }
+        let time = format_time(last, "%Y-%m-%d %H:%M");
+        let time = if color::enabled() { color::dim(&time) } else { time };
+        out.push_str(&format!("{}  {:>width$}  {}\n", time, count, display_path(dir), width = width));
+    }
+    pager::page(&out)?;
+    Ok(Outcome::Success)
+}
+
+/// 处理 'calendar' 命令
+pub fn handle_calendar(weeks: u32, recursive: bool, all: bool, sunday: bool) -> Result<()> {
+    let today = Local::now().date_naive();
+    let week_start = if sunday {
+        today.weekday().num_days_from_sunday()
+    } else {
+        today.weekday().num_days_from_monday()
+    };
+    let first_day = today - Duration::days(week_start as i64 + 7 * (weeks as i64 - 1));
+
+    let scope = if all {
+        Scope::All
+    } else {
+        Scope::from_recursive(recursive)
+    };
+    let counts: BTreeMap<NaiveDate, i64> = if db::is_initialized()? {
+        let conn = db::open_connection()?;
+        let (since, _) = local_day_range(first_day)?;
+        let (_, until) = local_day_range(today)?;
+        db::count_logs_per_day(&conn, &env::current_dir()?, scope, since, until)?
+            .into_iter()
+            .collect()
+    } else {
+        BTreeMap::new()
+    };
+
+    let max = counts.values().copied().max().unwrap_or(0);
+    let level = |count: i64| -> usize {
+        if count <= 0 || max == 0 {
+            0
+        } else {
+            // 按最大值的四分位划分为 1-4 级
+            ((count * 4 + max - 1) / max).clamp(1, 4) as usize
+        }
+    };
+
+    // 真彩色终端使用彩色方块，否则（包括输出被重定向或 --color never 时）使用 ASCII 密度字符
+    let truecolor = color::truecolor();
+    let cell = |lvl: usize| -> String {
+        if truecolor {
+            let (r, g, b) = HEATMAP_COLORS[lvl];
+            format!("\x1b[38;2;{};{};{}m{}\x1b[0m", r, g, b, HEATMAP_BLOCKS[lvl])
+        } else {
+            HEATMAP_ASCII[lvl].to_string()
+        }
+    };
+
+    // 月份坐标轴：在每月第一次出现的那一列标注月份，每列占两个字符
+    let mut axis = vec![' '; weeks as usize * 2 + 2];
+    let mut last_month = None;
+    let mut next_free = 0;
+    for week in 0..weeks as usize {
+        let column_start = first_day + Duration::days(week as i64 * 7);
+        if last_month == Some(column_start.month()) || week * 2 < next_free {
+            continue;
+        }
+        last_month = Some(column_start.month());
+        let label = column_start.format("%b").to_string();
+        for (i, c) in label.chars().enumerate() {
+            axis[week * 2 + i] = c;
+        }
+        next_free = week * 2 + label.len() + 1;
+    }
+    let axis: String = axis.into_iter().collect();
+    println!("     {}", axis.trim_end());
+
+    for row in 0..7 {
+        let label_day = first_day + Duration::days(row);
+        let label = match label_day.weekday() {
+            Weekday::Mon | Weekday::Wed | Weekday::Fri => label_day.format("%a").to_string(),
+            _ => String::new(),
+        };
+        let mut line = format!("{:<4} ", label);
+        for week in 0..weeks as i64 {
+            let day = first_day + Duration::days(week * 7 + row);
+            if day > today {
+                break;
+            }
+            line.push_str(&cell(level(counts.get(&day).copied().unwrap_or(0))));
+            line.push(' ');
+        }
+        println!("{}", line.trim_end());
+    }
+
+    let legend: Vec<String> = (0..5).map(cell).collect();
+    println!("\n     Less {} More", legend.join(" "));
+
+    let total: i64 = counts.values().sum();
+    let (current, longest) = logging_streaks(&counts, first_day, today);
+    println!(
+        "\n{} entries on {} days in the last {} weeks.",
+        total,
+        counts.len(),
+        weeks
+    );
+    println!("Current streak: {} day(s), longest streak: {} day(s).", current, longest);
+    Ok(())
+}
+
+
+/// 处理 'random' 命令
+pub fn handle_random(recursive: bool, all: bool, tag: Option<String>) -> Result<Outcome> {
+    if !db::is_initialized()? {
+        print_no_database_hint();
+        return Ok(Outcome::NoMatches);
+    }
+    let scope = if all {
+        Scope::All
+    } else {
+        Scope::from_recursive(recursive)
+    };
+    let tag = tag.as_deref().map(tags::normalize_tag).transpose()?;
+    let query = db::LogQuery {
+        tag: tag.as_deref(),
+        ..Default::default()
+    };
+    let conn = db::open_connection()?;
+    let Some(log) = db::fetch_random_log(&conn, &env::current_dir()?, scope, &query)? else {
+        if !quiet() {
+            println!("No logs to pick from.");
+        }
+        return Ok(Outcome::NoMatches);
+    };
+
+    let mut out = String::new();
+    let options = RenderOptions {
+        style: ListStyle::Full,
+        show_path: scope != Scope::Dir,
+        show_origin: false,
+        max_lines: None,
+        fit_terminal: true,
+        absolute_paths: false,
+    };
+    let related = db::fetch_links(&conn, &[log.id])?.remove(&log.id).unwrap_or_default();
+    render_log(&mut out, &log, options, &related);
+    pager::page(&out)?;
+    Ok(Outcome::Success)
+}
+
+/// 处理 'onthisday' 命令
+pub fn handle_on_this_day(recursive: bool, all: bool) -> Result<Outcome> {
+    if !db::is_initialized()? {
+        print_no_database_hint();
+        return Ok(Outcome::NoMatches);
+    }
+    let scope = if all {
+        Scope::All
+    } else {
+        Scope::from_recursive(recursive)
+    };
+    let today = Local::now().date_naive();
+    let query = db::LogQuery {
+        month_day: Some((today.month(), today.day())),
+        until: Some(local_day_range(today)?.0),
+        ..Default::default()
+    };
+    let conn = db::open_connection()?;
+    let logs = db::fetch_logs(&conn, &env::current_dir()?, scope, &query)?;
+    if logs.is_empty() {
+        if !quiet() {
+            println!("Nothing was logged on {} in previous years.", today.format("%B %-d"));
+        }
+        return Ok(Outcome::NoMatches);
+    }
+
+    let mut out = String::new();
+    let layout = ListLayout::for_batch(&logs);
+    let options = RenderOptions {
+        style: ListStyle::Full,
+        show_path: scope != Scope::Dir,
+        show_origin: false,
+        max_lines: Some(PREVIEW_LINES),
+        fit_terminal: true,
+        absolute_paths: false,
+    };
+    let mut current_year = None;
+    for log in &logs {
+        // 日志按时间倒序排列，年份变化时输出分组标题
+        let Some(year) = local_time(&log.timestamp).map(|t| t.year()) else {
+            continue;
+        };
+        if current_year != Some(year) {
+            if current_year.is_some() {
+                out.push('\n');
+            }
+            let ago = today.year() - year;
+            out.push_str(&format!("{} ({} year{} ago)\n", year, ago, if ago == 1 { "" } else { "s" }));
+            current_year = Some(year);
+        }
+        match &layout {
+            Some(layout) => render_log_aligned(&mut out, log, layout, options, &[]),
+            None => render_log(&mut out, log, options, &[]),
+        }
+    }
+    pager::page(&out)?;
+    Ok(Outcome::Success)
+}
+
+/// 处理 'grep' 命令
+pub fn handle_grep(
+    pattern: String,
+    regex: bool,
+    ignore_case: bool,
+    context: usize,
+    recursive: bool,
+    all: bool,
+) -> Result<Outcome> {
+    let matcher: Box<dyn Fn(&str) -> bool> = if regex {
+        let source = if ignore_case { format!("(?i){}", pattern) } else { pattern.clone() };
+        let re = fancy_regex::Regex::new(&source)
+            .map_err(|e| DlogError::InvalidInput(format!("Invalid regex '{}': {}", pattern, e)))?;
+        // 回溯次数超出限制时按不匹配处理
+        Box::new(move |line: &str| re.is_match(line).unwrap_or(false))
+    } else if ignore_case {
+        let needle = pattern.to_lowercase();
+        Box::new(move |line: &str| line.to_lowercase().contains(&needle))
+    } else {
+        let needle = pattern.clone();
+        Box::new(move |line: &str| line.contains(&needle))
+    };
+
+    if !db::is_initialized()? {
+        print_no_database_hint();
+        return Ok(Outcome::NoMatches);
+    }
+    let scope = if all {
+        Scope::All
+    } else {
+        Scope::from_recursive(recursive)
+    };
+    // 按字面查找时先在数据库中粗筛，正则表达式只能逐条检查
+    let terms = if regex { Vec::new() } else { vec![pattern.clone()] };
+    let query = db::LogQuery {
+        terms: &terms,
+        case_sensitive: !ignore_case,
+        ..Default::default()
+    };
+    let conn = db::open_connection()?;
+    let logs = db::fetch_logs(&conn, &env::current_dir()?, scope, &query)?;
+
+    let mut out = String::new();
+    let mut found = false;
+    for log in &logs {
+        if search::looks_binary(&log.content) {
+            if log.content.split('\0').any(|part| part.lines().any(&matcher)) {
+                out.push_str(&format!("Log #{}: binary content matches\n", log.id));
+                found = true;
+            }
+            continue;
+        }
+        let groups = search::grep_lines(&log.content, context, &matcher);
+        if groups.is_empty() {
+            continue;
+        }
+        if found {
+            out.push('\n');
+        }
+        found = true;
+        let path = if scope == Scope::Dir {
+            String::new()
+        } else {
+            format!("  {}", display_path(&log.directory))
+        };
+        let header = format!("[{}] {}{}", log.id, format_time(&log.timestamp, "%Y-%m-%d %H:%M"), path);
+        out.push_str(&if color::enabled() { color::dim(&header) } else { header });
+        out.push('\n');
+        for (i, group) in groups.iter().enumerate() {
+            if i > 0 {
+                out.push_str("--\n");
+            }
+            for line in group {
+                let sep = if line.matched { ':' } else { '-' };
+                out.push_str(&format!("{}{}{}{} {}\n", log.id, sep, line.number, sep, line.text));
+            }
+        }
+    }
+
+    if !found {
+        if !quiet() {
+            println!("No matches found.");
+        }
+        return Ok(Outcome::NoMatches);
+    }
+    pager::page(&out)?;
+    Ok(Outcome::Success)
+}
+
+/// 计算连续记录的天数：(当前连续天数, 最长连续天数)
+///
+/// 今天还没有记录时，当前连续天数从昨天开始计算。
+fn logging_streaks(counts: &BTreeMap<NaiveDate, i64>, first_day: NaiveDate, today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut day = first_day;
+    while day <= today {
+        if counts.contains_key(&day) {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+        day += Duration::days(1);
+    }
+
+    let mut current = 0;
+    let mut day = if counts.contains_key(&today) {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+    while day >= first_day && counts.contains_key(&day) {
+        current += 1;
+        day -= Duration::days(1);
+    }
+    (current, longest)
+}
+
+/// 处理 'completions' 命令，不访问数据库
+pub fn handle_completions(shell: Shell) -> Result<()> {
+    let mut cmd = Cli::command();
+    let name = cmd.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cmd, name, &mut script);
+    let script = complete::add_dynamic(shell, String::from_utf8_lossy(&script).into_owned());
+    pager::print(&script)?;
+    Ok(())
+}
+
+/// 处理隐藏的 '__complete' 命令，供补全脚本调用
+pub fn handle_complete(what: CompleteKind, prefix: String) -> Result<()> {
+    let mut out = String::new();
+    for candidate in complete::candidates(what, &prefix) {
+        out.push_str(&format!("{}\n", candidate));
+    }
+    // 补全过程中 shell 可能提前关闭管道，此时不报错
+    let _ = pager::print(&out);
+    Ok(())
+}
+
+/// 处理 'prune' 命令
+pub fn handle_prune(args: PruneArgs) -> Result<()> {
+    let scope = if args.all {
+        Scope::All
+    } else {
+        Scope::from_recursive(args.recursive)
+    };
+    let archive = args.archive_to.as_deref().map(expand_path).transpose()?;
+    prune_logs(&args.older_than, scope, archive.as_deref(), args.dry_run, args.yes)
+}
+
+/// 处理 'archive' 命令
+pub fn handle_archive(args: ArchiveArgs) -> Result<()> {
+    let archive = match args.to.as_deref() {
+        Some(p) => expand_path(p)?,
+        None => db::default_archive_path()?,
+    };
+    if args.restore {
+        let ids = parse_id_range(args.ids.as_deref().unwrap_or(""))?;
+        return restore_from_archive(&archive, &ids);
+    }
+
+    let scope = if args.all {
+        Scope::All
+    } else {
+        Scope::from_recursive(args.recursive)
+    };
+    // clap 保证不使用 --restore 时提供了 --older-than
+    let older_than = args.older_than.as_deref().unwrap_or_default();
+    prune_logs(older_than, scope, Some(&archive), args.dry_run, args.yes)
+}
+
+/// 将归档中的日志移回当前数据库
+fn restore_from_archive(archive: &Path, ids: &[i32]) -> Result<()> {
+    if !archive.exists() || !db::is_dlog_database(archive)? {
+        return Err(DlogError::InvalidInput(format!(
+            "{} is not a dlog archive",
+            display_path(archive)
+        )));
+    }
+    if !db::is_initialized()? {
+        db::initialize_db()?;
+    }
+    let conn = db::open_connection()?;
+    let restored = db::restore_logs(&conn, archive, ids)?;
+    println!("✓ Restored {} log(s) from: {}", restored, display_path(archive));
+    if restored < ids.len() {
+        println!("{} of the requested IDs were not in the archive.", ids.len() - restored);
+    }
+    Ok(())
+}
+
+/// 以只读方式打开 --from-archive 指定的归档文件，未指定文件时使用默认位置
+fn open_archive(file: Option<String>) -> Result<rusqlite::Connection> {
+    let path = match file.as_deref() {
+        Some(p) => expand_path(p)?,
+        None => db::default_archive_path()?,
+    };
+    db::open_readonly_at(&path)
+}
+
+/// 处理 'retag' 命令
+pub fn handle_retag(args: RetagArgs) -> Result<()> {
+    // 与 'log -t' 使用相同的整理规则
+    let parse = |input: Option<&str>| -> Result<Vec<String>> {
+        let normalized = input.map(tags::normalize_tags).transpose()?.flatten();
+        Ok(normalized
+            .as_deref()
+            .map(tags::split_tags)
+            .unwrap_or_default()
+            .into_iter()
+            .map(str::to_string)
+            .collect())
+    };
+    let add = parse(args.add_tag.as_deref())?;
+    let remove = parse(args.remove_tag.as_deref())?;
+    if add.is_empty() && remove.is_empty() {
+        return Err(DlogError::InvalidInput("No tags to add or remove.".to_string()));
+    }
+    if let Some(tag) = add.iter().find(|t| remove.contains(t)) {
+        return Err(DlogError::InvalidInput(format!(
+            "Tag '{}' cannot be both added and removed.",
+            tag
+        )));
+    }
+
+    let tag = args.tag.as_deref().map(tags::normalize_tag).transpose()?;
+    let terms: Vec<String> = match args.search.as_deref() {
+        Some(s) => {
+            let terms: Vec<String> = search::split_query(s).into_iter().map(str::to_string).collect();
+            if terms.is_empty() {
+                return Err(DlogError::InvalidInput("Search keyword is empty.".to_string()));
+            }
+            terms
+        }
+        None => Vec::new(),
+    };
+    let date_range = match args.date.as_deref() {
+        Some(d) => {
+            let (first, last) = parse_day_span(d)?;
+            Some((local_day_range(first)?.0, local_day_range(last)?.1))
+        }
+        None => None,
+    };
+    let scope = if args.all {
+        Scope::All
+    } else {
+        Scope::from_recursive(args.recursive)
+    };
+    let target_path = match args.path.as_deref() {
+        Some(p) => expand_path(p)?,
+        None => env::current_dir()?,
+    };
+    if !db::is_initialized()? {
+        print_no_database_hint();
+        return Ok(());
+    }
+
+    let conn = db::open_connection()?;
+    let query = db::LogQuery {
+        tag: tag.as_deref(),
+        terms: &terms,
+        since: date_range.map(|(start, _)| start),
+        until: date_range.map(|(_, end)| end),
+        ..Default::default()
     };
+    let logs = db::fetch_logs(&conn, &target_path, scope, &query)?;
+    if logs.is_empty() {
+        println!("No logs found.");
+        return Ok(());
+    }
 
-    if content.trim().is_empty() {
-        eprintln!("Empty log, skipped.");
+    let mut updates = Vec::new();
+    let mut changed = Vec::new();
+    for log in &logs {
+        if let Some(updated) = tags::retag(log.tags.as_deref(), &add, &remove) {
+            updates.push((log.id, updated));
+            changed.push(log);
+        }
+    }
+    let unchanged = logs.len() - changed.len();
+    if changed.is_empty() {
+        println!("All {} matching log(s) already have the requested tags.", logs.len());
         return Ok(());
     }
 
-    let dir = env::current_dir()?.to_string_lossy().to_string();
-    let conn = db::open_connection()?;
-    db::add_log(&conn, &dir, &content, tags.as_deref())?;
+    println!("{} log(s) will be updated:", changed.len());
+    for log in &changed {
+        println!(
+            "  [{}] {}  {}",
+            log.id,
+            format_time(&log.timestamp, "%Y-%m-%d"),
+            truncate_width(title_of(log), 60)
+        );
+    }
+    if unchanged > 0 {
+        println!("{} other matching log(s) already have the requested tags.", unchanged);
+    }
+    if args.dry_run {
+        println!("Dry run: no changes made.");
+        return Ok(());
+    }
+    if !confirm("Continue?", args.yes)? {
+        println!("Cancelled.");
+        return Ok(());
+    }
 
-    println!("✓ Log recorded.");
+    let count = db::update_log_tags(&conn, &updates)?;
+    println!("✓ Updated tags on {} log(s), {} already up to date.", count, unchanged);
     Ok(())
 }
 
-/// 处理 'get' 命令
-pub fn handle_get(
-    path: Option<String>,
-    num: Option<u32>,
-    recursive: bool,
-    tag: Option<String>,
-    date: Option<String>,
-    search: Option<String>,
+/// 删除早于 `older_than` 的日志，指定了 `archive` 时改为移动到该归档文件
+fn prune_logs(
+    older_than: &str,
+    scope: Scope,
+    archive: Option<&Path>,
+    dry_run: bool,
+    yes: bool,
 ) -> Result<()> {
-    let target_path = match path {
-        Some(p) => PathBuf::from(p),
-        None => env::current_dir()?,
-    };
-
-    if let Some(d) = &date {
-        if NaiveDate::parse_from_str(d, "%Y-%m-%d").is_err() {
+    let cutoff = parse_duration_cutoff(older_than)?;
+    if !db::is_initialized()? {
+        print_no_database_hint();
+        return Ok(());
+    }
+    if let Some(archive) = archive {
+        let db_path = db::get_db_path()?;
+        if archive.exists() && archive.canonicalize()? == db_path.canonicalize()? {
             return Err(DlogError::InvalidInput(
-                "Invalid date format. Use YYYY-MM-DD.".to_string(),
+                "The archive file cannot be the database itself".to_string(),
             ));
         }
+        if archive.exists() && !db::is_dlog_database(archive)? {
+            return Err(DlogError::InvalidInput(format!(
+                "{} exists but is not a dlog database",
+                display_path(archive)
+            )));
+        }
     }
 
-    let limit = num.unwrap_or(10);
     let conn = db::open_connection()?;
-    let logs = db::fetch_logs(
-        &conn,
-        &target_path,
-        recursive,
-        limit,
-        tag.as_deref(),
-        date.as_deref(),
-        search.as_deref(),
-    )?;
-
+    let query = db::LogQuery {
+        until: Some(cutoff),
+        ..Default::default()
+    };
+    let mut logs = db::fetch_logs(&conn, &env::current_dir()?, scope, &query)?;
+    let locked = exclude_locked(&mut logs);
+    if locked > 0 {
+        println!("{} (use 'dlog unlock' first to include them).", locked_note(locked));
+    }
     if logs.is_empty() {
-        println!("No logs found.");
+        println!("No logs older than {} found.", older_than);
         return Ok(());
     }
 
-    for log in logs {
-        // 在这里将字符串解析为 DateTime 进行格式化
-        let dt: DateTime<Utc> = log.timestamp.parse().unwrap_or(Utc::now());
-        let formatted_time = dt.format("%Y-%m-%d %H:%M:%S").to_string();
-        let tags_display = log.tags.map_or("".to_string(), |t| format!(" | Tags: {}", t));
+    let mut by_directory: BTreeMap<&str, usize> = BTreeMap::new();
+    for log in &logs {
+        *by_directory.entry(&log.directory).or_default() += 1;
+    }
+    println!("Found {} logs older than {}:", logs.len(), older_than);
+    for (dir, count) in &by_directory {
+        println!("  {:>6}  {}", count, display_path(dir));
+    }
 
-        println!(
-            "[{}] {} {}",
-            log.id,
-            formatted_time,
-            tags_display
-        );
-        // 如果是递归查询，显示日志所在目录
-        if recursive {
-            println!("  └─ Path: {}", log.directory);
+    let action = match archive {
+        Some(path) => format!("move them to {}", display_path(path)),
+        None => "permanently delete them".to_string(),
+    };
+    if dry_run {
+        println!("Dry run: would {}.", action);
+        return Ok(());
+    }
+    if !confirm(&format!("Do you want to {}?", action), yes)? {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    let ids: Vec<i32> = logs.iter().map(|l| l.id).collect();
+    match archive {
+        Some(path) => {
+            let moved = db::archive_logs(&conn, path, &ids)?;
+            println!("✓ Archived {} log(s) to: {}", moved, display_path(path));
+        }
+        None => {
+            let deletion = db::delete_logs_by_id(&conn, &ids, false)?;
+            report_locked_skipped(&deletion.locked);
+            println!("✓ Deleted {} log(s).", deletion.deleted);
         }
-        println!("{}", log.content.trim_end());
-        println!("{}", "─".repeat(40));
     }
     Ok(())
 }
 
 /// 处理 'fix' 命令
-pub fn handle_fix(id: i32) -> Result<()> {
+pub fn handle_fix(
+    id: i32,
+    message: Vec<String>,
+    append: Option<String>,
+    no_stamp: bool,
+    level: Option<String>,
+) -> Result<()> {
+    let level: Option<Level> = level.as_deref().map(str::parse).transpose()?;
     let conn = db::open_connection()?;
-    let old_content = db::get_log_content(&conn, id)?.ok_or(DlogError::LogNotFound(id))?;
+    // 锁定只防止删除，仍允许修改
+    if !quiet() && db::get_log(&conn, id)?.is_some_and(|log| log.locked) {
+        eprintln!("Warning: log #{} is locked; editing it anyway.", id);
+    }
+    // 只修改级别时不打开编辑器
+    if message.is_empty() && append.is_none() && level.is_none() {
+        return edit_log(&conn, id);
+    }
+
+    if !message.is_empty() || append.is_some() {
+        let result = match append {
+            Some(text) => append_to_log(&conn, id, &text, !no_stamp),
+            None => replace_log_content(&conn, id, &message),
+        };
+        match result {
+            // 内容没有变化，但还需要修改级别
+            Err(DlogError::NoChangesMade) if level.is_some() => {}
+            other => other?,
+        }
+    }
+    if let Some(level) = level {
+        if db::update_log_level(&conn, id, level)? == 0 {
+            return Err(DlogError::LogNotFound(id));
+        }
+        println!("✓ Log #{} is now {}.", id, level.as_str());
+    }
+    Ok(())
+}
+
+/// 用 -m 给出的内容替换日志内容
+fn replace_log_content(conn: &rusqlite::Connection, id: i32, message: &[String]) -> Result<()> {
+    let old_content = db::get_log_content(conn, id)?.ok_or(DlogError::LogNotFound(id))?;
+    let new_content = join_messages(message);
+    if new_content.trim().is_empty() {
+        return Err(DlogError::InvalidInput(format!(
+            "The new content is empty, use 'dlog del {}' to delete the log.",
+            id
+        )));
+    }
+    if new_content.trim() == old_content.trim() {
+        return Err(DlogError::NoChangesMade);
+    }
+    db::update_log_content(conn, id, &new_content)?;
+    println!("✓ Log #{} updated.", id);
+    Ok(())
+}
+
+/// 在日志末尾另起一行追加内容，`stamp` 为 true 时加上当前的本地时间
+fn append_to_log(conn: &rusqlite::Connection, id: i32, text: &str, stamp: bool) -> Result<()> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(DlogError::InvalidInput("Nothing to append, the text is empty.".to_string()));
+    }
+    let old_content = db::get_log_content(conn, id)?.ok_or(DlogError::LogNotFound(id))?;
+    let addendum = if stamp {
+        format!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M"), text)
+    } else {
+        text.to_string()
+    };
+    let new_content = format!("{}\n{}", old_content.trim_end(), addendum);
+    db::update_log_content(conn, id, &new_content)?;
+    println!("✓ Log #{} updated.", id);
+    Ok(())
+}
+
+/// 取出编辑器内容开头的标签行，见 `tags::parse_front_matter`
+///
+/// 格式有误时不能把标签行当作日志内容写入，将整个内容保存下来以便 'dlog recover' 恢复，然后报错。
+fn split_edited_tags<'a>(buffer: &'a str, original: &str) -> Result<(Option<String>, &'a str)> {
+    tags::parse_front_matter(buffer, original).or_else(|e| {
+        let path = recovery::save(buffer)?;
+        Err(DlogError::Recovered {
+            path: path.display().to_string(),
+            source: Box::new(e),
+        })
+    })
+}
+
+/// 在编辑器中修改指定的日志及其标签
+fn edit_log(conn: &rusqlite::Connection, id: i32) -> Result<()> {
+    let log = db::get_log(conn, id)?.ok_or(DlogError::LogNotFound(id))?;
+    let old_content = log.content.as_str();
+
+    if !io::stdin().is_terminal() {
+        return Err(DlogError::NonInteractive(format!(
+            "cannot open an editor to edit log #{}, run 'dlog fix' from a terminal or pass -m",
+            id
+        )));
+    }
 
     // 修正：重新添加 mut，因为我们需要调用 .write_all() 和 .flush()
     let mut temp_file = tempfile::NamedTempFile::new()?;
-    temp_file.write_all(old_content.as_bytes())?;
+    temp_file.write_all(tags::with_front_matter(log.tags.as_deref(), old_content).as_bytes())?;
     temp_file.flush()?;
 
     let editor = env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
@@ -158,16 +3917,133 @@ pub fn handle_fix(id: i32) -> Result<()> {
         return Err(DlogError::EditorError);
     }
 
-    let new_content = std::fs::read_to_string(temp_file.path())?;
-    if new_content.trim() == old_content.trim() {
+    let buffer = std::fs::read_to_string(temp_file.path())?;
+    apply_edited_buffer(conn, &log, &buffer, |prompt| confirm(prompt, false))
+}
+
+/// 将编辑器中保存的内容和标签写回日志 `log`
+///
+/// 内容被清空时通过 `confirm_delete` 询问是否改为删除这条日志，回答否时不做任何修改。
+fn apply_edited_buffer(
+    conn: &rusqlite::Connection,
+    log: &LogEntry,
+    buffer: &str,
+    confirm_delete: impl FnOnce(&str) -> Result<bool>,
+) -> Result<()> {
+    let (id, old_content) = (log.id, log.content.as_str());
+    let (new_tags, new_content) = split_edited_tags(buffer, old_content)?;
+    if new_content.trim() == old_content.trim() && new_tags == log.tags {
         return Err(DlogError::NoChangesMade);
     }
 
-    db::update_log_content(&conn, id, &new_content)?;
+    // 清空内容通常意味着想删除这条日志，不写入空内容
+    if new_content.trim().is_empty() {
+        let prompt = format!("The entry is now empty — delete log #{} instead?", id);
+        if !confirm_delete(&prompt)? {
+            return Err(DlogError::NoChangesMade);
+        }
+        // 锁定的日志需通过 'del --include-locked' 删除，这里不绕过
+        if db::delete_logs_by_id(conn, &[id], false)?.deleted == 0 {
+            return Err(DlogError::InvalidInput(format!(
+                "Log #{} is locked and was not deleted; run 'dlog unlock {}' first",
+                id, id
+            )));
+        }
+        println!("✓ Log #{} deleted.", id);
+        return Ok(());
+    }
+
+    let saved = recovery::save(new_content)?;
+    let result = db::update_log_content_and_tags(conn, id, new_content, new_tags.as_deref());
+    recovery::finish(Some(saved), result)?;
     println!("✓ Log #{} updated.", id);
     Ok(())
 }
 
+/// 处理 'copy' 命令
+pub fn handle_copy(id: i32, to: Option<String>, edit: bool) -> Result<()> {
+    let target_dir = match &to {
+        Some(d) => {
+            let path = expand_path(d)?;
+            if !path.is_dir() {
+                return Err(DlogError::InvalidInput(format!("Directory does not exist: {}", d)));
+            }
+            db::normalize_path(&path)?
+        }
+        None => db::normalize_path(&env::current_dir()?)?,
+    };
+    if edit && !io::stdin().is_terminal() {
+        return Err(DlogError::NonInteractive(
+            "cannot open an editor for the copy, run 'dlog copy' from a terminal or drop --edit".to_string(),
+        ));
+    }
+
+    let conn = db::open_connection()?;
+    let new_id = db::copy_log(&conn, id, &target_dir)?.ok_or(DlogError::LogNotFound(id))?;
+    if to.is_some() {
+        println!("✓ Copied log #{} to #{} in: {}", id, new_id, display_path(&target_dir));
+    } else {
+        println!("✓ Copied log #{} to #{}.", id, new_id);
+    }
+
+    if edit {
+        // 不修改直接保存时保留原样的副本
+        match edit_log(&conn, new_id) {
+            Err(DlogError::NoChangesMade) => {}
+            other => other?,
+        }
+    }
+    Ok(())
+}
+
+/// 处理 'recover' 命令
+pub fn handle_recover(name: Option<String>) -> Result<()> {
+    let Some(name) = name else {
+        let files = recovery::list()?;
+        if files.is_empty() {
+            println!("No saved buffers.");
+            return Ok(());
+        }
+        println!("Saved buffers in {}:", display_path(&recovery::recovery_dir()?));
+        for path in &files {
+            let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+            let content = std::fs::read_to_string(path)?;
+            let subject = content.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+            println!("  {}  {}", stem, truncate_width(subject, 60));
+        }
+        println!("Run 'dlog recover <NAME>' to log one in the current directory.");
+        return Ok(());
+    };
+
+    let path = recovery::find(&name)?;
+    let content = std::fs::read_to_string(&path)?;
+    if content.trim().is_empty() {
+        std::fs::remove_file(&path)?;
+        println!("Saved buffer '{}' was empty and has been removed.", name);
+        return Ok(());
+    }
+    // 因标签行格式有误而保存的内容仍带着标签行，需要先修正
+    let (tags, content) = tags::parse_front_matter(&content, "").map_err(|e| {
+        DlogError::InvalidInput(format!("{} (fix or remove it in {})", e, display_path(&path)))
+    })?;
+    let target_dir = db::normalize_path(&env::current_dir()?)?;
+    write_log(&target_dir, content, tags.as_deref(), false, Level::Normal, Utc::now())?;
+    std::fs::remove_file(&path)?;
+    println!("✓ Log recorded from saved buffer '{}'.", name);
+    Ok(())
+}
+
+/// 启动只读的网页服务，直到被 Ctrl-C 结束
+pub fn handle_serve(port: u16, bind: std::net::IpAddr) -> Result<()> {
+    if !db::is_initialized()? {
+        return Err(DlogError::DatabaseNotInitialized);
+    }
+    if !bind.is_loopback() && !quiet() {
+        eprintln!("Warning: serving on {} makes your logs readable by anyone who can reach this address.", bind);
+    }
+    serve::run(std::net::SocketAddr::new(bind, port))
+}
+
 /// 解析ID范围字符串 (例如 "1,3,5-7")
 fn parse_id_range(s: &str) -> Result<Vec<i32>> {
     let mut ids = BTreeSet::new(); // 使用 BTreeSet 自动排序和去重
@@ -198,29 +4074,192 @@ fn parse_id_range(s: &str) -> Result<Vec<i32>> {
     Ok(ids.into_iter().collect())
 }
 
+/// 找不到日志时最多提示的相近ID数
+const NEAR_ID_SUGGESTIONS: usize = 3;
+
+/// 找不到日志 `id` 时的提示：相近的已有ID（包括交换相邻两位数字得到的ID，常见于输入错误），
+/// 以及它是否已被删除；没有可提示的内容时返回 None
+fn log_not_found_hint(conn: &rusqlite::Connection, id: i32) -> Result<Option<String>> {
+    let digits: Vec<char> = id.to_string().chars().collect();
+    let swapped: Vec<i32> = (0..digits.len().saturating_sub(1))
+        .filter(|&i| digits[i] != digits[i + 1])
+        .filter_map(|i| {
+            let mut d = digits.clone();
+            d.swap(i, i + 1);
+            d.into_iter().collect::<String>().parse().ok()
+        })
+        // 交换后以 0 开头的不是输错的ID
+        .filter(|candidate: &i32| candidate.to_string().len() == digits.len())
+        .collect();
+    let mut suggestions: Vec<i32> = db::get_logs_by_ids(conn, &swapped)?.iter().map(|l| l.id).collect();
+    suggestions.truncate(NEAR_ID_SUGGESTIONS);
+    let mut nearest = db::nearest_ids(conn, id, NEAR_ID_SUGGESTIONS)?;
+    nearest.sort_by_key(|near| ((*near as i64 - id as i64).abs(), *near));
+    for near in nearest {
+        if suggestions.len() < NEAR_ID_SUGGESTIONS && !suggestions.contains(&near) {
+            suggestions.push(near);
+        }
+    }
+    suggestions.sort_unstable();
+
+    let mut parts = Vec::new();
+    if !suggestions.is_empty() {
+        let ids: Vec<String> = suggestions.iter().map(i32::to_string).collect();
+        parts.push(format!("Nearby existing IDs: {}.", ids.join(", ")));
+    }
+    if let Some(at) = db::deleted_at(conn, id)? {
+        parts.push(format!("({} was deleted on {}.)", id, format_time(&at, "%Y-%m-%d")));
+    }
+    Ok((!parts.is_empty()).then(|| parts.join(" ")))
+}
+
+/// 附加在错误信息后的提示，目前只有找不到日志时的相近ID；查询提示本身出错时忽略
+pub fn error_hint(error: &DlogError) -> String {
+    let hint = match error {
+        DlogError::LogNotFound(id) => db::open_connection()
+            .and_then(|conn| log_not_found_hint(&conn, *id))
+            .ok()
+            .flatten(),
+        _ => None,
+    };
+    hint.map_or(String::new(), |hint| format!(". {}", hint))
+}
+
+/// 删除前逐条列出的日志条数上限，超过时只列出开头和结尾各 `DEL_PREVIEW_EDGE` 条
+const DEL_PREVIEW_MAX: usize = 30;
+const DEL_PREVIEW_EDGE: usize = 5;
+
+/// 在删除确认前列出每条日志的ID、日期、标签和标题
+fn print_deletion_preview(logs: &[LogEntry]) {
+    let print = |log: &LogEntry| {
+        let tags = match log.tags.as_deref().filter(|t| !t.is_empty()) {
+            Some(tags) => format!(" [{}]", tags),
+            None => String::new(),
+        };
+        println!(
+            "  [{}] {}{}  {}",
+            log.id,
+            format_time(&log.timestamp, "%Y-%m-%d"),
+            tags,
+            truncate_width(title_of(log), 60)
+        );
+    };
+    if logs.len() <= DEL_PREVIEW_MAX {
+        logs.iter().for_each(print);
+        return;
+    }
+    logs[..DEL_PREVIEW_EDGE].iter().for_each(print);
+    println!("  …and {} more", logs.len() - 2 * DEL_PREVIEW_EDGE);
+    logs[logs.len() - DEL_PREVIEW_EDGE..].iter().for_each(print);
+}
+
 /// 处理 'del' 命令
-pub fn handle_del(ids_str: Option<String>, recursive: bool) -> Result<()> {
+pub fn handle_del(args: DelArgs) -> Result<()> {
+    let filtered = args.recursive
+        || args.older_than.is_some()
+        || args.tag.is_some()
+        || args.date.is_some()
+        || args.before.is_some()
+        || args.search.is_some();
+    if args.path.is_some() && !filtered {
+        return Err(DlogError::InvalidInput(
+            "--path must be combined with --recursive or a filter such as --older-than or --tag.".to_string(),
+        ));
+    }
+    // 目录不存在时 normalize_path 按字面解析，仍然能匹配记录时保存的路径
+    let target_dir = match &args.path {
+        Some(p) => PathBuf::from(db::normalize_path(&expand_path(p)?)?),
+        None => env::current_dir()?,
+    };
     let conn = db::open_connection()?;
-    let ids_to_delete = if recursive {
-        let current_dir = env::current_dir()?;
-        println!("Searching for logs to delete recursively from: {}", current_dir.display());
-        let logs = db::find_logs_in_path(&conn, &current_dir)?;
+    let ids_to_delete: Vec<i32> = if filtered {
+        let tag = args.tag.as_deref().map(tags::normalize_tag).transpose()?;
+        let terms: Vec<String> = match args.search.as_deref() {
+            Some(s) => {
+                let terms: Vec<String> = search::split_query(s).into_iter().map(str::to_string).collect();
+                if terms.is_empty() {
+                    return Err(DlogError::InvalidInput("Search keyword is empty.".to_string()));
+                }
+                terms
+            }
+            None => Vec::new(),
+        };
+        let mut since = None;
+        let mut until = args.older_than.as_deref().map(parse_duration_cutoff).transpose()?;
+        if let Some(d) = args.date.as_deref() {
+            let (first, last) = parse_day_span(d)?;
+            since = Some(local_day_range(first)?.0);
+            let end = local_day_range(last)?.1;
+            until = Some(until.map_or(end, |u| u.min(end)));
+        }
+        if let Some(d) = args.before.as_deref() {
+            let start = local_day_range(parse_day_span(d)?.0)?.0;
+            until = Some(until.map_or(start, |u| u.min(start)));
+        }
+        println!(
+            "Searching for logs to delete in: {}{}",
+            display_path(&target_dir),
+            if args.recursive { " (recursive)" } else { "" }
+        );
+        let query = db::LogQuery {
+            tag: tag.as_deref(),
+            terms: &terms,
+            since,
+            until,
+            ..Default::default()
+        };
+        let mut logs = db::fetch_logs(&conn, &target_dir, Scope::from_recursive(args.recursive), &query)?;
+        if !args.include_locked {
+            let locked = exclude_locked(&mut logs);
+            if locked > 0 {
+                println!("{} (use --include-locked to delete them too).", locked_note(locked));
+            }
+        }
         if logs.is_empty() {
-            println!("No logs found in this directory or subdirectories.");
+            println!("No matching logs found.");
             return Ok(());
         }
+        logs.sort_by_key(|l| l.id);
         println!("Found {} logs to delete:", logs.len());
-        for log in &logs {
-            // 在这里将字符串解析为 DateTime 进行格式化
-            let dt: DateTime<Utc> = log.timestamp.parse().unwrap_or(Utc::now());
-            println!("- ID: {}, Date: {}", log.id, dt.format("%Y-%m-%d"));
+        print_deletion_preview(&logs);
+        logs.iter().map(|l| l.id).collect()
+    } else if let Some(s) = &args.ids {
+        let requested = parse_id_range(s)?;
+        let mut logs = db::get_logs_by_ids(&conn, &requested)?;
+        let missing: Vec<i32> = requested
+            .iter()
+            .copied()
+            .filter(|id| logs.binary_search_by_key(id, |l| l.id).is_err())
+            .collect();
+        if !missing.is_empty() {
+            // 只缺少一个ID时多半是输错了，给出相近的ID
+            let hint = match missing[..] {
+                [id] => log_not_found_hint(&conn, id)?.map_or(String::new(), |hint| format!(" {}", hint)),
+                _ => String::new(),
+            };
+            let missing: Vec<String> = missing.iter().map(i32::to_string).collect();
+            eprintln!("Warning: no log with ID {} exists.{}", missing.join(", "), hint);
+        }
+        if !args.include_locked {
+            let locked: Vec<String> = logs.iter().filter(|l| l.locked).map(|l| l.id.to_string()).collect();
+            if !locked.is_empty() {
+                exclude_locked(&mut logs);
+                println!(
+                    "Skipping locked log(s) {} (use --include-locked to delete them too).",
+                    locked.join(", ")
+                );
+            }
+        }
+        if logs.is_empty() {
+            println!("No logs found with the given IDs.");
+            return Ok(());
         }
+        println!("Found {} logs to delete:", logs.len());
+        print_deletion_preview(&logs);
         logs.iter().map(|l| l.id).collect()
-    } else if let Some(s) = ids_str {
-        parse_id_range(&s)?
     } else {
         // clap应该已经阻止了这种情况，但为了安全起见
-        return Err(DlogError::InvalidInput("You must provide log IDs or use the --recursive flag.".to_string()));
+        return Err(DlogError::InvalidInput("You must provide log IDs, use the --recursive flag or give a filter such as --tag.".to_string()));
     };
 
     if ids_to_delete.is_empty() {
@@ -228,22 +4267,373 @@ pub fn handle_del(ids_str: Option<String>, recursive: bool) -> Result<()> {
         return Ok(());
     }
 
-    println!(
-        "\nYou are about to permanently delete the following log IDs: {:?}",
-        ids_to_delete
-    );
-    print!("Confirm deletion? (y/N): ");
-    io::stdout().flush()?;
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    if !input.trim().eq_ignore_ascii_case("y") {
+    if args.dry_run {
+        println!("\nDry run: would delete {} log(s), no changes made.", ids_to_delete.len());
+        return Ok(());
+    }
+    println!("\nYou are about to permanently delete {} log(s).", ids_to_delete.len());
+    if !confirm("Confirm deletion?", args.yes)? {
         println!("Cancelled.");
         return Ok(());
     }
 
-    let count = db::delete_logs_by_id(&conn, &ids_to_delete)?;
-    println!("✓ Successfully deleted {} log(s).", count);
+    let deletion = db::delete_logs_by_id(&conn, &ids_to_delete, args.include_locked)?;
+    report_locked_skipped(&deletion.locked);
+    println!("✓ Successfully deleted {} log(s).", deletion.deleted);
+
+    Ok(())
+}
+
+/// 从候选列表中去掉锁定的日志，返回去掉的条数
+fn exclude_locked(logs: &mut Vec<LogEntry>) -> usize {
+    let before = logs.len();
+    logs.retain(|l| !l.locked);
+    before - logs.len()
+}
+
+/// 说明有多少条锁定的日志没有列入候选
+fn locked_note(count: usize) -> String {
+    if count == 1 {
+        "1 locked entry was not included".to_string()
+    } else {
+        format!("{} locked entries were not included", count)
+    }
+}
+
+/// 报告删除时因锁定而跳过的日志（del 等命令的候选列表已排除锁定的日志，只有在此期间被锁定的才会出现）
+fn report_locked_skipped(ids: &[i32]) {
+    if ids.is_empty() {
+        return;
+    }
+    let ids: Vec<String> = ids.iter().map(i32::to_string).collect();
+    eprintln!(
+        "Warning: log(s) {} are locked and were not deleted; run 'dlog unlock' first.",
+        ids.join(", ")
+    );
+}
+
+/// 处理 'lock' 和 'unlock' 命令
+pub fn handle_lock(ids: String, locked: bool) -> Result<()> {
+    let requested = parse_id_range(&ids)?;
+    if requested.is_empty() {
+        return Err(DlogError::InvalidInput("No log IDs given.".to_string()));
+    }
+    let conn = db::open_connection()?;
+    let logs = db::get_logs_by_ids(&conn, &requested)?;
+    if logs.is_empty() {
+        return Err(DlogError::LogNotFound(requested[0]));
+    }
+    let missing: Vec<String> = requested
+        .iter()
+        .filter(|id| logs.binary_search_by_key(*id, |l| l.id).is_err())
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        eprintln!("Warning: no log with ID {} exists.", missing.join(", "));
+    }
 
+    let ids: Vec<i32> = logs.iter().map(|l| l.id).collect();
+    let changed = db::set_locked(&conn, &ids, locked)?;
+    let verb = if locked { "Locked" } else { "Unlocked" };
+    let unchanged = ids.len() - changed;
+    if unchanged > 0 {
+        println!(
+            "✓ {} {} log(s), {} already {}.",
+            verb,
+            changed,
+            unchanged,
+            if locked { "locked" } else { "unlocked" }
+        );
+    } else {
+        println!("✓ {} {} log(s).", verb, changed);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 临时目录中的新数据库，目录在返回的 TempDir 释放时删除
+    fn test_db() -> (tempfile::TempDir, rusqlite::Connection) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dlog.db");
+        db::initialize_db_at(&path).unwrap();
+        let conn = db::open_connection_at(&path).unwrap();
+        (dir, conn)
+    }
+
+    /// 所有字段都有值的日志；LogEntry 只能由库创建，因此先写入数据库再补齐字段
+    fn full_entry() -> LogEntry {
+        let (_dir, conn) = test_db();
+        let id = db::add_log(&conn, "/proj/app", "Fixed the cache bug", Some("bugfix,cache"), true).unwrap();
+        let mut log = db::get_log(&conn, id).unwrap().unwrap();
+        log.status = Some("done".to_string());
+        log.completed_at = Some("2026-03-02T10:00:00Z".to_string());
+        log.hostname = Some("laptop".to_string());
+        log.username = Some("me".to_string());
+        log.level = Level::High;
+        log.locked = true;
+        log.uuid = Some("0b6f8c1e-4a9e-4f1a-9d3c-2a7c5e1b9f00".to_string());
+        log.updated_at = Some("2026-03-02T10:00:00Z".to_string());
+        log
+    }
+
+    fn verbose_options() -> RenderOptions {
+        RenderOptions {
+            style: ListStyle::Verbose,
+            show_path: false,
+            show_origin: false,
+            max_lines: None,
+            fit_terminal: false,
+            absolute_paths: true,
+        }
+    }
+
+    #[test]
+    fn verbose_output_lists_every_field() {
+        let log = full_entry();
+        let mut out = String::new();
+        render_log_verbose(&mut out, &log, verbose_options(), &[]);
+        for (name, value) in log.fields() {
+            let line = out.lines().find(|l| l.starts_with(&format!("{}:", name)));
+            let line = line.unwrap_or_else(|| panic!("{} is missing from:\n{}", name, out));
+            assert!(line.contains(&value.unwrap()), "{}", line);
+        }
+        assert!(out.contains("Fixed the cache bug"));
+    }
+
+    #[test]
+    fn verbose_output_marks_empty_fields() {
+        let mut log = full_entry();
+        (log.tags, log.status, log.hostname) = (None, None, None);
+        let mut out = String::new();
+        render_log_verbose(&mut out, &log, verbose_options(), &[]);
+        for name in ["tags", "status", "hostname"] {
+            let line = out.lines().find(|l| l.starts_with(&format!("{}:", name))).unwrap();
+            assert!(line.ends_with("(none)"), "{}", line);
+        }
+    }
+
+    #[test]
+    fn every_shell_gets_a_script_naming_every_subcommand() {
+        use clap::ValueEnum;
+        let cmd = Cli::command();
+        let subcommands: Vec<&str> =
+            cmd.get_subcommands().filter(|c| !c.is_hide_set()).map(|c| c.get_name()).collect();
+        assert!(subcommands.contains(&"completions"));
+        for shell in Shell::value_variants() {
+            let mut script = Vec::new();
+            clap_complete::generate(*shell, &mut Cli::command(), "dlog", &mut script);
+            let script = complete::add_dynamic(*shell, String::from_utf8(script).unwrap());
+            for name in &subcommands {
+                assert!(script.contains(name), "{} completions do not mention '{}'", shell, name);
+            }
+        }
+    }
+
+    /// 两个时间点相差不超过一分钟
+    fn close_to(actual: DateTime<Utc>, expected: DateTime<Utc>) -> bool {
+        (actual - expected).num_seconds().abs() < 60
+    }
+
+    #[test]
+    fn duration_cutoffs_count_back_from_now() {
+        let now = Utc::now();
+        assert!(close_to(parse_duration_cutoff("3h").unwrap(), now - Duration::hours(3)));
+        assert!(close_to(parse_duration_cutoff("3d").unwrap(), now - Duration::days(3)));
+        assert!(close_to(parse_duration_cutoff(" 2w ").unwrap(), now - Duration::weeks(2)));
+        assert!(close_to(parse_duration_cutoff("6m").unwrap(), now.checked_sub_months(Months::new(6)).unwrap()));
+        assert!(close_to(parse_duration_cutoff("1y").unwrap(), now.checked_sub_months(Months::new(12)).unwrap()));
+    }
+
+    #[test]
+    fn zero_or_malformed_durations_are_rejected() {
+        for input in ["", "0d", "d", "3", "3x", "-3d", "1.5d", "3 d", "99999999999y", "4294967295y"] {
+            match parse_duration_cutoff(input) {
+                Err(DlogError::InvalidInput(message)) => {
+                    assert!(message.contains("h (hours), d (days), w (weeks), m (months) or y (years)"), "{}", message)
+                }
+                other => panic!("{:?} should be rejected, got {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn since_compares_full_timestamps() {
+        let (_dir, conn) = test_db();
+        let add = |content: &str, age: Duration| {
+            db::add_log_at(&conn, "/proj", content, None, false, Level::Normal, Utc::now() - age).unwrap()
+        };
+        add("fresh", Duration::minutes(30));
+        add("this morning", Duration::hours(5));
+        add("last week", Duration::days(8));
+
+        let since = |input: &str| {
+            let filter = db::LogQuery { since: Some(parse_duration_cutoff(input).unwrap()), ..Default::default() };
+            let logs = db::fetch_logs(&conn, Path::new("/proj"), Scope::Dir, &filter).unwrap();
+            logs.iter().map(|l| l.content.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(since("3h"), ["fresh"]);
+        assert_eq!(since("1d"), ["fresh", "this morning"]);
+        assert_eq!(since("2w").len(), 3);
+    }
+
+    #[test]
+    fn emptying_an_entry_in_the_editor_offers_to_delete_it() {
+        let (_dir, conn) = test_db();
+        let id = db::add_log(&conn, "/proj", "original", Some("a"), false).unwrap();
+        let log = db::get_log(&conn, id).unwrap().unwrap();
+        // 只剩空格和末尾换行的内容也算清空
+        let buffer = tags::with_front_matter(log.tags.as_deref(), "   \n");
+
+        let mut asked = None;
+        let declined = apply_edited_buffer(&conn, &log, &buffer, |prompt| {
+            asked = Some(prompt.to_string());
+            Ok(false)
+        });
+        assert!(matches!(declined, Err(DlogError::NoChangesMade)));
+        assert_eq!(asked.as_deref(), Some("The entry is now empty — delete log #1 instead?"));
+        assert_eq!(db::get_log(&conn, id).unwrap().unwrap().content, "original");
+
+        apply_edited_buffer(&conn, &log, &buffer, |_| Ok(true)).unwrap();
+        assert!(db::get_log(&conn, id).unwrap().is_none());
+    }
+
+    #[test]
+    fn emptied_entries_are_never_written_without_a_terminal() {
+        let (_dir, conn) = test_db();
+        let id = db::add_log(&conn, "/proj", "original", None, false).unwrap();
+        let log = db::get_log(&conn, id).unwrap().unwrap();
+        let buffer = tags::with_front_matter(None, " \n\n");
+        let result = apply_edited_buffer(&conn, &log, &buffer, |_| Err(DlogError::NonInteractive("no tty".into())));
+        assert!(matches!(result, Err(DlogError::NonInteractive(_))));
+        assert_eq!(db::get_log(&conn, id).unwrap().unwrap().content, "original");
+
+        db::set_locked(&conn, &[id], true).unwrap();
+        let locked = apply_edited_buffer(&conn, &log, &buffer, |_| Ok(true));
+        assert!(matches!(locked, Err(DlogError::InvalidInput(message)) if message.contains("is locked")));
+        assert!(db::get_log(&conn, id).unwrap().is_some());
+    }
+
+    #[test]
+    fn truncate_width_counts_display_columns() {
+        assert_eq!(truncate_width("连接池的说明", 6), "连接…");
+        assert_eq!(truncate_width("Größe", 5), "Größe");
+        assert_eq!(truncate_width("Größenordnung", 6), "Größe…");
+    }
+
+    #[test]
+    fn parse_id_range_accepts_joined_id_lists() {
+        assert_eq!(parse_id_range("4,3,1,").unwrap(), vec![1, 3, 4]);
+        assert_eq!(parse_id_range(" 7-9 , 8 ").unwrap(), vec![7, 8, 9]);
+        assert!(parse_id_range("3-").is_err());
+        assert!(parse_id_range("5-2").is_err());
+        assert!(parse_id_range("4\n3").is_err());
+    }
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn day_spans_cover_each_accepted_form() {
+        // 2024-03-06 是星期三
+        let today = ymd(2024, 3, 6);
+        let cases = [
+            ("2024-06-02", ymd(2024, 6, 2), ymd(2024, 6, 2)),
+            ("today", today, today),
+            (" Today ", today, today),
+            ("yesterday", ymd(2024, 3, 5), ymd(2024, 3, 5)),
+            ("wednesday", today, today),
+            ("monday", ymd(2024, 3, 4), ymd(2024, 3, 4)),
+            ("thu", ymd(2024, 2, 29), ymd(2024, 2, 29)),
+            ("2024-02", ymd(2024, 2, 1), ymd(2024, 2, 29)),
+            ("2023-02", ymd(2023, 2, 1), ymd(2023, 2, 28)),
+            ("2024-12", ymd(2024, 12, 1), ymd(2024, 12, 31)),
+        ];
+        for (arg, first, last) in cases {
+            assert_eq!(day_span_on(arg, today).unwrap(), (first, last), "{}", arg);
+        }
+        assert_eq!(day_span_on("yesterday", ymd(2024, 1, 1)).unwrap().0, ymd(2023, 12, 31));
+    }
+
+    #[test]
+    fn malformed_day_spans_list_the_accepted_forms() {
+        let today = ymd(2024, 3, 6);
+        for arg in ["", "2024-13", "2024-2", "24-02", "2024-02-30", "someday", "2024-+2", "06/02/2024"] {
+            let err = day_span_on(arg, today).unwrap_err().to_string();
+            assert!(err.contains("Use YYYY-MM-DD, YYYY-MM, today, yesterday or a weekday name"), "{}: {}", arg, err);
+        }
+    }
+
+    #[test]
+    fn month_spans_default_to_the_current_year() {
+        let year = Local::now().year();
+        assert_eq!(parse_month_span("12").unwrap(), (ymd(year, 12, 1), ymd(year, 12, 31)));
+        assert_eq!(parse_month_span("2024-2").unwrap(), (ymd(2024, 2, 1), ymd(2024, 2, 29)));
+        assert_eq!(parse_month_span("1900-02").unwrap().1, ymd(1900, 2, 28));
+        for arg in ["13", "0", "2024-00", "2024-123", "123", "May"] {
+            assert!(parse_month_span(arg).is_err(), "{}", arg);
+        }
+    }
+
+    #[test]
+    fn time_ranges_may_wrap_midnight_but_not_be_empty() {
+        let hm = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(parse_time_range("09:00-12:30").unwrap(), (hm(9, 0), hm(12, 30)));
+        assert_eq!(parse_time_range(" 22:00 - 02:00 ").unwrap(), (hm(22, 0), hm(2, 0)));
+        let empty = parse_time_range("08:00-08:00").unwrap_err().to_string();
+        assert!(empty.contains("is empty"), "{}", empty);
+        assert!(parse_time_range("24:00-01:00").is_err());
+    }
+
+    #[test]
+    fn expand_path_handles_tilde_only_at_the_start() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_path("~").unwrap(), home);
+        assert_eq!(expand_path("~/projects/foo").unwrap(), home.join("projects/foo"));
+        assert_eq!(expand_path("notes/~draft").unwrap(), PathBuf::from("notes/~draft"));
+        assert_eq!(expand_path("/tmp/~").unwrap(), PathBuf::from("/tmp/~"));
+        // 找不到的用户与 shell 一样按字面保留
+        assert_eq!(expand_path("~no_such_user_here/x").unwrap(), PathBuf::from("~no_such_user_here/x"));
+        #[cfg(unix)]
+        assert_eq!(expand_path("~root/x").unwrap(), user_home("root").unwrap().join("x"));
+    }
+
+    #[test]
+    fn expand_path_replaces_set_variables_and_rejects_unset_ones() {
+        env::set_var("DLOG_TEST_EXPAND_DIR", "/srv/work");
+        assert_eq!(expand_path("$DLOG_TEST_EXPAND_DIR/app").unwrap(), PathBuf::from("/srv/work/app"));
+        assert_eq!(expand_path("${DLOG_TEST_EXPAND_DIR}2").unwrap(), PathBuf::from("/srv/work2"));
+        assert_eq!(expand_path("~/$DLOG_TEST_EXPAND_DIR").unwrap(), dirs::home_dir().unwrap().join("srv/work"));
+        for literal in ["cost$", "$/x", "$1/x", "${bad-name}/x", "${}", "a$-b"] {
+            assert_eq!(expand_path(literal).unwrap(), PathBuf::from(literal), "{}", literal);
+        }
+        let err = expand_path("$DLOG_TEST_UNSET_VARIABLE/x").unwrap_err().to_string();
+        assert!(err.contains("$DLOG_TEST_UNSET_VARIABLE in path '$DLOG_TEST_UNSET_VARIABLE/x' is not set"), "{}", err);
+    }
+
+    #[test]
+    fn nearest_project_marker_wins_in_nested_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let module = repo.join("vendor/lib");
+        let deep = module.join("src/deeply/nested");
+        std::fs::create_dir_all(&deep).unwrap();
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        // 子模块中的 .git 是一个文件；Cargo.toml 同样算作标记
+        std::fs::write(module.join(".git"), "gitdir: ../../.git/modules/lib\n").unwrap();
+        std::fs::write(repo.join("vendor/Cargo.toml"), "").unwrap();
+        let markers = [".git", "Cargo.toml", "package.json"];
+
+        assert_eq!(find_project_root(&deep, &markers), Some(module.as_path()));
+        assert_eq!(find_project_root(&module, &markers), Some(module.as_path()));
+        assert_eq!(find_project_root(&repo.join("vendor"), &markers), Some(repo.join("vendor").as_path()));
+        assert_eq!(find_project_root(&repo.join("docs/x"), &markers), Some(repo.as_path()));
+        // 只认列出的标记
+        assert_eq!(find_project_root(&deep, &["Cargo.toml"]), Some(repo.join("vendor").as_path()));
+        assert_eq!(find_project_root(&deep, &["no-such-marker"]), None);
+        assert_eq!(find_project_root(&deep, &[]), None);
+    }
+}
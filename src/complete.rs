@@ -0,0 +1,201 @@
+// src/complete.rs
+
+use crate::cli::CompleteKind;
+use clap_complete::Shell;
+use dlog::db::{self, Scope};
+use dlog::models::title_of;
+use dlog::Result;
+use std::path::{Path, PathBuf};
+
+/// 列出ID时最多包含的最近日志条数
+const MAX_IDS: u32 = 50;
+
+/// 在 clap 生成的补全脚本中加入对 'dlog __complete' 的调用
+///
+/// 标签（log/get/search 的 -t）、日志ID（fix/del/done/copy/lock/unlock）和 get 的目录参数
+/// 从数据库中取得候选值；其他 shell 的脚本保持不变。
+pub fn add_dynamic(shell: Shell, script: String) -> String {
+    match shell {
+        Shell::Bash => format!("{}{}", script, BASH),
+        Shell::Fish => format!("{}{}", script, FISH),
+        Shell::Zsh => zsh(script),
+        _ => script,
+    }
+}
+
+/// zsh 脚本中的参数规格以 `:值名称:动作'` 结尾，将相应参数的 `_default` 换成查询数据库的函数
+fn zsh(script: String) -> String {
+    let mut out = String::with_capacity(script.len() + ZSH.len());
+    for line in script.lines() {
+        if line == "#compdef dlog" {
+            out.push_str(line);
+            out.push('\n');
+            out.push_str(ZSH);
+            continue;
+        }
+        let action = if line.contains(":TAGS:_default'") || line.contains(":TAG:_default'") {
+            Some("_dlog_complete_tags")
+        } else if line.starts_with("':id -- ")
+            || line.starts_with("':ids -- ")
+            || line.starts_with("'::ids -- ")
+        {
+            Some("_dlog_complete_ids")
+        } else if line.starts_with("'::path -- ") {
+            Some("_dlog_complete_dirs")
+        } else {
+            None
+        };
+        match action {
+            Some(action) => out.push_str(&line.replacen(":_default'", &format!(":{}'", action), 1)),
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// 列出以 `prefix` 开头的候选值，ID 后面跟着制表符和标题
+///
+/// 数据库不存在或无法读取时返回空列表，补全不应因此报错。
+pub fn candidates(what: CompleteKind, prefix: &str) -> Vec<String> {
+    list(what, prefix).unwrap_or_default()
+}
+
+fn list(what: CompleteKind, prefix: &str) -> Result<Vec<String>> {
+    let db_path = db::get_db_path()?;
+    if !db_path.exists() {
+        return Ok(Vec::new());
+    }
+    let conn = db::open_readonly_at(&db_path)?;
+    match what {
+        CompleteKind::Tags => {
+            // -t 接受逗号分隔的多个标签，只补全最后一个
+            let (done, last) = match prefix.rsplit_once(',') {
+                Some((done, last)) => (format!("{},", done), last),
+                None => (String::new(), prefix),
+            };
+            let last = last.to_lowercase();
+            Ok(db::get_distinct_tags(&conn)?
+                .into_iter()
+                .filter(|tag| tag.starts_with(&last))
+                .map(|tag| format!("{}{}", done, tag))
+                .collect())
+        }
+        CompleteKind::Dirs => {
+            let (base, shown) = dir_prefix(prefix);
+            let mut dirs: Vec<String> = db::get_distinct_directories(&conn)?
+                .into_iter()
+                .filter_map(|dir| {
+                    let rest = Path::new(&dir).strip_prefix(&base).ok()?.to_str()?.to_string();
+                    (!rest.is_empty()).then(|| join_shown(&shown, &rest))
+                })
+                .filter(|dir| dir.starts_with(prefix))
+                .collect();
+            dirs.sort();
+            Ok(dirs)
+        }
+        CompleteKind::Ids => {
+            // del 接受逗号分隔的ID列表，只补全最后一项
+            let (done, last) = match prefix.rsplit_once(',') {
+                Some((done, last)) => (format!("{},", done), last),
+                None => (String::new(), prefix),
+            };
+            let query = db::LogQuery { limit: MAX_IDS, ..Default::default() };
+            let logs = db::fetch_logs(&conn, &std::env::current_dir()?, Scope::Dir, &query)?;
+            Ok(logs
+                .iter()
+                .filter(|log| log.id.to_string().starts_with(last))
+                .map(|log| format!("{}{}\t{}", done, log.id, title_of(log)))
+                .collect())
+        }
+    }
+}
+
+/// 用户输入的目录前缀对应的绝对路径起点，以及候选值中代替它的写法
+///
+/// 只做字符串处理，不访问文件系统：`~/` 换成主目录，相对路径相对于当前目录。
+fn dir_prefix(prefix: &str) -> (PathBuf, String) {
+    if prefix.starts_with('/') {
+        return (PathBuf::from("/"), "/".to_string());
+    }
+    if prefix == "~" || prefix.starts_with("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return (home, "~".to_string());
+        }
+    }
+    (std::env::current_dir().unwrap_or_default(), String::new())
+}
+
+fn join_shown(shown: &str, rest: &str) -> String {
+    match shown {
+        "" => rest.to_string(),
+        "/" => format!("/{}", rest),
+        _ => format!("{}/{}", shown, rest),
+    }
+}
+
+const BASH: &str = r#"
+# 从数据库补全标签、日志ID和目录，其余情况交给上面生成的 _dlog
+_dlog_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}"
+    local sub="" i skip=0
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        if ((skip)); then skip=0; continue; fi
+        case "${COMP_WORDS[i]}" in
+            --db|--profile|--color) skip=1 ;;
+            -*) ;;
+            *) sub="${COMP_WORDS[i]}"; break ;;
+        esac
+    done
+    case "${sub},${prev}" in
+        log,-t|log,--tags|get,-t|get,--tag|search,-t|search,--tag)
+            mapfile -t COMPREPLY < <(dlog __complete tags "${cur}" 2>/dev/null)
+            return 0
+            ;;
+    esac
+    if [[ "${cur}" != -* ]]; then
+        case "${sub},${prev}" in
+            fix,fix|del,del|done,done|copy,copy|lock,lock|unlock,unlock)
+                mapfile -t COMPREPLY < <(dlog __complete ids "${cur}" 2>/dev/null | cut -f1)
+                return 0
+                ;;
+            get,get)
+                mapfile -t COMPREPLY < <(dlog __complete dirs "${cur}" 2>/dev/null)
+                return 0
+                ;;
+        esac
+    fi
+    _dlog "$@"
+}
+complete -F _dlog_dynamic -o bashdefault -o default dlog
+"#;
+
+const FISH: &str = r#"
+# 从数据库补全标签、日志ID和目录
+complete -c dlog -n "__fish_dlog_using_subcommand log" -s t -l tags -f -a "(dlog __complete tags (commandline -ct) 2>/dev/null)"
+complete -c dlog -n "__fish_dlog_using_subcommand get" -s t -l tag -f -a "(dlog __complete tags (commandline -ct) 2>/dev/null)"
+complete -c dlog -n "__fish_dlog_using_subcommand search" -s t -l tag -f -a "(dlog __complete tags (commandline -ct) 2>/dev/null)"
+complete -c dlog -n "__fish_dlog_using_subcommand fix; or __fish_dlog_using_subcommand del; or __fish_dlog_using_subcommand done; or __fish_dlog_using_subcommand copy; or __fish_dlog_using_subcommand lock; or __fish_dlog_using_subcommand unlock" -f -a "(dlog __complete ids (commandline -ct) 2>/dev/null)"
+complete -c dlog -n "__fish_dlog_using_subcommand get" -a "(dlog __complete dirs (commandline -ct) 2>/dev/null)"
+"#;
+
+const ZSH: &str = r#"
+# 从数据库补全标签、日志ID和目录
+_dlog_complete_tags() {
+    local -a candidates
+    candidates=(${(f)"$(dlog __complete tags "$PREFIX" 2>/dev/null)"})
+    compadd -Q -- $candidates
+}
+_dlog_complete_ids() {
+    local -a candidates
+    candidates=(${(f)"$(dlog __complete ids "$PREFIX" 2>/dev/null)"})
+    candidates=(${candidates//:/\\:})
+    candidates=(${candidates//$'\t'/:})
+    _describe -t ids 'log id' candidates
+}
+_dlog_complete_dirs() {
+    local -a candidates
+    candidates=(${(f)"$(dlog __complete dirs "$PREFIX" 2>/dev/null)"})
+    compadd -Q -- $candidates
+}
+"#;
@@ -0,0 +1,175 @@
+// src/config.rs
+
+use crate::error::{DlogError, Result};
+use crate::toml::{self, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// 配置文件中可以设置的键及其值的类型
+///
+/// 每个键也可以通过环境变量 `DLOG_<键名大写>` 设置，环境变量优先于配置文件。
+const KEYS: &[(&str, Kind)] = &[
+    ("cmd_max_output", Kind::Number),
+    ("duplicate_skip", Kind::Flag),
+    ("duplicate_window", Kind::Number),
+    ("hook_timeout", Kind::Number),
+    ("lowercase_tags", Kind::Flag),
+    ("pager", Kind::TextOrFlag),
+    ("post_log_hook", Kind::Text),
+    ("project_markers", Kind::Text),
+    ("record_host", Kind::Flag),
+];
+
+/// 配置项的值的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Flag,
+    Number,
+    Text,
+    TextOrFlag,
+}
+
+impl Kind {
+    fn accepts(self, value: &Value) -> bool {
+        match self {
+            Kind::Flag => matches!(value, Value::Bool(_)),
+            Kind::Number => matches!(value, Value::Integer(_)),
+            Kind::Text => matches!(value, Value::String(_)),
+            Kind::TextOrFlag => matches!(value, Value::String(_) | Value::Bool(_)),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Kind::Flag => "true or false",
+            Kind::Number => "a non-negative integer",
+            Kind::Text => "a string",
+            Kind::TextOrFlag => "a string or a boolean",
+        }
+    }
+}
+
+/// 已读取的配置文件；读取或解析失败时保存错误消息
+static FILE: OnceLock<std::result::Result<BTreeMap<String, Value>, String>> = OnceLock::new();
+
+/// 用户配置文件（$XDG_CONFIG_HOME/dlog/config.toml）
+pub fn config_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().ok_or(DlogError::HomeDirNotFound)?;
+    Ok(config_dir.join("dlog").join("config.toml"))
+}
+
+/// 读取并检查配置文件
+///
+/// 文件不存在时等同于空文件。格式错误、未知的键或类型不符时返回 InvalidInput，消息中包含文件
+/// 路径和行号。结果在本次运行中缓存；未调用本函数时，第一次读取配置项会加载文件并忽略错误。
+pub fn load() -> Result<()> {
+    match file() {
+        Ok(_) => Ok(()),
+        Err(message) => Err(DlogError::InvalidInput(message.clone())),
+    }
+}
+
+/// 配置文件中 `key` 的值，未设置时返回 None；不读取环境变量
+pub fn file_value(key: &str) -> Option<&'static Value> {
+    file().as_ref().ok()?.get(key)
+}
+
+/// 开关类配置项的值：环境变量优先（取值规则见 `env_flag`），其次是配置文件，都未设置时返回 `default`
+pub fn flag(key: &str, default: bool) -> bool {
+    let default = match file_value(key) {
+        Some(Value::Bool(value)) => *value,
+        _ => default,
+    };
+    env_flag(&env_name(key), default)
+}
+
+/// 整数配置项的值：环境变量优先（无法解析时忽略），其次是配置文件，都未设置时返回 `default`
+pub fn number(key: &str, default: u64) -> u64 {
+    let default = match file_value(key) {
+        Some(Value::Integer(value)) => *value,
+        _ => default,
+    };
+    env_number(&env_name(key), default)
+}
+
+/// 字符串配置项的值：环境变量优先，其次是配置文件，都未设置时返回 None
+pub fn text(key: &str) -> Option<String> {
+    match std::env::var(env_name(key)) {
+        Ok(value) => Some(value),
+        Err(_) => match file_value(key) {
+            Some(Value::String(value)) => Some(value.clone()),
+            _ => None,
+        },
+    }
+}
+
+/// 配置项对应的环境变量名，例如 pager 对应 DLOG_PAGER
+pub fn env_name(key: &str) -> String {
+    format!("DLOG_{}", key.to_ascii_uppercase())
+}
+
+fn file() -> &'static std::result::Result<BTreeMap<String, Value>, String> {
+    FILE.get_or_init(|| {
+        // 找不到主目录时没有配置文件可读，由需要主目录的操作自己报错
+        let Ok(path) = config_path() else {
+            return Ok(BTreeMap::new());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => parse_file(&path, &text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    })
+}
+
+fn parse_file(path: &Path, text: &str) -> std::result::Result<BTreeMap<String, Value>, String> {
+    let invalid = |line: usize, message: String| format!("{} line {}: {}", path.display(), line, message);
+    let mut values = BTreeMap::new();
+    for (key, line, value) in toml::parse(text).map_err(|(line, message)| invalid(line, message))? {
+        let Some(&(_, kind)) = KEYS.iter().find(|(name, _)| *name == key) else {
+            let supported: Vec<&str> = KEYS.iter().map(|(name, _)| *name).collect();
+            return Err(invalid(line, format!("unknown key \"{}\" (supported: {})", key, supported.join(", "))));
+        };
+        if !kind.accepts(&value) {
+            return Err(invalid(line, format!("{} must be {}, not {}", key, kind.describe(), value.kind())));
+        }
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+/// 读取开关类的环境变量：0、false、no、off 表示关闭，其他值表示开启，未设置时返回 `default`
+pub fn env_flag(name: &str, default: bool) -> bool {
+    match std::env::var(name) {
+        Ok(value) => !matches!(value.trim().to_ascii_lowercase().as_str(), "0" | "false" | "no" | "off"),
+        Err(_) => default,
+    }
+}
+
+/// 读取非负整数的环境变量，未设置或无法解析时返回 `default`
+pub fn env_number(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_file_checks_keys_and_types() {
+        let path = Path::new("/cfg/config.toml");
+        let values = parse_file(path, "pager = false\n").unwrap();
+        assert_eq!(values.get("pager"), Some(&Value::Bool(false)));
+
+        let unknown = parse_file(path, "\npagr = \"less\"\n").unwrap_err();
+        assert!(unknown.starts_with("/cfg/config.toml line 2: unknown key \"pagr\""), "{}", unknown);
+        let wrong_type = parse_file(path, "pager = 3\n").unwrap_err();
+        assert_eq!(wrong_type, "/cfg/config.toml line 1: pager must be a string or a boolean, not an integer");
+        let quoted_flag = parse_file(path, "lowercase_tags = \"no\"\n").unwrap_err();
+        assert_eq!(quoted_flag, "/cfg/config.toml line 1: lowercase_tags must be true or false, not a string");
+    }
+}
@@ -1,31 +1,552 @@
 // src/db.rs
 
+use crate::config;
 use crate::error::{DlogError, Result};
-use crate::models::LogEntry;
-use rusqlite::{params, Connection, OptionalExtension};
-use std::path::{Path, PathBuf};
+use crate::models::{Level, LogEntry, TODO_DONE, TODO_OPEN};
+use crate::sync::SyncRecord;
+use crate::tags;
+use chrono::{DateTime, DurationRound, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, Connection, ErrorCode, OpenFlags, OptionalExtension, Transaction, TransactionBehavior};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
 
-/// 获取数据库文件的标准路径 (~/.config/dlog/dlog.db)
+/// 通过 --db 参数指定的数据库路径，只在进程启动时设置一次
+static DB_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// 通过 --profile 参数选择的配置，只在进程启动时设置一次
+static PROFILE_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// 默认配置的名称
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// 设置本次运行使用的数据库路径，优先于其他所有配置
+pub fn set_db_path_override(path: PathBuf) {
+    let _ = DB_PATH_OVERRIDE.set(path);
+}
+
+/// 设置本次运行使用的配置，优先于 DLOG_PROFILE 环境变量
+pub fn set_profile_override(name: String) {
+    let _ = PROFILE_OVERRIDE.set(name);
+}
+
+/// 获取数据库文件的路径
+///
+/// 优先级：--db 参数 > DLOG_DB 环境变量 > --profile 参数 > DLOG_PROFILE 环境变量 >
+/// 'dlog init --db-path' 记录的路径 > 默认位置。
+/// 默认位于平台的数据目录（Linux 上为 $XDG_DATA_HOME/dlog/dlog.db，
+/// 即 ~/.local/share/dlog/dlog.db）。如果新位置还没有数据库而旧位置
+/// ~/.config/dlog/dlog.db 存在，则继续使用旧位置，直到 'dlog init' 迁移它。
 pub fn get_db_path() -> Result<PathBuf> {
+    if let Some(path) = configured_db_path()? {
+        return Ok(path);
+    }
+    default_profile_db_path()
+}
+
+/// 默认配置使用的数据库路径
+fn default_profile_db_path() -> Result<PathBuf> {
+    if let Some(path) = pointer_db_path()? {
+        return Ok(path);
+    }
+    let default_path = default_db_path()?;
+    if !default_path.exists() {
+        let legacy_path = legacy_db_path()?;
+        if legacy_path.exists() {
+            return Ok(legacy_path);
+        }
+    }
+    Ok(default_path)
+}
+
+/// 数据库的默认位置
+pub fn default_db_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().ok_or(DlogError::HomeDirNotFound)?;
+    Ok(data_dir.join("dlog").join("dlog.db"))
+}
+
+/// 旧版本使用的数据库位置 (~/.config/dlog/dlog.db)
+pub fn legacy_db_path() -> Result<PathBuf> {
     let home_dir = dirs::home_dir().ok_or(DlogError::HomeDirNotFound)?;
     Ok(home_dir.join(".config/dlog/dlog.db"))
 }
 
-/// 打开数据库连接
+/// 通过参数或环境变量指定的数据库路径，没有指定时返回 None
+fn configured_db_path() -> Result<Option<PathBuf>> {
+    if let Some(path) = DB_PATH_OVERRIDE.get() {
+        return Ok(Some(path.clone()));
+    }
+    if let Some(path) = std::env::var_os("DLOG_DB").filter(|p| !p.is_empty()) {
+        return Ok(Some(PathBuf::from(path)));
+    }
+    match active_profile()? {
+        Some(name) => Ok(Some(profile_db_path(&name)?)),
+        None => Ok(None),
+    }
+}
+
+/// 当前选择的配置名称，使用默认配置时返回 None
+pub fn active_profile() -> Result<Option<String>> {
+    let name = PROFILE_OVERRIDE
+        .get()
+        .cloned()
+        .or_else(|| std::env::var("DLOG_PROFILE").ok().filter(|p| !p.is_empty()));
+    match name {
+        Some(name) if name != DEFAULT_PROFILE => {
+            // 配置名称会成为文件名，只允许安全的字符
+            let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(DlogError::InvalidInput(format!(
+                    "Invalid profile name '{}'. Use letters, digits, '-' and '_' only.",
+                    name
+                )));
+            }
+            Ok(Some(name))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// 存放非默认配置数据库的目录
+pub fn profiles_dir() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().ok_or(DlogError::HomeDirNotFound)?;
+    Ok(data_dir.join("dlog").join("profiles"))
+}
+
+/// 配置对应的数据库路径
+pub fn profile_db_path(name: &str) -> Result<PathBuf> {
+    if name == DEFAULT_PROFILE {
+        return default_profile_db_path();
+    }
+    Ok(profiles_dir()?.join(format!("{}.db", name)))
+}
+
+/// 列出所有配置，默认配置排在最前，其余按名称排序
+pub fn list_profiles() -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    match std::fs::read_dir(profiles_dir()?) {
+        Ok(entries) => {
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "db") {
+                    if let Some(stem) = path.file_stem() {
+                        names.push(stem.to_string_lossy().to_string());
+                    }
+                }
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    names.retain(|n| n != DEFAULT_PROFILE);
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    Ok(names)
+}
+
+/// 'dlog init --db-path' 记录的数据库路径
+fn pointer_db_path() -> Result<Option<PathBuf>> {
+    match std::fs::read_to_string(db_pointer_path()?) {
+        Ok(content) if !content.trim().is_empty() => Ok(Some(PathBuf::from(content.trim()))),
+        Ok(_) => Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 记录自定义数据库位置的文件（$XDG_CONFIG_HOME/dlog/db-path）
+pub fn db_pointer_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().ok_or(DlogError::HomeDirNotFound)?;
+    Ok(config_dir.join("dlog").join("db-path"))
+}
+
+/// 'dlog archive' 默认使用的归档文件（$XDG_CONFIG_HOME/dlog/archive.db）
+pub fn default_archive_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().ok_or(DlogError::HomeDirNotFound)?;
+    Ok(config_dir.join("dlog").join("archive.db"))
+}
+
+/// 记录自定义的数据库位置，之后的命令都会使用该位置
+pub fn set_db_pointer(db_path: &Path) -> Result<()> {
+    let pointer = db_pointer_path()?;
+    if let Some(parent) = pointer.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(pointer, format!("{}\n", db_path.display()))?;
+    Ok(())
+}
+
+/// 打开指定数据库文件并统计日志条数，不是 dlog 数据库时返回 None
+pub fn count_logs_in_file(path: &Path) -> Result<Option<i64>> {
+    if !is_dlog_database(path)? {
+        return Ok(None);
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    count_logs(&conn).map(Some)
+}
+
+/// 检查已存在的文件是否为 dlog 数据库
+pub fn is_dlog_database(path: &Path) -> Result<bool> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    match has_logs_table(&conn) {
+        Ok(found) => Ok(found),
+        Err(DlogError::Sql(rusqlite::Error::SqliteFailure(e, _))) if e.code == ErrorCode::NotADatabase => {
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 如果仍在使用旧位置的数据库，返回 (旧路径, 新路径)
+///
+/// 显式配置了数据库位置时不做迁移。
+pub fn pending_db_move() -> Result<Option<(PathBuf, PathBuf)>> {
+    if configured_db_path()?.is_some() || pointer_db_path()?.is_some() {
+        return Ok(None);
+    }
+    let current = get_db_path()?;
+    let default_path = default_db_path()?;
+    Ok((current != default_path).then_some((current, default_path)))
+}
+
+/// 将数据库文件从 `from` 移动到 `to`，跨文件系统时改为复制后删除
+pub fn move_db_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to)?;
+        std::fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// 数据库迁移脚本，按顺序执行；第 N 个脚本执行后 user_version 为 N
+const MIGRATIONS: &[&str] = &[
+    // 1: 待办事项状态与完成时间
+    "ALTER TABLE logs ADD COLUMN status TEXT;
+     ALTER TABLE logs ADD COLUMN completed_at TEXT;",
+    // 2: 目录和日期查询的索引；限定单个目录时，(directory, timestamp) 反向扫描即可满足
+    //    ORDER BY timestamp DESC, id DESC，无需额外排序（排序中不能加入其他表达式）
+    "CREATE INDEX IF NOT EXISTS idx_logs_directory_timestamp ON logs(directory, timestamp);
+     CREATE INDEX IF NOT EXISTS idx_logs_date ON logs(date(timestamp));",
+    // 3: 日期过滤改为按本地日期换算的时间戳范围比较，date(timestamp) 索引不再使用
+    "DROP INDEX IF EXISTS idx_logs_date;",
+    // 4: 日志之间的关联，每对只存一行 (较小ID, 较大ID)；删除日志时由触发器清理关联
+    "CREATE TABLE IF NOT EXISTS log_links (
+         a INTEGER NOT NULL,
+         b INTEGER NOT NULL,
+         PRIMARY KEY (a, b),
+         CHECK (a < b)
+     );
+     CREATE INDEX IF NOT EXISTS idx_log_links_b ON log_links(b);
+     CREATE TRIGGER IF NOT EXISTS logs_delete_links AFTER DELETE ON logs
+     BEGIN
+         DELETE FROM log_links WHERE a = OLD.id OR b = OLD.id;
+     END;",
+    // 5: 记录日志的主机名和用户名
+    "ALTER TABLE logs ADD COLUMN hostname TEXT;
+     ALTER TABLE logs ADD COLUMN username TEXT;",
+    // 6: 日志的重要程度，已有的日志为 normal
+    "ALTER TABLE logs ADD COLUMN level TEXT NOT NULL DEFAULT 'normal';",
+    // 7: 时间戳统一换算为 `canonical_timestamp` 的格式（UTC、毫秒、以 Z 结尾），
+    //    带其他时区偏移的时间戳按字符串排序和比较时才不会出错；无法解析的保持原样
+    "UPDATE logs SET timestamp = strftime('%Y-%m-%dT%H:%M:%fZ', timestamp)
+     WHERE timestamp GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]T[0-9][0-9]:[0-9][0-9]:[0-9][0-9]*'
+       AND strftime('%Y-%m-%dT%H:%M:%fZ', timestamp) IS NOT NULL;
+     UPDATE logs SET completed_at = strftime('%Y-%m-%dT%H:%M:%fZ', completed_at)
+     WHERE strftime('%Y-%m-%dT%H:%M:%fZ', completed_at) IS NOT NULL;",
+    // 8: 锁定的日志不会被删除，除非显式要求
+    "ALTER TABLE logs ADD COLUMN locked INTEGER NOT NULL DEFAULT 0;",
+    // 9: 用于同步的 UUID 和修改时间，以及删除记录和同步状态
+    //    UUID 和修改时间由触发器维护：插入时补上未指定的值，修改内容时更新修改时间
+    //    （同时指定了 updated_at 的修改保留指定的值）；删除日志时记录删除时间，同步时不会恢复被删除的日志
+    "ALTER TABLE logs ADD COLUMN uuid TEXT;
+     ALTER TABLE logs ADD COLUMN updated_at TEXT;
+     UPDATE logs SET uuid = lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)), 2) || '-' || substr('89ab', 1 + abs(random()) % 4, 1) || substr(hex(randomblob(2)), 2) || '-' || hex(randomblob(6))), updated_at = timestamp;
+     CREATE UNIQUE INDEX IF NOT EXISTS idx_logs_uuid ON logs(uuid);
+     CREATE TRIGGER IF NOT EXISTS logs_insert_sync AFTER INSERT ON logs
+     WHEN NEW.uuid IS NULL OR NEW.updated_at IS NULL
+     BEGIN
+         UPDATE logs SET uuid = coalesce(NEW.uuid, lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)), 2) || '-' || substr('89ab', 1 + abs(random()) % 4, 1) || substr(hex(randomblob(2)), 2) || '-' || hex(randomblob(6)))),
+                         updated_at = coalesce(NEW.updated_at, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+         WHERE id = NEW.id;
+     END;
+     CREATE TRIGGER IF NOT EXISTS logs_update_sync
+     AFTER UPDATE OF timestamp, directory, content, tags, status, completed_at, level, locked ON logs
+     WHEN NEW.updated_at IS OLD.updated_at
+     BEGIN
+         UPDATE logs SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+     END;
+     CREATE TABLE IF NOT EXISTS deleted_logs (
+         uuid TEXT PRIMARY KEY,
+         deleted_at TEXT NOT NULL
+     );
+     CREATE TRIGGER IF NOT EXISTS logs_delete_sync AFTER DELETE ON logs
+     WHEN OLD.uuid IS NOT NULL
+     BEGIN
+         INSERT OR REPLACE INTO deleted_logs (uuid, deleted_at)
+         VALUES (OLD.uuid, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+     END;
+     CREATE TABLE IF NOT EXISTS sync_state (
+         key TEXT PRIMARY KEY,
+         value TEXT NOT NULL
+     );",
+    // 10: 删除记录中保存本地ID，找不到日志时可以提示它已被删除；同步导入的删除记录没有本地ID
+    "ALTER TABLE deleted_logs ADD COLUMN id INTEGER;
+     DROP TRIGGER IF EXISTS logs_delete_sync;
+     CREATE TRIGGER logs_delete_sync AFTER DELETE ON logs
+     WHEN OLD.uuid IS NOT NULL
+     BEGIN
+         INSERT OR REPLACE INTO deleted_logs (uuid, deleted_at, id)
+         VALUES (OLD.uuid, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), OLD.id);
+     END;",
+    // 11: 曾在升级时改写其他格式的时间戳；这种改写会把不带时区的本地时间当作 UTC，
+    //     已改由 'dlog doctor --fix-timestamps' 按需处理并报告。保留空的迁移，版本号保持不变
+    "",
+    // 12: 时间戳是否为正常的 RFC3339 格式（1 或 0），由触发器维护；无效的时间戳按原始字符串
+    //     无法正确排序，排在最后。(directory, ts_valid, timestamp) 索引反向扫描即可满足
+    //     ORDER BY ts_valid DESC, timestamp DESC, id DESC
+    "ALTER TABLE logs ADD COLUMN ts_valid INTEGER NOT NULL DEFAULT 1;
+     UPDATE logs SET ts_valid = (timestamp GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]T[0-9][0-9]:[0-9][0-9]:[0-9][0-9]*');
+     DROP INDEX IF EXISTS idx_logs_directory_timestamp;
+     CREATE INDEX idx_logs_directory_timestamp ON logs(directory, ts_valid, timestamp);
+     CREATE TRIGGER IF NOT EXISTS logs_insert_ts_valid AFTER INSERT ON logs
+     WHEN NEW.ts_valid IS NOT (NEW.timestamp GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]T[0-9][0-9]:[0-9][0-9]:[0-9][0-9]*')
+     BEGIN
+         UPDATE logs SET ts_valid = (NEW.timestamp GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]T[0-9][0-9]:[0-9][0-9]:[0-9][0-9]*') WHERE id = NEW.id;
+     END;
+     CREATE TRIGGER IF NOT EXISTS logs_update_ts_valid AFTER UPDATE OF timestamp, ts_valid ON logs
+     WHEN NEW.ts_valid IS NOT (NEW.timestamp GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]T[0-9][0-9]:[0-9][0-9]:[0-9][0-9]*')
+     BEGIN
+         UPDATE logs SET ts_valid = (NEW.timestamp GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]T[0-9][0-9]:[0-9][0-9]:[0-9][0-9]*') WHERE id = NEW.id;
+     END;",
+    // 13: 级别的排序键（high 为 2、normal 为 1、low 为 0，无法识别的值与 normal 相同），由触发器维护；
+    //     (directory, level_rank, ts_valid, timestamp) 索引反向扫描即可满足 --sort level 的
+    //     ORDER BY level_rank DESC, ts_valid DESC, timestamp DESC, id DESC
+    "ALTER TABLE logs ADD COLUMN level_rank INTEGER NOT NULL DEFAULT 1;
+     UPDATE logs SET level_rank = CASE level WHEN 'high' THEN 2 WHEN 'low' THEN 0 ELSE 1 END;
+     CREATE INDEX IF NOT EXISTS idx_logs_directory_level ON logs(directory, level_rank, ts_valid, timestamp);
+     CREATE TRIGGER IF NOT EXISTS logs_insert_level_rank AFTER INSERT ON logs
+     WHEN NEW.level_rank IS NOT CASE NEW.level WHEN 'high' THEN 2 WHEN 'low' THEN 0 ELSE 1 END
+     BEGIN
+         UPDATE logs SET level_rank = CASE NEW.level WHEN 'high' THEN 2 WHEN 'low' THEN 0 ELSE 1 END WHERE id = NEW.id;
+     END;
+     CREATE TRIGGER IF NOT EXISTS logs_update_level_rank AFTER UPDATE OF level, level_rank ON logs
+     WHEN NEW.level_rank IS NOT CASE NEW.level WHEN 'high' THEN 2 WHEN 'low' THEN 0 ELSE 1 END
+     BEGIN
+         UPDATE logs SET level_rank = CASE NEW.level WHEN 'high' THEN 2 WHEN 'low' THEN 0 ELSE 1 END WHERE id = NEW.id;
+     END;",
+];
+
+/// 时间戳的存储格式：UTC、毫秒精度、以 Z 结尾的定长 RFC3339（如 2024-05-01T08:30:00.000Z）
+///
+/// 所有写入数据库和用于比较的时间戳都使用这个格式，按字符串排序即按时间排序。
+/// 与迁移中 SQLite 的 strftime('%f') 一样四舍五入到毫秒。
+pub fn canonical_timestamp(timestamp: DateTime<Utc>) -> String {
+    let rounded = timestamp
+        .duration_round(chrono::Duration::milliseconds(1))
+        .unwrap_or(timestamp);
+    rounded.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// 查询日志时统一使用的列，顺序与 `row_to_entry` 保持一致
+const LOG_COLUMNS: &str =
+    "id, timestamp, content, tags, directory, status, completed_at, hostname, username, level, locked, uuid, updated_at";
+
+/// 将查询结果行转换为 LogEntry
+fn row_to_entry(row: &rusqlite::Row) -> rusqlite::Result<LogEntry> {
+    Ok(LogEntry {
+        id: row.get(0)?,
+        timestamp: row.get(1)?,
+        content: row.get(2)?,
+        tags: row.get(3)?,
+        directory: row.get(4)?,
+        status: row.get(5)?,
+        completed_at: row.get(6)?,
+        hostname: row.get(7)?,
+        username: row.get(8)?,
+        // 无法识别的值（例如其他版本写入的）按 normal 处理
+        level: row.get::<_, String>(9)?.parse().unwrap_or_default(),
+        locked: row.get::<_, i64>(10)? != 0,
+        uuid: row.get(11)?,
+        updated_at: row.get(12)?,
+    })
+}
+
+/// 打开当前配置的数据库（见 `get_db_path`），并确认数据库已初始化且结构符合预期
 pub fn open_connection() -> Result<Connection> {
+    open_connection_at(&get_db_path()?)
+}
+
+/// 打开指定位置的数据库，执行尚未应用的迁移并检查表结构
+pub fn open_connection_at(db_path: &Path) -> Result<Connection> {
+    if !db_path.exists() {
+        return Err(DlogError::DatabaseNotInitialized);
+    }
+    let conn = Connection::open(db_path).map_err(DlogError::Sql)?;
+    // 锁等待交给 `retry_busy` 的退避重试，单次等待不宜过长
+    conn.busy_timeout(Duration::from_millis(BUSY_TIMEOUT_MS))?;
+    if !retry_busy(|| has_logs_table(&conn))? {
+        return Err(DlogError::DatabaseNotInitialized);
+    }
+    migrate(&conn)?;
+    check_schema(&conn)?;
+    register_functions(&conn)?;
+    Ok(conn)
+}
+
+/// 注册查询中用到的自定义 SQL 函数
+///
+/// `lower_unicode(x)` 按 Unicode 规则转为小写；SQLite 自带的 lower() 和 LIKE 只处理 ASCII 字母。
+fn register_functions(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "lower_unicode",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(ctx.get::<Option<String>>(0)?.map(|s| s.to_lowercase())),
+    )?;
+    Ok(())
+}
+
+/// 检查 logs 表是否存在
+fn has_logs_table(conn: &Connection) -> Result<bool> {
+    let exists = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'logs')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(map_busy)?;
+    Ok(exists)
+}
+
+/// 通过 PRAGMA table_info 确认 logs 表包含所有需要的列
+fn check_schema(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("PRAGMA table_info(logs)")?;
+    let found = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let expected: Vec<&str> = LOG_COLUMNS.split(", ").collect();
+    if expected.iter().any(|col| !found.iter().any(|f| f == col)) {
+        return Err(DlogError::SchemaMismatch {
+            expected: expected.join(", "),
+            found: found.join(", "),
+        });
+    }
+    Ok(())
+}
+
+/// 执行尚未应用的数据库迁移
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(map_busy)?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            sql,
+            i + 1
+        ))
+        .map_err(map_busy)?;
+    }
+    Ok(())
+}
+
+/// 以只读方式打开归档等其他 dlog 数据库，不执行迁移
+pub fn open_readonly_at(db_path: &Path) -> Result<Connection> {
+    if !db_path.exists() || !is_dlog_database(db_path)? {
+        return Err(DlogError::InvalidInput(format!(
+            "{} is not a dlog database",
+            db_path.display()
+        )));
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    check_schema(&conn)?;
+    register_functions(&conn)?;
+    Ok(conn)
+}
+
+/// 以只读方式打开可能由较早版本的 dlog 创建的数据库，不执行迁移也不检查表结构
+///
+/// 用于 `merge_logs`，较早的版本缺少的列在读取时按默认值补齐。
+pub fn open_readonly_any_version_at(db_path: &Path) -> Result<Connection> {
+    if !db_path.exists() || !is_dlog_database(db_path)? {
+        return Err(DlogError::InvalidInput(format!(
+            "{} is not a dlog database",
+            db_path.display()
+        )));
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    register_functions(&conn)?;
+    Ok(conn)
+}
+
+/// 较早版本的 logs 表没有的列及读取时使用的默认值；其余的列从一开始就存在
+const ADDED_COLUMN_DEFAULTS: &[(&str, &str)] = &[
+    ("status", "NULL"),
+    ("completed_at", "NULL"),
+    ("hostname", "NULL"),
+    ("username", "NULL"),
+    ("level", "'normal'"),
+    ("locked", "0"),
+    ("uuid", "NULL"),
+    ("updated_at", "NULL"),
+];
+
+/// 按 `LOG_COLUMNS` 的顺序读取 `conn` 中 logs 表的列，表中没有的列换成默认值
+fn log_columns_of(conn: &Connection) -> Result<String> {
+    let mut stmt = conn.prepare("PRAGMA table_info(logs)")?;
+    let found = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let mut columns = Vec::new();
+    for column in LOG_COLUMNS.split(", ") {
+        if found.iter().any(|f| f == column) {
+            columns.push(column.to_string());
+        } else if let Some((_, default)) = ADDED_COLUMN_DEFAULTS.iter().find(|(name, _)| *name == column) {
+            columns.push(format!("{} AS {}", default, column));
+        } else {
+            return Err(DlogError::SchemaMismatch {
+                expected: LOG_COLUMNS.to_string(),
+                found: found.join(", "),
+            });
+        }
+    }
+    Ok(columns.join(", "))
+}
+
+/// 检查数据库文件和 logs 表是否已经存在
+pub fn is_initialized() -> Result<bool> {
     let db_path = get_db_path()?;
-    Connection::open(&db_path).map_err(DlogError::Sql)
+    if !db_path.exists() {
+        return Ok(false);
+    }
+    let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    has_logs_table(&conn)
 }
 
 /// 初始化数据库，如果表不存在则创建
 pub fn initialize_db() -> Result<()> {
-    let db_path = get_db_path()?;
+    initialize_db_at(&get_db_path()?)
+}
+
+/// 在指定位置创建（或升级）dlog 数据库
+pub fn initialize_db_at(db_path: &Path) -> Result<()> {
     if let Some(parent) = db_path.parent() {
         if !parent.exists() {
             std::fs::create_dir_all(parent)?;
         }
     }
-    let conn = Connection::open(&db_path)?;
+    let conn = Connection::open(db_path)?;
+    create_schema(&conn)
+}
+
+/// 创建最初版本的 logs 表，再执行所有尚未应用的迁移
+fn create_schema(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS logs (
             id INTEGER PRIMARY KEY,
@@ -36,27 +557,108 @@ pub fn initialize_db() -> Result<()> {
         )",
         [],
     )?;
-    Ok(())
+    migrate(conn)
 }
 
-/// 向数据库中插入一条新的日志
+/// 向数据库中插入一条新的日志，`todo` 为真时标记为未完成的待办事项，返回新日志的ID
 pub fn add_log(
     conn: &Connection,
     dir: &str,
     content: &str,
     tags: Option<&str>,
-) -> Result<()> {
-    // 生成 RFC3339 格式的时间戳字符串
-    let timestamp = chrono::Utc::now().to_rfc3339();
-    conn.execute(
-        "INSERT INTO logs (timestamp, directory, content, tags) VALUES (?1, ?2, ?3, ?4)",
-        params![timestamp, dir, content, tags],
+    todo: bool,
+) -> Result<i32> {
+    add_log_at(conn, dir, content, tags, todo, Level::Normal, Utc::now())
+}
+
+/// 以指定的时间戳插入一条日志，用于补记过去的日志，返回新日志的ID
+pub fn add_log_at(
+    conn: &Connection,
+    dir: &str,
+    content: &str,
+    tags: Option<&str>,
+    todo: bool,
+    level: Level,
+    timestamp: DateTime<Utc>,
+) -> Result<i32> {
+    let timestamp = canonical_timestamp(timestamp);
+    let status = todo.then_some(TODO_OPEN);
+    let (hostname, username) = current_origin();
+    retry_busy(|| {
+        conn.execute(
+            "INSERT INTO logs (timestamp, directory, content, tags, status, hostname, username, level)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![timestamp, dir, content, tags, status, hostname, username, level.as_str()],
+        )?;
+        Ok(conn.last_insert_rowid() as i32)
+    })
+}
+
+/// 查找同一目录中在 [since, until] 内记录、去掉两端空白后内容相同的日志，返回最新一条的ID
+///
+/// 借助 (directory, timestamp) 索引只扫描时间范围内的少量行，内容在 Rust 中比较，
+/// 因为 SQLite 的 trim() 默认只去掉空格。
+pub fn find_recent_duplicate(
+    conn: &Connection,
+    dir: &str,
+    content: &str,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Option<i32>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, content FROM logs
+         WHERE directory = ?1 AND ts_valid = 1 AND timestamp >= ?2 AND timestamp <= ?3
+         ORDER BY timestamp DESC, id DESC",
     )?;
-    Ok(())
+    let mut rows = stmt.query(params![dir, canonical_timestamp(since), canonical_timestamp(until)])?;
+    let content = content.trim();
+    while let Some(row) = rows.next()? {
+        let existing: String = row.get(1)?;
+        if existing.trim() == content {
+            return Ok(Some(row.get(0)?));
+        }
+    }
+    Ok(None)
+}
+
+/// 设为 false 时不记录主机名和用户名，对应的环境变量为 DLOG_RECORD_HOST
+const RECORD_ORIGIN_KEY: &str = "record_host";
+
+/// 当前的主机名，无法获取时返回 None
+pub fn current_hostname() -> Option<String> {
+    gethostname::gethostname()
+        .into_string()
+        .ok()
+        .filter(|h| !h.is_empty())
+}
+
+/// 当前的主机名和用户名，无法获取或已通过配置项 record_host 关闭时为 None
+///
+/// 只用于附加信息，任何情况下都不会让记录日志失败。
+fn current_origin() -> (Option<String>, Option<String>) {
+    if !config::flag(RECORD_ORIGIN_KEY, true) {
+        return (None, None);
+    }
+    let hostname = current_hostname();
+    let username = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|u| !u.is_empty());
+    (hostname, username)
 }
 
-/// 规范化路径，确保路径格式一致
-fn normalize_path(path: &Path) -> Result<String> {
+/// 检查是否已存在时间戳、目录和内容都相同的日志，用于导入时去重
+pub fn log_exists(conn: &Connection, dir: &str, content: &str, timestamp: DateTime<Utc>) -> Result<bool> {
+    let exists = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM logs WHERE timestamp = ?1 AND directory = ?2 AND content = ?3)",
+        params![canonical_timestamp(timestamp), dir, content],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
+
+/// 规范化路径，确保路径格式一致；相对路径相对于当前目录解析
+pub fn normalize_path(path: &Path) -> Result<String> {
     // 将路径转换为绝对路径
     let absolute_path = if path.is_relative() {
         std::env::current_dir()?.join(path)
@@ -65,73 +667,416 @@ fn normalize_path(path: &Path) -> Result<String> {
     };
     
     // 规范化路径：移除尾随斜杠，解析 . 和 ..
-    let canonical_path = absolute_path.canonicalize().unwrap_or(absolute_path);
+    // 路径不存在时无法 canonicalize，退而按字面解析
+    let canonical_path = absolute_path
+        .canonicalize()
+        .unwrap_or_else(|_| lexical_normalize(&absolute_path));
     
     // 转换为字符串并确保格式一致
-    Ok(canonical_path.to_string_lossy().to_string())
+    Ok(strip_windows_prefix(&canonical_path.to_string_lossy()))
+}
+
+/// 统一 Windows 路径的写法：去掉 canonicalize 产生的扩展长度前缀
+/// （`\\?\C:\` 和 `\\?\UNC\server\share`），并将盘符统一为大写
+///
+/// 对 Unix 路径没有影响。
+fn strip_windows_prefix(path: &str) -> String {
+    let path = match path.strip_prefix(r"\\?\UNC\") {
+        Some(rest) => format!(r"\\{}", rest),
+        None => path.strip_prefix(r"\\?\").unwrap_or(path).to_string(),
+    };
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => {
+            format!("{}{}", drive.to_ascii_uppercase(), &path[1..])
+        }
+        _ => path,
+    }
+}
+
+/// 不访问文件系统，按字面解析路径中的 . 和 ..
+fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// 日志查询的目录范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// 只查询指定目录
+    Dir,
+    /// 指定目录及其所有子目录
+    Recursive,
+    /// 指定目录及其 N 层以内的子目录（1 表示只包含直接子目录）
+    Depth(u32),
+    /// 所有目录，忽略查询路径
+    All,
+}
+
+impl Scope {
+    /// 根据 -r 参数选择目录范围
+    pub fn from_recursive(recursive: bool) -> Self {
+        if recursive {
+            Scope::Recursive
+        } else {
+            Scope::Dir
+        }
+    }
+
+    /// 根据 -r 和 --depth 参数选择目录范围，深度为 0 时等同于不递归
+    pub fn with_depth(recursive: bool, depth: Option<u32>) -> Self {
+        match depth {
+            Some(0) => Scope::Dir,
+            Some(n) if recursive => Scope::Depth(n),
+            _ => Scope::from_recursive(recursive),
+        }
+    }
+}
+
+/// 查询日志的过滤条件和分页参数
+#[derive(Debug, Default, Clone)]
+pub struct LogQuery<'a> {
+    pub tag: Option<&'a str>,
+    /// 每个词都必须出现在内容或标签中（按字面匹配，默认不区分大小写）
+    pub terms: &'a [String],
+    /// 内容或标签包含其中任意一个词的日志被排除（匹配方式与 `terms` 相同）
+    pub exclude: &'a [String],
+    /// `terms` 和 `exclude` 区分大小写
+    pub case_sensitive: bool,
+    /// 目录需要匹配的 glob 模式（SQLite GLOB 语义，`*` 可以跨越路径分隔符）
+    pub path_glob: Option<&'a str>,
+    /// 时间范围 [since, until)，均为 UTC
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// 一天中的时间段 [start, end)，按本地时间比较；start 晚于 end 时表示跨越午夜
+    pub between: Option<(NaiveTime, NaiveTime)>,
+    /// 一年中的某一天（月, 日），按本地时间比较，不限年份
+    pub month_day: Option<(u32, u32)>,
+    /// 只返回排在该日志之后（更早）的条目，用于游标分页
+    pub before_id: Option<i32>,
+    /// 只返回ID大于该值的日志，即在它之后插入的日志
+    pub after_id: Option<i32>,
+    /// 只返回与该日志直接或间接关联的日志（包括它自己）
+    pub linked: Option<i32>,
+    /// 只返回这些ID的日志
+    pub ids: Option<&'a [i32]>,
+    /// 只返回在该主机上记录的日志，不区分大小写
+    pub host: Option<&'a str>,
+    /// 只返回该级别的日志
+    pub level: Option<Level>,
+    /// 只返回不低于该级别的日志
+    pub min_level: Option<Level>,
+    /// 按级别从高到低排序，级别相同时再按时间倒序
+    pub by_level: bool,
+    /// 每个目录最多取最新的几条，之后再应用 `limit`，0 表示不限制
+    pub limit_per_dir: u32,
+    /// 返回的最大条数，0 表示不限制
+    pub limit: u32,
+    pub offset: u32,
 }
 
 /// 根据多种条件查询日志
 pub fn fetch_logs(
     conn: &Connection,
     path: &Path,
-    recursive: bool,
-    limit: u32,
-    tag: Option<&str>,
-    date: Option<&str>,
-    search: Option<&str>,
+    scope: Scope,
+    filter: &LogQuery,
 ) -> Result<Vec<LogEntry>> {
+    let (query, params) = fetch_logs_query(path, scope, filter)?;
+    let mut stmt = conn.prepare(&query)?;
+    let logs = stmt
+        .query_map(
+            rusqlite::params_from_iter(params.iter().map(|b| b.as_ref())),
+            row_to_entry,
+        )?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(logs)
+}
+
+/// `fetch_logs` 执行的查询及其参数
+fn fetch_logs_query(
+    path: &Path,
+    scope: Scope,
+    filter: &LogQuery,
+) -> Result<(String, Vec<Box<dyn rusqlite::ToSql>>)> {
+    let (where_clause, mut params) = log_query_filter(path, scope, filter)?;
+    let mut query = format!("SELECT {} FROM {}", LOG_COLUMNS, matching_logs(&where_clause, filter));
+
+    // SQLite 中负数的 LIMIT 表示不限制条数
+    let limit = if filter.limit == 0 { -1 } else { filter.limit as i64 };
+    if filter.by_level {
+        query.push_str("ORDER BY level_rank DESC, ");
+    } else {
+        query.push_str("ORDER BY ");
+    }
+    query.push_str("ts_valid DESC, timestamp DESC, id DESC LIMIT ? OFFSET ?");
+    params.push(Box::new(limit));
+    params.push(Box::new(filter.offset as i64));
+    Ok((query, params))
+}
+
+/// 从满足条件的日志中随机取一条，忽略排序和分页参数
+pub fn fetch_random_log(
+    conn: &Connection,
+    path: &Path,
+    scope: Scope,
+    filter: &LogQuery,
+) -> Result<Option<LogEntry>> {
+    let (where_clause, params) = log_query_filter(path, scope, filter)?;
+    let query = format!("SELECT {} FROM logs WHERE {} ORDER BY RANDOM() LIMIT 1", LOG_COLUMNS, where_clause);
+    let log = conn
+        .query_row(
+            &query,
+            rusqlite::params_from_iter(params.iter().map(|b| b.as_ref())),
+            row_to_entry,
+        )
+        .optional()?;
+    Ok(log)
+}
+
+/// 统计满足条件的日志总数，忽略 `limit` 和 `offset`
+pub fn count_matching_logs(
+    conn: &Connection,
+    path: &Path,
+    scope: Scope,
+    filter: &LogQuery,
+) -> Result<i64> {
+    let (where_clause, params) = log_query_filter(path, scope, filter)?;
+    let count = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {}", matching_logs(&where_clause, filter)),
+        rusqlite::params_from_iter(params.iter().map(|b| b.as_ref())),
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// 统计 `path` 的子目录中满足条件的日志数，按直接子目录的名称汇总，忽略 `limit` 和 `offset`
+///
+/// 过滤条件与 `fetch_logs` 相同，`path` 本身的日志不计入。按条数从多到少排列，条数相同时按名称排列。
+pub fn count_matching_logs_by_child(
+    conn: &Connection,
+    path: &Path,
+    filter: &LogQuery,
+) -> Result<Vec<(String, i64)>> {
+    let normalized_path = normalize_path(path)?;
+    let (where_clause, params) = log_query_filter(path, Scope::Recursive, filter)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT directory, COUNT(*) FROM {}GROUP BY directory",
+        matching_logs(&where_clause, filter)
+    ))?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params.iter().map(|b| b.as_ref())), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut by_child: BTreeMap<String, i64> = BTreeMap::new();
+    for (dir, count) in rows {
+        let child = Path::new(&dir)
+            .strip_prefix(&normalized_path)
+            .ok()
+            .and_then(|rest| rest.components().next())
+            .map(|c| c.as_os_str().to_string_lossy().into_owned());
+        if let Some(child) = child {
+            *by_child.entry(child).or_default() += count;
+        }
+    }
+    let mut children: Vec<(String, i64)> = by_child.into_iter().collect();
+    children.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(children)
+}
+
+/// 满足条件的日志，后面可以接 ORDER BY；设置了 `limit_per_dir` 时只保留每个目录中最新的几条
+///
+/// 每个目录内的排序与 `fetch_logs` 按时间倒序的排序一致。
+fn matching_logs(where_clause: &str, filter: &LogQuery) -> String {
+    if filter.limit_per_dir == 0 {
+        return format!("logs WHERE {}", where_clause);
+    }
+    format!(
+        "(SELECT *, ROW_NUMBER() OVER (PARTITION BY directory \
+         ORDER BY ts_valid DESC, timestamp DESC, id DESC) AS dir_rank FROM logs WHERE {}) WHERE dir_rank <= {} ",
+        where_clause, filter.limit_per_dir
+    )
+}
+
+/// 生成 `fetch_logs` 和 `count_matching_logs` 共用的 WHERE 子句及参数
+fn log_query_filter(
+    path: &Path,
+    scope: Scope,
+    filter: &LogQuery,
+) -> Result<(String, Vec<Box<dyn rusqlite::ToSql>>)> {
     // 规范化路径
     let normalized_path = normalize_path(path)?;
     
-    let mut query =
-        String::from("SELECT id, timestamp, content, tags, directory FROM logs WHERE ");
+    let (dir_clause, dir_params) = directory_filter(&normalized_path, scope);
+    let mut query = format!("{} ", dir_clause);
     let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+    for p in dir_params {
+        params.push(Box::new(p));
+    }
+    // 标签比较不区分大小写（包括非 ASCII 字符）
+    if let Some(t) = filter.tag {
+        query.push_str("AND instr(',' || lower_unicode(tags) || ',', ',' || ? || ',') > 0 ");
+        params.push(Box::new(t.to_lowercase()));
+    }
+
+    // 时间戳以 `canonical_timestamp` 的格式存储，可以直接按字符串比较；
+    // 格式无效的时间戳比较结果没有意义，不出现在按时间过滤的结果中
+    if filter.since.is_some() || filter.until.is_some() {
+        query.push_str("AND ts_valid = 1 ");
+    }
+    if let Some(since) = filter.since {
+        query.push_str("AND timestamp >= ? ");
+        params.push(Box::new(canonical_timestamp(since)));
+    }
+
+    if let Some(until) = filter.until {
+        query.push_str("AND timestamp < ? ");
+        params.push(Box::new(canonical_timestamp(until)));
+    }
+
+    if let Some((start, end)) = filter.between {
+        let op = if start <= end { "AND" } else { "OR" };
+        query.push_str(&format!(
+            "AND (time(timestamp, 'localtime') >= ? {} time(timestamp, 'localtime') < ?) ",
+            op
+        ));
+        params.push(Box::new(start.format("%H:%M:%S").to_string()));
+        params.push(Box::new(end.format("%H:%M:%S").to_string()));
+    }
+
+    if let Some((month, day)) = filter.month_day {
+        query.push_str("AND strftime('%m-%d', timestamp, 'localtime') = ? ");
+        params.push(Box::new(format!("{:02}-{:02}", month, day)));
+    }
+
+    // GLOB 不会把 % 和 _ 当作通配符，模式作为参数传入
+    if let Some(pattern) = filter.path_glob {
+        query.push_str("AND directory GLOB ? ");
+        params.push(Box::new(pattern.to_string()));
+    }
 
-    if recursive {
-        query.push_str("directory LIKE ? || '%' ");
-        params.push(Box::new(normalized_path));
+    // instr 按字面查找，不需要转义通配符；不区分大小写时两边都按 Unicode 规则转为小写
+    let (content_col, tags_col) = if filter.case_sensitive {
+        ("content", "ifnull(tags, '')")
     } else {
-        query.push_str("directory = ? ");
-        params.push(Box::new(normalized_path));
+        ("lower_unicode(content)", "lower_unicode(ifnull(tags, ''))")
+    };
+    let needle = |term: &str| if filter.case_sensitive { term.to_string() } else { term.to_lowercase() };
+    for term in filter.terms {
+        query.push_str(&format!("AND (instr({}, ?) > 0 OR instr({}, ?) > 0) ", content_col, tags_col));
+        params.push(Box::new(needle(term)));
+        params.push(Box::new(needle(term)));
     }
 
-    if let Some(t) = tag {
-        query.push_str("AND (tags = ? OR tags LIKE ? || ',%' OR tags LIKE '%,' || ? || ',%' OR tags LIKE '%,' || ?) ");
-        params.push(Box::new(t.to_string()));
-        params.push(Box::new(t.to_string()));
-        params.push(Box::new(t.to_string()));
-        params.push(Box::new(t.to_string()));
+    // tags 为 NULL 时 instr 的结果也是 NULL，因此先换成空串，否则没有标签的日志会被一并排除
+    for word in filter.exclude {
+        query.push_str(&format!("AND NOT (instr({}, ?) > 0 OR instr({}, ?) > 0) ", content_col, tags_col));
+        params.push(Box::new(needle(word)));
+        params.push(Box::new(needle(word)));
     }
 
-    if let Some(d) = date {
-        query.push_str("AND date(timestamp) = ? ");
-        params.push(Box::new(d.to_string()));
+    // 没有记录主机名的日志不会匹配
+    if let Some(host) = filter.host {
+        query.push_str("AND hostname = ? COLLATE NOCASE ");
+        params.push(Box::new(host.to_string()));
     }
 
-    if let Some(keyword) = search {
-        query.push_str("AND (content LIKE '%' || ? || '%' OR tags LIKE '%' || ? || '%') ");
-        params.push(Box::new(keyword.to_string()));
-        params.push(Box::new(keyword.to_string()));
+    if let Some(level) = filter.level {
+        query.push_str("AND level = ? ");
+        params.push(Box::new(level.as_str()));
     }
 
-    query.push_str("ORDER BY timestamp DESC LIMIT ?");
-    params.push(Box::new(limit as i64));
+    if let Some(min) = filter.min_level {
+        let names: Vec<&str> = Level::ALL.into_iter().filter(|l| *l >= min).map(Level::as_str).collect();
+        query.push_str(&format!("AND level IN ({}) ", vec!["?"; names.len()].join(",")));
+        for name in names {
+            params.push(Box::new(name));
+        }
+    }
 
-    let mut stmt = conn.prepare(&query)?;
-    let logs = stmt
-        .query_map(rusqlite::params_from_iter(params.iter().map(|b| b.as_ref())), |row| {
-            Ok(LogEntry {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                content: row.get(2)?,
-                tags: row.get(3)?,
-                directory: row.get(4)?,
-            })
-        })?
+    if let Some(id) = filter.linked {
+        // UNION 会去重，关联形成环时递归也会结束
+        query.push_str(
+            "AND id IN (WITH RECURSIVE cluster(id) AS (SELECT ? \
+             UNION SELECT CASE WHEN l.a = c.id THEN l.b ELSE l.a END \
+             FROM log_links l JOIN cluster c ON l.a = c.id OR l.b = c.id) \
+             SELECT id FROM cluster) ",
+        );
+        params.push(Box::new(id));
+    }
+
+    if let Some(ids) = filter.ids {
+        // 以 JSON 数组传入，ID 很多时也不会超过 SQLite 的参数个数限制
+        let list: Vec<String> = ids.iter().map(i32::to_string).collect();
+        query.push_str("AND id IN (SELECT value FROM json_each(?)) ");
+        params.push(Box::new(format!("[{}]", list.join(","))));
+    }
+
+    if let Some(id) = filter.after_id {
+        query.push_str("AND id > ? ");
+        params.push(Box::new(id));
+    }
+
+    if let Some(id) = filter.before_id {
+        // 与排序保持一致：时间戳相同时按ID区分，保证翻页时不重复也不遗漏
+        query.push_str("AND (ts_valid, timestamp, id) < (SELECT ts_valid, timestamp, id FROM logs WHERE id = ?) ");
+        params.push(Box::new(id));
+    }
+
+    Ok((query, params))
+}
+
+/// 数据库中最大的日志ID，没有日志时为 0
+pub fn max_log_id(conn: &Connection) -> Result<i32> {
+    let id = conn.query_row("SELECT COALESCE(MAX(id), 0) FROM logs", [], |row| row.get(0))?;
+    Ok(id)
+}
+
+/// 与 `id` 最接近的 `n` 个已存在的日志ID，按ID从小到大排列，不包括 `id` 本身
+pub fn nearest_ids(conn: &Connection, id: i32, n: usize) -> Result<Vec<i32>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM (SELECT id FROM logs WHERE id < ?1 ORDER BY id DESC LIMIT ?2)
+         UNION ALL
+         SELECT id FROM (SELECT id FROM logs WHERE id > ?1 ORDER BY id LIMIT ?2)",
+    )?;
+    let mut ids = stmt
+        .query_map(params![id, n as i64], |row| row.get::<_, i32>(0))?
         .collect::<std::result::Result<Vec<_>, _>>()?;
+    // 距离相同时较小的ID优先
+    ids.sort_by_key(|other| ((*other as i64 - id as i64).abs(), *other));
+    ids.truncate(n);
+    ids.sort_unstable();
+    Ok(ids)
+}
 
-    Ok(logs)
+/// 本地ID为 `id` 的日志最近一次被删除的时间（移到归档不算删除），没有记录时为 None
+pub fn deleted_at(conn: &Connection, id: i32) -> Result<Option<String>> {
+    let at = conn
+        .query_row(
+            "SELECT deleted_at FROM deleted_logs WHERE id = ? ORDER BY deleted_at DESC LIMIT 1",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(at)
+}
+
+/// 统计数据库中的日志总数
+pub fn count_logs(conn: &Connection) -> Result<i64> {
+    let count = conn.query_row("SELECT COUNT(*) FROM logs", [], |row| row.get(0))?;
+    Ok(count)
 }
 
 /// 根据ID获取单条日志的内容
@@ -146,47 +1091,662 @@ pub fn get_log_content(conn: &Connection, id: i32) -> Result<Option<String>> {
     Ok(content)
 }
 
-/// 更新日志内容
-pub fn update_log_content(conn: &Connection, id: i32, new_content: &str) -> Result<usize> {
-    let count = conn.execute(
-        "UPDATE logs SET content = ? WHERE id = ?",
-        (new_content, id),
-    )?;
-    Ok(count)
+/// 根据ID获取完整的日志条目
+pub fn get_log(conn: &Connection, id: i32) -> Result<Option<LogEntry>> {
+    let log = conn
+        .query_row(
+            &format!("SELECT {} FROM logs WHERE id = ?", LOG_COLUMNS),
+            [id],
+            row_to_entry,
+        )
+        .optional()?;
+    Ok(log)
 }
 
-/// 根据ID列表删除日志
-pub fn delete_logs_by_id(conn: &Connection, ids: &[i32]) -> Result<usize> {
-    if ids.is_empty() {
-        return Ok(0);
+/// 根据ID列表获取日志，按ID排序，不存在的ID被忽略
+pub fn get_logs_by_ids(conn: &Connection, ids: &[i32]) -> Result<Vec<LogEntry>> {
+    let mut logs = Vec::new();
+    // 分批查询，避免超过 SQLite 的参数个数上限
+    for chunk in ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM logs WHERE id IN ({})",
+            LOG_COLUMNS, placeholders
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(chunk), row_to_entry)?;
+        for row in rows {
+            logs.push(row?);
+        }
     }
-    
-    let placeholders = vec!["?"; ids.len()].join(",");
-    let query = format!("DELETE FROM logs WHERE id IN ({})", placeholders);
-    
-    let mut stmt = conn.prepare(&query)?;
-    let count = stmt.execute(rusqlite::params_from_iter(ids))?;
-    Ok(count)
+    logs.sort_by_key(|log| log.id);
+    logs.dedup_by_key(|log| log.id);
+    Ok(logs)
 }
 
-/// 根据路径递归查找日志
-pub fn find_logs_in_path(conn: &Connection, path: &Path) -> Result<Vec<LogEntry>> {
-    // 规范化路径
-    let normalized_path = normalize_path(path)?;
+/// 将日志复制到目录 `dir`，副本使用当前时间，返回副本的ID；日志不存在时返回 None
+///
+/// 内容、标签和级别保持不变，待办事项的副本重新标记为未完成，主机名和用户名记录为当前的值。
+pub fn copy_log(conn: &Connection, id: i32, dir: &str) -> Result<Option<i32>> {
+    let (hostname, username) = current_origin();
+    retry_busy(|| {
+        let count = conn.execute(
+            "INSERT INTO logs (timestamp, directory, content, tags, status, hostname, username, level)
+             SELECT ?1, ?2, content, tags, CASE WHEN status IS NULL THEN NULL ELSE ?3 END, ?4, ?5, level
+             FROM logs WHERE id = ?6",
+            params![canonical_timestamp(Utc::now()), dir, TODO_OPEN, hostname, username, id],
+        )?;
+        Ok((count > 0).then(|| conn.last_insert_rowid() as i32))
+    })
+}
+
+/// 修改日志的级别，返回修改的行数（日志不存在时为 0）
+pub fn update_log_level(conn: &Connection, id: i32, level: Level) -> Result<usize> {
+    retry_busy(|| Ok(conn.execute("UPDATE logs SET level = ? WHERE id = ?", (level.as_str(), id))?))
+}
+
+/// 更新日志内容
+pub fn update_log_content(conn: &Connection, id: i32, new_content: &str) -> Result<usize> {
+    retry_busy(|| Ok(conn.execute("UPDATE logs SET content = ? WHERE id = ?", (new_content, id))?))
+}
+
+/// 同时更新日志的内容和标签
+pub fn update_log_content_and_tags(
+    conn: &Connection,
+    id: i32,
+    new_content: &str,
+    tags: Option<&str>,
+) -> Result<usize> {
+    retry_busy(|| {
+        Ok(conn.execute(
+            "UPDATE logs SET content = ?, tags = ? WHERE id = ?",
+            params![new_content, tags, id],
+        )?)
+    })
+}
+
+/// 关联两条日志，返回 false 表示两者已经关联
+pub fn add_link(conn: &Connection, a: i32, b: i32) -> Result<bool> {
+    let count = retry_busy(|| {
+        Ok(conn.execute(
+            "INSERT OR IGNORE INTO log_links (a, b) VALUES (?1, ?2)",
+            params![a.min(b), a.max(b)],
+        )?)
+    })?;
+    Ok(count > 0)
+}
+
+/// 取消两条日志的关联，返回 false 表示两者没有关联
+pub fn remove_link(conn: &Connection, a: i32, b: i32) -> Result<bool> {
+    let count = retry_busy(|| {
+        Ok(conn.execute(
+            "DELETE FROM log_links WHERE a = ?1 AND b = ?2",
+            params![a.min(b), a.max(b)],
+        )?)
+    })?;
+    Ok(count > 0)
+}
+
+/// 查询一批日志各自关联的日志ID，结果按ID升序排列；没有关联的日志不出现在结果中
+pub fn fetch_links(conn: &Connection, ids: &[i32]) -> Result<BTreeMap<i32, Vec<i32>>> {
+    let mut links: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+    if ids.is_empty() {
+        return Ok(links);
+    }
+    let placeholders = vec!["?"; ids.len()].join(",");
+    let query = format!(
+        "SELECT a, b FROM log_links WHERE a IN ({0}) OR b IN ({0}) ORDER BY a, b",
+        placeholders
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let pairs = stmt
+        .query_map(rusqlite::params_from_iter(ids.iter().chain(ids)), |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    for (a, b) in pairs {
+        if ids.contains(&a) {
+            links.entry(a).or_default().push(b);
+        }
+        if ids.contains(&b) {
+            links.entry(b).or_default().push(a);
+        }
+    }
+    for related in links.values_mut() {
+        related.sort_unstable();
+    }
+    Ok(links)
+}
+
+/// `delete_logs_by_id` 的结果
+#[derive(Debug, Default)]
+pub struct Deletion {
+    pub deleted: usize,
+    /// 因锁定而没有删除的日志ID，从小到大排列
+    pub locked: Vec<i32>,
+}
+
+/// 根据ID列表删除日志；除非 `include_locked`，锁定的日志会被跳过并记录在结果中
+pub fn delete_logs_by_id(conn: &Connection, ids: &[i32], include_locked: bool) -> Result<Deletion> {
+    if ids.is_empty() {
+        return Ok(Deletion::default());
+    }
     
-    let mut stmt = conn.prepare("SELECT id, timestamp, content, tags, directory FROM logs WHERE directory LIKE ? || '%'")?;
-    let logs = stmt
-        .query_map([&normalized_path], |row| {
-            Ok(LogEntry {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                content: row.get(2)?,
-                tags: row.get(3)?,
-                directory: row.get(4)?,
-            })
+    let placeholders = vec!["?"; ids.len()].join(",");
+    let mut query = format!("DELETE FROM logs WHERE id IN ({})", placeholders);
+    if !include_locked {
+        query.push_str(" AND locked = 0");
+    }
+    // 删除日志的同时触发器会清理关联，放在同一个事务中
+    with_transaction(conn, |tx| {
+        let locked = if include_locked {
+            Vec::new()
+        } else {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT id FROM logs WHERE id IN ({}) AND locked != 0 ORDER BY id",
+                placeholders
+            ))?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(ids), |row| row.get(0))?;
+            rows.collect::<std::result::Result<Vec<i32>, _>>()?
+        };
+        let deleted = tx.execute(&query, rusqlite::params_from_iter(ids))?;
+        Ok(Deletion { deleted, locked })
+    })
+}
+
+/// 锁定或解锁日志，返回实际改变状态的数量
+pub fn set_locked(conn: &Connection, ids: &[i32], locked: bool) -> Result<usize> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let placeholders = vec!["?"; ids.len()].join(",");
+    let query = format!(
+        "UPDATE logs SET locked = {} WHERE id IN ({}) AND locked != {}",
+        locked as i32, placeholders, locked as i32
+    );
+    retry_busy(|| Ok(conn.execute(&query, rusqlite::params_from_iter(ids))?))
+}
+
+/// `merge_logs` 的结果
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    /// 导入（或试运行时将要导入）的日志，ID为在另一个数据库中的ID，目录已按前缀改写
+    pub imported: Vec<LogEntry>,
+    /// 导入的日志中原ID在本地已被占用、分配了新ID的条数
+    pub conflicted: usize,
+    /// 本地已经存在而跳过的条数
+    pub skipped: usize,
+    /// 新增的关联数
+    pub links: usize,
+}
+
+/// 将 `other` 中本地没有的日志复制到 `conn`
+///
+/// UUID 相同的日志视为同一条；UUID 不同或没有 UUID（来自较早版本的数据库）时，
+/// 时间、目录和内容都相同的日志也视为同一条，例如两台机器分别从同一个 git 仓库导入的提交。
+/// `other` 可以是尚未迁移的数据库，缺少的列按默认值读取。
+/// 目录先按 `prefixes` 中第一个匹配的 (旧前缀, 新前缀) 改写。原ID在本地未被占用时保留，
+/// 否则由 SQLite 分配新ID；两条日志都存在于本地（导入的或原有的）时复制它们之间的关联。
+/// 所有写入在同一个事务中完成，`dry_run` 为真时只统计而不写入。
+pub fn merge_logs(
+    conn: &Connection,
+    other: &Connection,
+    prefixes: &[(String, String)],
+    dry_run: bool,
+) -> Result<MergeReport> {
+    let mut incoming = {
+        let mut stmt = other.prepare(&format!("SELECT {} FROM logs ORDER BY id", log_columns_of(other)?))?;
+        let rows = stmt
+            .query_map([], row_to_entry)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows
+    };
+    for log in &mut incoming {
+        // 另一个数据库可能还没有迁移，时间戳先换成本地的存储格式再比较
+        if let Some(dt) = reparse_timestamp(&log.timestamp) {
+            log.timestamp = canonical_timestamp(dt);
+        }
+        if let Some(dir) = prefixes.iter().find_map(|(old, new)| replace_dir_prefix(&log.directory, old, new)) {
+            log.directory = dir;
+        }
+    }
+    let has_links: bool = other.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'log_links')",
+        [],
+        |row| row.get(0),
+    )?;
+    let links = if !has_links {
+        Vec::new()
+    } else {
+        let mut stmt = other.prepare("SELECT a, b FROM log_links ORDER BY a, b")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    with_transaction(conn, |tx| {
+        let mut report = MergeReport::default();
+        // 另一个数据库中的ID -> 本地ID，试运行时只记录已存在的日志
+        let mut ids: BTreeMap<i32, i32> = BTreeMap::new();
+        let mut keep_id = Vec::new();
+        let mut new_id = Vec::new();
+        let mut seen = BTreeSet::new();
+        let mut seen_uuids = BTreeSet::new();
+        for log in &incoming {
+            let key = (log.timestamp.as_str(), log.directory.as_str(), log.content.as_str());
+            let by_uuid: Option<i32> = match &log.uuid {
+                Some(uuid) => tx
+                    .query_row("SELECT id FROM logs WHERE uuid = ?1", params![uuid], |row| row.get(0))
+                    .optional()?,
+                None => None,
+            };
+            let existing = match by_uuid {
+                Some(id) => Some(id),
+                None => tx
+                    .query_row(
+                        "SELECT id FROM logs WHERE directory = ?1 AND timestamp = ?2 AND content = ?3 LIMIT 1",
+                        params![log.directory, log.timestamp, log.content],
+                        |row| row.get(0),
+                    )
+                    .optional()?,
+            };
+            if let Some(id) = existing {
+                ids.insert(log.id, id);
+                report.skipped += 1;
+                continue;
+            }
+            // 另一个数据库中自身的重复只导入一次
+            let new_uuid = log.uuid.as_deref().is_none_or(|uuid| seen_uuids.insert(uuid));
+            if !seen.insert(key) || !new_uuid {
+                report.skipped += 1;
+                continue;
+            }
+            let taken: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM logs WHERE id = ?)",
+                params![log.id],
+                |row| row.get(0),
+            )?;
+            if taken {
+                new_id.push(log);
+            } else {
+                keep_id.push(log);
+            }
+        }
+        report.conflicted = new_id.len();
+
+        if !dry_run {
+            // 先写入保留原ID的日志，之后分配的新ID才不会与它们冲突
+            for log in &keep_id {
+                insert_merged(tx, log, Some(log.id))?;
+                ids.insert(log.id, log.id);
+            }
+            for log in &new_id {
+                ids.insert(log.id, insert_merged(tx, log, None)?);
+            }
+            for (a, b) in &links {
+                if let (Some(&a), Some(&b)) = (ids.get(a), ids.get(b)) {
+                    report.links += tx.execute(
+                        "INSERT OR IGNORE INTO log_links (a, b) VALUES (?1, ?2)",
+                        params![a.min(b), a.max(b)],
+                    )?;
+                }
+            }
+        }
+
+        let imported: BTreeSet<i32> = keep_id.iter().chain(&new_id).map(|log| log.id).collect();
+        report.imported = incoming.iter().filter(|log| imported.contains(&log.id)).cloned().collect();
+        Ok(report)
+    })
+}
+
+/// 插入从另一个数据库合并来的日志，保留其所有字段；`id` 为 None 时分配新ID，返回本地ID
+///
+/// UUID 已被本地的其他日志使用时（例如两个数据库由同一个文件复制而来）分配新的 UUID。
+fn insert_merged(conn: &Connection, log: &LogEntry, id: Option<i32>) -> Result<i32> {
+    conn.execute(
+        "INSERT INTO logs (id, timestamp, directory, content, tags, status, completed_at, hostname, username, level, locked, uuid, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11,
+                 (SELECT ?12 WHERE NOT EXISTS (SELECT 1 FROM logs WHERE uuid = ?12)), ?13)",
+        params![
+            id,
+            log.timestamp,
+            log.directory,
+            log.content,
+            log.tags,
+            log.status,
+            log.completed_at,
+            log.hostname,
+            log.username,
+            log.level.as_str(),
+            log.locked,
+            log.uuid,
+            log.updated_at
+        ],
+    )?;
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+/// `dir` 等于 `old` 或位于其下时，返回把这部分换成 `new` 之后的路径
+fn replace_dir_prefix(dir: &str, old: &str, new: &str) -> Option<String> {
+    let rest = dir.strip_prefix(old)?;
+    if !rest.is_empty() && !rest.starts_with(['/', '\\']) {
+        return None;
+    }
+    Some(format!("{}{}", new, rest))
+}
+
+/// `sync_state` 中记录上次导出时间的键
+const LAST_EXPORT_KEY: &str = "last_export";
+
+/// 上次 'sync export' 开始的时间，从未导出过时为 None
+pub fn last_sync_export(conn: &Connection) -> Result<Option<String>> {
+    let value = conn
+        .query_row(
+            "SELECT value FROM sync_state WHERE key = ?",
+            params![LAST_EXPORT_KEY],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value)
+}
+
+/// 记录一次导出的开始时间，之后 `sync_changes` 从这个时间开始收集修改
+pub fn record_sync_export(conn: &Connection, at: &str) -> Result<()> {
+    retry_busy(|| {
+        conn.execute(
+            "INSERT OR REPLACE INTO sync_state (key, value) VALUES (?, ?)",
+            params![LAST_EXPORT_KEY, at],
+        )?;
+        Ok(())
+    })
+}
+
+/// 在 `since` 及之后创建、修改或删除的日志；`since` 为 None 时返回所有日志和删除记录
+///
+/// 日志按ID排列，删除记录按删除时间排列，都在日志之后。
+pub fn sync_changes(conn: &Connection, since: Option<&str>) -> Result<Vec<SyncRecord>> {
+    let since = since.unwrap_or("");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM logs WHERE ifnull(updated_at, '') >= ? ORDER BY id",
+        LOG_COLUMNS
+    ))?;
+    let mut records = stmt
+        .query_map(params![since], row_to_entry)?
+        .map(|row| row.map(SyncRecord::Entry))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let mut stmt = conn.prepare(
+        "SELECT uuid, deleted_at FROM deleted_logs WHERE deleted_at >= ? ORDER BY deleted_at, uuid",
+    )?;
+    for row in stmt.query_map(params![since], |row| {
+        Ok(SyncRecord::Deleted { uuid: row.get(0)?, deleted_at: row.get(1)? })
+    })? {
+        records.push(row?);
+    }
+    Ok(records)
+}
+
+/// 同步时本地保留了自己的版本的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncConflictKind {
+    /// 本地的修改比传入的更新
+    LocalNewer,
+    /// 日志在本地已被删除，不会被恢复
+    DeletedLocally,
+    /// 传入的是删除记录，但本地在删除之后又修改过
+    EditedAfterDeletion,
+    /// 传入的是删除记录，但本地的日志已锁定
+    Locked,
+}
+
+/// 同步时本地与传入的版本不一致、保留了本地版本的一条日志
+#[derive(Debug, Clone)]
+pub struct SyncConflict {
+    pub uuid: String,
+    /// 本地仍存在的日志ID
+    pub local_id: Option<i32>,
+    pub kind: SyncConflictKind,
+}
+
+/// `apply_sync_records` 的结果
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// 新增、更新或删除的日志数
+    pub applied: usize,
+    /// 本地已经是相同状态或比传入的更新、无需处理的记录数
+    pub skipped: usize,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// 按 UUID 把同步包中的记录应用到本地数据库
+///
+/// 两边都有的日志保留 `updated_at` 较新的版本；本地已删除的日志不会被恢复；
+/// 删除记录只在晚于本地最后一次修改、且本地日志未锁定时删除日志。
+/// 所有记录在同一个事务中应用，`dry_run` 为真时最后回滚，只返回统计。
+pub fn apply_sync_records(conn: &Connection, records: &[SyncRecord], dry_run: bool) -> Result<SyncReport> {
+    retry_busy(|| {
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+        let mut report = SyncReport::default();
+        for record in records {
+            apply_sync_record(&tx, record, &mut report)?;
+        }
+        if dry_run {
+            tx.rollback()?;
+        } else {
+            tx.commit()?;
+        }
+        Ok(report)
+    })
+}
+
+fn apply_sync_record(conn: &Connection, record: &SyncRecord, report: &mut SyncReport) -> Result<()> {
+    let uuid = record.uuid();
+    let local = conn
+        .query_row(&format!("SELECT {} FROM logs WHERE uuid = ?", LOG_COLUMNS), params![uuid], row_to_entry)
+        .optional()?;
+    let conflict = |local_id, kind| SyncConflict { uuid: uuid.to_string(), local_id, kind };
+    match (record, local) {
+        (SyncRecord::Entry(incoming), Some(local)) => {
+            if same_sync_content(incoming, &local) {
+                report.skipped += 1;
+            } else if incoming.updated_at > local.updated_at {
+                conn.execute(
+                    "UPDATE logs SET timestamp = ?1, directory = ?2, content = ?3, tags = ?4, status = ?5,
+                         completed_at = ?6, hostname = ?7, username = ?8, level = ?9, locked = ?10, updated_at = ?11
+                     WHERE id = ?12",
+                    params![
+                        incoming.timestamp,
+                        incoming.directory,
+                        incoming.content,
+                        incoming.tags,
+                        incoming.status,
+                        incoming.completed_at,
+                        incoming.hostname,
+                        incoming.username,
+                        incoming.level.as_str(),
+                        incoming.locked,
+                        incoming.updated_at,
+                        local.id
+                    ],
+                )?;
+                report.applied += 1;
+            } else {
+                report.conflicts.push(conflict(Some(local.id), SyncConflictKind::LocalNewer));
+            }
+        }
+        (SyncRecord::Entry(incoming), None) => {
+            let deleted_at: Option<String> = conn
+                .query_row("SELECT deleted_at FROM deleted_logs WHERE uuid = ?", params![uuid], |row| row.get(0))
+                .optional()?;
+            match deleted_at {
+                // 删除之后对方又修改过，仍然保持删除
+                Some(deleted_at) if incoming.updated_at.as_deref() > Some(deleted_at.as_str()) => {
+                    report.conflicts.push(conflict(None, SyncConflictKind::DeletedLocally));
+                }
+                Some(_) => report.skipped += 1,
+                None => {
+                    insert_merged(conn, incoming, None)?;
+                    report.applied += 1;
+                }
+            }
+        }
+        (SyncRecord::Deleted { deleted_at, .. }, Some(local)) => {
+            if local.locked {
+                report.conflicts.push(conflict(Some(local.id), SyncConflictKind::Locked));
+            } else if local.updated_at.as_deref() > Some(deleted_at.as_str()) {
+                report.conflicts.push(conflict(Some(local.id), SyncConflictKind::EditedAfterDeletion));
+            } else {
+                conn.execute("DELETE FROM logs WHERE id = ?", params![local.id])?;
+                // 触发器记录的是现在的时间，改为对方删除的时间，避免下次导出时再发回去
+                conn.execute(
+                    "UPDATE deleted_logs SET deleted_at = ? WHERE uuid = ?",
+                    params![deleted_at, uuid],
+                )?;
+                report.applied += 1;
+            }
+        }
+        (SyncRecord::Deleted { deleted_at, .. }, None) => {
+            // 记下删除，之后收到更早的修改时不会恢复这条日志
+            conn.execute(
+                "INSERT OR IGNORE INTO deleted_logs (uuid, deleted_at) VALUES (?, ?)",
+                params![uuid, deleted_at],
+            )?;
+            report.skipped += 1;
+        }
+    }
+    Ok(())
+}
+
+/// 两个版本除ID和修改时间外的内容是否相同
+fn same_sync_content(a: &LogEntry, b: &LogEntry) -> bool {
+    a.timestamp == b.timestamp
+        && a.directory == b.directory
+        && a.content == b.content
+        && a.tags == b.tags
+        && a.status == b.status
+        && a.completed_at == b.completed_at
+        && a.hostname == b.hostname
+        && a.username == b.username
+        && a.level == b.level
+        && a.locked == b.locked
+}
+
+/// 将指定ID的日志移动到归档数据库，返回移动的条数
+///
+/// 归档文件不存在时以相同的表结构创建，已存在时追加。日志在归档中获得新的ID，
+/// 避免与之前归档的日志冲突。复制和删除在同一个事务中完成。
+pub fn archive_logs(conn: &Connection, archive: &Path, ids: &[i32]) -> Result<usize> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    initialize_db_at(archive)?;
+
+    conn.execute(
+        "ATTACH DATABASE ?1 AS archive",
+        params![archive.to_string_lossy()],
+    )
+    .map_err(map_busy)?;
+    let moved = move_logs(conn, "main", "archive", ids);
+    conn.execute("DETACH DATABASE archive", [])?;
+    moved
+}
+
+/// 将归档文件中ID为 `ids` 的日志移回当前数据库，返回移动的条数；不存在的ID被忽略
+///
+/// 移回的日志在当前数据库中获得新的ID。
+pub fn restore_logs(conn: &Connection, archive: &Path, ids: &[i32]) -> Result<usize> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    // 较早创建的归档可能缺少新增的列，先补齐迁移
+    initialize_db_at(archive)?;
+
+    conn.execute(
+        "ATTACH DATABASE ?1 AS archive",
+        params![archive.to_string_lossy()],
+    )
+    .map_err(map_busy)?;
+    let moved = move_logs(conn, "archive", "main", ids);
+    conn.execute("DETACH DATABASE archive", [])?;
+    moved
+}
+
+/// 在同一个事务中把日志从 `from` 复制到 `to` 并从 `from` 中删除，副本在 `to` 中获得新的ID
+///
+/// 一起移动的日志之间的关联随之移动；与留在 `from` 中的日志的关联不会保留。
+fn move_logs(conn: &Connection, from: &str, to: &str, ids: &[i32]) -> Result<usize> {
+    let placeholders = vec!["?"; ids.len()].join(",");
+    with_transaction(conn, |tx| {
+        tx.execute(
+            &format!(
+                "INSERT INTO {to}.logs (timestamp, directory, content, tags, status, completed_at, hostname, username, level, locked, uuid, updated_at)
+                 SELECT timestamp, directory, content, tags, status, completed_at, hostname, username, level, locked, uuid, updated_at
+                 FROM {from}.logs WHERE id IN ({placeholders}) ORDER BY id"
+            ),
+            rusqlite::params_from_iter(ids),
+        )?;
+        // 两端都被移动的关联按 UUID 对应到新的ID；删除原日志时触发器会清理 `from` 中的关联
+        tx.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {to}.log_links (a, b)
+                 SELECT min(ta.id, tb.id), max(ta.id, tb.id)
+                 FROM {from}.log_links l
+                 JOIN {from}.logs fa ON fa.id = l.a
+                 JOIN {from}.logs fb ON fb.id = l.b
+                 JOIN {to}.logs ta ON ta.uuid = fa.uuid
+                 JOIN {to}.logs tb ON tb.uuid = fb.uuid
+                 WHERE l.a IN ({placeholders}) AND l.b IN ({placeholders})"
+            ),
+            rusqlite::params_from_iter(ids.iter().chain(ids)),
+        )?;
+        // 日志回到 `to` 后，之前移走时留下的删除记录不再成立
+        tx.execute(
+            &format!(
+                "DELETE FROM {to}.deleted_logs WHERE uuid IN (SELECT uuid FROM {from}.logs WHERE id IN ({placeholders}))"
+            ),
+            rusqlite::params_from_iter(ids),
+        )?;
+        let uuids: Vec<String> = tx
+            .prepare(&format!(
+                "SELECT uuid FROM {from}.logs WHERE id IN ({placeholders}) AND uuid IS NOT NULL"
+            ))?
+            .query_map(rusqlite::params_from_iter(ids), |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+        let deleted = tx.execute(
+            &format!("DELETE FROM {from}.logs WHERE id IN ({placeholders})"),
+            rusqlite::params_from_iter(ids),
+        )?;
+        // 移动不是删除：去掉触发器刚写入的删除记录，否则同步时另一台机器会删除这些日志
+        if !uuids.is_empty() {
+            tx.execute(
+                &format!(
+                    "DELETE FROM {from}.deleted_logs WHERE uuid IN ({})",
+                    vec!["?"; uuids.len()].join(",")
+                ),
+                rusqlite::params_from_iter(&uuids),
+            )?;
+        }
+        Ok(deleted)
+    })
+}
+
+/// 返回范围内所有带标签日志的 `(标签, 时间戳, 目录)`，用于统计标签的使用情况
+pub fn fetch_tag_usage(
+    conn: &Connection,
+    path: &Path,
+    scope: Scope,
+) -> Result<Vec<(String, String, String)>> {
+    let normalized_path = normalize_path(path)?;
+    let (dir_clause, dir_params) = directory_filter(&normalized_path, scope);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tags, timestamp, directory FROM logs WHERE {} AND tags IS NOT NULL AND tags != ''",
+        dir_clause
+    ))?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(dir_params), |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
-    Ok(logs)
+    Ok(rows)
 }
 
 /// 获取数据库中所有不重复的目录
@@ -198,16 +1758,1329 @@ pub fn get_distinct_directories(conn: &Connection) -> Result<Vec<String>> {
     Ok(dirs)
 }
 
-/// 根据目录列表删除日志
-pub fn delete_logs_by_directory(conn: &Connection, dirs: &[String]) -> Result<usize> {
+/// 获取数据库中出现过的所有标签，按字母顺序排列
+pub fn get_distinct_tags(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT DISTINCT tags FROM logs WHERE tags IS NOT NULL AND tags != ''")?;
+    let mut names = BTreeSet::new();
+    for row in stmt.query_map([], |row| row.get::<_, String>(0))? {
+        names.extend(tags::split_tags(&row?).into_iter().map(str::to_string));
+    }
+    Ok(names.into_iter().collect())
+}
+
+/// 根据目录列表删除日志；锁定的日志会被跳过并记录在结果中
+pub fn delete_logs_by_directory(conn: &Connection, dirs: &[String]) -> Result<Deletion> {
     if dirs.is_empty() {
-        return Ok(0);
+        return Ok(Deletion::default());
     }
-    
+
     let placeholders = vec!["?"; dirs.len()].join(",");
-    let query = format!("DELETE FROM logs WHERE directory IN ({})", placeholders);
-    
-    let mut stmt = conn.prepare(&query)?;
-    let count = stmt.execute(rusqlite::params_from_iter(dirs))?;
+    with_transaction(conn, |tx| {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT id FROM logs WHERE directory IN ({}) AND locked != 0 ORDER BY id",
+            placeholders
+        ))?;
+        let locked = stmt
+            .query_map(rusqlite::params_from_iter(dirs), |row| row.get(0))?
+            .collect::<std::result::Result<Vec<i32>, _>>()?;
+        let deleted = tx.execute(
+            &format!("DELETE FROM logs WHERE directory IN ({}) AND locked = 0", placeholders),
+            rusqlite::params_from_iter(dirs),
+        )?;
+        Ok(Deletion { deleted, locked })
+    })
+}
+
+/// 目录过滤条件及其参数
+///
+/// 递归时以范围比较代替 `LIKE ? || '%'` 做前缀匹配，这样既能利用目录索引，
+/// 也不会把路径中的 `%`、`_` 当作通配符。
+fn directory_filter(path: &str, scope: Scope) -> (String, Vec<String>) {
+    // 子目录必须以 "路径 + 分隔符" 开头，避免 /a/b 匹配到 /a/bc
+    let sep = std::path::MAIN_SEPARATOR;
+    let prefix = if path.ends_with(sep) {
+        path.to_string()
+    } else {
+        format!("{}{}", path, sep)
+    };
+    let subdirs = format!("directory >= ?{0} AND directory < ?{0}", DIRECTORY_COLLATE);
+    let subdir_params = vec![path.to_string(), prefix.clone(), format!("{}\u{10FFFF}", prefix)];
+
+    match scope {
+        Scope::Dir => (format!("directory = ?{}", DIRECTORY_COLLATE), vec![path.to_string()]),
+        Scope::Recursive => (
+            format!("(directory = ?{} OR ({}))", DIRECTORY_COLLATE, subdirs),
+            subdir_params,
+        ),
+        Scope::Depth(depth) => {
+            // 子目录的层数 = 比前缀多出的分隔符数 + 1
+            let prefix_separators = prefix.matches(sep).count();
+            let mut params = subdir_params;
+            params.push(sep.to_string());
+            (
+                format!(
+                    "(directory = ?{} OR ({} AND length(directory) - length(replace(directory, ?, '')) < {}))",
+                    DIRECTORY_COLLATE,
+                    subdirs,
+                    prefix_separators + depth as usize
+                ),
+                params,
+            )
+        }
+        Scope::All => ("1 = 1".to_string(), Vec::new()),
+    }
+}
+
+/// Windows 的文件系统不区分大小写，目录比较也应如此
+#[cfg(windows)]
+const DIRECTORY_COLLATE: &str = " COLLATE NOCASE";
+#[cfg(not(windows))]
+const DIRECTORY_COLLATE: &str = "";
+
+/// 查询目录下未完成的待办事项
+pub fn fetch_open_todos(conn: &Connection, path: &Path, scope: Scope) -> Result<Vec<LogEntry>> {
+    let normalized_path = normalize_path(path)?;
+
+    let (dir_clause, mut query_params) = directory_filter(&normalized_path, scope);
+    query_params.push(TODO_OPEN.to_string());
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM logs WHERE {} AND status = ? ORDER BY ts_valid DESC, timestamp DESC, id DESC",
+        LOG_COLUMNS, dir_clause
+    ))?;
+    let logs = stmt
+        .query_map(rusqlite::params_from_iter(query_params), row_to_entry)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(logs)
+}
+
+/// 统计目录下未完成的待办事项数量
+pub fn count_open_todos(conn: &Connection, path: &Path, scope: Scope) -> Result<i64> {
+    let normalized_path = normalize_path(path)?;
+
+    let (dir_clause, mut query_params) = directory_filter(&normalized_path, scope);
+    query_params.push(TODO_OPEN.to_string());
+    let count = conn.query_row(
+        &format!("SELECT COUNT(*) FROM logs WHERE {} AND status = ?", dir_clause),
+        rusqlite::params_from_iter(query_params),
+        |row| row.get(0),
+    )?;
     Ok(count)
 }
+
+/// 将待办事项标记为已完成并记录完成时间
+pub fn complete_todo(conn: &Connection, id: i32) -> Result<usize> {
+    let completed_at = canonical_timestamp(Utc::now());
+    retry_busy(|| {
+        Ok(conn.execute(
+            "UPDATE logs SET status = ?, completed_at = ? WHERE id = ? AND status = ?",
+            params![TODO_DONE, completed_at, id, TODO_OPEN],
+        )?)
+    })
+}
+
+/// 将 SQLITE_BUSY / SQLITE_LOCKED 转换为更友好的错误
+fn map_busy(err: rusqlite::Error) -> DlogError {
+    match err.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy) | Some(ErrorCode::DatabaseLocked) => DlogError::DatabaseBusy,
+        _ => DlogError::Sql(err),
+    }
+}
+
+/// 每次等待锁的最长时间（毫秒）
+const BUSY_TIMEOUT_MS: u64 = 1000;
+/// 数据库被锁定时的重试次数
+const BUSY_RETRIES: u32 = 4;
+/// 第一次重试前的等待时间（毫秒），之后每次加倍
+const BUSY_BACKOFF_MS: u64 = 50;
+
+fn is_busy(err: &DlogError) -> bool {
+    match err {
+        DlogError::DatabaseBusy => true,
+        DlogError::Sql(e) => matches!(
+            e.sqlite_error_code(),
+            Some(ErrorCode::DatabaseBusy) | Some(ErrorCode::DatabaseLocked)
+        ),
+        _ => false,
+    }
+}
+
+/// 执行 `f`，数据库被其他连接锁定时按指数退避重试，仍然失败时返回 DatabaseBusy
+pub fn retry_busy<T, F>(mut f: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut delay = BUSY_BACKOFF_MS;
+    for attempt in 0..=BUSY_RETRIES {
+        match f() {
+            Err(e) if is_busy(&e) => {
+                if attempt == BUSY_RETRIES {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(delay));
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    Err(DlogError::DatabaseBusy)
+}
+
+/// 在事务中执行 `f`：返回 Ok 时提交，返回错误时回滚，不会留下只完成一半的修改
+///
+/// 事务以 IMMEDIATE 方式开始，一开始就取得写锁；被锁定时整个事务按 `retry_busy` 重试，
+/// 因此 `f` 可能被调用多次。不能在已经开始的事务中调用。
+pub fn with_transaction<T, F>(conn: &Connection, mut f: F) -> Result<T>
+where
+    F: FnMut(&Transaction) -> Result<T>,
+{
+    retry_busy(|| {
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+        let value = f(&tx)?;
+        tx.commit()?;
+        Ok(value)
+    })
+}
+
+/// 整理数据库：重建索引和全文索引表，然后执行 VACUUM 和 ANALYZE
+pub fn compact(conn: &Connection) -> Result<()> {
+    conn.execute_batch("REINDEX;").map_err(map_busy)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master
+         WHERE type = 'table' AND sql LIKE 'CREATE VIRTUAL TABLE%USING fts%'",
+    )?;
+    let fts_tables = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<String>, _>>()?;
+    for table in &fts_tables {
+        conn.execute(
+            &format!("INSERT INTO \"{0}\"(\"{0}\") VALUES ('rebuild')", table),
+            [],
+        )
+        .map_err(map_busy)?;
+    }
+
+    conn.execute_batch("VACUUM; ANALYZE;").map_err(map_busy)?;
+    Ok(())
+}
+
+/// 将数据库完整地复制到 `path`（VACUUM INTO），目标文件不能已经存在
+pub fn backup_to(conn: &Connection, path: &Path) -> Result<()> {
+    conn.execute("VACUUM INTO ?", params![path.to_string_lossy()])
+        .map_err(map_busy)?;
+    Ok(())
+}
+
+/// 按时间顺序（无效的时间戳排在最后）为每条日志分配从 1 开始的新ID
+const RENUMBER_QUERY: &str =
+    "SELECT id AS old_id, ROW_NUMBER() OVER (ORDER BY ts_valid DESC, timestamp, id) AS new_id FROM logs";
+
+/// 重新编号会改变的日志，返回按新ID排列的 (旧ID, 新ID)
+pub fn renumber_plan(conn: &Connection) -> Result<Vec<(i32, i32)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT old_id, new_id FROM ({}) WHERE old_id != new_id ORDER BY new_id",
+        RENUMBER_QUERY
+    ))?;
+    let plan = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(plan)
+}
+
+/// 将日志ID按时间顺序重新编号为 1..N，并同步更新 log_links，返回改变的 (旧ID, 新ID)
+///
+/// 已删除日志的旧ID可能被重新分配给其他日志，因此删除记录中的本地ID会被清空。
+/// 在排他事务中执行，其他进程正在读写数据库时返回 DatabaseBusy。
+pub fn renumber_logs(conn: &Connection) -> Result<Vec<(i32, i32)>> {
+    let tx = retry_busy(|| Ok(Transaction::new_unchecked(conn, TransactionBehavior::Exclusive)?))?;
+    tx.execute_batch(&format!(
+        "CREATE TEMP TABLE renumber_map (old_id INTEGER PRIMARY KEY, new_id INTEGER NOT NULL);
+         INSERT INTO renumber_map {};",
+        RENUMBER_QUERY
+    ))?;
+    let plan = renumber_plan(&tx)?;
+    // 先换成负数，避免与尚未改写的ID冲突；关联按新ID重新排成 (较小ID, 较大ID)
+    tx.execute_batch(
+        "UPDATE logs SET id = -(SELECT new_id FROM renumber_map WHERE old_id = logs.id);
+         UPDATE logs SET id = -id;
+         CREATE TEMP TABLE renumber_links AS
+             SELECT min(ma.new_id, mb.new_id) AS a, max(ma.new_id, mb.new_id) AS b
+             FROM log_links
+             JOIN renumber_map ma ON ma.old_id = log_links.a
+             JOIN renumber_map mb ON mb.old_id = log_links.b;
+         DELETE FROM log_links;
+         INSERT INTO log_links (a, b) SELECT a, b FROM renumber_links;
+         DROP TABLE renumber_links;
+         DROP TABLE renumber_map;
+         UPDATE deleted_logs SET id = NULL WHERE id IS NOT NULL;",
+    )?;
+    tx.commit()?;
+    Ok(plan)
+}
+
+/// 执行 SQLite 完整性检查，返回发现的问题（没有问题时为空）
+pub fn integrity_check(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let messages = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<String>, _>>()?;
+    Ok(messages.into_iter().filter(|m| m != "ok").collect())
+}
+
+/// 每个目录的 `(目录, 最近一条日志的时间戳, 日志数量)`
+///
+/// `recent` 为真时按最近活动从新到旧排列，否则按目录排列；时间相同的目录按目录排列，保证顺序稳定。
+pub fn directory_activity(conn: &Connection, recent: bool) -> Result<Vec<(String, String, i64)>> {
+    let order = if recent { "MAX(timestamp) DESC, directory" } else { "directory" };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT directory, MAX(timestamp), COUNT(*) FROM logs GROUP BY directory ORDER BY {}",
+        order
+    ))?;
+    let dirs = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(dirs)
+}
+
+/// 统计每个目录下的日志数量
+pub fn count_logs_by_directory(conn: &Connection) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT directory, COUNT(*) FROM logs GROUP BY directory ORDER BY directory",
+    )?;
+    let counts = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(counts)
+}
+
+/// 将某个目录下的所有日志改为关联到新目录
+pub fn rename_directory(conn: &Connection, old: &str, new: &str) -> Result<usize> {
+    retry_busy(|| Ok(conn.execute("UPDATE logs SET directory = ? WHERE directory = ?", params![new, old])?))
+}
+
+/// 将目录 `old` 及其所有子目录中的日志改为指向 `new` 下对应的路径，返回修改的条数
+///
+/// 用于项目目录被移动之后：`old/sub` 会变为 `new/sub`。
+pub fn remap_directory(conn: &Connection, old: &str, new: &str) -> Result<usize> {
+    let (clause, dir_params) = directory_filter(old, Scope::Recursive);
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![
+        Box::new(new.to_string()),
+        Box::new(old.chars().count() as i64),
+    ];
+    for p in dir_params {
+        params.push(Box::new(p));
+    }
+    let query = format!("UPDATE logs SET directory = ? || substr(directory, ? + 1) WHERE {}", clause);
+    with_transaction(conn, |tx| {
+        Ok(tx.execute(&query, rusqlite::params_from_iter(params.iter().map(|b| b.as_ref())))?)
+    })
+}
+
+/// 查找内容为空或只包含空白字符的日志ID
+pub fn find_empty_logs(conn: &Connection) -> Result<Vec<i32>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM logs WHERE content IS NULL OR trim(content, ' ' || char(9, 10, 13)) = '' ORDER BY id",
+    )?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<i32>, _>>()?;
+    Ok(ids)
+}
+
+/// 查找时间戳无法按 RFC3339 解析，或者不是 `canonical_timestamp` 格式（例如手工修改过）的日志，
+/// 返回 (ID, 原始时间戳)
+pub fn find_invalid_timestamps(conn: &Connection) -> Result<Vec<(i32, String)>> {
+    let mut stmt = conn.prepare("SELECT id, timestamp FROM logs ORDER BY id")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(rows
+        .into_iter()
+        .filter(|(_, ts)| match ts.parse::<DateTime<Utc>>() {
+            Ok(dt) => canonical_timestamp(dt) != *ts,
+            Err(_) => true,
+        })
+        .collect())
+}
+
+/// 尝试按其他常见格式解析时间戳：不带时区的 `YYYY-MM-DD HH:MM:SS`（SQLite CURRENT_TIMESTAMP
+/// 的格式，按 UTC 处理，也接受 T 分隔和小数秒）以及 Unix 时间戳（秒）
+pub fn reparse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(dt) = raw.parse::<DateTime<Utc>>() {
+        return Some(dt);
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M"] {
+        if let Ok(naive) = chrono::NaiveDateTime::parse_from_str(raw, format) {
+            return Some(naive.and_utc());
+        }
+    }
+    if !raw.is_empty() && raw.bytes().all(|b| b.is_ascii_digit()) {
+        return raw.parse().ok().and_then(|secs| DateTime::from_timestamp(secs, 0));
+    }
+    None
+}
+
+/// 在一个事务中改写多条日志的时间戳，返回修改的条数
+pub fn update_log_timestamps(conn: &Connection, updates: &[(i32, DateTime<Utc>)]) -> Result<usize> {
+    with_transaction(conn, |tx| {
+        let mut count = 0;
+        for (id, timestamp) in updates {
+            count += tx.execute(
+                "UPDATE logs SET timestamp = ? WHERE id = ?",
+                params![canonical_timestamp(*timestamp), id],
+            )?;
+        }
+        Ok(count)
+    })
+}
+
+/// 查找时间戳、目录和内容完全相同的重复日志，每组按ID升序返回
+pub fn find_duplicate_logs(conn: &Connection) -> Result<Vec<Vec<i32>>> {
+    let mut stmt = conn.prepare(
+        "SELECT group_concat(id) FROM (
+            SELECT id, timestamp, directory, content FROM logs ORDER BY id
+         )
+         GROUP BY timestamp, directory, content
+         HAVING COUNT(*) > 1",
+    )?;
+    let groups = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(groups
+        .iter()
+        .map(|g| g.split(',').filter_map(|id| id.parse().ok()).collect())
+        .collect())
+}
+
+/// 在所有日志中将标签 `old` 重命名为 `new`，返回受影响的日志ID
+///
+/// 所有更新在同一个事务中完成；`dry_run` 为真时只计算受影响的日志而不提交。
+pub fn rename_tag(conn: &Connection, old: &str, new: &str, dry_run: bool) -> Result<Vec<i32>> {
+    update_tag_tokens(conn, old, directory_filter("", Scope::All), dry_run, |current| {
+        tags::rename_tag(current, old, new).map(Some)
+    })
+}
+
+/// 从 `path` 按 `scope` 确定的目录范围内的日志中移除标签 `name`，返回受影响的日志ID
+pub fn remove_tag(conn: &Connection, name: &str, path: &Path, scope: Scope, dry_run: bool) -> Result<Vec<i32>> {
+    let normalized_path = normalize_path(path)?;
+    update_tag_tokens(conn, name, directory_filter(&normalized_path, scope), dry_run, |current| {
+        tags::remove_tag(current, name)
+    })
+}
+
+/// 在一个事务中设置多条日志的标签，返回修改的条数
+pub fn update_log_tags(conn: &Connection, updates: &[(i32, Option<String>)]) -> Result<usize> {
+    with_transaction(conn, |tx| {
+        let mut count = 0;
+        for (id, tags) in updates {
+            count += tx.execute("UPDATE logs SET tags = ? WHERE id = ?", params![tags, id])?;
+        }
+        Ok(count)
+    })
+}
+
+/// 对 `dir_filter`（由 `directory_filter` 生成）范围内包含标签 `tag` 的日志逐条改写标签字符串
+///
+/// `rewrite` 返回 None 表示该日志不受影响，否则返回新的标签值（可能为 NULL）。
+fn update_tag_tokens<F>(
+    conn: &Connection,
+    tag: &str,
+    dir_filter: (String, Vec<String>),
+    dry_run: bool,
+    rewrite: F,
+) -> Result<Vec<i32>>
+where
+    F: Fn(&str) -> Option<Option<String>>,
+{
+    // 先用 LIKE 粗筛，再在 Rust 中按完整标签精确匹配
+    let (dir_clause, mut query_params) = dir_filter;
+    query_params.push(tag.to_string());
+    let sql = format!("SELECT id, tags FROM logs WHERE {} AND tags LIKE '%' || ? || '%' ORDER BY id", dir_clause);
+    with_transaction(conn, |tx| {
+        let candidates = {
+            let mut stmt = tx.prepare(&sql)?;
+            let rows = stmt
+                .query_map(rusqlite::params_from_iter(&query_params), |row| {
+                    Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        // 试运行时只计算受影响的日志，不做修改
+        let mut affected = Vec::new();
+        for (id, current) in candidates {
+            if let Some(updated) = rewrite(&current) {
+                if !dry_run {
+                    tx.execute("UPDATE logs SET tags = ? WHERE id = ?", params![updated, id])?;
+                }
+                affected.push(id);
+            }
+        }
+        Ok(affected)
+    })
+}
+
+/// 按本地日期统计 [since, until) 范围内每天的日志数量
+pub fn count_logs_per_day(
+    conn: &Connection,
+    path: &Path,
+    scope: Scope,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Vec<(NaiveDate, i64)>> {
+    let normalized_path = normalize_path(path)?;
+
+    let (dir_clause, mut query_params) = directory_filter(&normalized_path, scope);
+    query_params.push(canonical_timestamp(since));
+    query_params.push(canonical_timestamp(until));
+    let mut stmt = conn.prepare(&format!(
+        "SELECT date(timestamp, 'localtime') AS day, COUNT(*) FROM logs
+         WHERE {} AND timestamp >= ? AND timestamp < ?
+         GROUP BY day ORDER BY day",
+        dir_clause
+    ))?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(query_params), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(day, count)| {
+            NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                .ok()
+                .map(|d| (d, count))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 已执行所有迁移的内存数据库
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        register_functions(&conn).unwrap();
+        conn
+    }
+
+    /// `fetch_logs` 实际执行的查询的 EXPLAIN QUERY PLAN，每步一行
+    fn fetch_plan(conn: &Connection, path: &str, scope: Scope, filter: &LogQuery) -> Vec<String> {
+        let (query, params) = fetch_logs_query(Path::new(path), scope, filter).unwrap();
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", query)).unwrap();
+        stmt.query_map(rusqlite::params_from_iter(params.iter().map(|b| b.as_ref())), |row| {
+            row.get::<_, String>(3)
+        })
+        .unwrap()
+        .collect::<std::result::Result<_, _>>()
+        .unwrap()
+    }
+
+    #[test]
+    fn directory_query_reads_the_index_in_order() {
+        let conn = test_db();
+        let filter = LogQuery { limit: 10, ..Default::default() };
+        let plan = fetch_plan(&conn, "/proj/app", Scope::Dir, &filter);
+        assert!(
+            plan.iter().any(|step| step.contains("USING INDEX idx_logs_directory_timestamp")),
+            "{:?}",
+            plan
+        );
+        assert!(!plan.iter().any(|step| step.contains("TEMP B-TREE")), "{:?}", plan);
+
+        // 子目录的范围查询无论如何都要排序，两个以目录开头的索引都可以用来查找
+        let plan = fetch_plan(&conn, "/proj", Scope::Recursive, &filter);
+        assert!(plan.iter().any(|step| step.contains("USING INDEX idx_logs_directory_")), "{:?}", plan);
+    }
+
+    #[test]
+    fn level_sort_reads_the_level_index_in_order() {
+        let conn = test_db();
+        let filter = LogQuery { by_level: true, limit: 10, ..Default::default() };
+        let plan = fetch_plan(&conn, "/proj/app", Scope::Dir, &filter);
+        assert!(plan.iter().any(|step| step.contains("USING INDEX idx_logs_directory_level")), "{:?}", plan);
+        assert!(!plan.iter().any(|step| step.contains("TEMP B-TREE")), "{:?}", plan);
+
+        // 同一级别中按时间倒序，无效的时间戳排在该级别的最后；修改级别后排序键随之更新
+        let ids: Vec<i32> = ["low", "high", "normal", "high"]
+            .iter()
+            .map(|level| {
+                let id = add_log(&conn, "/proj/app", level, None, false).unwrap();
+                update_log_level(&conn, id, level.parse().unwrap()).unwrap();
+                id
+            })
+            .collect();
+        conn.execute("UPDATE logs SET timestamp = 'unknown' WHERE id = ?", params![ids[3]]).unwrap();
+        conn.execute("UPDATE logs SET level = 'urgent' WHERE id = ?", params![ids[0]]).unwrap();
+        let order = || -> Vec<i32> {
+            let logs = fetch_logs(&conn, Path::new("/proj/app"), Scope::Dir, &filter).unwrap();
+            logs.into_iter().map(|l| l.id).collect()
+        };
+        // 无法识别的级别（例如其他版本写入的）与 normal 同级
+        assert_eq!(order(), [ids[1], ids[3], ids[2], ids[0]]);
+        update_log_level(&conn, ids[2], Level::Low).unwrap();
+        assert_eq!(order(), [ids[1], ids[3], ids[0], ids[2]]);
+    }
+
+    #[test]
+    fn directory_query_over_many_rows() {
+        let conn = test_db();
+        conn.execute_batch(
+            "WITH RECURSIVE n(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM n WHERE i < 49999)
+             INSERT INTO logs (timestamp, directory, content)
+             SELECT strftime('%Y-%m-%dT%H:%M:%fZ', 1700000000 + i * 60, 'unixepoch'), '/proj/d' || (i % 100), 'entry ' || i
+             FROM n;",
+        )
+        .unwrap();
+
+        conn.execute_batch("ANALYZE;").unwrap();
+
+        let filter = LogQuery { limit: 10, ..Default::default() };
+        let logs = fetch_logs(&conn, Path::new("/proj/d7"), Scope::Dir, &filter).unwrap();
+        assert_eq!(logs.len(), 10);
+        assert_eq!(logs[0].content, "entry 49907");
+        assert!(logs.windows(2).all(|w| w[0].timestamp > w[1].timestamp));
+
+        // 有统计信息的大表上，单个目录和子目录的查询仍然按索引查找，而不是扫描全表
+        let plan = fetch_plan(&conn, "/proj/d7", Scope::Dir, &filter);
+        assert!(plan.iter().any(|step| step.contains("USING INDEX idx_logs_directory_timestamp")), "{:?}", plan);
+        assert!(!plan.iter().any(|step| step.contains("TEMP B-TREE")), "{:?}", plan);
+        let plan = fetch_plan(&conn, "/proj/d7", Scope::Recursive, &filter);
+        assert!(plan.iter().any(|step| step.contains("USING INDEX idx_logs_directory_")), "{:?}", plan);
+        assert!(!plan.iter().any(|step| step == "SCAN logs"), "{:?}", plan);
+    }
+
+    /// 在给定目录中各记录一条带 `tags` 的日志，返回日志ID
+    fn log_in(conn: &Connection, dirs: &[&str], tags: &str) -> Vec<i32> {
+        dirs.iter().map(|dir| add_log(conn, dir, "entry", Some(tags), false).unwrap()).collect()
+    }
+
+    #[test]
+    fn remove_tag_follows_the_scope() {
+        let conn = test_db();
+        let ids = log_in(&conn, &["/proj", "/proj/sub", "/project", "/other"], "wip,deploy");
+
+        assert_eq!(remove_tag(&conn, "wip", Path::new("/proj"), Scope::Dir, true).unwrap(), vec![ids[0]]);
+        // 前缀相同的兄弟目录 /project 不属于 /proj 的子目录
+        assert_eq!(remove_tag(&conn, "wip", Path::new("/proj"), Scope::Recursive, false).unwrap(), ids[..2]);
+        assert_eq!(remove_tag(&conn, "wip", Path::new("/proj"), Scope::All, false).unwrap(), ids[2..]);
+        let tags = conn.query_row("SELECT group_concat(tags, '|') FROM logs", [], |row| row.get::<_, String>(0));
+        assert_eq!(tags.unwrap(), "deploy|deploy|deploy|deploy");
+    }
+
+    #[test]
+    fn entry_fields_cover_every_stored_column() {
+        let conn = test_db();
+        let id = add_log(&conn, "/proj", "entry", Some("a"), true).unwrap();
+        let log = get_log(&conn, id).unwrap().unwrap();
+        let mut fields: Vec<&str> = log.fields().into_iter().map(|(name, _)| name).collect();
+        fields.sort_unstable();
+
+        // ts_valid 和 level_rank 是触发器维护的排序键，不是日志的字段
+        let sql = "SELECT name FROM pragma_table_info('logs')
+                   WHERE name NOT IN ('content', 'ts_valid', 'level_rank')";
+        let mut stmt = conn.prepare(sql).unwrap();
+        let mut columns: Vec<String> =
+            stmt.query_map([], |row| row.get(0)).unwrap().collect::<std::result::Result<_, _>>().unwrap();
+        columns.sort_unstable();
+        assert_eq!(fields, columns);
+    }
+
+    #[test]
+    fn opening_a_missing_or_empty_file_asks_for_init() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.db");
+        assert!(matches!(open_connection_at(&missing), Err(DlogError::DatabaseNotInitialized)));
+        assert!(!missing.exists(), "opening must not create the file");
+
+        let empty = dir.path().join("empty.db");
+        std::fs::write(&empty, b"").unwrap();
+        assert!(matches!(open_connection_at(&empty), Err(DlogError::DatabaseNotInitialized)));
+    }
+
+    #[test]
+    fn a_renamed_column_is_a_schema_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dlog.db");
+        initialize_db_at(&path).unwrap();
+        open_connection_at(&path).unwrap();
+        Connection::open(&path).unwrap().execute_batch("ALTER TABLE logs RENAME COLUMN tags TO labels").unwrap();
+
+        match open_connection_at(&path) {
+            Err(DlogError::SchemaMismatch { expected, found }) => {
+                assert!(expected.split(", ").any(|c| c == "tags"), "{}", expected);
+                assert!(found.split(", ").any(|c| c == "labels"), "{}", found);
+                assert!(!found.split(", ").any(|c| c == "tags"), "{}", found);
+            }
+            other => panic!("expected SchemaMismatch, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn rename_tag_touches_only_exact_tokens() {
+        let conn = test_db();
+        let exact = add_log(&conn, "/proj", "a", Some("test,ci"), false).unwrap();
+        let similar = add_log(&conn, "/proj", "b", Some("unit-test,testing"), false).unwrap();
+        let both = add_log(&conn, "/other", "c", Some("check,test"), false).unwrap();
+
+        assert_eq!(rename_tag(&conn, "test", "check", true).unwrap(), vec![exact, both]);
+        assert_eq!(get_log(&conn, exact).unwrap().unwrap().tags.as_deref(), Some("test,ci"), "dry run changed data");
+
+        assert_eq!(rename_tag(&conn, "test", "check", false).unwrap(), vec![exact, both]);
+        let tags = |id| get_log(&conn, id).unwrap().unwrap().tags;
+        assert_eq!(tags(exact).as_deref(), Some("check,ci"));
+        assert_eq!(tags(similar).as_deref(), Some("unit-test,testing"));
+        assert_eq!(tags(both).as_deref(), Some("check"));
+    }
+
+    #[test]
+    fn remove_tag_clears_the_column_when_nothing_is_left() {
+        let conn = test_db();
+        let ids: Vec<i32> = ["wip,api,ui", "api,ui,wip", "api,wip,ui", "wip", "wip, ,deploy", "api"]
+            .iter()
+            .map(|tags| add_log(&conn, "/proj", "entry", Some(tags), false).unwrap())
+            .collect();
+        assert_eq!(remove_tag(&conn, "wip", Path::new("/proj"), Scope::Dir, false).unwrap(), ids[..5]);
+
+        let tags: Vec<Option<String>> = ids.iter().map(|id| get_log(&conn, *id).unwrap().unwrap().tags).collect();
+        let expected = ["api,ui", "api,ui", "api,ui"].map(|t| Some(t.to_string()));
+        assert_eq!(tags[..3], expected);
+        assert_eq!(tags[3], None);
+        assert_eq!(tags[4].as_deref(), Some("deploy"));
+        assert_eq!(tags[5].as_deref(), Some("api"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_and_dotted_paths_normalize_to_the_real_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("real");
+        std::fs::create_dir(&real).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let expected = normalize_path(&real).unwrap();
+        assert_eq!(normalize_path(&link).unwrap(), expected);
+        assert_eq!(normalize_path(&link.join(".")).unwrap(), expected);
+        assert_eq!(normalize_path(&real.join("sub").join("..")).unwrap(), expected);
+        assert!(!expected.ends_with('/'));
+    }
+
+    #[test]
+    fn missing_paths_normalize_lexically() {
+        assert_eq!(normalize_path(Path::new("/no/such/dir/./x/../")).unwrap(), "/no/such/dir");
+    }
+
+    #[test]
+    fn windows_prefixes_and_drive_letters_are_unified() {
+        assert_eq!(strip_windows_prefix(r"\\?\C:\Users\me"), r"C:\Users\me");
+        assert_eq!(strip_windows_prefix(r"\\?\c:\Users\me"), r"C:\Users\me");
+        assert_eq!(strip_windows_prefix(r"d:\work"), r"D:\work");
+        assert_eq!(strip_windows_prefix(r"\\?\UNC\server\share\dir"), r"\\server\share\dir");
+        assert_eq!(strip_windows_prefix(r"\\server\share"), r"\\server\share");
+        assert_eq!(strip_windows_prefix("/home/me/c:"), "/home/me/c:");
+        assert_eq!(strip_windows_prefix("c"), "c");
+    }
+
+    #[test]
+    fn directory_filter_adds_exactly_one_separator() {
+        let sep = std::path::MAIN_SEPARATOR;
+        let root = format!("{0}proj", sep);
+        let (_, params) = directory_filter(&root, Scope::Recursive);
+        assert_eq!(params[1], format!("{}{}", root, sep));
+        let (_, params) = directory_filter(&format!("{}{}", root, sep), Scope::Recursive);
+        assert_eq!(params[1], format!("{}{}", root, sep));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_directories_match_regardless_of_case_and_prefix() {
+        let conn = test_db();
+        add_log(&conn, r"C:\Proj\App", "nested", None, false).unwrap();
+        add_log(&conn, r"C:\Project", "sibling", None, false).unwrap();
+        assert_eq!(normalize_path(Path::new(r"\\?\c:\proj\app")).unwrap(), r"C:\proj\app");
+
+        let found = |path: &str, scope| {
+            let logs = fetch_logs(&conn, Path::new(path), scope, &LogQuery::default()).unwrap();
+            logs.into_iter().map(|l| l.content).collect::<Vec<_>>()
+        };
+        assert_eq!(found(r"c:\proj\app", Scope::Dir), ["nested"]);
+        assert_eq!(found(r"c:\PROJ", Scope::Recursive), ["nested"]);
+        assert_eq!(found(r"C:\proj\", Scope::Recursive), ["nested"]);
+    }
+
+    /// 按目录记录日志：/p、/p/a、/p/a/b、/p/a/b/c 以及前缀相同的兄弟目录 /pa
+    fn depth_fixture() -> Connection {
+        let conn = test_db();
+        for dir in ["/p", "/p/a", "/p/a/b", "/p/a/b/c", "/pa", "/pa/x"] {
+            add_log(&conn, dir, dir, None, false).unwrap();
+        }
+        conn
+    }
+
+    fn dirs_in(conn: &Connection, scope: Scope, limit: u32) -> Vec<String> {
+        let filter = LogQuery { limit, ..Default::default() };
+        let mut dirs: Vec<String> =
+            fetch_logs(conn, Path::new("/p"), scope, &filter).unwrap().into_iter().map(|l| l.directory).collect();
+        dirs.sort();
+        dirs
+    }
+
+    #[test]
+    fn depth_counts_levels_below_the_queried_directory() {
+        let conn = depth_fixture();
+        assert_eq!(dirs_in(&conn, Scope::Depth(1), 0), ["/p", "/p/a"]);
+        assert_eq!(dirs_in(&conn, Scope::Depth(2), 0), ["/p", "/p/a", "/p/a/b"]);
+        assert_eq!(dirs_in(&conn, Scope::Depth(3), 0), ["/p", "/p/a", "/p/a/b", "/p/a/b/c"]);
+        assert_eq!(dirs_in(&conn, Scope::Depth(9), 0), dirs_in(&conn, Scope::Recursive, 0));
+        assert_eq!(dirs_in(&conn, Scope::with_depth(true, Some(0)), 0), dirs_in(&conn, Scope::Dir, 0));
+        assert_eq!(Scope::with_depth(true, Some(0)), Scope::Dir);
+    }
+
+    #[test]
+    fn depth_is_applied_before_the_limit() {
+        let conn = depth_fixture();
+        // 最新的日志在最深的目录和兄弟目录中，它们被排除后仍应返回满额的两条
+        assert_eq!(dirs_in(&conn, Scope::Depth(1), 2), ["/p", "/p/a"]);
+        assert_eq!(dirs_in(&conn, Scope::Depth(2), 2), ["/p/a", "/p/a/b"]);
+    }
+
+    /// 搜索测试用的日志，内容和标签各不相同，有的没有标签
+    fn search_fixture() -> Connection {
+        let conn = test_db();
+        for (content, tags) in [
+            ("timeout during TLS handshake", None),
+            ("TLS timeout", Some("net")),
+            ("connection refused by docker", Some("infra")),
+            ("docker: connection was refused", None),
+            ("100% CPU_usage", None),
+            ("Größe überschritten", Some("ÄRGER")),
+            ("daily standup", Some("meeting")),
+            ("standup moved, docker upgrade", Some("meeting")),
+        ] {
+            add_log(&conn, "/proj", content, tags, false).unwrap();
+        }
+        conn
+    }
+
+    /// 按 `terms` 和 `exclude` 搜索，返回按内容排序的结果
+    fn search(conn: &Connection, terms: &[&str], exclude: &[&str], case_sensitive: bool) -> Vec<String> {
+        let terms: Vec<String> = terms.iter().map(|t| t.to_string()).collect();
+        let exclude: Vec<String> = exclude.iter().map(|t| t.to_string()).collect();
+        let filter = LogQuery { terms: &terms, exclude: &exclude, case_sensitive, ..Default::default() };
+        let mut found: Vec<String> =
+            fetch_logs(conn, Path::new("/proj"), Scope::Dir, &filter).unwrap().into_iter().map(|l| l.content).collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn every_search_term_must_appear_in_content_or_tags() {
+        let conn = search_fixture();
+        assert_eq!(search(&conn, &["tls", "timeout"], &[], false), ["TLS timeout", "timeout during TLS handshake"]);
+        assert_eq!(search(&conn, &["timeout", "net"], &[], false), ["TLS timeout"]);
+        assert_eq!(search(&conn, &["docker", "handshake"], &[], false), Vec::<String>::new());
+        // 短语按字面匹配，不再拆分
+        assert_eq!(search(&conn, &["connection refused"], &[], false), ["connection refused by docker"]);
+        assert_eq!(search(&conn, &["connection refused", "docker"], &[], false), ["connection refused by docker"]);
+        assert_eq!(search(&conn, &["refused", "docker"], &[], false).len(), 2);
+    }
+
+    #[test]
+    fn search_terms_are_literal_and_unicode_case_insensitive() {
+        let conn = search_fixture();
+        assert_eq!(search(&conn, &["100%"], &[], false), ["100% CPU_usage"]);
+        assert_eq!(search(&conn, &["u_a"], &[], false), Vec::<String>::new());
+        assert_eq!(search(&conn, &["%"], &[], false).len(), 1);
+        // 按 Unicode 规则转为小写（ẞ 变为 ß），但不做 ß 与 ss 之间的折叠
+        assert_eq!(search(&conn, &["GRÖẞE"], &[], false), ["Größe überschritten"]);
+        assert_eq!(search(&conn, &["GRÖSSE"], &[], false), Vec::<String>::new());
+        assert_eq!(search(&conn, &["ÜBERSCHRITTEN", "ärger"], &[], false), ["Größe überschritten"]);
+        assert_eq!(search(&conn, &["tls"], &[], true), Vec::<String>::new());
+        assert_eq!(search(&conn, &["TLS"], &[], true).len(), 2);
+    }
+
+    #[test]
+    fn exclude_keeps_untagged_entries_and_wins_over_search() {
+        let conn = search_fixture();
+        let everything = search(&conn, &[], &[], false);
+        let without = search(&conn, &[], &["standup"], false);
+        // 没有标签的日志（tags 为 NULL）不能被 NOT instr(...) 一并排除
+        assert_eq!(without.len(), everything.len() - 2);
+        assert!(without.contains(&"docker: connection was refused".to_string()));
+        let docker = search(&conn, &["docker"], &["standup"], false);
+        assert_eq!(docker, ["connection refused by docker", "docker: connection was refused"]);
+        // 同时匹配包含词和排除词的日志被排除
+        assert_eq!(search(&conn, &["standup"], &["docker"], false), ["daily standup"]);
+        assert_eq!(search(&conn, &[], &["MEETING", "infra", "tls"], false).len(), everything.len() - 5);
+        assert_eq!(search(&conn, &[], &["ärger"], false).len(), everything.len() - 1);
+        assert_eq!(search(&conn, &[], &["Standup"], true).len(), everything.len());
+    }
+
+    /// 有删除、关联、锁定和无效时间戳的数据库，ID与时间顺序不一致
+    fn renumber_fixture(conn: &Connection) {
+        for (content, timestamp) in [
+            ("march", "2024-03-01T00:00:00Z"),
+            ("new year", "2024-01-01T00:00:00Z"),
+            ("deleted", "2024-02-01T00:00:00Z"),
+            ("mid february", "2024-02-15T00:00:00Z"),
+            ("mid january", "2024-01-15T00:00:00Z"),
+            ("broken time", "2024-04-01T00:00:00Z"),
+        ] {
+            let at = timestamp.parse().unwrap();
+            add_log_at(conn, "/proj", content, Some("t"), false, Level::Normal, at).unwrap();
+        }
+        conn.execute("UPDATE logs SET timestamp = 'garbage' WHERE id = 6", []).unwrap();
+        set_locked(conn, &[2], true).unwrap();
+        for (a, b) in [(1, 2), (4, 5), (2, 6), (3, 4)] {
+            add_link(conn, a, b).unwrap();
+        }
+        delete_logs_by_id(conn, &[3], false).unwrap();
+    }
+
+    /// 日志的内容、时间戳、标签、锁定状态和修改时间
+    type Snapshot = (String, String, Option<String>, bool, Option<String>);
+
+    /// 按 UUID 排列的日志，除ID以外的字段都应在重新编号后保持不变
+    fn by_uuid(conn: &Connection) -> BTreeMap<String, Snapshot> {
+        let logs = fetch_logs(conn, Path::new("/"), Scope::All, &LogQuery::default()).unwrap();
+        logs.into_iter()
+            .map(|l| (l.uuid.unwrap(), (l.content, l.timestamp, l.tags, l.locked, l.updated_at)))
+            .collect()
+    }
+
+    fn link_pairs(conn: &Connection) -> Vec<(i32, i32)> {
+        let mut stmt = conn.prepare("SELECT a, b FROM log_links ORDER BY a, b").unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn links_between_moved_logs_survive_archive_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("archive.db");
+        initialize_db_at(&archive).unwrap();
+        add_log(&open_connection_at(&archive).unwrap(), "/old", "archived before", None, false).unwrap();
+
+        let conn = test_db();
+        for content in ["one", "two", "three", "four"] {
+            add_log(&conn, "/proj", content, None, false).unwrap();
+        }
+        for (a, b) in [(1, 2), (2, 3), (3, 4)] {
+            add_link(&conn, a, b).unwrap();
+        }
+        let content = |conn: &Connection, id: i32| get_log(conn, id).unwrap().unwrap().content;
+
+        // 归档中已有一条日志，移入的日志从ID 2 开始；与留下的 four 的关联不再保留
+        assert_eq!(archive_logs(&conn, &archive, &[1, 2, 3]).unwrap(), 3);
+        assert_eq!(link_pairs(&conn), []);
+        let archived = open_connection_at(&archive).unwrap();
+        assert_eq!(link_pairs(&archived), [(2, 3), (3, 4)]);
+        assert_eq!((content(&archived, 2), content(&archived, 4)), ("one".to_string(), "three".to_string()));
+
+        // 移回 one 和 two：它们之间的关联回到当前数据库，与仍在归档中的 three 的关联不再保留
+        assert_eq!(restore_logs(&conn, &archive, &[2, 3]).unwrap(), 2);
+        assert_eq!(link_pairs(&conn), [(5, 6)]);
+        assert_eq!((content(&conn, 5), content(&conn, 6)), ("one".to_string(), "two".to_string()));
+        assert_eq!(link_pairs(&archived), []);
+    }
+
+    #[test]
+    fn renumber_orders_ids_by_time_and_remaps_links() {
+        let conn = test_db();
+        renumber_fixture(&conn);
+        let before = by_uuid(&conn);
+        let expected = vec![(2, 1), (5, 2), (4, 3), (1, 4), (6, 5)];
+        assert_eq!(renumber_plan(&conn).unwrap(), expected);
+
+        assert_eq!(renumber_logs(&conn).unwrap(), expected);
+        let contents: Vec<(i32, String)> = get_logs_by_ids(&conn, &[1, 2, 3, 4, 5])
+            .unwrap()
+            .into_iter()
+            .map(|l| (l.id, l.content))
+            .collect();
+        let names = ["new year", "mid january", "mid february", "march", "broken time"];
+        assert_eq!(contents, (1..).zip(names.map(String::from)).collect::<Vec<_>>());
+        assert_eq!(by_uuid(&conn), before, "only the ids may change");
+        assert!(get_log(&conn, 1).unwrap().unwrap().locked);
+        // (1,2) -> (4,1)，(4,5) -> (3,2)，(2,6) -> (1,5)；指向已删除日志的关联随删除一起清理
+        assert_eq!(link_pairs(&conn), [(1, 4), (1, 5), (2, 3)]);
+        let stale: i64 =
+            conn.query_row("SELECT count(*) FROM deleted_logs WHERE id IS NOT NULL", [], |r| r.get(0)).unwrap();
+        assert_eq!(stale, 0, "old ids in deletion records could now name other entries");
+        assert_eq!(deleted_at(&conn, 3).unwrap(), None);
+
+        assert!(integrity_check(&conn).unwrap().is_empty());
+        assert_eq!(renumber_plan(&conn).unwrap(), []);
+        assert_eq!(renumber_logs(&conn).unwrap(), []);
+        assert_eq!(add_log(&conn, "/proj", "next", None, false).unwrap(), 6);
+    }
+
+    #[test]
+    fn renumber_refuses_while_another_connection_reads() {
+        let (_dir, path, conn) = file_db();
+        renumber_fixture(&conn);
+        let plan = renumber_plan(&conn).unwrap();
+
+        let reader = Connection::open(&path).unwrap();
+        reader.execute_batch("BEGIN; SELECT count(*) FROM logs;").unwrap();
+        assert!(matches!(renumber_logs(&conn), Err(DlogError::DatabaseBusy)));
+        // 失败时没有改动任何ID
+        assert_eq!(renumber_plan(&conn).unwrap(), plan);
+        reader.execute_batch("COMMIT;").unwrap();
+        assert_eq!(renumber_logs(&conn).unwrap(), plan);
+    }
+
+    #[test]
+    fn unicode_case_folding_leaves_cjk_intact() {
+        let conn = test_db();
+        for (content, tags) in [
+            ("Über den Fluß", Some("Reise")),
+            ("über alles", None),
+            ("STRASSE gesperrt", Some("ÄMTER")),
+            ("修复了日志数据库的 Timeout 问题", Some("数据库")),
+            ("日本語のメモ", None),
+        ] {
+            add_log(&conn, "/proj", content, tags, false).unwrap();
+        }
+        assert_eq!(search(&conn, &["über"], &[], false), ["Über den Fluß", "über alles"]);
+        assert_eq!(search(&conn, &["ÜBER"], &[], false).len(), 2);
+        assert_eq!(search(&conn, &["Über"], &[], true), ["Über den Fluß"]);
+        assert_eq!(search(&conn, &["FLUSS"], &[], false), Vec::<String>::new());
+        assert_eq!(search(&conn, &["FLUß"], &[], false), ["Über den Fluß"]);
+        assert_eq!(search(&conn, &["ämter"], &[], false), ["STRASSE gesperrt"]);
+        // 没有大小写的文字在转为小写后保持不变，与拉丁字母混排时也能匹配
+        assert_eq!(search(&conn, &["日志数据库"], &[], false), ["修复了日志数据库的 Timeout 问题"]);
+        assert_eq!(search(&conn, &["数据库的 timeout"], &[], false).len(), 1);
+        assert_eq!(search(&conn, &["数据库的 timeout"], &[], true).len(), 0);
+        assert_eq!(search(&conn, &["メモ"], &[], true), ["日本語のメモ"]);
+        assert_eq!(search(&conn, &[], &["数据库"], false).len(), 4);
+
+        let tagged = |tag: &str| {
+            let filter = LogQuery { tag: Some(tag), ..Default::default() };
+            fetch_logs(&conn, Path::new("/proj"), Scope::Dir, &filter).unwrap().len()
+        };
+        assert_eq!((tagged("ÄMTER"), tagged("reise"), tagged("数据库"), tagged("数据")), (1, 1, 1, 0));
+    }
+
+    /// 临时目录中的数据库文件和一个不等待锁的连接，锁冲突只由 `retry_busy` 处理
+    fn file_db() -> (tempfile::TempDir, PathBuf, Connection) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dlog.db");
+        initialize_db_at(&path).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        conn.busy_timeout(Duration::ZERO).unwrap();
+        (dir, path, conn)
+    }
+
+    #[test]
+    fn transactions_retry_until_another_writer_lets_go() {
+        let (_dir, path, conn) = file_db();
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let writer = std::thread::spawn(move || {
+            let other = Connection::open(&path).unwrap();
+            other.execute_batch("BEGIN IMMEDIATE;").unwrap();
+            locked_tx.send(()).unwrap();
+            std::thread::sleep(Duration::from_millis(120));
+            other.execute_batch("COMMIT;").unwrap();
+        });
+        locked_rx.recv().unwrap();
+
+        let started = std::time::Instant::now();
+        let mut attempts = 0;
+        let id = with_transaction(&conn, |tx| {
+            attempts += 1;
+            tx.execute("INSERT INTO logs (timestamp, directory, content) VALUES ('2024-01-01T00:00:00Z', '/p', 'x')", [])?;
+            Ok(tx.last_insert_rowid())
+        })
+        .unwrap();
+        writer.join().unwrap();
+        assert_eq!(id, 1);
+        // 连接不等待锁，只能是退避重试等到了另一个连接提交
+        assert!(started.elapsed() >= Duration::from_millis(100), "{:?}", started.elapsed());
+        assert_eq!(attempts, 1, "the body runs once the write lock is held");
+        assert_eq!(count_logs(&conn).unwrap(), 1);
+    }
+
+    #[test]
+    fn a_lock_that_is_never_released_is_reported_as_busy() {
+        let (_dir, path, conn) = file_db();
+        let other = Connection::open(&path).unwrap();
+        other.execute_batch("BEGIN IMMEDIATE;").unwrap();
+
+        let started = std::time::Instant::now();
+        let mut attempts = 0;
+        let result = with_transaction(&conn, |tx| {
+            attempts += 1;
+            Ok(tx.execute("DELETE FROM logs", [])?)
+        });
+        assert!(matches!(result, Err(DlogError::DatabaseBusy)), "{:?}", result);
+        assert_eq!(attempts, 0, "the write lock is taken before the body runs");
+        assert!(started.elapsed() >= Duration::from_millis(BUSY_BACKOFF_MS * 15));
+        assert!(result.unwrap_err().to_string().contains("another dlog process may be using it"));
+
+        // 普通语句的重试也以 DatabaseBusy 结束，读取不受影响
+        assert!(matches!(add_log(&conn, "/p", "x", None, false), Err(DlogError::DatabaseBusy)));
+        assert_eq!(count_logs(&conn).unwrap(), 0);
+        other.execute_batch("COMMIT;").unwrap();
+        assert_eq!(add_log(&conn, "/p", "x", None, false).unwrap(), 1);
+    }
+
+    /// 最初版本的数据库（只有 logs 表的五列），写入原样的时间戳后执行全部迁移
+    fn legacy_db(timestamps: &[&str]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE logs (id INTEGER PRIMARY KEY, timestamp TEXT NOT NULL, directory TEXT NOT NULL,
+                                content TEXT NOT NULL, tags TEXT);",
+        )
+        .unwrap();
+        for (i, timestamp) in timestamps.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO logs (timestamp, directory, content) VALUES (?1, '/proj', ?2)",
+                params![timestamp, format!("row {}", i)],
+            )
+            .unwrap();
+        }
+        migrate(&conn).unwrap();
+        register_functions(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn legacy_rows_with_mixed_offsets_sort_and_filter_by_instant() {
+        let conn = legacy_db(&[
+            "2024-05-01T10:00:00+02:00",
+            "2024-05-01T09:30:00Z",
+            "2024-05-01T07:15:30.5-03:00",
+            "2024-04-30T23:30:00-01:00",
+            "2024-05-02T01:00:00+05:00",
+            "2024-05-01 12:00",
+            "1714600800",
+            "sometime in May",
+        ]);
+        let rows = |filter: &LogQuery| -> Vec<(String, String)> {
+            fetch_logs(&conn, Path::new("/proj"), Scope::Dir, filter)
+                .unwrap()
+                .into_iter()
+                .map(|l| (l.content, l.timestamp))
+                .collect()
+        };
+        // 带时区偏移的时间戳已换算为 UTC；其他格式在迁移时保持原样，作为无效的时间戳排在最后
+        let raw = |rows: &[(&str, &str)]| -> Vec<(String, String)> {
+            rows.iter().map(|(c, t)| (c.to_string(), t.to_string())).collect()
+        };
+        let valid = [
+            ("row 4", "2024-05-01T20:00:00.000Z"),
+            ("row 2", "2024-05-01T10:15:30.500Z"),
+            ("row 1", "2024-05-01T09:30:00.000Z"),
+            ("row 0", "2024-05-01T08:00:00.000Z"),
+            ("row 3", "2024-05-01T00:30:00.000Z"),
+        ];
+        let unparsed = [("row 7", "sometime in May"), ("row 5", "2024-05-01 12:00"), ("row 6", "1714600800")];
+        assert_eq!(rows(&LogQuery::default()), raw(&[&valid[..], &unparsed[..]].concat()));
+        let invalid: Vec<i32> = find_invalid_timestamps(&conn).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(invalid, [6, 7, 8]);
+
+        // doctor --fix-timestamps 改写能识别的格式，之后只剩无法解析的一条排在最后
+        let fixes: Vec<(i32, DateTime<Utc>)> = find_invalid_timestamps(&conn)
+            .unwrap()
+            .into_iter()
+            .filter_map(|(id, ts)| reparse_timestamp(&ts).map(|t| (id, t)))
+            .collect();
+        assert_eq!(update_log_timestamps(&conn, &fixes).unwrap(), 2);
+        let expected = raw(&[
+            ("row 6", "2024-05-01T22:00:00.000Z"),
+            ("row 4", "2024-05-01T20:00:00.000Z"),
+            ("row 5", "2024-05-01T12:00:00.000Z"),
+            ("row 2", "2024-05-01T10:15:30.500Z"),
+            ("row 1", "2024-05-01T09:30:00.000Z"),
+            ("row 0", "2024-05-01T08:00:00.000Z"),
+            ("row 3", "2024-05-01T00:30:00.000Z"),
+        ]);
+        let all = rows(&LogQuery::default());
+        assert_eq!(all[..7], expected[..]);
+        assert_eq!(all[7], ("row 7".to_string(), "sometime in May".to_string()));
+
+        // --date 换算成的 UTC 范围：无效的时间戳不参与比较，边界按时刻而不是原始字符串判断
+        let between = |since: &str, until: &str| {
+            let at = |s: &str| Some(s.parse::<DateTime<Utc>>().unwrap());
+            rows(&LogQuery { since: at(since), until: at(until), ..Default::default() })
+        };
+        assert_eq!(between("2024-05-01T00:00:00Z", "2024-05-02T00:00:00Z"), expected);
+        assert_eq!(between("2024-05-01T09:00:00Z", "2024-05-01T12:00:00Z"), expected[3..5]);
+        // UTC+5 的 5 月 1 日是 UTC 的 4 月 30 日 19:00 到 5 月 1 日 19:00
+        assert_eq!(between("2024-05-01T00:00:00+05:00", "2024-05-02T00:00:00+05:00"), expected[2..]);
+        assert_eq!(find_invalid_timestamps(&conn).unwrap(), [(8, "sometime in May".to_string())]);
+
+        // 翻页的游标同样把无效的时间戳排在最后
+        let after = |id: i32| rows(&LogQuery { before_id: Some(id), ..Default::default() });
+        assert_eq!(after(4), [all[7].clone()]);
+        assert_eq!(after(8), []);
+    }
+
+    #[test]
+    fn triggers_keep_invalid_timestamps_sorted_last() {
+        let conn = test_db();
+        let first = add_log(&conn, "/proj", "first", None, false).unwrap();
+        conn.execute("INSERT INTO logs (timestamp, directory, content) VALUES ('yesterday', '/proj', 'raw')", [])
+            .unwrap();
+        let second = add_log(&conn, "/proj", "second", None, false).unwrap();
+        let order = |conn: &Connection| -> Vec<String> {
+            fetch_logs(conn, Path::new("/proj"), Scope::Dir, &LogQuery::default())
+                .unwrap()
+                .into_iter()
+                .map(|l| l.content)
+                .collect()
+        };
+        assert_eq!(order(&conn), ["second", "first", "raw"]);
+
+        // 改写时间戳时同样更新排序键，直接写入的排序键会被纠正
+        conn.execute("UPDATE logs SET timestamp = 'soon' WHERE id = ?", params![second]).unwrap();
+        conn.execute("UPDATE logs SET ts_valid = 1 WHERE content = 'raw'", []).unwrap();
+        assert_eq!(order(&conn), ["first", "raw", "second"]);
+        update_log_timestamps(&conn, &[(second, Utc::now())]).unwrap();
+        assert_eq!(order(&conn), ["second", "first", "raw"]);
+        let valid: Vec<i64> = conn
+            .prepare("SELECT ts_valid FROM logs ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!((first, valid), (1, vec![1, 0, 1]));
+    }
+
+    /// 两台机器上的数据库：在 `a` 中记录一条日志并同步到 `b`，返回它的 UUID
+    fn synced_pair() -> (Connection, Connection, String) {
+        let (a, b) = (test_db(), test_db());
+        let id = add_log(&a, "/work/app", "original", Some("sync"), false).unwrap();
+        let report = apply_sync_records(&b, &sync_changes(&a, None).unwrap(), false).unwrap();
+        assert_eq!((report.applied, report.skipped, report.conflicts.len()), (1, 0, 0));
+        let uuid = get_log(&a, id).unwrap().unwrap().uuid.unwrap();
+        (a, b, uuid)
+    }
+
+    /// 修改日志内容，并指定修改时间
+    fn edit(conn: &Connection, uuid: &str, content: &str, updated_at: &str) {
+        let sql = "UPDATE logs SET content = ?, updated_at = ? WHERE uuid = ?";
+        assert_eq!(conn.execute(sql, params![content, updated_at, uuid]).unwrap(), 1);
+    }
+
+    /// 删除日志，并指定删除时间
+    fn delete(conn: &Connection, uuid: &str, deleted_at: &str) {
+        conn.execute("DELETE FROM logs WHERE uuid = ?", params![uuid]).unwrap();
+        conn.execute("UPDATE deleted_logs SET deleted_at = ? WHERE uuid = ?", params![deleted_at, uuid]).unwrap();
+    }
+
+    fn content_of(conn: &Connection, uuid: &str) -> Option<String> {
+        conn.query_row("SELECT content FROM logs WHERE uuid = ?", params![uuid], |row| row.get(0))
+            .optional()
+            .unwrap()
+    }
+
+    /// 把 `from` 中的所有日志和删除记录应用到 `to`
+    fn sync(from: &Connection, to: &Connection) -> SyncReport {
+        apply_sync_records(to, &sync_changes(from, None).unwrap(), false).unwrap()
+    }
+
+    fn conflict_kinds(report: &SyncReport) -> Vec<SyncConflictKind> {
+        report.conflicts.iter().map(|c| c.kind).collect()
+    }
+
+    #[test]
+    fn sync_keeps_the_newer_of_two_edits() {
+        let (a, b, uuid) = synced_pair();
+        edit(&a, &uuid, "edited on a", "2030-01-01T10:00:00.000Z");
+        edit(&b, &uuid, "edited on b", "2030-01-01T11:00:00.000Z");
+
+        // b 的修改较新：收到 a 的版本时保留自己的，报告冲突
+        let report = sync(&a, &b);
+        assert_eq!((report.applied, report.skipped), (0, 0));
+        assert_eq!(conflict_kinds(&report), [SyncConflictKind::LocalNewer]);
+        assert_eq!(report.conflicts[0].local_id, Some(1));
+        assert_eq!(content_of(&b, &uuid).as_deref(), Some("edited on b"));
+
+        // 反过来 a 采用 b 的版本和修改时间，之后两边一致，再同步只会跳过
+        let dry = apply_sync_records(&a, &sync_changes(&b, None).unwrap(), true).unwrap();
+        assert_eq!(dry.applied, 1);
+        assert_eq!(content_of(&a, &uuid).as_deref(), Some("edited on a"), "a dry run must roll back");
+        assert_eq!(sync(&b, &a).applied, 1);
+        assert_eq!(content_of(&a, &uuid).as_deref(), Some("edited on b"));
+        let updated_at = |conn: &Connection| get_log(conn, 1).unwrap().unwrap().updated_at;
+        assert_eq!(updated_at(&a).as_deref(), Some("2030-01-01T11:00:00.000Z"));
+        for (from, to) in [(&a, &b), (&b, &a)] {
+            let report = sync(from, to);
+            assert_eq!((report.applied, report.skipped, report.conflicts.len()), (0, 1, 0));
+        }
+
+        // 内容相同、只有修改时间不同的版本不算冲突
+        edit(&b, &uuid, "edited on b", "2030-01-01T12:00:00.000Z");
+        let report = sync(&a, &b);
+        assert_eq!((report.applied, report.skipped, report.conflicts.len()), (0, 1, 0));
+    }
+
+    #[test]
+    fn sync_applies_a_deletion_only_after_the_last_local_edit() {
+        let (a, b, uuid) = synced_pair();
+        edit(&b, &uuid, "edited on b", "2030-01-01T10:00:00.000Z");
+        let stale_edit = sync_changes(&b, None).unwrap();
+        delete(&a, &uuid, "2030-01-01T11:00:00.000Z");
+
+        // 删除晚于 b 的修改：b 删除日志，并记下对方的删除时间，之后不会再把删除发回去
+        let report = sync(&a, &b);
+        assert_eq!((report.applied, report.conflicts.len()), (1, 0));
+        assert_eq!(content_of(&b, &uuid), None);
+        let deleted_at: String =
+            b.query_row("SELECT deleted_at FROM deleted_logs WHERE uuid = ?", params![uuid], |row| row.get(0)).unwrap();
+        assert_eq!(deleted_at, "2030-01-01T11:00:00.000Z");
+
+        // 早于删除的修改传到 a 时不会恢复日志
+        let report = apply_sync_records(&a, &stale_edit, false).unwrap();
+        assert_eq!((report.applied, report.skipped, report.conflicts.len()), (0, 1, 0));
+        assert_eq!(content_of(&a, &uuid), None);
+    }
+
+    #[test]
+    fn sync_never_resurrects_an_entry_edited_after_its_deletion() {
+        let (a, b, uuid) = synced_pair();
+        delete(&a, &uuid, "2030-01-01T10:00:00.000Z");
+        edit(&b, &uuid, "edited on b", "2030-01-01T11:00:00.000Z");
+
+        // b 在删除之后修改过，保留日志并报告冲突
+        let report = sync(&a, &b);
+        assert_eq!(report.applied, 0);
+        assert_eq!(conflict_kinds(&report), [SyncConflictKind::EditedAfterDeletion]);
+        assert_eq!(content_of(&b, &uuid).as_deref(), Some("edited on b"));
+
+        // a 收到修改时也保持删除，冲突中没有本地ID
+        let report = sync(&b, &a);
+        assert_eq!(report.applied, 0);
+        assert_eq!(conflict_kinds(&report), [SyncConflictKind::DeletedLocally]);
+        assert_eq!(report.conflicts[0].local_id, None);
+        assert_eq!(content_of(&a, &uuid), None);
+
+        // 删除记录先于日志到达时同样不会插入日志
+        let c = test_db();
+        let deletion: Vec<SyncRecord> =
+            sync_changes(&a, None).unwrap().into_iter().filter(|r| matches!(r, SyncRecord::Deleted { .. })).collect();
+        assert_eq!(apply_sync_records(&c, &deletion, false).unwrap().skipped, 1);
+        let report = apply_sync_records(&c, &sync_changes(&b, None).unwrap(), false).unwrap();
+        assert_eq!(conflict_kinds(&report), [SyncConflictKind::DeletedLocally]);
+        assert_eq!(count_logs(&c).unwrap(), 0);
+    }
+
+    #[test]
+    fn sync_keeps_a_locked_entry_that_was_deleted_elsewhere() {
+        let (a, b, uuid) = synced_pair();
+        set_locked(&b, &[1], true).unwrap();
+        delete(&a, &uuid, "2999-01-01T00:00:00.000Z");
+        let report = sync(&a, &b);
+        assert_eq!(conflict_kinds(&report), [SyncConflictKind::Locked]);
+        assert_eq!(content_of(&b, &uuid).as_deref(), Some("original"));
+    }
+}
@@ -0,0 +1,116 @@
+// src/dir_config.rs
+
+use crate::error::{DlogError, Result};
+use crate::tags;
+use crate::toml::{self, Value};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 目录配置文件的文件名
+pub const FILE_NAME: &str = ".dlog.toml";
+
+/// 目录中 `.dlog.toml` 的设置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirConfig {
+    /// 配置文件的路径
+    pub path: PathBuf,
+    /// 记录日志时自动加上的标签，已按 `tags::normalize_tag` 整理
+    pub default_tags: Vec<String>,
+}
+
+/// 从 `start` 开始逐级向上查找 `.dlog.toml`，返回离 `start` 最近的一个
+///
+/// 查找到主目录（包含主目录本身）或文件系统根目录为止。最近的文件完全代替上层目录中的
+/// 配置，不会与之合并。文件格式错误时返回 InvalidInput，消息中包含文件路径和行号。
+pub fn find(start: &Path) -> Result<Option<DirConfig>> {
+    let home = dirs::home_dir();
+    for dir in start.ancestors() {
+        let path = dir.join(FILE_NAME);
+        if path.is_file() {
+            return load(&path).map(Some);
+        }
+        if home.as_deref() == Some(dir) {
+            break;
+        }
+    }
+    Ok(None)
+}
+
+/// 读取并解析一个配置文件
+pub fn load(path: &Path) -> Result<DirConfig> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| DlogError::Io(io::Error::new(e.kind(), format!("{}: {}", path.display(), e))))?;
+    let invalid = |line: usize, message: String| {
+        DlogError::InvalidInput(format!("{} line {}: {}", path.display(), line, message))
+    };
+    let mut config = DirConfig { path: path.to_path_buf(), default_tags: Vec::new() };
+    for (key, line, value) in toml::parse(&text).map_err(|(line, message)| invalid(line, message))? {
+        match key.as_str() {
+            "default_tags" => {
+                let Value::Array(values) = value else {
+                    return Err(invalid(line, "expected an array of strings, e.g. [\"ops\"]".to_string()));
+                };
+                for value in values {
+                    let tag =
+                        tags::normalize_tag(&value).map_err(|_| invalid(line, format!("invalid tag {:?}", value)))?;
+                    if !config.default_tags.contains(&tag) {
+                        config.default_tags.push(tag);
+                    }
+                }
+            }
+            other => return Err(invalid(line, format!("unknown key \"{}\" (supported: default_tags)", other))),
+        }
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, text: &str) -> PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        let path = dir.join(FILE_NAME);
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn nearest_file_replaces_the_parent_configuration() {
+        let root = tempfile::tempdir().unwrap();
+        let infra = root.path().join("infra");
+        let web = infra.join("web");
+        let outer = write(&infra, "default_tags = [\"ops\"]\n");
+        let inner = write(&web, "# web 服务\ndefault_tags = [\"web\", \" Web \", \"deploy\"]\n");
+        std::fs::create_dir_all(web.join("src/api")).unwrap();
+        std::fs::create_dir_all(infra.join("db")).unwrap();
+
+        let found = |dir: &Path| find(dir).unwrap().unwrap();
+        let expected = DirConfig { path: inner, default_tags: vec!["web".into(), "deploy".into()] };
+        assert_eq!(found(&web.join("src/api")), expected);
+        assert_eq!(found(&infra.join("db")), DirConfig { path: outer.clone(), default_tags: vec!["ops".into()] });
+        assert_eq!(found(&infra).path, outer);
+
+        // 空列表同样代替上层的配置
+        write(&web, "default_tags = []\n");
+        assert_eq!(found(&web).default_tags, Vec::<String>::new());
+    }
+
+    #[test]
+    fn malformed_files_name_the_path_and_line() {
+        let root = tempfile::tempdir().unwrap();
+        let message = |text: &str| {
+            let path = write(root.path(), text);
+            let err = load(&path).unwrap_err().to_string();
+            assert!(err.contains(&path.display().to_string()), "{}", err);
+            err
+        };
+        assert!(message("default_tags = \"ops\"\n").contains("line 1: expected an array of strings"));
+        assert!(message("\ndefault_tag = [\"ops\"]\n").contains("line 2: unknown key \"default_tag\""));
+        assert!(message("default_tags = [\"a,b\"]\n").contains("line 1: invalid tag \"a,b\""));
+        assert!(message("default_tags = [\"ops\"\n").contains("line 2: unterminated array"));
+        // 上层目录中格式错误的文件在查找时同样报错
+        std::fs::create_dir_all(root.path().join("child")).unwrap();
+        assert!(find(&root.path().join("child")).is_err());
+    }
+}
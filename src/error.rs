@@ -2,7 +2,9 @@
 
 use thiserror::Error;
 
+/// dlog 所有操作的错误类型；以后可能增加新的错误种类
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum DlogError {
     #[error("IO Error: {0}")]
     Io(#[from] std::io::Error),
@@ -13,6 +15,12 @@ pub enum DlogError {
     #[error("Home directory not found")]
     HomeDirNotFound,
 
+    #[error("Database not initialized, run 'dlog init' first")]
+    DatabaseNotInitialized,
+
+    #[error("Database schema mismatch (expected columns: {expected}; found: {found}), run 'dlog init' to upgrade it")]
+    SchemaMismatch { expected: String, found: String },
+
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
@@ -24,6 +32,25 @@ pub enum DlogError {
 
     #[error("No changes detected in log content")]
     NoChangesMade,
+
+    #[error("Log #{0} is not a todo")]
+    NotATodo(i32),
+
+    #[error("Found {0} unresolved problem(s)")]
+    ProblemsFound(usize),
+
+    #[error("The database is busy, another dlog process may be using it. Try again later")]
+    DatabaseBusy,
+
+    #[error("Not running in an interactive terminal: {0}")]
+    NonInteractive(String),
+
+    #[error("{source} (the edited text was saved to {path}, run 'dlog recover' to restore it)")]
+    Recovered {
+        path: String,
+        #[source]
+        source: Box<DlogError>,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, DlogError>;
@@ -0,0 +1,106 @@
+// src/highlight.rs
+
+use crate::color;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::as_24_bit_terminal_escaped;
+
+/// 每个代码块最多高亮的行数，超出部分以暗淡样式显示，避免很长的代码块拖慢输出
+const MAX_HIGHLIGHT_LINES: usize = 500;
+/// 超过该长度的行（例如压缩过的代码）不高亮
+const MAX_HIGHLIGHT_LINE_LEN: usize = 2000;
+/// 代码高亮使用的主题
+const THEME: &str = "base16-ocean.dark";
+
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static HIGHLIGHT_THEME: OnceLock<Theme> = OnceLock::new();
+
+/// 日志内容中的一行，`in_code` 表示该行属于代码块，输出时不折行
+pub struct StyledLine {
+    pub text: String,
+    pub in_code: bool,
+}
+
+/// 按行拆分日志内容，颜色开启时为 ``` 代码块着色
+///
+/// 真彩色终端按代码块开始标记后的语言名高亮，未知语言和其他终端只以暗淡样式
+/// 与正文区分。每行单独重置样式，未闭合的代码块在本条日志结束时终止，
+/// 不会影响后续输出。
+pub fn style_content(content: &str) -> Vec<StyledLine> {
+    let color = color::enabled();
+    let mut block: Option<CodeBlock> = None;
+    let mut lines = Vec::new();
+
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            block = match block {
+                Some(_) => None,
+                None => Some(CodeBlock::open(line, color)),
+            };
+            let text = if color { color::dim(line) } else { line.to_string() };
+            lines.push(StyledLine { text, in_code: true });
+            continue;
+        }
+        let styled = match block.as_mut() {
+            Some(block) => StyledLine {
+                text: block.style(line),
+                in_code: true,
+            },
+            None => StyledLine {
+                text: line.to_string(),
+                in_code: false,
+            },
+        };
+        lines.push(styled);
+    }
+    lines
+}
+
+/// 正在输出的代码块
+struct CodeBlock {
+    color: bool,
+    highlighter: Option<HighlightLines<'static>>,
+    lines: usize,
+}
+
+impl CodeBlock {
+    /// 根据开始标记中的语言名选择语法，只有真彩色终端才会高亮
+    fn open(fence: &str, color: bool) -> Self {
+        let language = fence.trim_start().trim_start_matches('`').split_whitespace().next();
+        let highlighter = language.filter(|_| color::truecolor()).and_then(|lang| {
+            let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_nonewlines);
+            let syntax = syntaxes.find_syntax_by_token(lang)?;
+            let theme = HIGHLIGHT_THEME.get_or_init(|| {
+                ThemeSet::load_defaults().themes.remove(THEME).unwrap_or_default()
+            });
+            Some(HighlightLines::new(syntax, theme))
+        });
+        CodeBlock {
+            color,
+            highlighter,
+            lines: 0,
+        }
+    }
+
+    fn style(&mut self, line: &str) -> String {
+        if !self.color {
+            return line.to_string();
+        }
+        self.lines += 1;
+        if self.lines > MAX_HIGHLIGHT_LINES {
+            self.highlighter = None;
+        }
+        if let Some(highlighter) = self.highlighter.as_mut() {
+            if line.len() <= MAX_HIGHLIGHT_LINE_LEN {
+                if let Some(syntaxes) = SYNTAXES.get() {
+                    if let Ok(ranges) = highlighter.highlight_line(line, syntaxes) {
+                        return format!("{}\x1b[0m", as_24_bit_terminal_escaped(&ranges, false));
+                    }
+                }
+            }
+        }
+        color::dim(line)
+    }
+}
@@ -0,0 +1,109 @@
+// src/hook.rs
+
+use dlog::config;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// 记录日志后执行的命令，由 shell 解释；对应的环境变量为 DLOG_POST_LOG_HOOK
+const HOOK_KEY: &str = "post_log_hook";
+/// 钩子的最长运行时间（秒），超时后被终止；对应的环境变量为 DLOG_HOOK_TIMEOUT
+const TIMEOUT_KEY: &str = "hook_timeout";
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+/// 等待钩子结束时检查的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 传给钩子的新日志
+pub struct HookEntry<'a> {
+    pub id: i32,
+    pub directory: &'a str,
+    pub content: &'a str,
+    pub tags: Option<&'a str>,
+    pub timestamp: &'a str,
+    pub level: &'a str,
+    pub todo: bool,
+}
+
+/// 执行配置项 post_log_hook 中的命令，未配置时什么也不做
+///
+/// 日志的ID、目录、标签等通过 DLOG_* 环境变量传入，内容写到钩子的标准输入。
+/// 钩子无法启动、以非零状态退出或超时（见配置项 hook_timeout）时返回描述原因的错误，
+/// 由调用方作为警告显示：此时日志已经保存，不应让整个命令失败。
+pub fn run_post_log(entry: &HookEntry) -> Result<(), String> {
+    let Some(command) = config::text(HOOK_KEY).filter(|c| !c.trim().is_empty()) else {
+        return Ok(());
+    };
+    let mut cmd = shell(&command);
+    isolate(&mut cmd);
+    let mut child = cmd
+        .env("DLOG_ID", entry.id.to_string())
+        .env("DLOG_DIR", entry.directory)
+        .env("DLOG_TAGS", entry.tags.unwrap_or(""))
+        .env("DLOG_TIMESTAMP", entry.timestamp)
+        .env("DLOG_LEVEL", entry.level)
+        .env("DLOG_TODO", if entry.todo { "1" } else { "0" })
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not run '{}': {}", command, e))?;
+
+    // 在单独的线程中写入，钩子不读取标准输入时也不会阻塞；钩子提前关闭输入不算失败
+    if let Some(mut stdin) = child.stdin.take() {
+        let content = entry.content.to_string();
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(content.as_bytes());
+        });
+    }
+
+    let timeout = Duration::from_secs(config::number(TIMEOUT_KEY, DEFAULT_TIMEOUT_SECS));
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => return Err(format!("'{}' failed ({})", command, status)),
+            Ok(None) if started.elapsed() >= timeout => {
+                kill_tree(&mut child);
+                return Err(format!("'{}' timed out after {}s and was stopped", command, timeout.as_secs()));
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(format!("could not wait for '{}': {}", command, e)),
+        }
+    }
+}
+
+/// 由系统的 shell 解释执行 `command`
+#[cfg(windows)]
+pub fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", command]);
+    cmd
+}
+
+#[cfg(not(windows))]
+pub fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+/// 让钩子在单独的进程组中运行，超时后可以连同它启动的子进程一起终止
+#[cfg(unix)]
+fn isolate(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    cmd.process_group(0);
+}
+
+#[cfg(not(unix))]
+fn isolate(_cmd: &mut Command) {}
+
+/// 终止钩子及其进程组中的所有进程并回收
+///
+/// 只终止 shell 时，它启动的命令仍会继续运行并占用 dlog 的输出，管道中的下一个命令就要一直等待。
+fn kill_tree(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: kill 只接受整数参数；进程组ID就是钩子的进程ID，它在 wait 之前不会被回收和复用
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
@@ -0,0 +1,185 @@
+// src/import.rs
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use std::path::Path;
+
+/// 从其他日记格式中解析出的一条日志，时间为本地时间
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedEntry {
+    pub timestamp: NaiveDateTime,
+    pub content: String,
+    pub tags: Vec<String>,
+}
+
+/// jrnl 条目头部支持的时间格式（方括号内的部分）
+const JRNL_TIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %I:%M %p"];
+
+/// 解析 jrnl 默认格式的导出文本
+///
+/// 每个条目以 `[YYYY-MM-DD HH:MM] 标题` 开头，后续各行直到下一个条目头部都属于正文。
+/// 标题可以为空。第一个条目头部之前的内容会被忽略。内容为空的条目也会返回，
+/// 由调用方决定是否跳过。
+pub fn parse_jrnl(text: &str) -> Vec<ImportedEntry> {
+    let mut entries = Vec::new();
+    let mut current: Option<(NaiveDateTime, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        if let Some((timestamp, title)) = parse_jrnl_header(line) {
+            if let Some((ts, lines)) = current.take() {
+                entries.push(jrnl_entry(ts, &lines));
+            }
+            current = Some((timestamp, vec![title]));
+        } else if let Some((_, lines)) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    if let Some((ts, lines)) = current {
+        entries.push(jrnl_entry(ts, &lines));
+    }
+    entries
+}
+
+/// 解析 `[YYYY-MM-DD HH:MM] 标题` 形式的条目头部，返回时间和标题
+fn parse_jrnl_header(line: &str) -> Option<(NaiveDateTime, &str)> {
+    let rest = line.strip_prefix('[')?;
+    let (time, title) = rest.split_once(']')?;
+    let timestamp = JRNL_TIME_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(time.trim(), fmt).ok())?;
+    // jrnl 用标题前的 * 标记星标条目
+    let title = title.trim();
+    let title = title.strip_prefix('*').unwrap_or(title).trim_start();
+    Some((timestamp, title))
+}
+
+fn jrnl_entry(timestamp: NaiveDateTime, lines: &[&str]) -> ImportedEntry {
+    let content = lines.join("\n").trim().to_string();
+    let tags = jrnl_tags(&content);
+    ImportedEntry {
+        timestamp,
+        content,
+        tags,
+    }
+}
+
+/// 提取文本中 jrnl 风格的 `@标签`，按首次出现的顺序去重
+///
+/// 只识别位于行首或空白之后的 `@`，避免把邮箱地址当作标签；
+/// 标签末尾的标点会被去掉。
+pub fn jrnl_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(name) = word.strip_prefix('@') else {
+            continue;
+        };
+        let name = name.trim_end_matches(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'));
+        if name.is_empty() || name.contains(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-')) {
+            continue;
+        }
+        if !tags.iter().any(|t| t == name) {
+            tags.push(name.to_string());
+        }
+    }
+    tags
+}
+
+/// 从 `YYYY-MM-DD.txt` 形式的文件名中解析日期
+pub fn date_from_filename(path: &Path) -> Option<NaiveDate> {
+    let stem = path.file_stem()?.to_str()?;
+    NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok()
+}
+
+/// 传给 `git log` 的输出格式：短哈希、提交时间和完整的提交信息，
+/// 字段之间以 0x1f 分隔，每个提交以 0x1e 结束
+pub const GIT_LOG_FORMAT: &str = "--pretty=format:%h%x1f%cI%x1f%B%x1e";
+
+/// 导入的提交在内容末尾记录短哈希的行前缀
+const GIT_COMMIT_PREFIX: &str = "commit ";
+
+/// 解析按 `GIT_LOG_FORMAT` 输出的 `git log`，每个提交对应一条日志
+///
+/// 内容为提交信息，末尾另起一段记录 `commit <短哈希>`；标签为 `git`。
+/// 提交时间无法解析的提交会被忽略。
+pub fn parse_git_log(text: &str) -> Vec<ImportedEntry> {
+    let mut entries = Vec::new();
+    for record in text.split('\x1e') {
+        let mut fields = record.trim_start_matches('\n').splitn(3, '\x1f');
+        let (Some(hash), Some(time), Some(message)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let Ok(time) = DateTime::parse_from_rfc3339(time) else {
+            continue;
+        };
+        let message = message.trim();
+        let commit = format!("{}{}", GIT_COMMIT_PREFIX, hash);
+        entries.push(ImportedEntry {
+            timestamp: time.with_timezone(&Local).naive_local(),
+            content: if message.is_empty() { commit } else { format!("{}\n\n{}", message, commit) },
+            tags: vec!["git".to_string()],
+        });
+    }
+    entries
+}
+
+/// 由 `parse_git_log` 导入的日志中记录的短哈希
+pub fn recorded_commit(content: &str) -> Option<&str> {
+    let hash = content.lines().last()?.strip_prefix(GIT_COMMIT_PREFIX)?;
+    (!hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit())).then_some(hash)
+}
+
+/// 两个短哈希是否指向同一个提交：git 在仓库变大后会使用更长的短哈希，较短的一方是较长一方的前缀
+pub fn same_commit(a: &str, b: &str) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn jrnl_entries_keep_multi_line_bodies() {
+        let text = "preamble that is ignored\n\
+                    [2021-03-04 09:12] Deployed the API @ops\n\
+                    First line of the body.\n\
+                    \n\
+                    Second paragraph @release.\n\
+                    [2021-03-05 18:00] * Starred entry\n";
+        let entries = parse_jrnl(text);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].timestamp, at("2021-03-04 09:12"));
+        assert_eq!(
+            entries[0].content,
+            "Deployed the API @ops\nFirst line of the body.\n\nSecond paragraph @release."
+        );
+        assert_eq!(entries[0].tags, ["ops", "release"]);
+        assert_eq!(entries[1].content, "Starred entry");
+        assert!(entries[1].tags.is_empty());
+    }
+
+    #[test]
+    fn jrnl_entries_without_a_title() {
+        let entries = parse_jrnl("[2021-03-04 09:12]\nBody only\n[2021-03-04 10:00 PM]\n[2021-03-06 07:30:15] x\n");
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].content, "Body only");
+        assert_eq!(entries[1].timestamp, at("2021-03-04 22:00"));
+        assert_eq!(entries[1].content, "", "empty entries are returned for the caller to skip");
+        assert_eq!(entries[2].timestamp, at("2021-03-06 07:30") + chrono::Duration::seconds(15));
+    }
+
+    #[test]
+    fn jrnl_tags_ignore_email_addresses() {
+        assert_eq!(jrnl_tags("mail me@example.com about @work, then @work again @x-y."), ["work", "x-y"]);
+        assert!(jrnl_tags("@ alone and @!!").is_empty());
+    }
+
+    #[test]
+    fn plain_files_are_dated_by_their_name() {
+        assert_eq!(date_from_filename(Path::new("notes/2021-03-04.txt")), NaiveDate::from_ymd_opt(2021, 3, 4));
+        assert_eq!(date_from_filename(Path::new("2021-02-30.txt")), None);
+        assert_eq!(date_from_filename(Path::new("todo.txt")), None);
+    }
+}
@@ -0,0 +1,168 @@
+// src/json.rs
+
+use crate::error::{DlogError, Result};
+use std::collections::BTreeMap;
+
+/// 扁平 JSON 对象中的值；dlog 读写的 JSON 不包含数组和嵌套对象
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    String(String),
+}
+
+/// 以 JSON 字符串的形式转义 `s`，包含两端的引号
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// 解析一个只包含字符串、布尔值和 null 的 JSON 对象，如 `{"a":"x","b":null}`
+///
+/// 重复的键以最后一个为准。数字、数组和嵌套对象返回 InvalidInput。
+pub fn parse_flat_object(text: &str) -> Result<BTreeMap<String, Value>> {
+    let mut parser = Parser { chars: text.char_indices().peekable(), text };
+    let mut object = BTreeMap::new();
+    parser.expect('{')?;
+    if !parser.eat('}') {
+        loop {
+            let key = parser.string()?;
+            parser.expect(':')?;
+            let value = parser.value()?;
+            object.insert(key, value);
+            if parser.eat('}') {
+                break;
+            }
+            parser.expect(',')?;
+        }
+    }
+    parser.skip_whitespace();
+    if let Some((pos, _)) = parser.chars.peek() {
+        return Err(invalid(format!("unexpected text after the object at offset {}", pos)));
+    }
+    Ok(object)
+}
+
+fn invalid(message: String) -> DlogError {
+    DlogError::InvalidInput(format!("Invalid JSON: {}", message))
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    text: &'a str,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| matches!(c, ' ' | '\t' | '\n' | '\r')).is_some() {}
+    }
+
+    /// 跳过空白后，下一个字符是 `c` 时消耗它并返回 true
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if(|(_, next)| *next == c).is_some()
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            return Ok(());
+        }
+        Err(match self.chars.peek() {
+            Some((pos, found)) => invalid(format!("expected '{}' but found '{}' at offset {}", c, found, pos)),
+            None => invalid(format!("expected '{}' but the text ended", c)),
+        })
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        match self.chars.peek().map(|(_, c)| *c) {
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('t' | 'f' | 'n') => {
+                let (start, _) = *self.chars.peek().unwrap_or(&(0, ' '));
+                let rest = &self.text[start..];
+                let (word, value) = [("true", Value::Bool(true)), ("false", Value::Bool(false)), ("null", Value::Null)]
+                    .into_iter()
+                    .find(|(word, _)| rest.starts_with(word))
+                    .ok_or_else(|| invalid(format!("unknown literal at offset {}", start)))?;
+                for _ in 0..word.len() {
+                    self.chars.next();
+                }
+                Ok(value)
+            }
+            Some(c) => Err(invalid(format!("unsupported value starting with '{}'", c))),
+            None => Err(invalid("expected a value but the text ended".to_string())),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(out),
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, '"')) => out.push('"'),
+                    Some((_, '\\')) => out.push('\\'),
+                    Some((_, '/')) => out.push('/'),
+                    Some((_, 'b')) => out.push('\u{8}'),
+                    Some((_, 'f')) => out.push('\u{c}'),
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 'r')) => out.push('\r'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, 'u')) => out.push(self.unicode_escape()?),
+                    Some((pos, c)) => return Err(invalid(format!("invalid escape '\\{}' at offset {}", c, pos))),
+                    None => break,
+                },
+                Some((pos, c)) if (c as u32) < 0x20 => {
+                    return Err(invalid(format!("unescaped control character at offset {}", pos)))
+                }
+                Some((_, c)) => out.push(c),
+                None => break,
+            }
+        }
+        Err(invalid("unterminated string".to_string()))
+    }
+
+    /// 解析 `\u` 之后的四位十六进制数，代理对需要两个连续的转义
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| invalid(format!("invalid code point \\u{:04x}", high)));
+        }
+        if !(self.chars.next_if(|(_, c)| *c == '\\').is_some() && self.chars.next_if(|(_, c)| *c == 'u').is_some()) {
+            return Err(invalid(format!("unpaired surrogate \\u{:04x}", high)));
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(invalid(format!("unpaired surrogate \\u{:04x}", high)));
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            .ok_or_else(|| invalid("invalid surrogate pair".to_string()))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self
+                .chars
+                .next()
+                .and_then(|(_, c)| c.to_digit(16))
+                .ok_or_else(|| invalid("invalid \\u escape".to_string()))?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,34 @@
+// src/lib.rs
+
+//! dlog 的核心功能：日志数据库的读写、查询以及各种格式的解析。
+//!
+//! 命令行程序只是这些接口之上的一层。库中的函数不提示用户，也不输出任何内容：
+//! 目录通过参数传入（相对路径相对于当前目录解析），结果以返回值给出，
+//! 错误统一为 [`DlogError`]。`db::open_connection` 使用命令行的数据库位置规则，
+//! 其他程序通常应使用 `db::open_connection_at` 指定数据库文件。
+//!
+//! ```no_run
+//! use dlog::db::{self, LogQuery, Scope};
+//! use std::path::Path;
+//!
+//! let conn = db::open_connection_at(Path::new("/tmp/dlog.db"))?;
+//! db::add_log(&conn, "/home/me/project", "Fixed the cache bug", Some("bugfix"), false)?;
+//! let logs = db::fetch_logs(&conn, Path::new("/home/me/project"), Scope::Dir, &LogQuery::default())?;
+//! # Ok::<(), dlog::DlogError>(())
+//! ```
+
+pub mod config;
+pub mod db;
+pub mod dir_config;
+pub mod error;
+pub mod import;
+pub mod json;
+pub mod models;
+pub mod search;
+pub mod sync;
+pub mod tags;
+pub mod toml;
+pub mod words;
+
+pub use error::{DlogError, Result};
+pub use models::LogEntry;
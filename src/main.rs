@@ -1,33 +1,135 @@
 // src/main.rs
 
+mod capture;
 mod cli;
+mod color;
+mod complete;
 mod commands;
-mod db;
-mod error;
-mod models;
+mod highlight;
+mod hook;
+mod pager;
+mod recovery;
+mod serve;
 
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, ProfileCommands, SyncCommands, TagCommands};
 use clap::Parser;
-use error::Result;
+use commands::{OrphanAction, Outcome};
+use dlog::{db, Result};
+
+/// 命令执行成功但没有匹配结果时的退出码（2 已被 clap 用于参数错误）
+const EXIT_NO_MATCHES: i32 = 3;
 
 fn main() {
     let cli = Cli::parse();
-
-    // 运行命令并处理结果
-    if let Err(e) = run_command(cli.command) {
+    if let Err(e) = dlog::config::load() {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
-}
+    if let Some(path) = &cli.db {
+        match commands::expand_path(path) {
+            Ok(path) => db::set_db_path_override(path),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(profile) = &cli.profile {
+        db::set_profile_override(profile.clone());
+    }
+    if cli.no_pager {
+        pager::disable();
+    }
+    if cli.quiet {
+        commands::set_quiet();
+    }
+    color::set_choice(cli.color);
 
-fn run_command(command: Commands) -> Result<()> {
-    match command {
-        Commands::Init => commands::handle_init(),
-        Commands::Log { message, tags } => commands::handle_log(message, tags),
-        Commands::Get { path, num, recursive, tag, date, search } => {
-            commands::handle_get(path, num, recursive, tag, date, search)
-        }
-        Commands::Fix { id } => commands::handle_fix(id),
-        Commands::Del { ids, recursive } => commands::handle_del(ids, recursive),
+    // 运行命令并处理结果
+    match run_command(cli.command) {
+        Ok(Outcome::Success) => {}
+        Ok(Outcome::NoMatches) => std::process::exit(EXIT_NO_MATCHES),
+        Ok(Outcome::CommandFailed(code)) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("Error: {}{}", e, commands::error_hint(&e));
+            std::process::exit(1);
+        }
     }
 }
+
+fn run_command(command: Commands) -> Result<Outcome> {
+    let result = match command {
+        Commands::Init { yes, prune, keep, remap, compact, db_path } => {
+            // --yes 只跳过确认，删除日志必须明确指定 --prune
+            let orphans = if prune {
+                OrphanAction::Prune
+            } else if keep {
+                OrphanAction::Keep
+            } else if yes {
+                OrphanAction::List
+            } else {
+                OrphanAction::Prompt
+            };
+            commands::handle_init(yes, orphans, remap, compact, db_path)
+        }
+        Commands::Compact => commands::handle_compact(),
+        Commands::Renumber { map_file, dry_run, yes } => commands::handle_renumber(map_file, dry_run, yes),
+        Commands::Doctor { fix, fix_timestamps, yes } => commands::handle_doctor(fix, fix_timestamps, yes),
+        Commands::Log(args) => return commands::handle_log(args),
+        Commands::Get(args) => return commands::handle_get(*args),
+        Commands::Search(args) => return commands::handle_search(args),
+        Commands::Todo { recursive } => return commands::handle_todo(recursive),
+        Commands::Done { id } => commands::handle_done(id),
+        Commands::Link { a, b } => commands::handle_link(a, b),
+        Commands::Unlink { a, b } => commands::handle_unlink(a, b),
+        Commands::Lock { ids } => commands::handle_lock(ids, true),
+        Commands::Unlock { ids } => commands::handle_lock(ids, false),
+        Commands::Tag { action } => match action {
+            TagCommands::Rename { old, new, dry_run } => commands::handle_tag_rename(old, new, dry_run),
+            TagCommands::Rm { name, recursive, all, yes } => commands::handle_tag_rm(name, recursive, all, yes),
+            TagCommands::Stats { path, recursive, all, top } => {
+                commands::handle_tag_stats(path, recursive, all, top)
+            }
+        },
+        Commands::Profile { action } => match action {
+            ProfileCommands::List => commands::handle_profile_list(),
+        },
+        Commands::Report(args) => commands::handle_report(args),
+        Commands::Export(args) => commands::handle_export(args),
+        Commands::Import(args) => commands::handle_import(args),
+        Commands::Merge { file, prefix_dir, dry_run } => commands::handle_merge(file, prefix_dir, dry_run),
+        Commands::Sync { action } => match action {
+            SyncCommands::Export { file, since_last, force, dry_run } => {
+                commands::handle_sync_export(file, since_last, force, dry_run)
+            }
+            SyncCommands::Import { file, dry_run } => commands::handle_sync_import(file, dry_run),
+        },
+        Commands::Stats { path, recursive, all, words, json } => {
+            return commands::handle_stats(path, recursive, all, words, json)
+        }
+        Commands::Dirs { recent, num, paths_only, existing } => {
+            return commands::handle_dirs(recent, num, paths_only, existing)
+        }
+        Commands::Calendar { weeks, recursive, all, sunday } => {
+            commands::handle_calendar(weeks, recursive, all, sunday)
+        }
+        Commands::Random { recursive, all, tag } => return commands::handle_random(recursive, all, tag),
+        Commands::OnThisDay { recursive, all } => return commands::handle_on_this_day(recursive, all),
+        Commands::Grep { pattern, regex, ignore_case, context, recursive, all } => {
+            return commands::handle_grep(pattern, regex, ignore_case, context, recursive, all)
+        }
+        Commands::Completions { shell } => commands::handle_completions(shell),
+        Commands::Prune(args) => commands::handle_prune(args),
+        Commands::Archive(args) => commands::handle_archive(args),
+        Commands::Retag(args) => commands::handle_retag(args),
+        Commands::Fix { id, message, append, no_stamp, level } => {
+            commands::handle_fix(id, message, append, no_stamp, level)
+        }
+        Commands::Copy { id, to, edit } => commands::handle_copy(id, to, edit),
+        Commands::Recover { name } => commands::handle_recover(name),
+        Commands::Serve { port, bind } => commands::handle_serve(port, bind),
+        Commands::Complete { what, prefix } => commands::handle_complete(what, prefix),
+        Commands::Del(args) => commands::handle_del(args),
+    };
+    result.map(|()| Outcome::Success)
+}
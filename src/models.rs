@@ -1,10 +1,133 @@
 // src/models.rs
 
-#[derive(Debug)]
+use crate::error::DlogError;
+use std::str::FromStr;
+
+/// 数据库中的一条日志；以后可能增加新的字段，因此只能由本库创建
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct LogEntry {
     pub id: i32,
     pub timestamp: String, // 在数据库中存储为 RFC3339 字符串
     pub content: String,
     pub tags: Option<String>,
     pub directory: String,
+    pub status: Option<String>, // 非待办日志为 NULL
+    pub completed_at: Option<String>,
+    pub hostname: Option<String>, // 记录日志的主机，旧日志为 NULL
+    pub username: Option<String>,
+    pub level: Level,
+    pub locked: bool, // 锁定的日志不会被删除，除非显式要求
+    pub uuid: Option<String>, // 在不同数据库之间同步时用于识别同一条日志
+    pub updated_at: Option<String>, // 最后一次修改的时间，与 timestamp 格式相同
+}
+
+impl LogEntry {
+    /// 除内容以外的所有字段，按 `(列名, 存储的值)` 排列，NULL 为 None
+    ///
+    /// 按字段逐一解构，增加新字段时编译器会要求在这里补上，
+    /// 因此 'get --verbose' 等列出全部字段的地方不会遗漏。
+    pub fn fields(&self) -> Vec<(&'static str, Option<String>)> {
+        let LogEntry {
+            id,
+            timestamp,
+            content: _,
+            tags,
+            directory,
+            status,
+            completed_at,
+            hostname,
+            username,
+            level,
+            locked,
+            uuid,
+            updated_at,
+        } = self;
+        vec![
+            ("id", Some(id.to_string())),
+            ("timestamp", Some(timestamp.clone())),
+            ("directory", Some(directory.clone())),
+            ("tags", tags.clone().filter(|t| !t.is_empty())),
+            ("level", Some(level.as_str().to_string())),
+            ("status", status.clone()),
+            ("completed_at", completed_at.clone()),
+            ("hostname", hostname.clone()),
+            ("username", username.clone()),
+            ("locked", Some(locked.to_string())),
+            ("uuid", uuid.clone()),
+            ("updated_at", updated_at.clone()),
+        ]
+    }
+}
+
+/// 日志的重要程度，按 low < normal < high 排序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Level {
+    /// 所有级别，从低到高
+    pub const ALL: [Level; 3] = [Level::Low, Level::Normal, Level::High];
+
+    /// 在数据库中存储的名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Low => "low",
+            Level::Normal => "normal",
+            Level::High => "high",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = DlogError;
+
+    /// 不区分大小写地解析级别名称，无效的名称返回 InvalidInput
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Level::ALL
+            .into_iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                DlogError::InvalidInput(format!("Invalid level '{}'. Use low, normal or high.", s))
+            })
+    }
+}
+
+/// 待办事项状态：未完成
+pub const TODO_OPEN: &str = "open";
+/// 待办事项状态：已完成
+pub const TODO_DONE: &str = "done";
+
+/// 日志的标题，见 `content_title`
+pub fn title_of(log: &LogEntry) -> &str {
+    content_title(&log.content)
+}
+
+/// 内容的标题：第一行非空白的文本
+///
+/// 以 Markdown 标题（`# 标题`，最多六个 #）开头时去掉 # 标记，只剩 # 时保持原样。
+/// 内容全为空白时返回空字符串。
+pub fn content_title(content: &str) -> &str {
+    let line = content.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    let rest = line.trim_start_matches('#');
+    let level = line.len() - rest.len();
+    if level == 0 || level > 6 || !(rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        return line;
+    }
+    // 可选的结尾 # 序列也不属于标题文本
+    let text = rest.trim();
+    let text = match text.trim_end_matches('#') {
+        stripped if stripped.is_empty() || stripped.ends_with(char::is_whitespace) => stripped.trim_end(),
+        _ => text,
+    };
+    if text.is_empty() {
+        line
+    } else {
+        text
+    }
 }
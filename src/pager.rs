@@ -0,0 +1,95 @@
+// src/pager.rs
+
+use dlog::config;
+use dlog::toml::Value;
+use dlog::Result;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 配置文件中设置分页器的键，对应的环境变量为 DLOG_PAGER
+const PAGER_KEY: &str = "pager";
+
+/// 未设置 $DLOG_PAGER、pager 配置项和 $PAGER 时使用的分页器；-F 让一屏以内的输出直接显示
+const DEFAULT_PAGER: &str = "less -FRX";
+
+/// 通过 --no-pager 关闭分页
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// 本次运行不使用分页器
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// 输出已渲染好的文本
+///
+/// 标准输出是终端且内容超过一屏时交给分页器（依次为 $DLOG_PAGER、配置文件中的 pager、$PAGER
+/// 和 less -FRX），否则直接写到标准输出。$DLOG_PAGER 或 pager 设为空字符串、pager 设为 false
+/// 可以关闭分页。
+/// 分页器或管道提前关闭（例如在 less 中按 q）不视为错误。
+pub fn page(text: &str) -> Result<()> {
+    if let Some(command) = pager_command(text) {
+        if run_pager(&command, text)? {
+            return Ok(());
+        }
+    }
+    ignore_broken_pipe(print(text))
+}
+
+/// 不经过分页器直接输出文本并刷新标准输出
+pub fn print(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()
+}
+
+/// 需要分页时返回分页器命令
+fn pager_command(text: &str) -> Option<String> {
+    if DISABLED.load(Ordering::Relaxed) || !io::stdout().is_terminal() {
+        return None;
+    }
+    let (_, terminal_size::Height(rows)) = terminal_size::terminal_size()?;
+    if text.lines().count() < rows as usize {
+        return None;
+    }
+
+    let command = match std::env::var(config::env_name(PAGER_KEY)) {
+        Ok(value) => value,
+        Err(_) => match config::file_value(PAGER_KEY) {
+            Some(Value::Bool(false)) => return None,
+            Some(Value::String(value)) => value.clone(),
+            _ => std::env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string()),
+        },
+    };
+    let command = command.trim();
+    // 与 git 一致，分页器为 cat 时等同于不分页
+    if command.is_empty() || command == "cat" {
+        None
+    } else {
+        Some(command.to_string())
+    }
+}
+
+/// 通过分页器显示文本；分页器无法启动时返回 false，由调用方直接输出
+fn run_pager(command: &str, text: &str) -> Result<bool> {
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        return Ok(false);
+    };
+    let mut child = match Command::new(program).args(parts).stdin(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(_) => return Ok(false),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        ignore_broken_pipe(stdin.write_all(text.as_bytes()))?;
+    }
+    child.wait()?;
+    Ok(true)
+}
+
+fn ignore_broken_pipe(result: io::Result<()>) -> Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => Ok(other?),
+    }
+}
@@ -0,0 +1,115 @@
+// src/recovery.rs
+
+use chrono::Local;
+use dlog::{DlogError, Result};
+use std::path::{Path, PathBuf};
+
+/// 保存编辑器内容的目录 (~/.config/dlog/recovery)
+pub fn recovery_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().ok_or(DlogError::HomeDirNotFound)?;
+    Ok(config_dir.join("dlog").join("recovery"))
+}
+
+/// 将编辑器中写好的内容保存为 `<时间>.md`，返回保存的路径
+///
+/// 在写入数据库之前调用，这样即使写入失败或进程被中断，内容也不会丢失。
+pub fn save(content: &str) -> Result<PathBuf> {
+    save_in(&recovery_dir()?, content)
+}
+
+/// 将内容保存到目录 `dir` 中，文件名重复时加上序号
+fn save_in(dir: &Path, content: &str) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let stem = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut path = dir.join(format!("{}.md", stem));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.md", stem, n));
+        n += 1;
+    }
+    std::fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// 根据写入数据库的结果处理保存的内容：成功时删除，失败时保留并在错误中给出路径
+pub fn finish<T>(saved: Option<PathBuf>, result: Result<T>) -> Result<T> {
+    let Some(path) = saved else {
+        return result;
+    };
+    match result {
+        Ok(value) => {
+            // 删除失败不影响已经写入的日志
+            let _ = std::fs::remove_file(&path);
+            Ok(value)
+        }
+        Err(e) => Err(DlogError::Recovered {
+            path: path.display().to_string(),
+            source: Box::new(e),
+        }),
+    }
+}
+
+/// 列出保存的内容，按保存时间从早到晚排序
+pub fn list() -> Result<Vec<PathBuf>> {
+    let dir = recovery_dir()?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// 按名称查找保存的内容，名称可以带或不带 .md 后缀
+pub fn find(name: &str) -> Result<PathBuf> {
+    let stem = name.strip_suffix(".md").unwrap_or(name);
+    let invalid = stem.is_empty() || Path::new(stem).components().count() != 1 || stem.starts_with('.');
+    let path = recovery_dir()?.join(format!("{}.md", stem));
+    if invalid || !path.is_file() {
+        return Err(DlogError::InvalidInput(format!(
+            "No saved buffer named '{}', run 'dlog recover' to list them",
+            name
+        )));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::{Connection, OpenFlags};
+
+    #[test]
+    fn a_failed_write_keeps_the_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("dlog.db");
+        dlog::db::initialize_db_at(&db_path).unwrap();
+        let read_only = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
+
+        let recovery = dir.path().join("recovery");
+        let saved = save_in(&recovery, "carefully written text").unwrap();
+        let result = dlog::db::add_log(&read_only, "/proj", "carefully written text", None, false);
+        match finish(Some(saved.clone()), result) {
+            Err(DlogError::Recovered { path, source }) => {
+                assert_eq!(Path::new(&path), saved);
+                assert!(matches!(*source, DlogError::Sql(_)), "{:?}", source);
+            }
+            other => panic!("expected Recovered, got {:?}", other),
+        }
+        assert_eq!(std::fs::read_to_string(&saved).unwrap(), "carefully written text");
+    }
+
+    #[test]
+    fn a_successful_write_removes_the_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = save_in(dir.path(), "one").unwrap();
+        let second = save_in(dir.path(), "two").unwrap();
+        assert_ne!(first, second, "buffers saved in the same second must not overwrite each other");
+        assert_eq!(finish(Some(first.clone()), Ok(7)).unwrap(), 7);
+        assert!(!first.exists());
+        assert!(second.exists());
+    }
+}
@@ -0,0 +1,240 @@
+// src/search.rs
+
+use crate::models::LogEntry;
+
+/// 标签中的匹配比正文中的一次匹配更能说明相关性
+const TAG_WEIGHT: usize = 5;
+/// 第一行（标题行）中的匹配额外加分
+const SUBJECT_WEIGHT: usize = 3;
+/// 摘要中命中位置之前保留的字符数
+const SNIPPET_CONTEXT: usize = 30;
+
+/// 将查询拆分为搜索词，并转为小写，拆分规则见 `split_query`
+pub fn parse_query(query: &str) -> Vec<String> {
+    split_query(query).into_iter().map(str::to_lowercase).collect()
+}
+
+/// 将查询拆分为搜索词：空白分隔的单词，双引号包围的部分作为一个短语，保留原有大小写
+///
+/// 缺少结束引号时，引号之后的全部内容作为一个短语。
+pub fn split_query(query: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut rest = query;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        let (term, after) = match rest.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => {
+                let end = rest.find(|c: char| c.is_whitespace() || c == '"').unwrap_or(rest.len());
+                rest.split_at(end)
+            }
+        };
+        let term = term.trim();
+        if !term.is_empty() {
+            terms.push(term);
+        }
+        rest = after;
+    }
+    terms
+}
+
+/// 计算日志与搜索词的相关度：正文中的出现次数，加上标题行和标签中的加权匹配
+pub fn score(log: &LogEntry, terms: &[String]) -> usize {
+    let content = log.content.to_lowercase();
+    let subject = content.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    let tags = log.tags.as_deref().unwrap_or("").to_lowercase();
+    terms
+        .iter()
+        .map(|term| {
+            content.matches(term.as_str()).count()
+                + SUBJECT_WEIGHT * usize::from(subject.contains(term.as_str()))
+                + TAG_WEIGHT * usize::from(tags.contains(term.as_str()))
+        })
+        .sum()
+}
+
+/// 找到第一个包含搜索词的行，返回该行以命中位置为中心的摘要，超出宽度的部分由调用方截断
+///
+/// 只有标签匹配、正文中没有命中时返回第一行非空内容。
+pub fn snippet<'a>(content: &'a str, terms: &[String]) -> (bool, &'a str) {
+    for term in terms {
+        for line in content.lines() {
+            if let Some(pos) = find_ignore_case(line, term) {
+                let start = line[..pos]
+                    .char_indices()
+                    .rev()
+                    .nth(SNIPPET_CONTEXT - 1)
+                    .map_or(0, |(i, _)| i);
+                let shown = line[start..].trim();
+                return (start > 0, shown);
+            }
+        }
+    }
+    let first = content.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    (false, first)
+}
+
+/// 不区分大小写地查找小写的 `needle`，返回在 `haystack` 中的字节位置（位于字符边界上）
+///
+/// 转为小写可能改变字符的字节长度，因此记录每个小写字节对应的原始位置。
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    let mut lowered = String::with_capacity(haystack.len());
+    let mut origin = Vec::with_capacity(haystack.len());
+    for (i, c) in haystack.char_indices() {
+        let before = lowered.len();
+        lowered.extend(c.to_lowercase());
+        origin.resize(origin.len() + lowered.len() - before, i);
+    }
+    lowered.find(needle).map(|pos| origin[pos])
+}
+
+/// `grep_lines` 返回的一行，行号从 1 开始；`matched` 为假时是上下文行
+#[derive(Debug, PartialEq)]
+pub struct GrepLine<'a> {
+    pub number: usize,
+    pub matched: bool,
+    pub text: &'a str,
+}
+
+/// 找出满足 `is_match` 的行及其前后各 `context` 行，按相连的范围分组
+///
+/// 重叠或相邻的范围合并为一组，行按 `str::lines` 切分（同时去掉 `\r\n`）。没有匹配时返回空列表。
+pub fn grep_lines<'a, F>(content: &'a str, context: usize, is_match: F) -> Vec<Vec<GrepLine<'a>>>
+where
+    F: Fn(&str) -> bool,
+{
+    let lines: Vec<&str> = content.lines().collect();
+    let mut groups: Vec<Vec<GrepLine>> = Vec::new();
+    // 已经输出的行的结束位置（不包含）
+    let mut end = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if !is_match(line) {
+            continue;
+        }
+        let start = i.saturating_sub(context);
+        if groups.is_empty() || start > end {
+            groups.push(Vec::new());
+            end = start;
+        }
+        let stop = (i + context + 1).min(lines.len());
+        let group = groups.last_mut().expect("group was just pushed");
+        // 上一个匹配的下文中可能已经包含这一行，此时只需把它标记为匹配
+        if let Some(existing) = group.iter_mut().find(|l| l.number == i + 1) {
+            existing.matched = true;
+        }
+        for (j, text) in lines.iter().enumerate().take(stop).skip(end.max(start)) {
+            group.push(GrepLine { number: j + 1, matched: j == i, text });
+        }
+        end = end.max(stop);
+    }
+    groups
+}
+
+/// 内容中是否包含 NUL 字符，这样的内容按二进制处理，不逐行输出
+pub fn looks_binary(content: &str) -> bool {
+    content.contains('\0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(query: &str) -> Vec<String> {
+        parse_query(query)
+    }
+
+    #[test]
+    fn snippet_starts_on_a_char_boundary_before_multibyte_text() {
+        let content = format!("{}连接池 pooling notes", "数据库".repeat(20));
+        let (clipped, shown) = snippet(&content, &terms("pooling"));
+        assert!(clipped);
+        // 命中位置之前正好保留 SNIPPET_CONTEXT 个字符，而不是字节
+        let before = shown.split("pooling").next().unwrap();
+        assert_eq!(before.chars().count(), SNIPPET_CONTEXT);
+        assert!(before.ends_with("连接池 "));
+    }
+
+    #[test]
+    fn snippet_is_not_clipped_when_the_hit_is_near_the_start() {
+        let (clipped, shown) = snippet("  Größe der Pool-Verbindung  ", &terms("pool"));
+        assert!(!clipped);
+        assert_eq!(shown, "Größe der Pool-Verbindung");
+    }
+
+    #[test]
+    fn snippet_maps_positions_when_lowercasing_changes_byte_length() {
+        // İ 转为小写后是两个字符（i 和组合附加符号），字节长度也变了
+        let content = format!("{}: ÖFFNEN pool", "İ".repeat(40));
+        let (clipped, shown) = snippet(&content, &terms("öffnen"));
+        assert!(clipped);
+        assert!(shown.ends_with("ÖFFNEN pool"), "{}", shown);
+        assert_eq!(find_ignore_case("İİ Straße", "straße"), Some("İİ ".len()));
+        assert_eq!(find_ignore_case("İİ", "x"), None);
+    }
+
+    #[test]
+    fn snippet_uses_the_first_matching_line_or_the_subject() {
+        let content = "\n  Title line\nsecond mentions 日志 here\n";
+        assert_eq!(snippet(content, &terms("日志")), (false, "second mentions 日志 here"));
+        // 只有标签命中时显示第一行非空内容
+        assert_eq!(snippet(content, &terms("absent")), (false, "Title line"));
+        assert_eq!(snippet("", &terms("absent")), (false, ""));
+    }
+
+    #[test]
+    fn split_query_keeps_quoted_phrases() {
+        assert_eq!(split_query(r#"pool "Connection Reset" x"#), vec!["pool", "Connection Reset", "x"]);
+        assert_eq!(split_query(r#"a"b c  "#), vec!["a", "b c"]);
+        assert_eq!(split_query(r#" "" "#), Vec::<&str>::new());
+    }
+
+    /// 每组的 (行号, 是否匹配, 内容)
+    fn grep<'a>(content: &'a str, context: usize, needle: &str) -> Vec<Vec<(usize, bool, &'a str)>> {
+        grep_lines(content, context, |line| line.contains(needle))
+            .into_iter()
+            .map(|group| group.into_iter().map(|l| (l.number, l.matched, l.text)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn grep_lines_keeps_multibyte_lines_whole() {
+        let content = "第一行\r\n连接池耗尽 — pool exhausted\r\nÜberlauf\n\n最后 pool";
+        assert_eq!(
+            grep(content, 0, "pool"),
+            vec![vec![(2, true, "连接池耗尽 — pool exhausted")], vec![(5, true, "最后 pool")]]
+        );
+        assert_eq!(grep(content, 0, "池"), vec![vec![(2, true, "连接池耗尽 — pool exhausted")]]);
+        assert_eq!(grep(content, 0, "absent"), Vec::<Vec<_>>::new());
+    }
+
+    #[test]
+    fn grep_context_windows_merge_and_stop_at_the_edges() {
+        let content = "ä\nmatch ö\nü\nß\n日\nmatch 本\n語\nend";
+        let groups = grep(content, 1, "match");
+        assert_eq!(
+            groups,
+            vec![
+                vec![(1, false, "ä"), (2, true, "match ö"), (3, false, "ü")],
+                vec![(5, false, "日"), (6, true, "match 本"), (7, false, "語")],
+            ]
+        );
+        // 上下文相接或重叠时合并为一组，每行只出现一次
+        let merged = grep(content, 2, "match");
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].iter().map(|l| l.0).collect::<Vec<_>>(), (1..=8).collect::<Vec<_>>());
+        assert_eq!(merged[0].iter().filter(|l| l.1).count(), 2);
+        let adjacent = grep("a\nb\nc\nd", 1, "");
+        assert_eq!(adjacent.len(), 1);
+        assert!(adjacent[0].iter().all(|l| l.1));
+        assert_eq!(grep("x\nmatch", 5, "match")[0].len(), 2);
+    }
+
+    #[test]
+    fn nul_characters_mark_binary_content() {
+        assert!(looks_binary("PNG\0\u{1}"));
+        assert!(!looks_binary("普通的文字\n"));
+    }
+}
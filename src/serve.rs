@@ -0,0 +1,310 @@
+// src/serve.rs
+
+use crate::commands::{display_path, format_time, local_day_range, parse_day_span};
+use dlog::db::{self, Scope};
+use dlog::models::{title_of, LogEntry};
+use dlog::json::json_string;
+use dlog::{search, tags, DlogError, Result};
+use rusqlite::Connection;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+/// 未指定 limit 时每页显示的日志条数
+const DEFAULT_LIMIT: u32 = 100;
+/// 读取请求的超时时间，避免一个不发送数据的连接阻塞其他请求
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 在 `addr` 上提供只读的网页和 JSON 接口，直到进程被 Ctrl-C 结束
+///
+/// 数据库以只读方式打开，没有任何修改数据的接口，因此直接结束进程也不会留下未完成的写入。
+/// 请求按顺序逐个处理。
+pub fn run(addr: SocketAddr) -> Result<()> {
+    let conn = db::open_readonly_at(&db::get_db_path()?)?;
+    let listener = TcpListener::bind(addr)?;
+    println!("Serving logs at http://{}/ (press Ctrl-C to stop)", listener.local_addr()?);
+    for stream in listener.incoming() {
+        // 单个连接出错（例如浏览器中途断开）不影响服务
+        let Ok(stream) = stream else { continue };
+        let _ = handle_connection(&conn, stream);
+    }
+    Ok(())
+}
+
+/// 一个 HTTP 响应
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn html(body: String) -> Self {
+        Response { status: "200 OK", content_type: "text/html; charset=utf-8", body }
+    }
+
+    fn json(body: String) -> Self {
+        Response { status: "200 OK", content_type: "application/json", body }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", message),
+        }
+    }
+}
+
+fn handle_connection(conn: &Connection, mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // 请求头不需要，读到空行为止
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = parse_query_string(query);
+
+    let response = match (method, path) {
+        ("GET" | "HEAD", "/") => index_page(conn),
+        ("GET" | "HEAD", "/dir") => dir_page(conn, &params),
+        ("GET" | "HEAD", "/api/logs") => api_logs(conn, &params),
+        ("GET" | "HEAD", _) => Ok(Response::error("404 Not Found", "Not found")),
+        _ => Ok(Response::error("405 Method Not Allowed", "Only GET requests are supported")),
+    };
+    let response = response.unwrap_or_else(|e| match e {
+        DlogError::InvalidInput(message) => Response::error("400 Bad Request", &message),
+        other => Response::error("500 Internal Server Error", &other.to_string()),
+    });
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAllow: GET, HEAD\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(response.body.as_bytes())?;
+    }
+    stream.flush()
+}
+
+/// 首页：所有目录及其日志条数
+fn index_page(conn: &Connection) -> Result<Response> {
+    let mut body = String::from("<h1>dlog</h1>\n<table>\n<tr><th>Directory</th><th>Logs</th></tr>\n");
+    for (dir, count) in db::count_logs_by_directory(conn)? {
+        body.push_str(&format!(
+            "<tr><td><a href=\"/dir?path={}\">{}</a></td><td>{}</td></tr>\n",
+            percent_encode(&dir),
+            escape_html(&display_path(&dir)),
+            count
+        ));
+    }
+    body.push_str("</table>\n");
+    Ok(Response::html(page("dlog", &body)))
+}
+
+/// 目录页：按查询参数过滤的日志列表
+fn dir_page(conn: &Connection, params: &[(String, String)]) -> Result<Response> {
+    let dir = param(params, "path")
+        .ok_or_else(|| DlogError::InvalidInput("Missing the 'path' parameter".to_string()))?;
+    let logs = fetch(conn, params)?;
+
+    let value = |name: &str| escape_html(param(params, name).unwrap_or(""));
+    let mut body = format!(
+        "<p><a href=\"/\">All directories</a></p>\n<h1>{}</h1>\n\
+         <form method=\"get\" action=\"/dir\">\n\
+         <input type=\"hidden\" name=\"path\" value=\"{}\">\n\
+         <input name=\"q\" placeholder=\"search\" value=\"{}\">\n\
+         <input name=\"tag\" placeholder=\"tag\" value=\"{}\">\n\
+         <input name=\"date\" placeholder=\"YYYY-MM-DD\" value=\"{}\">\n\
+         <label><input type=\"checkbox\" name=\"recursive\" value=\"1\"{}> subdirectories</label>\n\
+         <button>Filter</button>\n</form>\n",
+        escape_html(&display_path(dir)),
+        escape_html(dir),
+        value("q"),
+        value("tag"),
+        value("date"),
+        if flag(params, "recursive") { " checked" } else { "" }
+    );
+    if logs.is_empty() {
+        body.push_str("<p>No logs found.</p>\n");
+    }
+    for log in &logs {
+        body.push_str(&format!(
+            "<article>\n<h2>#{} {}</h2>\n<p class=\"meta\">{}",
+            log.id,
+            escape_html(title_of(log)),
+            format_time(&log.timestamp, "%Y-%m-%d %H:%M:%S")
+        ));
+        if let Some(tags) = log.tags.as_deref().filter(|t| !t.is_empty()) {
+            body.push_str(&format!(" · {}", escape_html(tags)));
+        }
+        if log.directory != dir {
+            body.push_str(&format!(" · {}", escape_html(&display_path(&log.directory))));
+        }
+        // Markdown 不做渲染，转义后原样显示
+        body.push_str(&format!(
+            " · {}</p>\n<pre>{}</pre>\n</article>\n",
+            log.level.as_str(),
+            escape_html(log.content.trim_end())
+        ));
+    }
+    Ok(Response::html(page(&display_path(dir), &body)))
+}
+
+/// JSON 接口，过滤参数与目录页相同；不指定 path 时查询所有目录
+fn api_logs(conn: &Connection, params: &[(String, String)]) -> Result<Response> {
+    let logs = fetch(conn, params)?;
+    let items: Vec<String> = logs.iter().map(log_json).collect();
+    Ok(Response::json(format!("[{}]\n", items.join(","))))
+}
+
+/// 按查询参数查询日志：path、recursive、tag、date（与 'get --date' 相同的格式）、q 和 limit
+fn fetch(conn: &Connection, params: &[(String, String)]) -> Result<Vec<LogEntry>> {
+    let (dir, scope) = match param(params, "path") {
+        Some(p) => (Path::new(p), Scope::from_recursive(flag(params, "recursive"))),
+        None => (Path::new("/"), Scope::All),
+    };
+    let tag = param(params, "tag").map(tags::normalize_tag).transpose()?;
+    let range = match param(params, "date") {
+        Some(d) => {
+            let (first, last) = parse_day_span(d)?;
+            Some((local_day_range(first)?.0, local_day_range(last)?.1))
+        }
+        None => None,
+    };
+    let terms: Vec<String> = param(params, "q")
+        .map(|q| search::split_query(q).into_iter().map(str::to_string).collect())
+        .unwrap_or_default();
+    let limit = match param(params, "limit") {
+        Some(l) => l
+            .parse()
+            .map_err(|_| DlogError::InvalidInput(format!("Invalid limit '{}'", l)))?,
+        None => DEFAULT_LIMIT,
+    };
+
+    let query = db::LogQuery {
+        tag: tag.as_deref(),
+        terms: &terms,
+        since: range.map(|(start, _)| start),
+        until: range.map(|(_, end)| end),
+        limit,
+        ..Default::default()
+    };
+    db::fetch_logs(conn, dir, scope, &query)
+}
+
+fn log_json(log: &LogEntry) -> String {
+    let optional = |value: &Option<String>| value.as_deref().map_or("null".to_string(), json_string);
+    let tags: Vec<String> = log
+        .tags
+        .as_deref()
+        .map(tags::split_tags)
+        .unwrap_or_default()
+        .into_iter()
+        .map(json_string)
+        .collect();
+    format!(
+        "{{\"id\":{},\"timestamp\":{},\"directory\":{},\"content\":{},\"tags\":[{}],\"level\":{},\"status\":{},\"completed_at\":{},\"hostname\":{},\"username\":{}}}",
+        log.id,
+        json_string(&log.timestamp),
+        json_string(&log.directory),
+        json_string(&log.content),
+        tags.join(","),
+        json_string(log.level.as_str()),
+        optional(&log.status),
+        optional(&log.completed_at),
+        optional(&log.hostname),
+        optional(&log.username)
+    )
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>body{{font-family:sans-serif;max-width:60em;margin:2em auto;padding:0 1em}}\
+         td{{padding:0.2em 1em 0.2em 0}}article{{border-top:1px solid #ccc}}\
+         pre{{white-space:pre-wrap}}.meta{{color:#666}}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        body
+    )
+}
+
+fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+        .filter(|value| !value.is_empty())
+}
+
+fn flag(params: &[(String, String)], name: &str) -> bool {
+    matches!(param(params, name), Some("1" | "true" | "on"))
+}
+
+/// 解析 `a=1&b=2` 形式的查询字符串
+fn parse_query_string(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+/// 解码 URL 中的 %XX 和表示空格的 +，无效的序列原样保留
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match s.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// 编码 URL 查询参数的值，只保留不需要转义的字符
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,128 @@
+// src/sync.rs
+
+use crate::db::canonical_timestamp;
+use crate::error::{DlogError, Result};
+use crate::json::{self, json_string, Value};
+use crate::models::LogEntry;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// 同步包中的一行：一条日志的当前内容，或一条日志已被删除的记录
+///
+/// 日志按 `uuid` 对应，本地ID不会写入同步包。
+#[derive(Debug, Clone)]
+pub enum SyncRecord {
+    Entry(LogEntry),
+    Deleted { uuid: String, deleted_at: String },
+}
+
+impl SyncRecord {
+    pub fn uuid(&self) -> &str {
+        match self {
+            SyncRecord::Entry(log) => log.uuid.as_deref().unwrap_or_default(),
+            SyncRecord::Deleted { uuid, .. } => uuid,
+        }
+    }
+
+    /// 写入同步包的一行 JSON，不含换行符
+    pub fn to_line(&self) -> String {
+        match self {
+            SyncRecord::Entry(log) => {
+                let optional = |value: &Option<String>| value.as_deref().map_or("null".to_string(), json_string);
+                format!(
+                    "{{\"type\":\"entry\",\"uuid\":{},\"updated_at\":{},\"timestamp\":{},\"directory\":{},\"content\":{},\"tags\":{},\"status\":{},\"completed_at\":{},\"hostname\":{},\"username\":{},\"level\":{},\"locked\":{}}}",
+                    optional(&log.uuid),
+                    optional(&log.updated_at),
+                    json_string(&log.timestamp),
+                    json_string(&log.directory),
+                    json_string(&log.content),
+                    optional(&log.tags),
+                    optional(&log.status),
+                    optional(&log.completed_at),
+                    optional(&log.hostname),
+                    optional(&log.username),
+                    json_string(log.level.as_str()),
+                    log.locked
+                )
+            }
+            SyncRecord::Deleted { uuid, deleted_at } => format!(
+                "{{\"type\":\"deleted\",\"uuid\":{},\"deleted_at\":{}}}",
+                json_string(uuid),
+                json_string(deleted_at)
+            ),
+        }
+    }
+}
+
+/// 解析同步包的一行；时间戳统一换算为 `canonical_timestamp` 的格式，得到的日志ID为 0
+pub fn parse_line(line: &str) -> Result<SyncRecord> {
+    let mut fields = json::parse_flat_object(line)?;
+    let uuid = take_string(&mut fields, "uuid")?
+        .filter(|u| !u.is_empty())
+        .ok_or_else(|| missing("uuid"))?;
+    match take_string(&mut fields, "type")?.as_deref() {
+        Some("deleted") => {
+            let deleted_at = required_timestamp(&mut fields, "deleted_at")?;
+            Ok(SyncRecord::Deleted { uuid, deleted_at })
+        }
+        Some("entry") => {
+            let completed_at = take_string(&mut fields, "completed_at")?
+                .map(|t| normalize_timestamp(&t, "completed_at"))
+                .transpose()?;
+            let level = take_string(&mut fields, "level")?
+                .as_deref()
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or_default();
+            let locked = match fields.remove("locked") {
+                None | Some(Value::Null) => false,
+                Some(Value::Bool(b)) => b,
+                Some(Value::String(_)) => {
+                    return Err(DlogError::InvalidInput("\"locked\" must be true or false".to_string()))
+                }
+            };
+            Ok(SyncRecord::Entry(LogEntry {
+                id: 0,
+                timestamp: required_timestamp(&mut fields, "timestamp")?,
+                content: take_string(&mut fields, "content")?.ok_or_else(|| missing("content"))?,
+                tags: take_string(&mut fields, "tags")?.filter(|t| !t.is_empty()),
+                directory: take_string(&mut fields, "directory")?.ok_or_else(|| missing("directory"))?,
+                status: take_string(&mut fields, "status")?,
+                completed_at,
+                hostname: take_string(&mut fields, "hostname")?,
+                username: take_string(&mut fields, "username")?,
+                level,
+                locked,
+                uuid: Some(uuid),
+                updated_at: Some(required_timestamp(&mut fields, "updated_at")?),
+            }))
+        }
+        Some(other) => Err(DlogError::InvalidInput(format!("Unknown record type \"{}\"", other))),
+        None => Err(missing("type")),
+    }
+}
+
+/// 取出一个字符串字段，不存在或为 null 时返回 None
+fn take_string(fields: &mut BTreeMap<String, Value>, name: &str) -> Result<Option<String>> {
+    match fields.remove(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(Value::Bool(_)) => Err(DlogError::InvalidInput(format!("\"{}\" must be a string", name))),
+    }
+}
+
+fn missing(name: &str) -> DlogError {
+    DlogError::InvalidInput(format!("Missing \"{}\"", name))
+}
+
+fn required_timestamp(fields: &mut BTreeMap<String, Value>, name: &str) -> Result<String> {
+    let value = take_string(fields, name)?.ok_or_else(|| missing(name))?;
+    normalize_timestamp(&value, name)
+}
+
+fn normalize_timestamp(value: &str, name: &str) -> Result<String> {
+    let parsed: DateTime<Utc> = value
+        .parse()
+        .map_err(|_| DlogError::InvalidInput(format!("\"{}\" is not an RFC3339 timestamp: {}", name, value)))?;
+    Ok(canonical_timestamp(parsed))
+}
@@ -0,0 +1,323 @@
+// src/tags.rs
+
+use crate::config;
+use crate::error::{DlogError, Result};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// 设为 false 时保留标签的大小写，对应的环境变量为 DLOG_LOWERCASE_TAGS
+const LOWERCASE_KEY: &str = "lowercase_tags";
+
+/// 将逗号分隔的标签字符串拆分为标签列表，去掉空白并忽略空标签
+pub fn split_tags(tags: &str) -> Vec<&str> {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// 将用户输入的逗号分隔标签整理为存储格式
+///
+/// 去掉每个标签两端的空白和空标签，默认转为小写（见配置项 lowercase_tags），
+/// 并按首次出现的顺序去重。包含换行的输入会破坏存储格式，返回 InvalidInput；
+/// 整理后没有标签时返回 None。
+pub fn normalize_tags(input: &str) -> Result<Option<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in split_tags(input) {
+        let tag = normalize_tag(tag)?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    let refs: Vec<&str> = normalized.iter().map(String::as_str).collect();
+    Ok(join_tags(&refs))
+}
+
+/// 整理单个标签名，规则与 `normalize_tags` 相同；逗号和换行会破坏存储格式，返回 InvalidInput
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let trimmed = tag.trim();
+    if trimmed.is_empty() || trimmed.contains([',', '\n', '\r']) {
+        return Err(DlogError::InvalidInput(format!("Invalid tag name: {:?}", tag)));
+    }
+    if config::flag(LOWERCASE_KEY, true) {
+        Ok(trimmed.to_lowercase())
+    } else {
+        Ok(trimmed.to_string())
+    }
+}
+
+
+/// 将标签列表合并为存储格式，列表为空时返回 None
+pub fn join_tags(tags: &[&str]) -> Option<String> {
+    if tags.is_empty() {
+        None
+    } else {
+        Some(tags.join(","))
+    }
+}
+
+/// 将标签字符串中与 `old` 完全相同的标签替换为 `new`，并去除由此产生的重复
+///
+/// 只匹配完整的标签，`test` 不会影响 `unit-test`。不包含 `old` 时返回 None。
+pub fn rename_tag(tags: &str, old: &str, new: &str) -> Option<String> {
+    let tokens = split_tags(tags);
+    if !tokens.contains(&old) {
+        return None;
+    }
+
+    let mut renamed: Vec<&str> = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let token = if token == old { new } else { token };
+        if !renamed.contains(&token) {
+            renamed.push(token);
+        }
+    }
+    join_tags(&renamed)
+}
+
+/// 从标签字符串中移除与 `name` 完全相同的标签，保留其余标签的顺序
+///
+/// 不包含 `name` 时返回 None；移除后没有剩余标签时返回 `Some(None)`。
+pub fn remove_tag(tags: &str, name: &str) -> Option<Option<String>> {
+    let tokens = split_tags(tags);
+    if !tokens.contains(&name) {
+        return None;
+    }
+    let remaining: Vec<&str> = tokens.into_iter().filter(|t| *t != name).collect();
+    Some(join_tags(&remaining))
+}
+
+/// 向标签字符串中添加 `add` 中的标签、移除 `remove` 中的标签，保留原有标签的顺序
+///
+/// 已存在的标签不会重复添加，新标签追加在末尾。结果与原标签相同时返回 None；
+/// 修改后没有剩余标签时返回 `Some(None)`。
+pub fn retag(tags: Option<&str>, add: &[String], remove: &[String]) -> Option<Option<String>> {
+    let tokens = split_tags(tags.unwrap_or(""));
+    let mut updated: Vec<&str> = tokens
+        .iter()
+        .copied()
+        .filter(|t| !remove.iter().any(|r| r == t))
+        .collect();
+    for tag in add {
+        if !updated.contains(&tag.as_str()) {
+            updated.push(tag);
+        }
+    }
+    if updated == tokens {
+        None
+    } else {
+        Some(join_tags(&updated))
+    }
+}
+
+/// 编辑器中标签行的前缀
+const FRONT_MATTER_PREFIX: &str = "Tags:";
+/// 编辑器中分隔标签行和日志内容的行
+pub const FRONT_MATTER_SEPARATOR: &str =
+    "# ^ tags (comma separated). The log content goes below this line. ^";
+
+/// 在编辑器的内容前加上标签行和分隔行，用户可以在编辑器中同时修改标签
+pub fn with_front_matter(tags: Option<&str>, content: &str) -> String {
+    let tags = split_tags(tags.unwrap_or("")).join(", ");
+    let line = if tags.is_empty() {
+        FRONT_MATTER_PREFIX.to_string()
+    } else {
+        format!("{} {}", FRONT_MATTER_PREFIX, tags)
+    };
+    format!("{}\n{}\n{}", line, FRONT_MATTER_SEPARATOR, content)
+}
+
+/// 从编辑器的内容中取出标签行，返回整理后的标签和去掉标签行后的内容
+///
+/// 只有前两行分别是标签行和分隔行时才作为标签处理，因此正文第一行以 `Tags:` 开头的日志
+/// 不受影响。标签行和分隔行都被删掉时表示清除标签，整个内容作为日志内容。
+/// 以下情况返回 InvalidInput，调用方应保留内容而不是把标签行写进日志：
+/// 分隔行还在但不在第二行、标签行前面多了内容，或者只删掉了分隔行
+/// （第一行以 `Tags:` 开头，而原内容 `original` 不是这样开头的）。
+pub fn parse_front_matter<'a>(buffer: &'a str, original: &str) -> Result<(Option<String>, &'a str)> {
+    let malformed = |reason: &str| {
+        DlogError::InvalidInput(format!("Could not read the tags header: {}", reason))
+    };
+    let (first, rest) = buffer.split_once('\n').unwrap_or((buffer, ""));
+    let first = first.trim_end_matches('\r');
+    let (second, body) = rest.split_once('\n').unwrap_or((rest, ""));
+    if second.trim_end_matches('\r') == FRONT_MATTER_SEPARATOR {
+        let tags = first
+            .strip_prefix(FRONT_MATTER_PREFIX)
+            .ok_or_else(|| malformed("the first line must start with 'Tags:'"))?;
+        return Ok((normalize_tags(tags)?, body));
+    }
+    if buffer.lines().any(|line| line == FRONT_MATTER_SEPARATOR) {
+        return Err(malformed("the separator line must directly follow the 'Tags:' line"));
+    }
+    if first.starts_with(FRONT_MATTER_PREFIX) && !original.starts_with(FRONT_MATTER_PREFIX) {
+        return Err(malformed("the separator line below 'Tags:' is missing"));
+    }
+    Ok((None, buffer))
+}
+
+/// 一个标签的使用情况，见 `usage_stats`
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct TagUsage {
+    pub tag: String,
+    pub total: usize,
+    /// 时间戳不早于 `recent_since` 的日志数
+    pub recent: usize,
+    /// 最早和最近一次使用的时间戳（RFC3339）
+    pub first_used: String,
+    pub last_used: String,
+    /// 带有该标签的日志最多的目录，数量相同时取排序在前的路径
+    pub top_directory: String,
+}
+
+/// 按标签汇总 `(标签, 时间戳, 目录)` 记录，带有多个标签的日志对每个标签各计一次
+///
+/// 结果按总数从多到少排列，总数相同时按标签名排列。
+pub fn usage_stats(rows: &[(String, String, String)], recent_since: &str) -> Vec<TagUsage> {
+    struct Acc<'a> {
+        total: usize,
+        recent: usize,
+        first: &'a str,
+        last: &'a str,
+        dirs: BTreeMap<&'a str, usize>,
+    }
+    let mut by_tag: BTreeMap<&str, Acc> = BTreeMap::new();
+    for (tags, timestamp, directory) in rows {
+        let unique: BTreeSet<&str> = split_tags(tags).into_iter().collect();
+        for tag in unique {
+            let acc = by_tag.entry(tag).or_insert_with(|| Acc {
+                total: 0,
+                recent: 0,
+                first: timestamp,
+                last: timestamp,
+                dirs: BTreeMap::new(),
+            });
+            acc.total += 1;
+            acc.recent += usize::from(timestamp.as_str() >= recent_since);
+            acc.first = acc.first.min(timestamp);
+            acc.last = acc.last.max(timestamp);
+            *acc.dirs.entry(directory).or_default() += 1;
+        }
+    }
+
+    let mut stats: Vec<TagUsage> = by_tag
+        .into_iter()
+        .map(|(tag, acc)| TagUsage {
+            tag: tag.to_string(),
+            total: acc.total,
+            recent: acc.recent,
+            first_used: acc.first.to_string(),
+            last_used: acc.last.to_string(),
+            // BTreeMap 按路径排序，max_by_key 在数量相同时取最后一个，因此反向遍历
+            top_directory: acc
+                .dirs
+                .iter()
+                .rev()
+                .max_by_key(|(_, count)| **count)
+                .map_or_else(String::new, |(dir, _)| dir.to_string()),
+        })
+        .collect();
+    stats.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.tag.cmp(&b.tag)));
+    stats
+}
+
+/// 统计出现在同一条日志中的标签对，按次数从多到少排列
+pub fn co_occurrence(rows: &[(String, String, String)]) -> Vec<((String, String), usize)> {
+    let mut pairs: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for (tags, _, _) in rows {
+        let unique: BTreeSet<&str> = split_tags(tags).into_iter().collect();
+        let unique: Vec<&str> = unique.into_iter().collect();
+        for (i, a) in unique.iter().enumerate() {
+            for b in &unique[i + 1..] {
+                *pairs.entry((a, b)).or_default() += 1;
+            }
+        }
+    }
+    let mut pairs: Vec<((String, String), usize)> = pairs
+        .into_iter()
+        .map(|((a, b), count)| ((a.to_string(), b.to_string()), count))
+        .collect();
+    pairs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_matches_whole_tags_only() {
+        assert_eq!(rename_tag("unit-test,testing", "test", "check"), None);
+        assert_eq!(rename_tag("test,unit-test", "test", "check").as_deref(), Some("check,unit-test"));
+        assert_eq!(rename_tag("a, bugifx ,b", "bugifx", "bugfix").as_deref(), Some("a,bugfix,b"));
+    }
+
+    #[test]
+    fn rename_drops_the_duplicate_it_creates() {
+        assert_eq!(rename_tag("bugifx,bugfix,ui", "bugifx", "bugfix").as_deref(), Some("bugfix,ui"));
+        assert_eq!(rename_tag("ui,bugfix,bugifx", "bugifx", "bugfix").as_deref(), Some("ui,bugfix"));
+    }
+
+    #[test]
+    fn remove_keeps_the_order_of_the_others() {
+        assert_eq!(remove_tag("wip,api,ui", "wip"), Some(Some("api,ui".to_string())));
+        assert_eq!(remove_tag("api,ui,wip", "wip"), Some(Some("api,ui".to_string())));
+        assert_eq!(remove_tag("api,wip,ui", "wip"), Some(Some("api,ui".to_string())));
+        assert_eq!(remove_tag("wip", "wip"), Some(None));
+        assert_eq!(remove_tag("api,wipe", "wip"), None);
+    }
+
+    #[test]
+    fn remove_cleans_up_sloppy_values() {
+        assert_eq!(remove_tag("wip, ,deploy", "wip"), Some(Some("deploy".to_string())));
+        assert_eq!(remove_tag(" wip ,,", "wip"), Some(None));
+    }
+
+    #[test]
+    fn messy_input_is_normalized() {
+        assert_eq!(normalize_tags(" Backend , backend,").unwrap().as_deref(), Some("backend"));
+        assert_eq!(normalize_tags("ops, API ,,ops , ui").unwrap().as_deref(), Some("ops,api,ui"));
+        assert_eq!(normalize_tags(" , ,").unwrap(), None);
+    }
+
+    #[test]
+    fn tags_that_break_the_storage_format_are_rejected() {
+        assert!(matches!(normalize_tags("ops,line\nbreak"), Err(DlogError::InvalidInput(_))));
+        assert!(matches!(normalize_tag("a,b"), Err(DlogError::InvalidInput(_))));
+        assert!(matches!(normalize_tag("  "), Err(DlogError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn front_matter_round_trips_a_genuine_tags_first_line() {
+        let original = "Tags: are hard to name\nNotes from the naming meeting\n";
+        let buffer = with_front_matter(Some("meeting"), original);
+        assert_eq!(parse_front_matter(&buffer, original).unwrap(), (Some("meeting".to_string()), original));
+
+        // 删掉整个标签头表示清除标签，正文原本的 Tags: 行保留为内容
+        assert_eq!(parse_front_matter(original, original).unwrap(), (None, original));
+        // 标签头中的修改按 -t 的规则整理
+        let edited = buffer.replacen("Tags: meeting", "Tags:  Ops , ops,naming", 1);
+        assert_eq!(parse_front_matter(&edited, original).unwrap(), (Some("ops,naming".to_string()), original));
+        let cleared = buffer.replacen("Tags: meeting", "Tags:", 1);
+        assert_eq!(parse_front_matter(&cleared, original).unwrap(), (None, original));
+        let crlf = buffer.replace('\n', "\r\n");
+        assert_eq!(parse_front_matter(&crlf, original).unwrap().0.as_deref(), Some("meeting"));
+    }
+
+    #[test]
+    fn malformed_front_matter_is_an_error_not_content() {
+        let original = "plain body";
+        let separator = FRONT_MATTER_SEPARATOR;
+        for buffer in [
+            format!("Tags: a\nextra line\n{}\nplain body", separator),
+            format!("note above\nTags: a\n{}\nplain body", separator),
+            format!("Tag: a\n{}\nplain body", separator),
+            "Tags: a\nplain body".to_string(),
+        ] {
+            assert!(matches!(parse_front_matter(&buffer, original), Err(DlogError::InvalidInput(_))), "{:?}", buffer);
+        }
+        // 原内容第一行就是 Tags: 时，只删掉分隔行也只能当作正文
+        let genuine = "Tags: a\nplain body";
+        assert_eq!(parse_front_matter(genuine, genuine).unwrap(), (None, genuine));
+    }
+}
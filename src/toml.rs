@@ -0,0 +1,214 @@
+// src/toml.rs
+
+use std::collections::BTreeSet;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// 配置文件中的值；dlog 的配置只用到字符串、布尔值、非负整数和字符串数组
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Bool(bool),
+    Integer(u64),
+    Array(Vec<String>),
+}
+
+impl Value {
+    /// 用于错误消息的类型名
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Bool(_) => "a boolean",
+            Value::Integer(_) => "an integer",
+            Value::Array(_) => "an array",
+        }
+    }
+}
+
+/// 解析错误：出错的行号和说明
+pub type ParseResult<T> = std::result::Result<T, (usize, String)>;
+
+/// 解析 TOML 中 dlog 用到的部分：顶层的 `键 = 值`，数组可以跨行，支持 `#` 注释
+///
+/// 返回每个键、所在行号和值。表、浮点数、日期和内联表都返回错误。
+pub fn parse(text: &str) -> ParseResult<Vec<(String, usize, Value)>> {
+    let mut cursor = Cursor { chars: text.chars().peekable(), line: 1 };
+    let mut seen = BTreeSet::new();
+    let mut entries = Vec::new();
+    loop {
+        cursor.skip_trivia(true);
+        let line = cursor.line;
+        let key = match cursor.chars.peek() {
+            None => break,
+            Some('[') => return Err((line, "tables are not supported".to_string())),
+            Some('"' | '\'') => cursor.string()?,
+            Some(_) => cursor.bare_key()?,
+        };
+        if !seen.insert(key.clone()) {
+            return Err((line, format!("duplicate key \"{}\"", key)));
+        }
+        cursor.skip_trivia(false);
+        cursor.expect('=')?;
+        cursor.skip_trivia(false);
+        let value = cursor.value()?;
+        cursor.skip_trivia(false);
+        match cursor.chars.next() {
+            None | Some('\n') => cursor.line += 1,
+            Some(c) => return Err((cursor.line, format!("unexpected '{}' after the value", c))),
+        }
+        entries.push((key, line, value));
+    }
+    Ok(entries)
+}
+
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Cursor<'_> {
+    /// 跳过空格、制表符和注释；`newlines` 为 true 时也跳过换行
+    fn skip_trivia(&mut self, newlines: bool) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                ' ' | '\t' | '\r' => {}
+                '\n' if newlines => self.line += 1,
+                '#' => {
+                    while self.chars.next_if(|c| *c != '\n').is_some() {}
+                    continue;
+                }
+                _ => break,
+            }
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> ParseResult<()> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some('\n') | None => Err((self.line, format!("expected '{}' but the line ended", expected))),
+            Some(c) => Err((self.line, format!("expected '{}' but found '{}'", expected, c))),
+        }
+    }
+
+    fn bare_key(&mut self) -> ParseResult<String> {
+        let mut key = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-')) {
+            key.push(c);
+        }
+        match self.chars.peek() {
+            _ if !key.is_empty() => Ok(key),
+            Some(c) => Err((self.line, format!("expected a key but found '{}'", c))),
+            None => Err((self.line, "expected a key".to_string())),
+        }
+    }
+
+    fn value(&mut self) -> ParseResult<Value> {
+        match self.chars.peek() {
+            Some('[') => self.string_array().map(Value::Array),
+            Some('"' | '\'') => self.string().map(Value::String),
+            Some(c) if c.is_ascii_alphanumeric() || matches!(c, '+' | '-') => self.bare_value(),
+            Some('\n') | None => Err((self.line, "expected a value but the line ended".to_string())),
+            Some(c) => Err((self.line, format!("expected a value but found '{}'", c))),
+        }
+    }
+
+    /// true、false 或十进制的非负整数（可以用 `_` 分隔数位）
+    fn bare_value(&mut self) -> ParseResult<Value> {
+        let mut word = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.')) {
+            word.push(c);
+        }
+        match word.as_str() {
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            _ => {}
+        }
+        let digits = word.strip_prefix('+').unwrap_or(&word);
+        let valid = digits.starts_with(|c: char| c.is_ascii_digit())
+            && !digits.ends_with('_')
+            && !digits.contains("__")
+            && digits.chars().all(|c| c.is_ascii_digit() || c == '_');
+        match digits.replace('_', "").parse() {
+            Ok(n) if valid => Ok(Value::Integer(n)),
+            _ if valid => Err((self.line, format!("{} is too large", word))),
+            _ if word.starts_with('-') => Err((self.line, format!("{} is negative", word))),
+            _ => Err((self.line, format!("invalid value \"{}\" (strings need quotes)", word))),
+        }
+    }
+
+    fn string_array(&mut self) -> ParseResult<Vec<String>> {
+        self.chars.next();
+        let mut values = Vec::new();
+        loop {
+            self.skip_trivia(true);
+            if self.chars.next_if_eq(&']').is_some() {
+                return Ok(values);
+            }
+            match self.chars.peek() {
+                Some('"' | '\'') => values.push(self.string()?),
+                Some(c) => return Err((self.line, format!("expected a string but found '{}'", c))),
+                None => return Err((self.line, "unterminated array".to_string())),
+            }
+            self.skip_trivia(true);
+            match self.chars.next() {
+                Some(',') => {}
+                Some(']') => return Ok(values),
+                Some(c) => return Err((self.line, format!("expected ',' or ']' but found '{}'", c))),
+                None => return Err((self.line, "unterminated array".to_string())),
+            }
+        }
+    }
+
+    /// 单行的基本字符串（`"..."`，支持常用转义）或字面字符串（`'...'`）
+    fn string(&mut self) -> ParseResult<String> {
+        let quote = self.chars.next().unwrap_or('"');
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some(c) if c == quote => return Ok(out),
+                Some('\\') if quote == '"' => match self.chars.next() {
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    Some('t') => out.push('\t'),
+                    Some(c) => return Err((self.line, format!("unsupported escape '\\{}'", c))),
+                    None => break,
+                },
+                Some('\n') | None => break,
+                Some(c) => out.push(c),
+            }
+        }
+        Err((self.line, "unterminated string".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_value_type() {
+        let text = "# comment\npager = \"less -R\" # trailing\nenabled = false\ntimeout = 1_000\ntags = [\n  'a',\n  \"b\",\n]\n";
+        let entries = parse(text).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ("pager".to_string(), 2, Value::String("less -R".to_string())),
+                ("enabled".to_string(), 3, Value::Bool(false)),
+                ("timeout".to_string(), 4, Value::Integer(1000)),
+                ("tags".to_string(), 5, Value::Array(vec!["a".to_string(), "b".to_string()])),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_the_line_of_an_error() {
+        assert_eq!(parse("a = 1\n\nb = less\n").unwrap_err().0, 3);
+        assert_eq!(parse("a = -1\n").unwrap_err(), (1, "-1 is negative".to_string()));
+        assert_eq!(parse("a = 1\na = 2\n").unwrap_err(), (2, "duplicate key \"a\"".to_string()));
+        assert!(parse("[table]\n").is_err());
+        assert!(parse("a = 1.5\n").is_err());
+        assert!(parse("a = \"open\n").is_err());
+        assert!(parse("a = true false\n").is_err());
+    }
+}
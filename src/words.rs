@@ -0,0 +1,84 @@
+// src/words.rs
+
+/// 一段文本的词数和字数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextCount {
+    pub words: usize,
+    /// 不含空白的字符数
+    pub chars: usize,
+}
+
+/// 统计文本的词数和字数
+///
+/// 字母和数字连在一起算作一个词，词中间的撇号和连字符（如 don't、well-known）不断开；
+/// 汉字、平假名和片假名不以空格分词，每个字算作一个词，与常见的字数统计方式一致。
+/// 标点和符号不算作词，但计入字数。
+pub fn count(text: &str) -> TextCount {
+    let mut result = TextCount::default();
+    let mut in_word = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            in_word = false;
+            continue;
+        }
+        result.chars += 1;
+        if is_cjk(c) {
+            result.words += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                result.words += 1;
+                in_word = true;
+            }
+        } else if in_word && matches!(c, '\'' | '’' | '-') {
+            // 只有后面紧跟字母或数字时才算作词的一部分
+            in_word = chars.peek().is_some_and(|next| next.is_alphanumeric() && !is_cjk(*next));
+        } else {
+            in_word = false;
+        }
+    }
+    result
+}
+
+/// 不以空格分词的中日文字符：CJK 统一汉字（含扩展区和兼容汉字）、平假名和片假名
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF       // 平假名、片假名
+        | 0x3400..=0x4DBF     // 扩展 A
+        | 0x4E00..=0x9FFF     // 基本区
+        | 0xF900..=0xFAFF     // 兼容汉字
+        | 0x20000..=0x3134F)  // 扩展 B 到 G
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counted(text: &str) -> (usize, usize) {
+        let c = count(text);
+        (c.words, c.chars)
+    }
+
+    #[test]
+    fn latin_words_keep_inner_apostrophes_and_hyphens() {
+        assert_eq!(counted("Don't panic: a well-known rule."), (5, 27));
+        assert_eq!(counted("rock 'n' roll -- twice"), (4, 18));
+        assert_eq!(counted("trailing- and ’quoted’"), (3, 20));
+        assert_eq!(counted("Größe über 100 Meter"), (4, 17));
+        assert_eq!(counted("  \n\t "), (0, 0));
+    }
+
+    #[test]
+    fn cjk_characters_count_as_words_one_by_one() {
+        assert_eq!(counted("修复了连接池"), (6, 6));
+        assert_eq!(counted("修复了 TLS 超时。"), (6, 9));
+        assert_eq!(counted("日本語のメモ"), (6, 6));
+        // 汉字与拉丁字母、数字相连时各自计数，后面的撇号不会把下一个汉字并入词中
+        assert_eq!(counted("v2版本"), (3, 4));
+        assert_eq!(counted("dlog'日志"), (3, 7));
+        assert_eq!(counted("𠀀𪜀"), (2, 2));
+        // 韩文以空格分词，按普通的词计数
+        assert_eq!(counted("로그 기록"), (2, 4));
+    }
+}
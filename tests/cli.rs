@@ -0,0 +1,858 @@
+// tests/cli.rs
+
+//! 以子进程运行 dlog 的端到端测试；每个测试使用独立的临时主目录，不会读写真实的数据库
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// 一个临时主目录，数据库和配置文件都在其中
+struct Home {
+    dir: tempfile::TempDir,
+}
+
+impl Home {
+    fn new() -> Self {
+        Home { dir: tempfile::tempdir().unwrap() }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// 在临时主目录中运行 dlog 的命令，标准输入默认为空
+    fn dlog(&self, args: &[&str]) -> Command {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_dlog"));
+        cmd.args(args)
+            .current_dir(self.path())
+            .env("HOME", self.path())
+            .env("NO_COLOR", "1")
+            .env("TZ", "UTC")
+            .stdin(Stdio::null());
+        for (name, _) in std::env::vars() {
+            if name.starts_with("DLOG_") || name.starts_with("XDG_") || name == "PAGER" || name == "EDITOR" {
+                cmd.env_remove(name);
+            }
+        }
+        cmd
+    }
+
+    /// 运行命令并等待结束
+    fn run(&self, args: &[&str]) -> Output {
+        self.dlog(args).output().unwrap()
+    }
+
+    /// 运行命令，把 `input` 写入它的标准输入
+    fn run_with_input(&self, args: &[&str], input: &str) -> Output {
+        let mut child =
+            self.dlog(args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    /// 运行必须成功的命令，返回标准输出
+    fn ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(output.status.success(), "dlog {:?} failed: {}", args, stderr(&output));
+        stdout(&output)
+    }
+
+    /// 写入 ~/.config/dlog/config.toml
+    fn write_config(&self, text: &str) {
+        let dir = self.path().join(".config/dlog");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("config.toml"), text).unwrap();
+    }
+
+    fn db_path(&self) -> PathBuf {
+        self.path().join(".local/share/dlog/dlog.db")
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn log_reads_piped_stdin() {
+    let home = Home::new();
+    let output = home.run_with_input(&["log"], "from a pipe\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(home.ok(&["get", "--field", "content"]).trim_end(), "from a pipe");
+}
+
+#[test]
+fn log_without_content_fails_instead_of_opening_an_editor() {
+    let home = Home::new();
+    // 标准输入为空（/dev/null）或已关闭时都不能等待编辑器
+    let empty = home.run(&["log"]);
+    assert_eq!(empty.status.code(), Some(1));
+    assert!(stderr(&empty).contains("Not running in an interactive terminal"), "{}", stderr(&empty));
+
+    let mut closed = home.dlog(&["log"]).stdin(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    drop(closed.stdin.take());
+    let closed = closed.wait_with_output().unwrap();
+    assert_eq!(closed.status.code(), Some(1));
+    assert!(!home.db_path().exists(), "nothing should have been logged");
+}
+
+#[test]
+fn confirmation_prompts_fail_without_a_terminal() {
+    let home = Home::new();
+    home.ok(&["log", "-m", "keep me"]);
+
+    let del = home.run(&["del", "1"]);
+    assert_eq!(del.status.code(), Some(1));
+    assert!(stderr(&del).contains("pass --yes"), "{}", stderr(&del));
+    assert_eq!(home.ok(&["get", "--field", "id"]), "1\n");
+
+    home.ok(&["del", "1", "--yes"]);
+    assert_eq!(home.run(&["get", "--field", "id"]).stdout, b"");
+}
+
+#[test]
+fn fix_refuses_to_open_an_editor_without_a_terminal() {
+    let home = Home::new();
+    home.ok(&["log", "-m", "original"]);
+    let fix = home.run_with_input(&["fix", "1"], "");
+    assert_eq!(fix.status.code(), Some(1));
+    assert!(stderr(&fix).contains("cannot open an editor"), "{}", stderr(&fix));
+    assert_eq!(home.ok(&["get", "--field", "content"]), "original\n");
+}
+
+#[test]
+fn init_reports_an_existing_database() {
+    let home = Home::new();
+    assert!(home.ok(&["init", "--yes"]).contains("Database initialized successfully"));
+    home.ok(&["log", "-m", "one"]);
+    let again = home.ok(&["init", "--yes"]);
+    assert!(again.contains("Database already initialized"), "{}", again);
+    assert!(again.contains("1 logs present"), "{}", again);
+}
+
+#[test]
+fn commands_on_a_missing_database_ask_for_init() {
+    let home = Home::new();
+    let output = home.run(&["fix", "1", "-m", "x"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("run 'dlog init' first"), "{}", stderr(&output));
+}
+
+#[test]
+fn completions_work_before_the_database_exists() {
+    let home = Home::new();
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let script = home.ok(&["completions", shell]);
+        assert!(script.contains("dlog"), "{} script:\n{}", shell, script);
+    }
+    assert!(!home.db_path().exists());
+}
+
+#[test]
+fn import_keeps_original_timestamps_and_jrnl_tags() {
+    let home = Home::new();
+    let journal = home.path().join("journal.txt");
+    std::fs::write(&journal, "[2021-03-04 09:12] Title @ops\nbody\n[2021-03-05 10:00]\n").unwrap();
+    let summary = home.ok(&["import", "--format", "jrnl", journal.to_str().unwrap()]);
+    assert!(summary.contains("Parsed 2, imported 1"), "{}", summary);
+    assert_eq!(home.ok(&["get", "--field", "timestamp"]), "2021-03-04T09:12:00.000Z\n");
+    assert_eq!(home.ok(&["get", "--field", "tags"]), "ops\n");
+
+    let notes = home.path().join("notes");
+    std::fs::create_dir(&notes).unwrap();
+    std::fs::write(notes.join("2021-03-06.txt"), "Walked the dog.\n").unwrap();
+    let file = notes.join("2021-03-06.txt");
+    let dry_run = home.ok(&["import", "--format", "plain", "--date-from-filename", "--dry-run", file.to_str().unwrap()]);
+    assert!(dry_run.contains("2021-03-06 00:00  Walked the dog."), "{}", dry_run);
+    assert_eq!(home.ok(&["get", "--field", "id"]), "1\n", "dry run must not import");
+}
+
+#[cfg(unix)]
+#[test]
+fn entries_logged_through_a_symlink_are_found_from_the_real_path() {
+    let home = Home::new();
+    let real = home.path().join("checkout");
+    std::fs::create_dir(&real).unwrap();
+    let link = home.path().join("link");
+    std::os::unix::fs::symlink(&real, &link).unwrap();
+
+    let through_link = format!("{}/.", link.display());
+    home.ok(&["log", "--dir", &through_link, "-m", "via the link"]);
+    assert_eq!(home.ok(&["get", real.to_str().unwrap(), "--field", "content"]), "via the link\n");
+    assert_eq!(home.ok(&["get", link.to_str().unwrap(), "--field", "directory"]), format!("{}\n", real.display()));
+}
+
+#[cfg(unix)]
+#[test]
+fn doctor_fix_renormalizes_legacy_symlinked_rows() {
+    let home = Home::new();
+    let real = home.path().join("checkout");
+    std::fs::create_dir(&real).unwrap();
+    let link = home.path().join("link");
+    std::os::unix::fs::symlink(&real, &link).unwrap();
+    home.ok(&["log", "--dir", real.to_str().unwrap(), "-m", "legacy"]);
+    // 较早的版本按原样保存了经过符号链接的路径
+    let conn = rusqlite::Connection::open(home.db_path()).unwrap();
+    conn.execute("UPDATE logs SET directory = ?", [link.to_str().unwrap()]).unwrap();
+    drop(conn);
+
+    let report = home.run(&["doctor"]);
+    assert!(stdout(&report).contains("not normalized"), "{}", stdout(&report));
+    let fixed = home.ok(&["doctor", "--fix", "--yes"]);
+    assert!(fixed.contains("(1 logs fixed)"), "{}", fixed);
+    assert_eq!(home.ok(&["get", "--all", "--field", "directory"]), format!("{}\n", real.display()));
+}
+
+#[test]
+fn messy_tags_round_trip_through_log_and_get() {
+    let home = Home::new();
+    home.ok(&["log", "-m", "tagged", "-t", " Backend , backend,"]);
+    assert_eq!(home.ok(&["get", "--field", "tags"]), "backend\n");
+    assert_eq!(home.ok(&["get", "-t", " BACKEND ", "--field", "content"]), "tagged\n");
+
+    let bad = home.run(&["log", "-m", "x", "-t", "a\nb"]);
+    assert_eq!(bad.status.code(), Some(1));
+    assert!(stderr(&bad).contains("Invalid tag name"), "{}", stderr(&bad));
+}
+
+#[test]
+fn lowercase_tags_can_be_turned_off_in_the_config_file() {
+    let home = Home::new();
+    home.write_config("lowercase_tags = false\n");
+    home.ok(&["log", "-m", "kept", "-t", " Backend ,API"]);
+    assert_eq!(home.ok(&["get", "--field", "tags"]), "Backend,API\n");
+
+    let overridden = home.dlog(&["log", "-m", "lowered", "-t", "Ops"]).env("DLOG_LOWERCASE_TAGS", "1").output().unwrap();
+    assert!(overridden.status.success());
+    assert_eq!(home.ok(&["get", "-n", "1", "--field", "tags"]), "ops\n");
+}
+
+#[test]
+fn recover_lists_and_logs_saved_buffers() {
+    let home = Home::new();
+    assert_eq!(home.ok(&["recover"]), "No saved buffers.\n");
+    let dir = home.path().join(".config/dlog/recovery");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("20260301-093000.md"), "Lost note\nwith a body\n").unwrap();
+
+    let listing = home.ok(&["recover"]);
+    assert!(listing.contains("20260301-093000  Lost note"), "{}", listing);
+    home.ok(&["recover", "20260301-093000"]);
+    assert_eq!(home.ok(&["get", "--field", "content"]), "Lost note\nwith a body\n\n");
+    assert!(!dir.join("20260301-093000.md").exists());
+    assert!(!home.run(&["recover", "../dlog"]).status.success());
+}
+
+#[test]
+fn ids_only_output_feeds_back_into_id_lists() {
+    let home = Home::new();
+    let sub = home.path().join("sub");
+    std::fs::create_dir(&sub).unwrap();
+    home.ok(&["log", "-m", "one", "-t", "tmp"]);
+    home.ok(&["log", "-m", "two"]);
+    home.ok(&["log", "--dir", sub.to_str().unwrap(), "-m", "three", "-t", "tmp"]);
+    home.ok(&["log", "-m", "four", "-t", "tmp"]);
+
+    let ids = home.ok(&["get", "-r", "-t", "tmp", "--ids-only", "-n", "0", "--color", "always"]);
+    assert_eq!(ids, "4\n3\n1\n");
+    // 与 `tr '\n' ','` 的结果相同，末尾多一个逗号
+    let list = ids.replace('\n', ",");
+    assert_eq!(home.ok(&["get", "--ids", &list, "--field", "content"]), "four\nthree\none\n");
+    assert_eq!(home.ok(&["get", "-r", "-t", "tmp", "--ids-only", "-n", "2"]), "4\n3\n");
+
+    home.ok(&["del", &list, "--yes"]);
+    assert_eq!(home.ok(&["get", "-r", "--field", "content"]), "two\n");
+    let none = home.run(&["get", "-r", "-t", "tmp", "--ids-only"]);
+    assert_eq!(none.status.code(), Some(3));
+    assert_eq!(none.stdout, b"");
+}
+
+/// 记录一条日志并把它的时间改为 `timestamp`（UTC，与 TZ=UTC 下的本地时间相同）
+fn log_at(home: &Home, timestamp: &str, content: &str, tags: Option<&str>) {
+    let mut args = vec!["log", "-m", content];
+    if let Some(tags) = tags {
+        args.extend(["-t", tags]);
+    }
+    home.ok(&args);
+    let conn = rusqlite::Connection::open(home.db_path()).unwrap();
+    conn.execute("UPDATE logs SET timestamp = ? WHERE id = (SELECT MAX(id) FROM logs)", [timestamp]).unwrap();
+}
+
+#[test]
+fn month_filter_covers_december_and_leap_february() {
+    let home = Home::new();
+    log_at(&home, "2023-11-30T23:59:59.999Z", "november", None);
+    log_at(&home, "2023-12-01T00:00:00.000Z", "first of december", Some("work"));
+    log_at(&home, "2023-12-31T23:59:59.999Z", "new year's eve", Some("work"));
+    log_at(&home, "2024-01-01T00:00:00.000Z", "new year", Some("work"));
+    log_at(&home, "2023-02-28T23:00:00.000Z", "end of february 2023", None);
+    log_at(&home, "2023-03-01T00:00:00.000Z", "march 2023", None);
+    log_at(&home, "2024-02-29T12:00:00.000Z", "leap day", Some("work"));
+    log_at(&home, "2024-03-01T00:00:00.000Z", "march 2024", None);
+
+    let month = |args: &[&str]| home.ok(&[&["get", "--field", "content"], args].concat());
+    assert_eq!(month(&["--month", "2023-12"]), "new year's eve\nfirst of december\n");
+    assert_eq!(month(&["--month", "2024-01"]), "new year\n");
+    assert_eq!(month(&["--month", "2024-02"]), "leap day\n");
+    assert_eq!(month(&["--month", "2023-02"]), "end of february 2023\n");
+    assert_eq!(month(&["--month", "2023-12", "-t", "work"]), "new year's eve\nfirst of december\n");
+    assert_eq!(month(&["--month", "2024-1", "-t", "work"]), "new year\n");
+    assert_eq!(month(&["--month", "2023-12", "-s", "first"]), "first of december\n");
+    assert_eq!(month(&["--month", "2024-02", "-t", "work", "-s", "leap"]), "leap day\n");
+    assert_eq!(home.run(&["get", "--month", "2023-12", "-s", "leap"]).status.code(), Some(3));
+
+    let bad = home.run(&["get", "--month", "2024-13"]);
+    assert_eq!(bad.status.code(), Some(1));
+    assert!(stderr(&bad).contains("Invalid month '2024-13'. Use YYYY-MM or MM."), "{}", stderr(&bad));
+    assert!(!home.run(&["get", "--month", "2023-12", "--date", "2023-12-01"]).status.success());
+}
+
+#[test]
+fn between_filters_by_local_time_of_day_and_wraps_midnight() {
+    let home = Home::new();
+    log_at(&home, "2024-06-01T21:59:59.999Z", "before the night", None);
+    log_at(&home, "2024-06-01T22:00:00.000Z", "late", None);
+    log_at(&home, "2024-06-01T23:30:00.000Z", "night", Some("ops"));
+    log_at(&home, "2024-06-02T01:59:00.000Z", "small hours", None);
+    log_at(&home, "2024-06-02T02:00:00.000Z", "two o'clock", None);
+    log_at(&home, "2024-06-02T09:00:00.000Z", "morning", Some("ops"));
+    log_at(&home, "2024-06-02T11:59:59.000Z", "before noon", None);
+    log_at(&home, "2024-06-02T12:00:00.000Z", "noon", None);
+
+    let get = |args: &[&str]| home.ok(&[&["get", "-n", "0", "--field", "content"], args].concat());
+    assert_eq!(get(&["--date", "2024-06-02", "--between", "09:00-12:00"]), "before noon\nmorning\n");
+    assert_eq!(get(&["--between", "22:00-02:00"]), "small hours\nnight\nlate\n");
+    assert_eq!(get(&["--between", "22:00-02:00", "--date", "2024-06-02"]), "small hours\n");
+    assert_eq!(get(&["--between", "22:00-02:00", "-t", "ops"]), "night\n");
+    assert_eq!(get(&["--between", "00:00-12:00", "-t", "ops", "-s", "morn"]), "morning\n");
+    assert_eq!(home.run(&["get", "--between", "09:00-12:00", "--since", "1d"]).status.code(), Some(3));
+
+    // 按本地时间比较：UTC+9 的 18:00-21:00 是 UTC 的 09:00-12:00
+    let tokyo = home.dlog(&["get", "--between", "18:00-21:00", "--field", "content"]).env("TZ", "JST-9").output().unwrap();
+    assert_eq!(stdout(&tokyo), "before noon\nmorning\n");
+
+    for bad in ["09:00-09:00", "9-12", "09:00", "25:00-26:00"] {
+        let output = home.run(&["get", "--between", bad]);
+        assert_eq!(output.status.code(), Some(1), "{}", bad);
+    }
+    assert!(stderr(&home.run(&["get", "--between", "9-12"])).contains("Use HH:MM-HH:MM"));
+}
+
+#[test]
+fn search_ands_words_and_keeps_quoted_phrases() {
+    let home = Home::new();
+    home.ok(&["log", "-m", "timeout during TLS handshake"]);
+    home.ok(&["log", "-m", "connection refused by docker"]);
+    home.ok(&["log", "-m", "docker refused the connection"]);
+
+    assert_eq!(home.ok(&["get", "-s", "tls timeout", "--field", "id"]), "1\n");
+    assert_eq!(home.ok(&["get", "-s", "refused docker", "--field", "id"]), "3\n2\n");
+    assert_eq!(home.ok(&["get", "-s", "\"connection refused\" docker", "--field", "id"]), "2\n");
+    assert_eq!(home.run(&["get", "-s", "\"tls timeout\""]).status.code(), Some(3));
+    for empty in ["", "  ", "\"\""] {
+        let output = home.run(&["get", "-s", empty]);
+        assert_eq!(output.status.code(), Some(1), "{:?}", empty);
+        assert!(stderr(&output).contains("Search keyword is empty."), "{}", stderr(&output));
+    }
+}
+
+#[test]
+fn exclude_composes_with_search_tags_and_dates() {
+    let home = Home::new();
+    log_at(&home, "2024-05-02T09:00:00.000Z", "daily standup", None);
+    log_at(&home, "2024-05-02T10:00:00.000Z", "deploy after standup", Some("ops"));
+    log_at(&home, "2024-05-02T11:00:00.000Z", "deploy hotfix", Some("ops"));
+    log_at(&home, "2024-05-03T09:00:00.000Z", "untagged note", None);
+    log_at(&home, "2024-05-03T10:00:00.000Z", "retro", Some("standup"));
+
+    let get = |args: &[&str]| home.ok(&[&["get", "-n", "0", "--field", "content"], args].concat());
+    assert_eq!(get(&["--exclude", "standup"]), "untagged note\ndeploy hotfix\n");
+    assert_eq!(get(&["--exclude", "standup", "--exclude", "hotfix"]), "untagged note\n");
+    assert_eq!(get(&["-s", "deploy", "--exclude", "standup"]), "deploy hotfix\n");
+    assert_eq!(get(&["-t", "ops", "--exclude", "HOTFIX"]), "deploy after standup\n");
+    assert_eq!(get(&["--date", "2024-05-03", "--exclude", "standup"]), "untagged note\n");
+    assert_eq!(home.run(&["get", "-s", "standup", "--exclude", "standup"]).status.code(), Some(3));
+    assert_eq!(home.run(&["get", "--exclude", " "]).status.code(), Some(1));
+}
+
+#[cfg(unix)]
+#[test]
+fn post_log_hook_receives_the_entry_and_never_fails_the_log() {
+    let home = Home::new();
+    let out = home.path().join("hook-out");
+    std::fs::create_dir(&out).unwrap();
+    let script = home.path().join("hook.sh");
+    std::fs::write(
+        &script,
+        format!("env | grep '^DLOG_' | sort > '{0}/env-'\"$DLOG_ID\"\ncat > '{0}/stdin-'\"$DLOG_ID\"\n", out.display()),
+    )
+    .unwrap();
+    home.write_config(&format!("post_log_hook = \"sh {}\"\n", script.display()));
+
+    home.ok(&["log", "-m", "first line", "-m", "second line", "-t", "ops,deploy"]);
+    let env = std::fs::read_to_string(out.join("env-1")).unwrap();
+    let timestamp = home.ok(&["get", "--field", "timestamp"]);
+    let expected = format!(
+        "DLOG_DIR={}\nDLOG_ID=1\nDLOG_LEVEL=normal\nDLOG_TAGS=ops,deploy\nDLOG_TIMESTAMP={}DLOG_TODO=0\n",
+        home.path().canonicalize().unwrap().display(),
+        timestamp
+    );
+    assert_eq!(env, expected);
+    assert_eq!(std::fs::read_to_string(out.join("stdin-1")).unwrap(), "first line\n\nsecond line");
+
+    // 跳过的、空的和 --no-hook 的日志都不执行钩子
+    let duplicate = home.run(&["log", "-m", "first line", "-m", "second line"]);
+    assert!(stderr(&duplicate).contains("skipped"), "{}", stderr(&duplicate));
+    assert!(stderr(&home.run(&["log", "-m", "  "])).contains("Empty log, skipped."));
+    home.ok(&["log", "-m", "quiet one", "--no-hook"]);
+    let written: Vec<_> = std::fs::read_dir(&out).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(written.len(), 2, "{:?}", written);
+
+    // 钩子失败或超时只给出警告，日志已经保存
+    home.write_config("post_log_hook = \"exit 4\"\n");
+    let failed = home.run(&["log", "-m", "hook fails"]);
+    assert!(failed.status.success());
+    assert!(stderr(&failed).contains("Warning: post-log hook 'exit 4' failed"), "{}", stderr(&failed));
+
+    // sleep 是 shell 启动的子进程，超时后也要一起终止，否则它会一直占用 dlog 的输出
+    home.write_config("post_log_hook = \"sleep 30; true\"\nhook_timeout = 1\n");
+    let started = std::time::Instant::now();
+    let hung = home.run(&["log", "-m", "hook hangs"]);
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    assert!(hung.status.success());
+    assert!(stderr(&hung).contains("timed out after 1s"), "{}", stderr(&hung));
+    assert_eq!(home.ok(&["get", "-n", "2", "--field", "content"]), "hook hangs\nhook fails\n");
+}
+
+#[test]
+fn renumber_backs_up_and_saves_the_mapping() {
+    let home = Home::new();
+    log_at(&home, "2024-03-01T00:00:00.000Z", "third", None);
+    log_at(&home, "2024-01-01T00:00:00.000Z", "first", None);
+    log_at(&home, "2024-02-01T00:00:00.000Z", "second", None);
+    home.ok(&["log", "-m", "gone"]);
+    home.ok(&["del", "4", "--yes"]);
+
+    let dry_run = home.ok(&["renumber", "--dry-run"]);
+    assert!(dry_run.contains("3 of 3 log(s) will get a new ID"), "{}", dry_run);
+    assert!(dry_run.contains("  2 -> 1\n  3 -> 2\n  1 -> 3\n"), "{}", dry_run);
+    let refused = home.run(&["renumber"]);
+    assert_eq!(refused.status.code(), Some(1));
+    assert!(stderr(&refused).contains("pass --yes"), "{}", stderr(&refused));
+    assert_eq!(home.ok(&["get", "--field", "id"]), "1\n3\n2\n", "nothing may change before confirming");
+
+    let map = home.path().join("map.tsv");
+    let done = home.ok(&["renumber", "--yes", "--map-file", map.to_str().unwrap()]);
+    assert!(done.contains("✓ Renumbered 3 log(s)"), "{}", done);
+    assert_eq!(std::fs::read_to_string(&map).unwrap(), "2\t1\n3\t2\n1\t3\n");
+    assert_eq!(home.ok(&["get", "--field", "id"]), "3\n2\n1\n");
+    assert_eq!(home.ok(&["get", "--ids", "1", "--field", "content"]), "first\n");
+
+    let backups: Vec<_> = std::fs::read_dir(home.db_path().parent().unwrap())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("dlog.before-renumber-"))
+        .collect();
+    assert_eq!(backups.len(), 1, "{:?}", backups);
+    assert_eq!(home.ok(&["renumber"]), "Log IDs are already contiguous and in time order.\n");
+}
+
+#[test]
+fn quoted_tilde_and_variables_in_paths_are_expanded() {
+    let home = Home::new();
+    let project = home.path().join("projects/foo");
+    std::fs::create_dir_all(&project).unwrap();
+    let elsewhere = home.path().join("elsewhere");
+    std::fs::create_dir(&elsewhere).unwrap();
+
+    let log = home.dlog(&["log", "--dir", "~/projects/foo", "-m", "in foo"]).current_dir(&elsewhere).output().unwrap();
+    assert!(log.status.success(), "{}", stderr(&log));
+    assert_eq!(home.ok(&["get", "~/projects/foo", "--field", "content"]), "in foo\n");
+    assert_eq!(home.ok(&["get", "$HOME/projects/foo", "--field", "content"]), "in foo\n");
+    let braced = home.dlog(&["get", "${WORK}/foo", "--field", "content"]).env("WORK", project.parent().unwrap()).output();
+    assert_eq!(stdout(&braced.unwrap()), "in foo\n");
+
+    let unset = home.run(&["get", "$DLOG_NOT_SET_ANYWHERE/foo"]);
+    assert_eq!(unset.status.code(), Some(1));
+    assert!(stderr(&unset).contains("$DLOG_NOT_SET_ANYWHERE in path"), "{}", stderr(&unset));
+}
+
+#[test]
+fn search_is_case_insensitive_beyond_ascii_unless_asked() {
+    let home = Home::new();
+    home.ok(&["log", "-m", "Über die Brücke", "-t", "Straße"]);
+    home.ok(&["log", "-m", "über den Fluß"]);
+    home.ok(&["log", "-m", "数据库连接超时"]);
+
+    assert_eq!(home.ok(&["get", "-s", "über", "--field", "id"]), "2\n1\n");
+    assert_eq!(home.ok(&["get", "-s", "über", "--case-sensitive", "--field", "id"]), "2\n");
+    assert_eq!(home.run(&["get", "-s", "STRASSE"]).status.code(), Some(3));
+    assert_eq!(home.ok(&["get", "-s", "STRAẞE", "--field", "id"]), "1\n");
+    assert_eq!(home.ok(&["get", "-t", "STRAßE", "--field", "id"]), "1\n");
+    assert_eq!(home.ok(&["get", "-s", "连接", "--field", "content"]), "数据库连接超时\n");
+    assert_eq!(home.ok(&["get", "-s", "连接", "--case-sensitive", "--field", "id"]), "3\n");
+}
+
+#[test]
+fn grep_prints_matching_lines_with_context_and_grep_exit_codes() {
+    let home = Home::new();
+    home.ok(&["log", "-m", "标题\n连接池耗尽\n重启之后\n恢复正常\n\n连接池又满了"]);
+    home.ok(&["log", "-m", "Überlauf im Puffer\nnichts weiter"]);
+    let conn = rusqlite::Connection::open(home.db_path()).unwrap();
+    let sql = "INSERT INTO logs (timestamp, directory, content) SELECT timestamp, directory, ? FROM logs WHERE id = 1";
+    conn.execute(sql, ["PNG\0连接池"]).unwrap();
+    drop(conn);
+
+    let out = home.ok(&["grep", "连接池", "-C", "1"]);
+    // 二进制内容只提示匹配，不逐行输出；每条日志的标题只出现一次
+    assert!(out.starts_with("Log #3: binary content matches\n\n[1] "), "{}", out);
+    assert!(out.ends_with("\n1-1- 标题\n1:2: 连接池耗尽\n1-3- 重启之后\n--\n1-5- \n1:6: 连接池又满了\n"), "{}", out);
+    assert!(!out.contains("PNG"), "{}", out);
+
+    assert!(home.ok(&["grep", "-i", "ÜBERLAUF"]).contains("2:1: Überlauf im Puffer\n"));
+    assert!(home.ok(&["grep", "-E", "^恢复.常$"]).contains("1:4: 恢复正常\n"));
+    let none = home.run(&["grep", "überlauf"]);
+    assert_eq!(none.status.code(), Some(3));
+    assert_eq!(stdout(&none), "No matches found.\n");
+    assert_eq!(home.run(&["grep", "-E", "(unclosed"]).status.code(), Some(1));
+}
+
+/// 在 `dir` 中运行 git，提交的作者和时间由参数指定，不读取用户的 git 配置
+fn git(dir: &Path, args: &[&str], author: &str, date: &str) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_AUTHOR_NAME", author)
+        .env("GIT_AUTHOR_EMAIL", format!("{}@example.com", author))
+        .env("GIT_COMMITTER_NAME", author)
+        .env("GIT_COMMITTER_EMAIL", format!("{}@example.com", author))
+        .env("GIT_AUTHOR_DATE", date)
+        .env("GIT_COMMITTER_DATE", date)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed: {}", args, stderr(&output));
+    stdout(&output)
+}
+
+#[test]
+fn git_history_imports_once_per_commit() {
+    let home = Home::new();
+    let repo = home.path().join("repo");
+    std::fs::create_dir_all(repo.join("sub")).unwrap();
+    let commit = |message: &[&str], author: &str, date: &str| {
+        let mut args = vec!["commit", "-q", "--allow-empty"];
+        for paragraph in message {
+            args.extend(["-m", paragraph]);
+        }
+        git(&repo, &args, author, date);
+    };
+    git(&repo, &["init", "-q", "-b", "main"], "alice", "2023-01-01T00:00:00Z");
+    commit(&["First commit", "With a body"], "alice", "2023-01-05T10:00:00+02:00");
+    git(&repo, &["checkout", "-q", "-b", "feature"], "bob", "2023-02-01T00:00:00Z");
+    commit(&["Feature work"], "bob", "2023-02-01T12:00:00Z");
+    git(&repo, &["checkout", "-q", "main"], "alice", "2023-02-02T00:00:00Z");
+    commit(&["Main work"], "alice", "2023-02-02T12:00:00Z");
+    git(&repo, &["merge", "-q", "--no-ff", "feature", "-m", "Merge feature"], "alice", "2023-02-03T12:00:00Z");
+    let first_hash = git(&repo, &["log", "--format=%h", "--reverse"], "alice", "2023-01-01T00:00:00Z");
+    let first_hash = first_hash.lines().next().unwrap().to_string();
+
+    // 在仓库的子目录中运行，日志记录到仓库根目录
+    let import = |args: &[&str]| {
+        let output = home.dlog(&[&["import", "--from-git"], args].concat()).current_dir(repo.join("sub")).output();
+        let output = output.unwrap();
+        assert!(output.status.success(), "{}", stderr(&output));
+        stdout(&output)
+    };
+    let since = import(&["--since", "2023-02-02", "--dry-run"]);
+    assert!(since.contains("Dry run: parsed 2, would import 2"), "{}", since);
+    assert!(!home.db_path().exists(), "a dry run must not create the database");
+
+    let mine = import(&["--author", "alice", "--no-merges"]);
+    assert!(mine.contains("Parsed 2, imported 2"), "{}", mine);
+    let in_repo = |field: &str| home.ok(&["get", repo.to_str().unwrap(), "-n", "0", "--field", field]);
+    assert_eq!(in_repo("timestamp"), "2023-02-02T12:00:00.000Z\n2023-01-05T08:00:00.000Z\n");
+    assert_eq!(in_repo("tags"), "git\ngit\n");
+    let first = home.ok(&["get", "--all", "--ids", "1", "--field", "content"]);
+    assert_eq!(first, format!("First commit\n\nWith a body\n\ncommit {}\n", first_hash));
+    assert_eq!(home.ok(&["get", "--all", "--ids", "1", "--field", "directory"]), format!("{}\n", repo.display()));
+
+    // 再次导入时跳过已经记录的提交，只导入之前被过滤掉的两个
+    let rest = import(&[]);
+    assert!(rest.contains("Parsed 4, imported 2"), "{}", rest);
+    assert!(rest.contains("skipped 2"), "{}", rest);
+    let again = import(&[]);
+    assert!(again.contains("Parsed 4, imported 0"), "{}", again);
+    assert_eq!(in_repo("id").lines().count(), 4);
+
+    let outside = home.run(&["import", "--from-git"]);
+    assert_eq!(outside.status.code(), Some(1));
+    assert!(stderr(&outside).contains("git rev-parse failed"), "{}", stderr(&outside));
+}
+
+#[test]
+fn word_stats_count_cjk_characters_and_recent_weeks() {
+    let home = Home::new();
+    home.ok(&["log", "-m", "修复了 TLS 超时。"]);
+    home.ok(&["log", "-m", "Don't panic"]);
+    log_at(&home, "2020-01-06T09:00:00.000Z", "旧的记录", None);
+
+    let json = home.ok(&["stats", "--all", "--words", "--json"]);
+    let totals = "\"words\": 12, \"characters\": 23, \"words_per_entry\": 4.0, \"characters_per_entry\": 7.7";
+    assert!(json.contains(totals), "{}", json);
+    assert!(json.contains("\"longest\": {\"id\": 1, \"words\": 6, \"title\": \"修复了 TLS 超时。\"}"), "{}", json);
+    // 每周的词数只包括最近几周，本周在最后
+    assert_eq!(json.matches("\"week\": ").count(), 12, "{}", json);
+    assert!(json.trim_end().ends_with("\"words\": 8}]}"), "{}", json);
+    assert_eq!(json.matches("\"words\": 0}").count(), 11, "{}", json);
+
+    let text = home.ok(&["stats", "--all", "--words"]);
+    assert!(text.contains("Words:        12 (4.0 per entry)"), "{}", text);
+    assert!(text.contains("Longest:      #1, 6 words: 修复了 TLS 超时。"), "{}", text);
+}
+
+#[test]
+fn sync_bundles_resolve_edit_and_delete_conflicts() {
+    let (laptop, desktop) = (Home::new(), Home::new());
+    laptop.ok(&["log", "-m", "edited on both"]);
+    laptop.ok(&["log", "-m", "deleted on the laptop"]);
+    laptop.ok(&["log", "-m", "deleted here, finished there", "--todo"]);
+    let bundle = |home: &Home, name: &str| home.path().join(name).to_str().unwrap().to_string();
+    let first = bundle(&laptop, "first.jsonl");
+    let exported = laptop.ok(&["sync", "export", "--since-last", &first]);
+    assert!(exported.contains("Exported 3 entries and 0 deletion(s) to"), "{}", exported);
+    let imported = desktop.ok(&["sync", "import", &first]);
+    assert!(imported.contains("✓ Applied 3 change(s), skipped 0, 0 conflict(s)."), "{}", imported);
+    let unchanged = laptop.ok(&["sync", "export", "--since-last", &bundle(&laptop, "none.jsonl")]);
+    assert!(unchanged.starts_with("No changes since the last export"), "{}", unchanged);
+
+    // 两边都修改了 #1，桌面的修改较晚；笔记本删除了 #2 和 #3，桌面之后又完成了 #3
+    laptop.ok(&["lock", "1"]);
+    laptop.ok(&["del", "2,3", "--yes"]);
+    desktop.ok(&["lock", "1"]);
+    desktop.ok(&["unlock", "1"]);
+    desktop.ok(&["done", "3"]);
+
+    let changes = bundle(&laptop, "changes.jsonl");
+    let exported = laptop.ok(&["sync", "export", "--since-last", &changes]);
+    assert!(exported.contains("Exported 1 entries and 2 deletion(s) changed since the last export"), "{}", exported);
+    let dry = desktop.ok(&["sync", "import", &changes, "--dry-run"]);
+    assert!(dry.contains("Dry run: would apply 1 change(s), skipped 0, 2 conflict(s)."), "{}", dry);
+    let applied = desktop.ok(&["sync", "import", &changes]);
+    assert!(applied.contains("#1") && applied.contains("kept the local version, it is newer"), "{}", applied);
+    assert!(applied.contains("#3") && applied.contains("deleted there but edited here afterwards"), "{}", applied);
+    assert!(applied.contains("✓ Applied 1 change(s), skipped 0, 2 conflict(s)."), "{}", applied);
+    let kept = desktop.ok(&["get", "--all", "-n", "0", "--field", "id"]);
+    assert_eq!(kept, "3\n1\n");
+
+    // 反向同步：#1 采用桌面的版本，#3 在笔记本上保持删除
+    let back = bundle(&desktop, "back.jsonl");
+    desktop.ok(&["sync", "export", "--since-last", &back]);
+    let applied = laptop.ok(&["sync", "import", &back]);
+    assert!(applied.contains("edited there but deleted here, kept it deleted"), "{}", applied);
+    assert!(applied.contains("✓ Applied 1 change(s),"), "{}", applied);
+    assert_eq!(laptop.ok(&["get", "--all", "-n", "0", "--field", "id"]), "1\n");
+    assert!(laptop.ok(&["get", "--all", "--ids", "1"]).contains("edited on both"));
+    assert!(!laptop.ok(&["get", "--all", "--ids", "1"]).contains("LOCKED"));
+}
+
+#[test]
+fn project_flag_uses_the_nearest_marked_ancestor() {
+    let home = Home::new();
+    let repo = home.path().join("repo");
+    let module = repo.join("modules/lib");
+    let nested = module.join("src/deeply/nested");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::create_dir_all(repo.join(".git")).unwrap();
+    std::fs::write(module.join("package.json"), "{}").unwrap();
+    let in_dir = |dir: &Path, args: &[&str]| {
+        let output = home.dlog(args).current_dir(dir).output().unwrap();
+        assert!(output.status.success(), "dlog {:?} failed: {}", args, stderr(&output));
+        output
+    };
+
+    // 子模块中的日志记录到子模块的根目录，仓库中的其他位置记录到仓库根目录
+    in_dir(&nested, &["log", "-p", "-m", "in the module"]);
+    in_dir(&repo.join("modules"), &["log", "--project", "-m", "in the repo"]);
+    in_dir(&nested, &["log", "-m", "deep down"]);
+    let directories = home.ok(&["get", "--all", "-n", "0", "--field", "directory"]);
+    let expected = format!("{}\n{}\n{}\n", nested.display(), repo.display(), module.display());
+    assert_eq!(directories, expected);
+
+    // get -p 包含项目根目录下的所有日志，嵌套项目的日志也在上层项目中
+    let here = |dir: &Path| stdout(&in_dir(dir, &["get", "-p", "-n", "0", "--field", "content"]));
+    assert_eq!(here(&nested), "deep down\nin the module\n");
+    assert_eq!(here(&repo.join("modules")), "deep down\nin the repo\nin the module\n");
+
+    // 找不到标记时使用当前目录并提示
+    let output = home
+        .dlog(&["log", "-p", "-m", "no project"])
+        .current_dir(&nested)
+        .env("DLOG_PROJECT_MARKERS", "go.mod, ")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Note: no project marker (go.mod) found above"), "{}", stderr(&output));
+    assert_eq!(home.ok(&["get", "--all", "-n", "1", "--field", "directory"]), format!("{}\n", nested.display()));
+}
+
+#[test]
+fn project_markers_come_from_the_config_file() {
+    let home = Home::new();
+    let module = home.path().join("service");
+    let nested = module.join("cmd/server");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(module.join("go.mod"), "module service\n").unwrap();
+    std::fs::write(nested.join("package.json"), "{}").unwrap();
+    home.write_config("project_markers = \"go.mod\"\n");
+    let log_in_nested = |message: &str, markers: Option<&str>| {
+        let mut cmd = home.dlog(&["log", "-p", "-m", message]);
+        cmd.current_dir(&nested);
+        if let Some(markers) = markers {
+            cmd.env("DLOG_PROJECT_MARKERS", markers);
+        }
+        let output = cmd.output().unwrap();
+        assert!(output.status.success(), "{}", stderr(&output));
+        home.ok(&["get", "--all", "-n", "1", "--field", "directory"])
+    };
+
+    // 配置文件中的标记生效，默认列表中的 package.json 不再算作标记
+    assert_eq!(log_in_nested("from the config", None), format!("{}\n", module.display()));
+    // 环境变量仍然优先于配置文件
+    assert_eq!(log_in_nested("from the env", Some("package.json")), format!("{}\n", nested.display()));
+}
+
+#[test]
+fn field_output_prints_raw_values_with_newline_or_nul_separators() {
+    let home = Home::new();
+    home.ok(&["log", "-m", "tagged", "-t", "ops"]);
+    home.ok(&["log", "-m", "First line", "-m", "second paragraph\twith a tab"]);
+    let raw = |args: &[&str]| {
+        let output = home.run(args);
+        assert!(output.status.success(), "dlog {:?} failed: {}", args, stderr(&output));
+        output.stdout
+    };
+
+    // 多行内容原样输出；--print0 时每条记录以 NUL 结束，内容中的换行不分隔记录
+    assert_eq!(raw(&["get", "--ids", "2", "--field", "content"]), b"First line\n\nsecond paragraph\twith a tab\n");
+    let both = raw(&["get", "--ids", "1-2", "--field", "content", "--print0"]);
+    assert_eq!(both, b"First line\n\nsecond paragraph\twith a tab\0tagged\0");
+    assert_eq!(raw(&["get", "--field", "id", "--print0"]), b"2\x001\0");
+
+    // 没有标签的日志输出空记录，而不是 None 或 null
+    assert_eq!(raw(&["get", "--field", "tags"]), b"\nops\n");
+    assert_eq!(raw(&["get", "--field", "tags", "--print0"]), b"\0ops\0");
+    assert_eq!(raw(&["get", "--ids", "2", "--field", "directory"]), format!("{}\n", home.path().display()).as_bytes());
+    let timestamp = String::from_utf8(raw(&["get", "--ids", "1", "--field", "timestamp"])).unwrap();
+    assert!(timestamp.trim_end().parse::<chrono::DateTime<chrono::Utc>>().is_ok(), "{:?}", timestamp);
+
+    // 没有匹配时没有任何输出，以退出码 3 结束
+    let none = home.run(&["get", "--tag", "missing", "--field", "content", "--print0"]);
+    assert_eq!((none.status.code(), none.stdout.as_slice(), stderr(&none).as_str()), (Some(3), &b""[..], ""));
+
+    // --print0 需要 --field，--field 不能与其他输出格式同时使用
+    let invalid = [&["get", "--print0"][..], &["get", "--field", "id", "--oneline"], &["get", "--field", "id", "--ids-only"]];
+    for args in invalid {
+        assert_eq!(home.run(args).status.code(), Some(2), "{:?}", args);
+    }
+}
+
+#[test]
+fn nested_dir_config_overrides_default_tags_of_the_parent() {
+    let home = Home::new();
+    let infra = home.path().join("infra");
+    let web = infra.join("web");
+    std::fs::create_dir_all(web.join("src")).unwrap();
+    std::fs::write(infra.join(".dlog.toml"), "default_tags = [\"ops\"]\n").unwrap();
+    std::fs::write(web.join(".dlog.toml"), "default_tags = [\"web\", \"Deploy\"]\n").unwrap();
+    let log_in = |dir: &Path, args: &[&str]| {
+        let args = [&["log"][..], args].concat();
+        let output = home.dlog(&args).current_dir(dir).output().unwrap();
+        assert!(output.status.success(), "dlog {:?} failed: {}", args, stderr(&output));
+    };
+    let last_tags = || home.ok(&["get", "--all", "-n", "1", "--field", "tags"]);
+
+    // 子目录的配置完全代替上层的，默认标签排在 -t 之后，重复的标签只保留一个
+    log_in(&web.join("src"), &["-m", "web change", "-t", "DEPLOY,hotfix"]);
+    assert_eq!(last_tags(), "deploy,hotfix,web\n");
+    log_in(&infra, &["-m", "infra change"]);
+    assert_eq!(last_tags(), "ops\n");
+    log_in(&web, &["-m", "opted out", "-t", "misc", "--no-default-tags"]);
+    assert_eq!(last_tags(), "misc\n");
+    log_in(&web, &["-m", "elsewhere", "--dir", infra.to_str().unwrap()]);
+    assert_eq!(last_tags(), "ops\n");
+
+    // 查找到主目录为止，主目录之上的配置不起作用
+    let user = home.path().join("user");
+    std::fs::create_dir_all(user.join("notes")).unwrap();
+    std::fs::write(home.path().join(".dlog.toml"), "default_tags = [\"above-home\"]\n").unwrap();
+    let output = home.dlog(&["log", "-m", "at home"]).env("HOME", &user).current_dir(user.join("notes")).output();
+    assert!(output.unwrap().status.success());
+    let tags = home.dlog(&["get", "--all", "-n", "1", "--field", "tags"]).env("HOME", &user).output().unwrap();
+    assert_eq!(stdout(&tags), "\n");
+
+    // 格式错误的配置文件使记录失败，错误消息包含文件路径
+    std::fs::write(web.join(".dlog.toml"), "default_tags = ops\n").unwrap();
+    let output = home.dlog(&["log", "-m", "broken"]).current_dir(&web).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let message = stderr(&output);
+    assert!(message.contains(&format!("{} line 1:", web.join(".dlog.toml").display())), "{}", message);
+    assert_eq!(home.ok(&["get", "--all", "-n", "0", "--field", "id"]).lines().count(), 4);
+}
+
+#[test]
+fn duplicate_window_and_skip_come_from_the_config_file() {
+    let home = Home::new();
+    let count = || home.ok(&["get", "-n", "0", "--field", "id"]).lines().count();
+    home.ok(&["log", "-m", "deployed v1.2"]);
+    let skipped = home.run(&["log", "-m", " deployed v1.2 "]);
+    assert!(stderr(&skipped).contains("Warning: log #1 with the same content was just recorded here, skipped"));
+    assert_eq!(count(), 1);
+
+    // duplicate_skip = false 只提示，环境变量仍然优先于配置文件
+    home.write_config("duplicate_skip = false\n");
+    let warned = home.run(&["log", "-m", "deployed v1.2"]);
+    assert!(stderr(&warned).contains("was just recorded here."), "{}", stderr(&warned));
+    assert_eq!(count(), 2);
+    let output = home.dlog(&["log", "-m", "deployed v1.2"]).env("DLOG_DUPLICATE_SKIP", "1").output().unwrap();
+    assert!(stderr(&output).contains("skipped"), "{}", stderr(&output));
+    assert_eq!(count(), 2);
+
+    // duplicate_window = 0 关闭检查
+    home.write_config("duplicate_window = 0\n");
+    let output = home.run(&["log", "-m", "deployed v1.2"]);
+    assert_eq!(stderr(&output), "");
+    assert_eq!(count(), 3);
+    home.write_config("duplicate_window = \"5m\"\n");
+    let invalid = home.run(&["log", "-m", "deployed v1.2"]);
+    assert!(stderr(&invalid).contains("duplicate_window must be a non-negative integer"), "{}", stderr(&invalid));
+}
+
+#[cfg(unix)]
+#[test]
+fn cmd_output_cap_comes_from_the_config_file() {
+    let home = Home::new();
+    // 3000 字节的输出：默认上限 200 KB 时完整保留
+    let command = "head -c 3000 /dev/zero | tr '\\0' x";
+    let content = || home.ok(&["get", "-n", "1", "--field", "content"]);
+    home.ok(&["log", "--cmd", command]);
+    assert!(content().contains(&"x".repeat(3000)));
+
+    // cmd_max_output = 1 只保留开头和结尾各 512 字节
+    home.write_config("cmd_max_output = 1\n");
+    let output = home.run(&["log", "--cmd", command, "--allow-duplicate"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Note: 1976 bytes of output were left out of the log (see cmd_max_output"));
+    let trimmed = content();
+    let kept = "x".repeat(512);
+    assert!(trimmed.contains(&format!("{}\n… 1976 bytes omitted …\n{}\n```", kept, kept)), "{}", trimmed);
+
+    // 环境变量仍然优先于配置文件
+    let output = home.dlog(&["log", "--cmd", command, "--allow-duplicate"]).env("DLOG_CMD_MAX_OUTPUT", "2").output();
+    assert!(stderr(&output.unwrap()).contains("Note: 952 bytes of output"));
+}
@@ -0,0 +1,76 @@
+// tests/lib.rs
+
+//! 直接调用库接口的测试：数据库位置由调用方指定，不读取命令行使用的 DLOG_DB 等设置
+
+use dlog::db::{self, LogQuery, Scope};
+use dlog::models::Level;
+use dlog::DlogError;
+use std::path::Path;
+
+/// 临时目录中新建的数据库，目录在返回的 TempDir 释放时删除
+fn open() -> (tempfile::TempDir, rusqlite::Connection) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested/dlog.db");
+    db::initialize_db_at(&path).unwrap();
+    let conn = db::open_connection_at(&path).unwrap();
+    (dir, conn)
+}
+
+#[test]
+fn opening_a_missing_database_is_an_error_not_a_prompt() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("absent.db");
+    assert!(matches!(db::open_connection_at(&path), Err(DlogError::DatabaseNotInitialized)));
+    assert!(!path.exists(), "opening must not create the file");
+
+    // 不是 dlog 数据库的 SQLite 文件同样视为未初始化
+    rusqlite::Connection::open(&path).unwrap().execute_batch("CREATE TABLE other (x);").unwrap();
+    assert!(matches!(db::open_connection_at(&path), Err(DlogError::DatabaseNotInitialized)));
+}
+
+#[test]
+fn logs_are_added_and_fetched_by_explicit_directory() {
+    let (_dir, conn) = open();
+    let first = db::add_log(&conn, "/work/app", "Fixed the cache bug", Some("bugfix,cache"), false).unwrap();
+    let second = db::add_log(&conn, "/work/app/api", "Added an endpoint", None, true).unwrap();
+    db::add_log(&conn, "/work/other", "Unrelated", None, false).unwrap();
+
+    let here = db::fetch_logs(&conn, Path::new("/work/app"), Scope::Dir, &LogQuery::default()).unwrap();
+    assert_eq!(here.iter().map(|l| l.id).collect::<Vec<_>>(), vec![first]);
+    assert_eq!(here[0].tags.as_deref(), Some("bugfix,cache"));
+    assert_eq!(here[0].status, None);
+
+    let below = db::fetch_logs(&conn, Path::new("/work/app"), Scope::Recursive, &LogQuery::default()).unwrap();
+    assert_eq!(below.iter().map(|l| l.id).collect::<Vec<_>>(), vec![second, first]);
+    assert_eq!(below[0].status.as_deref(), Some("open"));
+
+    let tagged = LogQuery { tag: Some("cache"), ..LogQuery::default() };
+    assert_eq!(db::fetch_logs(&conn, Path::new("/"), Scope::All, &tagged).unwrap().len(), 1);
+    assert_eq!(db::count_logs(&conn).unwrap(), 3);
+}
+
+#[test]
+fn entries_are_updated_and_deleted_through_the_library() {
+    let (_dir, conn) = open();
+    let id = db::add_log(&conn, "/work/app", "draft", None, false).unwrap();
+    assert_eq!(db::update_log_content(&conn, id, "final").unwrap(), 1);
+    assert_eq!(db::update_log_level(&conn, id, Level::High).unwrap(), 1);
+    let log = db::get_log(&conn, id).unwrap().unwrap();
+    assert_eq!((log.content.as_str(), log.level), ("final", Level::High));
+    assert!(log.updated_at.is_some());
+
+    // 锁定的日志只有显式要求时才会删除
+    db::set_locked(&conn, &[id], true).unwrap();
+    let kept = db::delete_logs_by_id(&conn, &[id], false).unwrap();
+    assert_eq!((kept.deleted, kept.locked), (0, vec![id]));
+    assert_eq!(db::delete_logs_by_id(&conn, &[id], true).unwrap().deleted, 1);
+    assert!(db::get_log(&conn, id).unwrap().is_none());
+}
+
+#[test]
+fn relative_directories_are_normalized_against_the_current_directory() {
+    let cwd = std::env::current_dir().unwrap();
+    let expected = db::normalize_path(&cwd).unwrap();
+    assert_eq!(db::normalize_path(Path::new(".")).unwrap(), expected);
+    assert_eq!(db::normalize_path(Path::new("missing/..")).unwrap(), expected);
+}